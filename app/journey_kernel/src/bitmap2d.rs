@@ -186,6 +186,23 @@ impl BitMap2D {
         self.lods = levels;
     }
 
+    /// OR `other` into this bitmap. LOD levels are merged the same way when
+    /// both have them, otherwise they are rebuilt if either had them.
+    pub fn merge(&mut self, other: &BitMap2D) {
+        assert_eq!(
+            self.width_exp, other.width_exp,
+            "Cannot merge bitmaps of different width_exp"
+        );
+        self.bits |= other.bits.as_bitslice();
+        if self.lods.len() == other.lods.len() {
+            for (lod, other_lod) in self.lods.iter_mut().zip(&other.lods) {
+                *lod |= other_lod.as_bitslice();
+            }
+        } else {
+            self.build_lods();
+        }
+    }
+
     /// Access LOD level k (0 = half-res, 1 = quarter-res, ...).
     /// Returns `None` if LODs are not built or k is out of range.
     pub fn lod_level(&self, k: usize) -> Option<&BitVec> {
@@ -470,6 +487,31 @@ mod tests {
         assert!(ds.get(1, 1));
     }
 
+    #[test]
+    fn merge_ors_base_and_lods() {
+        let mut a = BitMap2D::new(3);
+        a.set(1, 1, true);
+        a.build_lods();
+        let mut b = BitMap2D::new(3);
+        b.set(6, 7, true);
+        b.build_lods();
+
+        let mut expected = BitMap2D::new(3);
+        expected.set(1, 1, true);
+        expected.set(6, 7, true);
+        expected.build_lods();
+
+        a.merge(&b);
+        assert_eq!(a.as_bitvec(), expected.as_bitvec());
+        assert_eq!(a.lod_levels(), expected.lod_levels());
+
+        // without LODs on one side, they are rebuilt
+        let mut c = BitMap2D::new(3);
+        c.set(1, 1, true);
+        c.merge(&b);
+        assert_eq!(c.lod_levels(), expected.lod_levels());
+    }
+
     #[test]
     fn downscale_empty_returns_empty() {
         let bm = BitMap2D::new(3);
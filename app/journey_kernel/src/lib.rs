@@ -1,5 +1,6 @@
 pub mod bitmap2d;
 pub mod tile_archive;
+pub mod tile_buffer;
 pub mod tile_iter;
 pub mod tile_range;
 pub mod utils;
@@ -7,7 +8,12 @@ pub mod utils;
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
+pub use tile_buffer::TileBuffer;
+
+pub use tile_range::encode_tile_range_delta_from_tiles;
+pub use tile_range::encode_tile_range_layers_from_tiles;
 pub use tile_range::encode_tile_range_response_from_tiles;
+pub use tile_range::TileLayerPixelData;
 pub use tile_range::TilePixelData;
 
 pub use tile_archive::FTA_COMPRESSION_DEFLATE;
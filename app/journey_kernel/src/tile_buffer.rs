@@ -0,0 +1,691 @@
+use crate::bitmap2d::BitMap2D;
+use crate::tile_range::{
+    decode_tile_range_delta, decode_tile_range_layers, decompress_tile_range_response,
+    parse_tile_layers_from_body, parse_tile_range_header, parse_tiles_from_body, ChangedTile,
+    TileRangeHeader,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PixelType {
+    Pixel32,
+    Pixel64,
+    Triangle64,
+}
+
+pub fn push_mercator_pixel(
+    pixels: &mut Vec<f32>,
+    pixel_type: PixelType,
+    merc_x: f64,
+    merc_y: f64,
+    pixel_mercator_size: f64,
+) {
+    match pixel_type {
+        PixelType::Pixel32 => add_f32_mercator_coordinates(pixels, merc_x, merc_y),
+        PixelType::Pixel64 => add_f64_mercator_coordinates(pixels, merc_x, merc_y),
+        PixelType::Triangle64 => {
+            let delta = pixel_mercator_size;
+            add_f64_mercator_coordinates(pixels, merc_x, merc_y);
+            add_f64_mercator_coordinates(pixels, merc_x + delta, merc_y);
+            add_f64_mercator_coordinates(pixels, merc_x, merc_y + delta);
+            add_f64_mercator_coordinates(pixels, merc_x + delta, merc_y);
+            add_f64_mercator_coordinates(pixels, merc_x + delta, merc_y + delta);
+            add_f64_mercator_coordinates(pixels, merc_x, merc_y + delta);
+        }
+    }
+}
+
+fn add_f32_mercator_coordinates(pixels: &mut Vec<f32>, x: f64, y: f64) {
+    pixels.push(x as f32);
+    pixels.push(y as f32);
+}
+
+fn add_f64_mercator_coordinates(pixels: &mut Vec<f32>, x: f64, y: f64) {
+    fn f32_residue(val: f64) -> f32 {
+        (val - (val as f32 as f64)) as f32
+    }
+    pixels.push(x as f32);
+    pixels.push(y as f32);
+    pixels.push(f32_residue(x));
+    pixels.push(f32_residue(y));
+}
+
+type MercatorCacheKey = (i32, i32, u8, u8, PixelType);
+
+/// Decoded tile container built from TileRangeResponse wire-format bytes.
+/// TileBuffer stores a set of tiles, and proxy the queries the requests to the tiles.
+///   TileBuffer allows two groups of queries:
+///   - get_tile_pixels: get pixel coordinates within a single tile(subtile or tile).
+///   - query_range_pixels: query pixels within a range of tiles.
+///
+/// Both have a `*_mercator_pixels` variant returning web mercator coordinates
+/// (`0..1` across the world) instead, see `push_mercator_pixel`.
+///
+/// A buffer built from a layered response also keeps the tiles of each layer,
+/// which can be queried with `get_layer_tile_pixels`; the other queries see
+/// all layers merged.
+///
+/// The wire format itself is defined in `crate::tile_range`. This is the
+/// platform-neutral implementation, `crate::wasm::TileBuffer` wraps it for JS.
+pub struct TileBuffer {
+    grid_origin_x: i32,
+    grid_origin_y: i32,
+    grid_w: u16,
+    grid_h: u16,
+    /// Row-major grid: index = (y - grid_origin_y) * grid_w + (x - grid_origin_x).
+    /// Absent tiles are `None`.
+    tiles: Vec<Option<BitMap2D>>,
+    /// Same grid as `tiles` for each layer, in ascending id order. Empty if
+    /// the response is not layered.
+    layers: Vec<TileBufferLayer>,
+    _level0_exp: u8,
+    tile_grid_exp: u8,
+    tile_bitmap_exp: u8,
+    render_exp: u8,
+    /// See `TILE_RANGE_FLAG_LOADING`, from the last response applied.
+    loading: bool,
+    /// Cache of mercator pixel output keyed by (tile_x, tile_y, tile_z, render_exp, pixel_type).
+    /// Uses RefCell for interior mutability since query methods take &self.
+    mercator_cache: RefCell<HashMap<MercatorCacheKey, Vec<f32>>>,
+}
+
+struct TileBufferLayer {
+    id: u8,
+    name: String,
+    tiles: Vec<Option<BitMap2D>>,
+}
+
+fn merge_layer_tiles(layers: &[TileBufferLayer], idx: usize) -> Option<BitMap2D> {
+    let mut merged: Option<BitMap2D> = None;
+    for tile in layers.iter().filter_map(|layer| layer.tiles[idx].as_ref()) {
+        match &mut merged {
+            None => merged = Some(tile.clone()),
+            Some(merged) => merged.merge(tile),
+        }
+    }
+    merged
+}
+
+impl TileBuffer {
+    fn find_tile<'a>(
+        &self,
+        tiles: &'a [Option<BitMap2D>],
+        grid_x: i32,
+        grid_y: i32,
+    ) -> Option<&'a BitMap2D> {
+        // X-wrap normalization: the query x may be offset by multiples of the world
+        // size (1 << tile_grid_exp) due to multi-world-copy rendering or antimeridian
+        // crossing during drag. Use Euclidean modulo to map any x that has a
+        // modular-equivalent copy inside the buffer range back into that range.
+        let world_size = 1i32 << self.tile_grid_exp;
+        let offset = ((grid_x - self.grid_origin_x) % world_size + world_size) % world_size;
+        let grid_x = self.grid_origin_x + offset;
+
+        let dx = grid_x - self.grid_origin_x;
+        let dy = grid_y - self.grid_origin_y;
+        if dx < 0 || dy < 0 || dx >= self.grid_w as i32 || dy >= self.grid_h as i32 {
+            return None;
+        }
+        tiles[dy as usize * self.grid_w as usize + dx as usize].as_ref()
+    }
+
+    fn tile_index(&self, header: &TileRangeHeader, x: i32, y: i32) -> usize {
+        (y - header.y0) as usize * self.grid_w as usize + (x - header.x0) as usize
+    }
+
+    fn check_same_range(&self, header: &TileRangeHeader) -> Result<(), String> {
+        if header.z != self.tile_grid_exp
+            || header.tile_bitmap_exp != self.tile_bitmap_exp
+            || header.x0 != self.grid_origin_x
+            || header.y0 != self.grid_origin_y
+            || header.range_w != self.grid_w
+            || header.range_h != self.grid_h
+        {
+            return Err("TileRange delta does not match the range of the TileBuffer".to_string());
+        }
+        Ok(())
+    }
+
+    fn clamped_query_render_exp(&self, tile_z: u8, requested_render_exp: u8) -> u8 {
+        let world_detail_exp = self.tile_grid_exp as i16 + self.tile_bitmap_exp as i16;
+        let max_render_exp = (world_detail_exp - tile_z as i16).max(0) as u8;
+        requested_render_exp.min(max_render_exp)
+    }
+
+    /// Query tile buffer for pixels within a single tile(subtile or tile).
+    pub fn get_tile_pixels(
+        &self,
+        tile_x: i32,
+        tile_y: i32,
+        tile_z: u8,
+        render_exp: u8,
+    ) -> Vec<u16> {
+        self.tile_pixels(&self.tiles, tile_x, tile_y, tile_z, render_exp)
+    }
+
+    /// Same as `get_tile_pixels`, but only with the pixels of one layer (see
+    /// `crate::tile_range::TILE_RANGE_LAYER_*`). Empty if the layer is absent.
+    pub fn get_layer_tile_pixels(
+        &self,
+        layer_id: u8,
+        tile_x: i32,
+        tile_y: i32,
+        tile_z: u8,
+        render_exp: u8,
+    ) -> Vec<u16> {
+        match self.layers.iter().find(|layer| layer.id == layer_id) {
+            Some(layer) => self.tile_pixels(&layer.tiles, tile_x, tile_y, tile_z, render_exp),
+            None => Vec::new(),
+        }
+    }
+
+    /// Ids of the layers in this buffer, empty if the response is not layered.
+    pub fn layer_ids(&self) -> Vec<u8> {
+        self.layers.iter().map(|layer| layer.id).collect()
+    }
+
+    pub fn layer_name(&self, layer_id: u8) -> Option<String> {
+        self.layers
+            .iter()
+            .find(|layer| layer.id == layer_id)
+            .map(|layer| layer.name.clone())
+    }
+
+    fn tile_pixels(
+        &self,
+        tiles: &[Option<BitMap2D>],
+        tile_x: i32,
+        tile_y: i32,
+        tile_z: u8,
+        render_exp: u8,
+    ) -> Vec<u16> {
+        let Some(tiles_per_axis) = 1i64.checked_shl(tile_z as u32) else {
+            return Vec::new();
+        };
+        // y is always non-negative in web mercator; x can be negative for world wrapping
+        if tile_y < 0 || tile_y as i64 >= tiles_per_axis {
+            return Vec::new();
+        }
+
+        let render_exp = self.clamped_query_render_exp(tile_z, render_exp);
+        let mut packed = Vec::new();
+
+        if tile_z >= self.tile_grid_exp {
+            // Case 1: The queried tiles are smaller than the TileBuffer's internal tile grid.
+            let dz = tile_z - self.tile_grid_exp;
+            let parent_x = tile_x >> dz;
+            let parent_y = tile_y >> dz;
+
+            let Some(tile) = self.find_tile(tiles, parent_x, parent_y) else {
+                return Vec::new();
+            };
+
+            let child_mask = if dz == 0 { 0 } else { (1i32 << dz) - 1 };
+            let child_x = (tile_x & child_mask) as i64;
+            let child_y = (tile_y & child_mask) as i64;
+            let child_z = dz as i16;
+            for (px, py) in tile.iter_pixels(0, 0, child_x, child_y, child_z, render_exp as i16) {
+                if (0..=u16::MAX as i64).contains(&px) && (0..=u16::MAX as i64).contains(&py) {
+                    packed.push(px as u16);
+                    packed.push(py as u16);
+                }
+            }
+            return packed;
+        }
+
+        let span = self.tile_grid_exp - tile_z;
+        let subtiles_per_axis = 1u32 << span;
+        let base_x = (tile_x as i64) << span;
+        let base_y = (tile_y as i64) << span;
+
+        if render_exp >= span {
+            // Case 2: The queried tiles are larger than the TileBuffer's internal tile grid.
+            // TODO(opt): with the grid-indexed layout we could iterate the tiles slice
+            // directly instead of calling find_tile per (dx, dy).
+            let sub_render_exp = render_exp - span;
+            for dy in 0..subtiles_per_axis {
+                for dx in 0..subtiles_per_axis {
+                    let gx = base_x + dx as i64;
+                    let gy = base_y + dy as i64;
+                    if gx < i32::MIN as i64 || gx > i32::MAX as i64 {
+                        continue;
+                    }
+                    let Some(tile) = self.find_tile(tiles, gx as i32, gy as i32) else {
+                        continue;
+                    };
+                    for (px, py) in tile.iter_pixels(0, 0, 0, 0, 0, sub_render_exp as i16) {
+                        let out_x = (dx << sub_render_exp) + px as u32;
+                        let out_y = (dy << sub_render_exp) + py as u32;
+                        if out_x <= u16::MAX as u32 && out_y <= u16::MAX as u32 {
+                            packed.push(out_x as u16);
+                            packed.push(out_y as u16);
+                        }
+                    }
+                }
+            }
+            return packed;
+        }
+
+        // Case 3: The requested resolution is below the subtile grid resolution.
+        // Reduce each internal tile to occupancy and OR into coarse output pixels.
+        // TODO(opt): same as Case 2 — direct grid slice iteration would avoid per-cell find_tile.
+        let coarse_shift = span - render_exp;
+        for dy in 0..subtiles_per_axis {
+            for dx in 0..subtiles_per_axis {
+                let gx = base_x + dx as i64;
+                let gy = base_y + dy as i64;
+                if gx < i32::MIN as i64 || gx > i32::MAX as i64 {
+                    continue;
+                }
+                let Some(tile) = self.find_tile(tiles, gx as i32, gy as i32) else {
+                    continue;
+                };
+                if tile.is_empty() {
+                    continue;
+                }
+                let out_x = dx >> coarse_shift;
+                let out_y = dy >> coarse_shift;
+                if out_x <= u16::MAX as u32 && out_y <= u16::MAX as u32 {
+                    packed.push(out_x as u16);
+                    packed.push(out_y as u16);
+                }
+            }
+        }
+
+        packed
+    }
+
+    /// Same pixels as `get_tile_pixels`, as mercator coordinates of their
+    /// top-left corners (the whole pixel for `PixelType::Triangle64`).
+    pub fn get_tile_mercator_pixels(
+        &self,
+        tile_x: i32,
+        tile_y: i32,
+        tile_z: u8,
+        render_exp: u8,
+        pixel_type: PixelType,
+    ) -> Vec<f32> {
+        // pixels are always in the grid of the clamped render_exp
+        let render_exp = self.clamped_query_render_exp(tile_z, render_exp);
+        let key = (tile_x, tile_y, tile_z, render_exp, pixel_type);
+        if let Some(pixels) = self.mercator_cache.borrow().get(&key) {
+            return pixels.clone();
+        }
+
+        let pixel_mercator_size = 1.0 / (1u64 << (tile_z as u32 + render_exp as u32)) as f64;
+        let base_x = (tile_x as i64) << render_exp;
+        let base_y = (tile_y as i64) << render_exp;
+        let mut pixels = Vec::new();
+        for xy in self
+            .get_tile_pixels(tile_x, tile_y, tile_z, render_exp)
+            .chunks_exact(2)
+        {
+            push_mercator_pixel(
+                &mut pixels,
+                pixel_type,
+                (base_x + xy[0] as i64) as f64 * pixel_mercator_size,
+                (base_y + xy[1] as i64) as f64 * pixel_mercator_size,
+                pixel_mercator_size,
+            );
+        }
+        self.mercator_cache.borrow_mut().insert(key, pixels.clone());
+        pixels
+    }
+
+    /// Parses raw TileRangeResponse bytes returned by the `/tile-range` endpoint.
+    ///
+    /// `data` must match the binary format documented in `crate::tile_range`.
+    pub fn new_from_tile_range_response(level0_exp: u8, data: &[u8]) -> Result<TileBuffer, String> {
+        let decompressed = decompress_tile_range_response(data)
+            .map_err(|e| format!("Failed to decompress TileRangeResponse: {}", e))?;
+        let header = parse_tile_range_header(&decompressed)
+            .map_err(|e| format!("Failed to parse TileRange header: {}", e))?;
+        if header.is_delta() {
+            return Err("Cannot build a TileBuffer from a delta TileRangeResponse".to_string());
+        }
+        let mut buffer = TileBuffer {
+            grid_origin_x: header.x0,
+            grid_origin_y: header.y0,
+            grid_w: header.range_w,
+            grid_h: header.range_h,
+            tiles: vec![None; header.range_w as usize * header.range_h as usize],
+            layers: Vec::new(),
+            _level0_exp: level0_exp,
+            tile_grid_exp: header.z,
+            tile_bitmap_exp: header.tile_bitmap_exp,
+            render_exp: header.tile_bitmap_exp,
+            loading: header.is_loading(),
+            mercator_cache: RefCell::new(HashMap::new()),
+        };
+        let body = &decompressed[header.size()..];
+        let parse_error = |e: String| format!("Failed to parse TileRangeResponse: {}", e);
+        if header.is_layered() {
+            let layers = parse_tile_layers_from_body(&header, body).map_err(parse_error)?;
+            let mut changed = Vec::new();
+            for layer in layers {
+                buffer.layers.push(TileBufferLayer {
+                    id: layer.id,
+                    name: layer.name,
+                    tiles: vec![None; buffer.tiles.len()],
+                });
+                changed.push(layer.tiles);
+            }
+            buffer.apply_layer_tiles(&header, changed);
+        } else {
+            let parsed = parse_tiles_from_body(
+                header.tile_bitmap_exp,
+                header.x0,
+                header.y0,
+                header.range_w as usize,
+                header.tile_count as usize,
+                header.present_count as usize,
+                body,
+            )
+            .map_err(parse_error)?;
+            for (x, y, bm) in parsed {
+                let idx = buffer.tile_index(&header, x, y);
+                buffer.tiles[idx] = Some(bm);
+            }
+        }
+        Ok(buffer)
+    }
+
+    /// Replaces the changed tiles of each layer (in the order of
+    /// `self.layers`) and merges them again.
+    fn apply_layer_tiles(&mut self, header: &TileRangeHeader, changed: Vec<Vec<ChangedTile>>) {
+        let mut changed_indexes = Vec::new();
+        for (layer, tiles) in self.layers.iter_mut().zip(changed) {
+            for (x, y, bm) in tiles {
+                let idx =
+                    (y - header.y0) as usize * self.grid_w as usize + (x - header.x0) as usize;
+                layer.tiles[idx] = bm;
+                changed_indexes.push(idx);
+            }
+        }
+        for idx in changed_indexes {
+            self.tiles[idx] = merge_layer_tiles(&self.layers, idx);
+        }
+    }
+
+    /// Patches the buffer with a delta TileRangeResponse (see `TILE_RANGE_FLAG_DELTA`).
+    ///
+    /// The delta must cover the same range (and the same layers, if layered)
+    /// as the buffer; only the changed tiles are replaced, the rest are kept
+    /// as is.
+    pub fn apply_tile_range_delta(&mut self, data: &[u8]) -> Result<(), String> {
+        let decode_error = |e: String| format!("Failed to decode TileRange delta: {}", e);
+        if self.layers.is_empty() {
+            let (header, changed) = decode_tile_range_delta(data).map_err(decode_error)?;
+            self.check_same_range(&header)?;
+            for (x, y, bm) in changed {
+                let idx = self.tile_index(&header, x, y);
+                self.tiles[idx] = bm;
+            }
+            self.loading = header.is_loading();
+        } else {
+            let (header, layers) = decode_tile_range_layers(data).map_err(decode_error)?;
+            self.check_same_range(&header)?;
+            if !header.is_delta()
+                || layers.len() != self.layers.len()
+                || layers.iter().zip(&self.layers).any(|(a, b)| a.id != b.id)
+            {
+                return Err(
+                    "TileRange delta does not match the layers of the TileBuffer".to_string(),
+                );
+            }
+            self.apply_layer_tiles(&header, layers.into_iter().map(|x| x.tiles).collect());
+            self.loading = header.is_loading();
+        }
+        self.mercator_cache.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// Whether the server was still loading its data, the range should be
+    /// requested again (a delta is enough) to get the rest.
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    pub fn set_render_exp(&mut self, exp: u8) {
+        self.render_exp = exp;
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.tiles.iter().filter(|t| t.is_some()).count() as u32
+    }
+
+    pub fn total_pixel_count(&self) -> u32 {
+        let mut count = 0u32;
+        for bm in self.tiles.iter().filter_map(|t| t.as_ref()) {
+            count += bm
+                .iter_pixels(0, 0, 0, 0, 0, self.tile_bitmap_exp as i16)
+                .count() as u32;
+        }
+        count
+    }
+
+    /// Split range query into tile queries and merge the results.
+    pub fn query_range_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        z: u8,
+        w: u32,
+        h: u32,
+        render_exp: u8,
+    ) -> Vec<u16> {
+        let mut out = Vec::new();
+        for (tile_x, tile_y) in range_tiles(x, y, w, h) {
+            out.extend_from_slice(&self.get_tile_pixels(tile_x, tile_y, z, render_exp));
+        }
+        out
+    }
+
+    /// Same as `query_range_pixels` with `get_tile_mercator_pixels`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn query_range_mercator_pixels(
+        &self,
+        x: i32,
+        y: i32,
+        z: u8,
+        w: u32,
+        h: u32,
+        render_exp: u8,
+        pixel_type: PixelType,
+    ) -> Vec<f32> {
+        let mut out = Vec::new();
+        for (tile_x, tile_y) in range_tiles(x, y, w, h) {
+            out.extend_from_slice(
+                &self.get_tile_mercator_pixels(tile_x, tile_y, z, render_exp, pixel_type),
+            );
+        }
+        out
+    }
+}
+
+/// The tiles of a `w` x `h` range in row-major order, skipping the ones that
+/// overflow `i32`.
+fn range_tiles(x: i32, y: i32, w: u32, h: u32) -> impl Iterator<Item = (i32, i32)> {
+    (0..h).flat_map(move |dy| {
+        (0..w).filter_map(move |dx| {
+            let tile_x = i32::try_from(x as i64 + dx as i64).ok()?;
+            let tile_y = i32::try_from(y as i64 + dy as i64).ok()?;
+            Some((tile_x, tile_y))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_range::{
+        encode_tile_range_delta_from_tiles, encode_tile_range_layers_from_tiles,
+        encode_tile_range_response_from_tiles, mark_tile_range_loading, TileLayerPixelData,
+        TilePixelData,
+    };
+    use crate::FTA_COMPRESSION_ZSTD;
+
+    fn tile(x: i32, y: i32, pixels: &[(usize, usize)]) -> TilePixelData {
+        let mut bitmap = BitMap2D::new(2);
+        for (px, py) in pixels {
+            bitmap.set(*px, *py, true);
+        }
+        TilePixelData { x, y, bitmap }
+    }
+
+    // a 2x2 range at z3 starting at (2, 3), with 4x4 pixel tiles
+    fn response(tiles: Vec<TilePixelData>) -> Vec<u8> {
+        encode_tile_range_response_from_tiles(3, 2, 3, 2, 2, 2, FTA_COMPRESSION_ZSTD, tiles)
+            .unwrap()
+    }
+
+    fn pixels(packed: &[u16]) -> Vec<(u16, u16)> {
+        let mut pixels: Vec<_> = packed.chunks_exact(2).map(|xy| (xy[0], xy[1])).collect();
+        pixels.sort();
+        pixels
+    }
+
+    #[test]
+    fn tile_pixels_at_every_zoom() {
+        let data = response(vec![tile(2, 3, &[(1, 2)]), tile(3, 4, &[(3, 3)])]);
+        let buffer = TileBuffer::new_from_tile_range_response(0, &data).unwrap();
+        assert_eq!(buffer.tile_count(), 2);
+        assert_eq!(buffer.total_pixel_count(), 2);
+
+        // same zoom
+        assert_eq!(pixels(&buffer.get_tile_pixels(2, 3, 3, 2)), vec![(1, 2)]);
+        assert!(buffer.get_tile_pixels(3, 3, 3, 2).is_empty());
+        // a child tile, clamped to the 2x2 pixels left of it
+        assert_eq!(pixels(&buffer.get_tile_pixels(7, 9, 4, 8)), vec![(1, 1)]);
+        // parent tiles
+        assert_eq!(pixels(&buffer.get_tile_pixels(1, 1, 2, 3)), vec![(1, 6)]);
+        assert_eq!(pixels(&buffer.get_tile_pixels(1, 2, 2, 3)), vec![(7, 3)]);
+        // a parent tile below the subtile resolution
+        assert_eq!(pixels(&buffer.get_tile_pixels(1, 2, 2, 0)), vec![(0, 0)]);
+        // world copies
+        assert_eq!(
+            pixels(&buffer.get_tile_pixels(2 - 8, 3, 3, 2)),
+            vec![(1, 2)]
+        );
+
+        assert_eq!(
+            pixels(&buffer.query_range_pixels(2, 3, 3, 2, 2, 2)),
+            vec![(1, 2), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn mercator_pixels() {
+        let data = response(vec![tile(2, 3, &[(1, 2)])]);
+        let buffer = TileBuffer::new_from_tile_range_response(0, &data).unwrap();
+        // 32 pixels across the world
+        let pixels = buffer.get_tile_mercator_pixels(2, 3, 3, 2, PixelType::Pixel32);
+        assert_eq!(pixels, vec![9.0 / 32.0, 14.0 / 32.0]);
+        let pixels = buffer.get_tile_mercator_pixels(2, 3, 3, 2, PixelType::Triangle64);
+        assert_eq!(pixels.len(), 6 * 4);
+        assert_eq!(pixels[4..6], [10.0 / 32.0, 14.0 / 32.0]);
+        assert_eq!(
+            buffer.query_range_mercator_pixels(1, 3, 3, 2, 1, 2, PixelType::Pixel32),
+            vec![9.0 / 32.0, 14.0 / 32.0]
+        );
+    }
+
+    #[test]
+    fn apply_delta() {
+        let data = response(vec![tile(2, 3, &[(1, 2)]), tile(3, 4, &[(3, 3)])]);
+        let mut buffer = TileBuffer::new_from_tile_range_response(0, &data).unwrap();
+        let _ = buffer.get_tile_mercator_pixels(2, 3, 3, 2, PixelType::Pixel32);
+
+        let delta = encode_tile_range_delta_from_tiles(
+            3,
+            2,
+            3,
+            2,
+            2,
+            2,
+            FTA_COMPRESSION_ZSTD,
+            vec![tile(2, 3, &[(0, 0)]), tile(3, 4, &[])],
+        )
+        .unwrap();
+        buffer.apply_tile_range_delta(&delta).unwrap();
+        assert_eq!(buffer.tile_count(), 1);
+        assert_eq!(pixels(&buffer.get_tile_pixels(2, 3, 3, 2)), vec![(0, 0)]);
+        assert_eq!(
+            buffer.get_tile_mercator_pixels(2, 3, 3, 2, PixelType::Pixel32),
+            vec![8.0 / 32.0, 12.0 / 32.0]
+        );
+
+        assert!(!buffer.is_loading());
+        let mut loading_delta =
+            encode_tile_range_delta_from_tiles(3, 2, 3, 2, 2, 2, FTA_COMPRESSION_ZSTD, vec![])
+                .unwrap();
+        mark_tile_range_loading(&mut loading_delta).unwrap();
+        buffer.apply_tile_range_delta(&loading_delta).unwrap();
+        assert!(buffer.is_loading());
+        assert_eq!(buffer.tile_count(), 1);
+
+        // a different range
+        let other = encode_tile_range_delta_from_tiles(3, 0, 0, 2, 2, 2, 0, vec![]).unwrap();
+        assert!(buffer.apply_tile_range_delta(&other).is_err());
+        // a full response is not a delta
+        assert!(TileBuffer::new_from_tile_range_response(0, &delta).is_err());
+    }
+
+    #[test]
+    fn layers() {
+        let encode = |is_delta, layers| {
+            encode_tile_range_layers_from_tiles(
+                3,
+                2,
+                3,
+                2,
+                2,
+                2,
+                FTA_COMPRESSION_ZSTD,
+                is_delta,
+                layers,
+            )
+            .unwrap()
+        };
+        let layer = |id, name: &str, tiles| TileLayerPixelData {
+            id,
+            name: name.to_string(),
+            tiles,
+        };
+        let data = encode(
+            false,
+            vec![
+                layer(2, "flight", vec![tile(2, 3, &[(1, 2)])]),
+                layer(1, "default", vec![tile(2, 3, &[(0, 0)])]),
+            ],
+        );
+        let mut buffer = TileBuffer::new_from_tile_range_response(0, &data).unwrap();
+        assert_eq!(buffer.layer_ids(), vec![1, 2]);
+        assert_eq!(buffer.layer_name(2).as_deref(), Some("flight"));
+        assert_eq!(
+            pixels(&buffer.get_tile_pixels(2, 3, 3, 2)),
+            vec![(0, 0), (1, 2)]
+        );
+        assert_eq!(
+            pixels(&buffer.get_layer_tile_pixels(2, 2, 3, 3, 2)),
+            vec![(1, 2)]
+        );
+        assert!(buffer.get_layer_tile_pixels(0, 2, 3, 3, 2).is_empty());
+
+        let delta = encode(
+            true,
+            vec![
+                layer(1, "default", vec![tile(2, 3, &[])]),
+                layer(2, "flight", vec![]),
+            ],
+        );
+        buffer.apply_tile_range_delta(&delta).unwrap();
+        assert_eq!(pixels(&buffer.get_tile_pixels(2, 3, 3, 2)), vec![(1, 2)]);
+
+        // the layers must match
+        let delta = encode(true, vec![layer(1, "default", vec![])]);
+        assert!(buffer.apply_tile_range_delta(&delta).is_err());
+    }
+}
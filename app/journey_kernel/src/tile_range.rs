@@ -1,19 +1,21 @@
 //! TileRangeResponse binary wire format used by `/tile-range`.
 //!
-//! The message contains a fixed-width 20-byte header followed by an encoded tail.
+//! The message contains a fixed-width 20-byte header (28 bytes with
+//! `TILE_RANGE_FLAG_LAYERED`) followed by an encoded tail.
 //! All multi-byte integer fields use little-endian byte order.
 //!
 //! Header layout:
 //! - byte 0: `tile_bitmap_exp` (`u8`)
 //! - byte 1: `z` (`u8`)
 //! - byte 2: `compression` (`u8`, see `FTA_COMPRESSION_*`)
-//! - byte 3: reserved (`0`)
+//! - byte 3: `flags` (`u8`, see `TILE_RANGE_FLAG_*`)
 //! - bytes 4..8: `x0` (`i32`)
 //! - bytes 8..12: `y0` (`i32`)
 //! - bytes 12..14: `range_w` (`u16`)
 //! - bytes 14..16: `range_h` (`u16`)
 //! - bytes 16..18: `tile_count` (`u16`, equals `range_w * range_h`)
 //! - bytes 18..20: `present_count` (`u16`)
+//! - bytes 20..28: `layer_mask` (`u64`), only with `TILE_RANGE_FLAG_LAYERED`
 //!
 //! Tail layout:
 //! 1. Presence bitmap (`ceil(tile_count / 8)` bytes), LSB-first bit order.
@@ -27,6 +29,26 @@
 //!   - bitmap bytes (`ceil(bit_count / 8)`)
 //!
 //! The tail may be compressed based on the header `compression` field.
+//!
+//! With `TILE_RANGE_FLAG_DELTA`, the response only carries tiles changed since
+//! a previous version of the same range: the presence bitmap marks changed
+//! tiles rather than non-empty ones, and a changed tile that became empty has a
+//! blob with a level count of `0`. Tiles not marked are unchanged.
+//!
+//! With `TILE_RANGE_FLAG_LAYERED`, the tiles are split into layers (e.g. one per
+//! journey kind) so clients can style them separately. Bit `i` of `layer_mask`
+//! is set when the layer with id `i` is included (see `TILE_RANGE_LAYER_*`), and
+//! the tail has a section per included layer in ascending id order:
+//! - name length (`u8`) and the UTF-8 name
+//! - `present_count` of the layer (`u16`)
+//! - presence bitmap and mipmap blobs, same as above
+//!
+//! The header `present_count` is then the sum over all layers. The delta flag
+//! applies to each layer.
+//!
+//! `TILE_RANGE_FLAG_LOADING` only marks that the server is still loading its
+//! data (e.g. some tiles are a low-resolution overview), the same range
+//! should be requested again later. It does not change the layout.
 use crate::bitmap2d::BitMap2D;
 use crate::tile_archive::{
     compress_with_len_prefix, decompress_zstd_block, deserialize_mipmap, serialize_mipmap,
@@ -37,17 +59,66 @@ use lz4_flex::{compress_prepend_size, decompress_size_prepended};
 
 pub const TILE_RANGE_HEADER_SIZE: usize = 20;
 
+pub const TILE_RANGE_LAYERED_HEADER_SIZE: usize = 28;
+
+pub const TILE_RANGE_FLAG_DELTA: u8 = 1;
+pub const TILE_RANGE_FLAG_LAYERED: u8 = 2;
+pub const TILE_RANGE_FLAG_LOADING: u8 = 4;
+
+pub const TILE_RANGE_LAYER_CURRENT_JOURNEY: u8 = 0;
+pub const TILE_RANGE_LAYER_DEFAULT: u8 = 1;
+pub const TILE_RANGE_LAYER_FLIGHT: u8 = 2;
+/// Custom journey kinds use the ids from here, in the order of their names.
+pub const TILE_RANGE_LAYER_FIRST_CUSTOM: u8 = 3;
+pub const TILE_RANGE_MAX_LAYERS: u8 = 64;
+
+/// A tile of a delta response, the bitmap is `None` when the tile became empty.
+pub type ChangedTile = (i32, i32, Option<BitMap2D>);
+
 #[derive(Clone, Copy, Debug)]
 pub struct TileRangeHeader {
     pub tile_bitmap_exp: u8,
     pub z: u8,
     pub compression: u8,
+    pub flags: u8,
     pub x0: i32,
     pub y0: i32,
     pub range_w: u16,
     pub range_h: u16,
     pub tile_count: u16,
     pub present_count: u16,
+    /// `0` unless `TILE_RANGE_FLAG_LAYERED` is set.
+    pub layer_mask: u64,
+}
+
+impl TileRangeHeader {
+    pub fn size(&self) -> usize {
+        if self.is_layered() {
+            TILE_RANGE_LAYERED_HEADER_SIZE
+        } else {
+            TILE_RANGE_HEADER_SIZE
+        }
+    }
+
+    pub fn is_delta(&self) -> bool {
+        self.flags & TILE_RANGE_FLAG_DELTA != 0
+    }
+
+    pub fn is_layered(&self) -> bool {
+        self.flags & TILE_RANGE_FLAG_LAYERED != 0
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.flags & TILE_RANGE_FLAG_LOADING != 0
+    }
+}
+
+/// The tiles of one layer in a layered response, see `TILE_RANGE_FLAG_LAYERED`.
+pub struct TileRangeLayer {
+    pub id: u8,
+    pub name: String,
+    /// Bitmaps are only `None` in delta responses, for tiles that became empty.
+    pub tiles: Vec<ChangedTile>,
 }
 
 /// Per-tile source data used by `encode_tile_range_response_from_tiles`.
@@ -72,6 +143,134 @@ pub fn encode_tile_range_response_from_tiles(
     compression: u8,
     tiles: Vec<TilePixelData>,
 ) -> Result<Vec<u8>, String> {
+    encode_tile_range(z, x0, y0, w, h, tile_bitmap_exp, compression, 0, tiles)
+}
+
+/// Same as `encode_tile_range_response_from_tiles`, but `tiles` are the tiles
+/// changed since a previous version, see `TILE_RANGE_FLAG_DELTA`.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_tile_range_delta_from_tiles(
+    z: u8,
+    x0: i32,
+    y0: i32,
+    w: u32,
+    h: u32,
+    tile_bitmap_exp: u8,
+    compression: u8,
+    tiles: Vec<TilePixelData>,
+) -> Result<Vec<u8>, String> {
+    encode_tile_range(
+        z,
+        x0,
+        y0,
+        w,
+        h,
+        tile_bitmap_exp,
+        compression,
+        TILE_RANGE_FLAG_DELTA,
+        tiles,
+    )
+}
+
+/// Tiles of one layer for `encode_tile_range_layers_from_tiles`.
+pub struct TileLayerPixelData {
+    /// Bit of the layer in `TileRangeHeader::layer_mask`, see `TILE_RANGE_LAYER_*`.
+    pub id: u8,
+    pub name: String,
+    pub tiles: Vec<TilePixelData>,
+}
+
+/// Encodes a layered TileRangeResponse (see `TILE_RANGE_FLAG_LAYERED`), with
+/// `is_delta` the tiles of each layer are the ones changed since a previous
+/// version (see `TILE_RANGE_FLAG_DELTA`).
+#[allow(clippy::too_many_arguments)]
+pub fn encode_tile_range_layers_from_tiles(
+    z: u8,
+    x0: i32,
+    y0: i32,
+    w: u32,
+    h: u32,
+    tile_bitmap_exp: u8,
+    compression: u8,
+    is_delta: bool,
+    mut layers: Vec<TileLayerPixelData>,
+) -> Result<Vec<u8>, String> {
+    layers.sort_by_key(|layer| layer.id);
+    let mut layer_mask = 0u64;
+    for layer in &layers {
+        if layer.id >= TILE_RANGE_MAX_LAYERS {
+            return Err(format!("Invalid layer id: {}", layer.id));
+        }
+        if layer_mask & (1 << layer.id) != 0 {
+            return Err(format!("Duplicate layer id: {}", layer.id));
+        }
+        if layer.name.len() > u8::MAX as usize {
+            return Err(format!("Layer name too long: {}", layer.name));
+        }
+        layer_mask |= 1 << layer.id;
+    }
+    let flags = TILE_RANGE_FLAG_LAYERED | if is_delta { TILE_RANGE_FLAG_DELTA } else { 0 };
+    let sections = layers
+        .into_iter()
+        .map(|layer| (Some(layer.name), layer.tiles))
+        .collect();
+    encode_tile_range_sections(
+        z,
+        x0,
+        y0,
+        w,
+        h,
+        tile_bitmap_exp,
+        compression,
+        flags,
+        layer_mask,
+        sections,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encode_tile_range(
+    z: u8,
+    x0: i32,
+    y0: i32,
+    w: u32,
+    h: u32,
+    tile_bitmap_exp: u8,
+    compression: u8,
+    flags: u8,
+    tiles: Vec<TilePixelData>,
+) -> Result<Vec<u8>, String> {
+    encode_tile_range_sections(
+        z,
+        x0,
+        y0,
+        w,
+        h,
+        tile_bitmap_exp,
+        compression,
+        flags,
+        0,
+        vec![(None, tiles)],
+    )
+}
+
+/// A section is the presence bitmap and mipmap blobs of a tile grid, layered
+/// responses have one per layer, each prefixed with the layer name and its
+/// `present_count`.
+#[allow(clippy::too_many_arguments)]
+fn encode_tile_range_sections(
+    z: u8,
+    x0: i32,
+    y0: i32,
+    w: u32,
+    h: u32,
+    tile_bitmap_exp: u8,
+    compression: u8,
+    flags: u8,
+    layer_mask: u64,
+    sections: Vec<(Option<String>, Vec<TilePixelData>)>,
+) -> Result<Vec<u8>, String> {
+    let is_delta = flags & TILE_RANGE_FLAG_DELTA != 0;
     if w == 0 || h == 0 {
         return Err("Invalid tile range".to_string());
     }
@@ -94,94 +293,129 @@ pub fn encode_tile_range_response_from_tiles(
 
     let _ = bitmap_bytes_for_exp(tile_bitmap_exp)
         .map_err(|e| format!("Invalid tile_bitmap_exp: {e}"))?;
-    let presence_len = (tile_count as usize).div_ceil(8);
-    let mut presence = vec![0u8; presence_len];
-    let mut payload = Vec::new();
-    let mut present_count = 0u16;
-    let mut tile_blobs = vec![None; tile_count as usize];
-
-    for tile in tiles {
-        let tx = tile.x as i64;
-        let ty = tile.y as i64;
-        if tx < x0 as i64 || tx > x1 || ty < y0 as i64 || ty > y1 {
-            return Err(format!(
-                "Tile ({}, {}) is outside query bounds x=[{}..{}], y=[{}..{}]",
-                tx, ty, x0, x1, y0, y1
-            ));
-        }
 
-        let idx = ((ty - y0 as i64) as u32 * range_w + (tx - x0 as i64) as u32) as usize;
-        if tile_blobs[idx].is_some() {
-            return Err(format!(
-                "Duplicate tile coordinates in input: ({}, {})",
-                tx, ty
-            ));
-        }
+    let mut raw_tail = Vec::new();
+    let mut total_present_count = 0u16;
+    for (name, tiles) in sections {
+        let presence_len = (tile_count as usize).div_ceil(8);
+        let mut presence = vec![0u8; presence_len];
+        let mut payload = Vec::new();
+        let mut present_count = 0u16;
+        let mut tile_blobs = vec![None; tile_count as usize];
+
+        for tile in tiles {
+            let tx = tile.x as i64;
+            let ty = tile.y as i64;
+            if tx < x0 as i64 || tx > x1 || ty < y0 as i64 || ty > y1 {
+                return Err(format!(
+                    "Tile ({}, {}) is outside query bounds x=[{}..{}], y=[{}..{}]",
+                    tx, ty, x0, x1, y0, y1
+                ));
+            }
+
+            let idx = ((ty - y0 as i64) as u32 * range_w + (tx - x0 as i64) as u32) as usize;
+            if tile_blobs[idx].is_some() {
+                return Err(format!(
+                    "Duplicate tile coordinates in input: ({}, {})",
+                    tx, ty
+                ));
+            }
 
-        assert_eq!(
-            tile.bitmap.width_exp(),
-            tile_bitmap_exp,
-            "TilePixelData bitmap width_exp mismatch: expected {}, got {}",
-            tile_bitmap_exp,
-            tile.bitmap.width_exp()
-        );
+            assert_eq!(
+                tile.bitmap.width_exp(),
+                tile_bitmap_exp,
+                "TilePixelData bitmap width_exp mismatch: expected {}, got {}",
+                tile_bitmap_exp,
+                tile.bitmap.width_exp()
+            );
 
-        if tile.bitmap.is_empty() {
-            continue;
+            if tile.bitmap.is_empty() {
+                if is_delta {
+                    tile_blobs[idx] = Some(serialize_mipmap(&[]));
+                }
+                continue;
+            }
+            let mut bm = tile.bitmap;
+            bm.build_lods();
+            let levels = bm.into_all_levels();
+            tile_blobs[idx] = Some(serialize_mipmap(&levels));
         }
-        let mut bm = tile.bitmap;
-        bm.build_lods();
-        let levels = bm.into_all_levels();
-        tile_blobs[idx] = Some(serialize_mipmap(&levels));
-    }
 
-    for (idx, tile_blob) in tile_blobs.into_iter().enumerate() {
-        if let Some(blob) = tile_blob {
-            set_lsb_bit(&mut presence, idx, true);
-            payload.extend_from_slice(&blob);
-            present_count = present_count
-                .checked_add(1)
-                .ok_or_else(|| "present_count overflow".to_string())?;
+        for (idx, tile_blob) in tile_blobs.into_iter().enumerate() {
+            if let Some(blob) = tile_blob {
+                set_lsb_bit(&mut presence, idx, true);
+                payload.extend_from_slice(&blob);
+                present_count = present_count
+                    .checked_add(1)
+                    .ok_or_else(|| "present_count overflow".to_string())?;
+            }
+        }
+        total_present_count = total_present_count
+            .checked_add(present_count)
+            .ok_or_else(|| "present_count overflow".to_string())?;
+
+        if let Some(name) = name {
+            raw_tail.push(name.len() as u8);
+            raw_tail.extend_from_slice(name.as_bytes());
+            raw_tail.extend_from_slice(&present_count.to_le_bytes());
         }
+        raw_tail.extend_from_slice(&presence);
+        raw_tail.extend_from_slice(&payload);
     }
 
-    let mut raw_tail = Vec::with_capacity(presence.len() + payload.len());
-    raw_tail.extend_from_slice(&presence);
-    raw_tail.extend_from_slice(&payload);
     let encoded_tail = compress_tile_range_tail(&raw_tail, compression)
         .map_err(|e| format!("Failed to encode TileRangeResponse tail: {e}"))?;
 
-    let mut out = Vec::with_capacity(TILE_RANGE_HEADER_SIZE + encoded_tail.len());
+    let mut out = Vec::with_capacity(TILE_RANGE_LAYERED_HEADER_SIZE + encoded_tail.len());
     out.push(tile_bitmap_exp);
     out.push(z);
     out.push(compression);
-    out.push(0);
+    out.push(flags);
     out.extend_from_slice(&x0.to_le_bytes());
     out.extend_from_slice(&y0.to_le_bytes());
     out.extend_from_slice(&(range_w as u16).to_le_bytes());
     out.extend_from_slice(&(range_h as u16).to_le_bytes());
     out.extend_from_slice(&(tile_count as u16).to_le_bytes());
-    out.extend_from_slice(&present_count.to_le_bytes());
+    out.extend_from_slice(&total_present_count.to_le_bytes());
+    if flags & TILE_RANGE_FLAG_LAYERED != 0 {
+        out.extend_from_slice(&layer_mask.to_le_bytes());
+    }
     out.extend_from_slice(&encoded_tail);
     Ok(out)
 }
 
+/// Sets `TILE_RANGE_FLAG_LOADING` on an encoded response (full, delta or
+/// layered), the header is never compressed.
+pub fn mark_tile_range_loading(data: &mut [u8]) -> Result<(), String> {
+    parse_tile_range_header(data)?;
+    data[3] |= TILE_RANGE_FLAG_LOADING;
+    Ok(())
+}
+
 pub fn parse_tile_range_header(data: &[u8]) -> Result<TileRangeHeader, String> {
     if data.len() < TILE_RANGE_HEADER_SIZE {
         return Err("TileRangeResponse too small".to_string());
     }
 
-    let header = TileRangeHeader {
+    let mut header = TileRangeHeader {
         tile_bitmap_exp: data[0],
         z: data[1],
         compression: data[2],
+        flags: data[3],
         x0: i32::from_le_bytes([data[4], data[5], data[6], data[7]]),
         y0: i32::from_le_bytes([data[8], data[9], data[10], data[11]]),
         range_w: u16::from_le_bytes([data[12], data[13]]),
         range_h: u16::from_le_bytes([data[14], data[15]]),
         tile_count: u16::from_le_bytes([data[16], data[17]]),
         present_count: u16::from_le_bytes([data[18], data[19]]),
+        layer_mask: 0,
     };
+    if header.is_layered() {
+        let layer_mask = data
+            .get(TILE_RANGE_HEADER_SIZE..TILE_RANGE_LAYERED_HEADER_SIZE)
+            .ok_or_else(|| "TileRangeResponse too small for layer_mask".to_string())?;
+        header.layer_mask = u64::from_le_bytes(layer_mask.try_into().unwrap());
+    }
 
     if header.range_w as usize * header.range_h as usize != header.tile_count as usize {
         return Err("Tile count mismatch".to_string());
@@ -201,11 +435,11 @@ pub fn decompress_tile_range_response(data: &[u8]) -> Result<Vec<u8>, String> {
         return Ok(data.to_vec());
     }
     let encoded_tail = data
-        .get(TILE_RANGE_HEADER_SIZE..)
+        .get(header.size()..)
         .ok_or_else(|| "Missing TileRangeResponse body".to_string())?;
     let raw_tail = decompress_tile_range_tail(encoded_tail, header.compression)?;
-    let mut normalized = Vec::with_capacity(TILE_RANGE_HEADER_SIZE + raw_tail.len());
-    normalized.extend_from_slice(&data[..TILE_RANGE_HEADER_SIZE]);
+    let mut normalized = Vec::with_capacity(header.size() + raw_tail.len());
+    normalized.extend_from_slice(&data[..header.size()]);
     normalized[2] = FTA_COMPRESSION_NONE;
     normalized.extend_from_slice(&raw_tail);
     Ok(normalized)
@@ -221,6 +455,88 @@ pub fn parse_tiles_from_body(
     present_count: usize,
     body: &[u8],
 ) -> Result<Vec<(i32, i32, BitMap2D)>, String> {
+    parse_tile_blobs_from_body(
+        tile_bitmap_exp,
+        x_origin,
+        y_origin,
+        range_w,
+        tile_count,
+        present_count,
+        false,
+        body,
+    )?
+    .into_iter()
+    .map(|(x, y, bitmap)| {
+        bitmap
+            .map(|bitmap| (x, y, bitmap))
+            .ok_or_else(|| "Empty tile in a non-delta TileRangeResponse".to_string())
+    })
+    .collect()
+}
+
+/// Parses the changed tiles from an already decompressed delta
+/// TileRangeResponse body, `None` means the tile became empty.
+pub fn parse_tile_delta_from_body(
+    tile_bitmap_exp: u8,
+    x_origin: i32,
+    y_origin: i32,
+    range_w: usize,
+    tile_count: usize,
+    present_count: usize,
+    body: &[u8],
+) -> Result<Vec<ChangedTile>, String> {
+    parse_tile_blobs_from_body(
+        tile_bitmap_exp,
+        x_origin,
+        y_origin,
+        range_w,
+        tile_count,
+        present_count,
+        true,
+        body,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn parse_tile_blobs_from_body(
+    tile_bitmap_exp: u8,
+    x_origin: i32,
+    y_origin: i32,
+    range_w: usize,
+    tile_count: usize,
+    present_count: usize,
+    is_delta: bool,
+    body: &[u8],
+) -> Result<Vec<ChangedTile>, String> {
+    let (tiles, len) = parse_tile_section(
+        tile_bitmap_exp,
+        x_origin,
+        y_origin,
+        range_w,
+        tile_count,
+        present_count,
+        is_delta,
+        body,
+    )?;
+    if len != body.len() {
+        return Err("Unexpected trailing bytes in TileRangeResponse".to_string());
+    }
+    Ok(tiles)
+}
+
+/// Parses a presence bitmap and the mipmap blobs following it, returning the
+/// tiles and the length of the section.
+#[allow(clippy::too_many_arguments)]
+fn parse_tile_section(
+    tile_bitmap_exp: u8,
+    x_origin: i32,
+    y_origin: i32,
+    range_w: usize,
+    tile_count: usize,
+    present_count: usize,
+    is_delta: bool,
+    body: &[u8],
+) -> Result<(Vec<ChangedTile>, usize), String> {
     let presence_len = tile_count.div_ceil(8);
     if body.len() < presence_len {
         return Err("TileRangeResponse body too small for presence bitmap".to_string());
@@ -239,11 +555,15 @@ pub fn parse_tiles_from_body(
             let blob_len = parse_mipmap_blob_len(mipmap_blob)?;
             let blob = &mipmap_blob[..blob_len];
             let levels = deserialize_mipmap(blob)?;
-            validate_leaf_mipmap_levels(tile_bitmap_exp, &levels)?;
             offset += blob_len;
-            let base = levels[0].clone();
-            let lods = levels[1..].to_vec();
-            let bitmap = BitMap2D::from_precomputed(tile_bitmap_exp, base, lods);
+            let bitmap = if is_delta && levels.is_empty() {
+                None
+            } else {
+                validate_leaf_mipmap_levels(tile_bitmap_exp, &levels)?;
+                let base = levels[0].clone();
+                let lods = levels[1..].to_vec();
+                Some(BitMap2D::from_precomputed(tile_bitmap_exp, base, lods))
+            };
             let x = x_origin + (idx % range_w) as i32;
             let y = y_origin + (idx / range_w) as i32;
             out.push((x, y, bitmap));
@@ -254,15 +574,59 @@ pub fn parse_tiles_from_body(
     if seen_present != present_count {
         return Err("present_count does not match presence bitmap".to_string());
     }
-    if offset != payload.len() {
+    Ok((out, presence_len + offset))
+}
+
+/// Parses the layers from an already decompressed layered TileRangeResponse
+/// body.
+pub fn parse_tile_layers_from_body(
+    header: &TileRangeHeader,
+    body: &[u8],
+) -> Result<Vec<TileRangeLayer>, String> {
+    let mut layers = Vec::new();
+    let mut offset = 0usize;
+    let mut total_present_count = 0usize;
+    for id in (0..TILE_RANGE_MAX_LAYERS).filter(|id| header.layer_mask & (1 << id) != 0) {
+        let truncated = || "Truncated TileRangeResponse layer".to_string();
+        let name_len = *body.get(offset).ok_or_else(truncated)? as usize;
+        let name = body
+            .get(offset + 1..offset + 1 + name_len)
+            .ok_or_else(truncated)?;
+        let name = String::from_utf8(name.to_vec())
+            .map_err(|_| "Invalid TileRangeResponse layer name".to_string())?;
+        offset += 1 + name_len;
+        let present_count = body.get(offset..offset + 2).ok_or_else(truncated)?;
+        let present_count = u16::from_le_bytes([present_count[0], present_count[1]]) as usize;
+        offset += 2;
+        let (tiles, len) = parse_tile_section(
+            header.tile_bitmap_exp,
+            header.x0,
+            header.y0,
+            header.range_w as usize,
+            header.tile_count as usize,
+            present_count,
+            header.is_delta(),
+            &body[offset..],
+        )?;
+        offset += len;
+        total_present_count += present_count;
+        layers.push(TileRangeLayer { id, name, tiles });
+    }
+    if total_present_count != header.present_count as usize {
+        return Err("present_count does not match the layers".to_string());
+    }
+    if offset != body.len() {
         return Err("Unexpected trailing bytes in TileRangeResponse".to_string());
     }
-    Ok(out)
+    Ok(layers)
 }
 
 pub fn decode_tile_range_response(data: &[u8]) -> Result<Vec<(i32, i32, BitMap2D)>, String> {
     let decompressed = decompress_tile_range_response(data)?;
     let header = parse_tile_range_header(&decompressed)?;
+    if header.is_delta() || header.is_layered() {
+        return Err("Expected a full TileRangeResponse without layers".to_string());
+    }
     let body = decompressed
         .get(TILE_RANGE_HEADER_SIZE..)
         .ok_or_else(|| "Missing TileRangeResponse body".to_string())?;
@@ -277,6 +641,42 @@ pub fn decode_tile_range_response(data: &[u8]) -> Result<Vec<(i32, i32, BitMap2D
     )
 }
 
+/// Decodes a delta TileRangeResponse into its header and the changed tiles.
+pub fn decode_tile_range_delta(data: &[u8]) -> Result<(TileRangeHeader, Vec<ChangedTile>), String> {
+    let decompressed = decompress_tile_range_response(data)?;
+    let header = parse_tile_range_header(&decompressed)?;
+    if !header.is_delta() || header.is_layered() {
+        return Err("Expected a delta TileRangeResponse without layers".to_string());
+    }
+    let body = decompressed
+        .get(TILE_RANGE_HEADER_SIZE..)
+        .ok_or_else(|| "Missing TileRangeResponse body".to_string())?;
+    let tiles = parse_tile_delta_from_body(
+        header.tile_bitmap_exp,
+        header.x0,
+        header.y0,
+        header.range_w as usize,
+        header.tile_count as usize,
+        header.present_count as usize,
+        body,
+    )?;
+    Ok((header, tiles))
+}
+
+/// Decodes a layered TileRangeResponse (full or delta) into its header and
+/// layers.
+pub fn decode_tile_range_layers(
+    data: &[u8],
+) -> Result<(TileRangeHeader, Vec<TileRangeLayer>), String> {
+    let decompressed = decompress_tile_range_response(data)?;
+    let header = parse_tile_range_header(&decompressed)?;
+    if !header.is_layered() {
+        return Err("Expected a layered TileRangeResponse".to_string());
+    }
+    let layers = parse_tile_layers_from_body(&header, &decompressed[header.size()..])?;
+    Ok((header, layers))
+}
+
 /// Compresses the response tail (presence bitmap + mipmap payload).
 pub fn compress_tile_range_tail(raw_tail: &[u8], compression: u8) -> Result<Vec<u8>, String> {
     match compression {
@@ -23,6 +23,7 @@ declare global {
     onMapMoved?: FlutterMessageChannel;
     onMapViewChanged?: FlutterMessageChannel;
     onMapZoomChanged?: FlutterMessageChannel;
+    onJourneyLoadingChanged?: FlutterMessageChannel;
     trySetup?: () => Promise<void>;
     updateLocationMarker?: (
       lng: number,
@@ -73,6 +74,15 @@ export class FlutterBridge {
     }
   }
 
+  /**
+   * Notify Flutter whether the journey data is still loading
+   */
+  notifyJourneyLoadingChanged(loading: boolean): void {
+    if (window.onJourneyLoadingChanged) {
+      window.onJourneyLoadingChanged.postMessage(loading ? "true" : "false");
+    }
+  }
+
   /**
    * Get the underlying map instance
    */
@@ -138,6 +148,17 @@ export class FlutterBridge {
     this.map.on("idle", () => {
       this.notifyMapViewChanged();
     });
+
+    // Notify Flutter when the journey data starts or finishes loading, the
+    // first data may have been fetched before this
+    window.addEventListener("journeyLoadingChanged", (event: Event) => {
+      const { loading } = (event as CustomEvent<{ loading: boolean }>).detail;
+      this.notifyJourneyLoadingChanged(loading);
+    });
+    const tileProvider = this.mapController.getTileProvider();
+    if (tileProvider?.loading) {
+      this.notifyJourneyLoadingChanged(true);
+    }
   }
 
   /**
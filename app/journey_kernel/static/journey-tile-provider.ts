@@ -32,6 +32,8 @@ interface TileBufferRequestParams {
   height: number;
   buffer_size_power: number;
   cached_version?: string;
+  mode?: "delta";
+  layers?: 1;
 }
 
 /**
@@ -69,8 +71,15 @@ export class JourneyTileProvider {
   private viewRange: ViewRange | null; // Store the current viewport tile range [x, y, w, h, z]
   // TODO: evaluate whether we need to make this public (also the bufferSizePower)
   tileBuffer: TileBuffer | null; // Store the tile buffer data
+  // The [x, y, w, h, z, bufferSizePower, layers] tileBuffer covers, deltas only apply to the same range
+  private tileBufferRange: number[] | null;
+  // Ask for tiles split by layer (journey kind), see `TileBuffer.get_layer_tile_pixels`
+  requestLayers: boolean;
   private viewRangeUpdated: boolean; // Flag indicating view range has been updated
   private downloadInProgress: boolean; // Flag indicating download is in progress
+  // The server is still loading the journeys, tileBuffer may be a low-resolution overview
+  loading: boolean;
+  private loadingPollTimeoutId: ReturnType<typeof setTimeout> | null;
   bufferSizePower: number;
   private isGlobeProjection: boolean; // Flag indicating if globe projection is used
   private tileBufferCallbacks: TileBufferCallback[]; // Array to store tile buffer update callbacks
@@ -86,8 +95,12 @@ export class JourneyTileProvider {
     this.currentVersion = null;
     this.viewRange = null;
     this.tileBuffer = null;
+    this.tileBufferRange = null;
+    this.requestLayers = false;
     this.viewRangeUpdated = false;
     this.downloadInProgress = false;
+    this.loading = false;
+    this.loadingPollTimeoutId = null;
 
     this.bufferSizePower = this.getBufferSizePowerFromRenderMode(
       params.renderMode,
@@ -264,9 +277,29 @@ export class JourneyTileProvider {
       height: h,
       buffer_size_power: this.bufferSizePower,
     };
+    if (this.requestLayers) {
+      requestParams.layers = 1;
+    }
 
+    const range = [
+      x,
+      y,
+      w,
+      h,
+      z,
+      this.bufferSizePower,
+      this.requestLayers ? 1 : 0,
+    ];
     if (!forceUpdate && this.currentVersion) {
       requestParams.cached_version = this.currentVersion;
+      // Only the changed tiles are needed if we already have this range
+      if (
+        this.tileBuffer &&
+        this.tileBufferRange &&
+        this.tileBufferRange.every((value, i) => value === range[i])
+      ) {
+        requestParams.mode = "delta";
+      }
     }
 
     let tileBufferUpdated = false;
@@ -318,12 +351,20 @@ export class JourneyTileProvider {
       // Consider moving this into a web worker so that it won't block the main thread.
       // TODO: remove this number
       const LEVEL0_EXP = 9; // is it reasonable?
-      this.tileBuffer = TileBuffer.new_from_tile_range_response(
-        LEVEL0_EXP,
-        bytes,
-      );
-
-      console.log(`Tile buffer fetched and deserialized successfully`);
+      if (
+        rawResponse.headers.get("X-Tile-Delta") === "true" &&
+        this.tileBuffer
+      ) {
+        this.tileBuffer.apply_tile_range_delta(bytes);
+        console.log(`Tile buffer patched with delta successfully`);
+      } else {
+        this.tileBuffer = TileBuffer.new_from_tile_range_response(
+          LEVEL0_EXP,
+          bytes,
+        );
+        this.tileBufferRange = range;
+        console.log(`Tile buffer fetched and deserialized successfully`);
+      }
 
       this.notifyTileBufferReady(
         x,
@@ -336,8 +377,11 @@ export class JourneyTileProvider {
       );
 
       tileBufferUpdated = true;
+      this.updateLoading(this.tileBuffer.is_loading());
     } catch (error) {
       console.error("Error fetching or deserializing tile buffer:", error);
+      // The tile buffer may no longer match currentVersion, fetch it in full next time
+      this.currentVersion = null;
     } finally {
       this.downloadInProgress = false;
 
@@ -351,4 +395,20 @@ export class JourneyTileProvider {
 
     return tileBufferUpdated;
   }
+
+  private updateLoading(loading: boolean): void {
+    if (loading !== this.loading) {
+      this.loading = loading;
+      window.dispatchEvent(
+        new CustomEvent("journeyLoadingChanged", { detail: { loading } }),
+      );
+    }
+    // Keep fetching while the server streams in the rest, even without auto refresh
+    if (loading && this.loadingPollTimeoutId === null) {
+      this.loadingPollTimeoutId = setTimeout(() => {
+        this.loadingPollTimeoutId = null;
+        this.pollForJourneyUpdates(false);
+      }, 1000);
+    }
+  }
 }
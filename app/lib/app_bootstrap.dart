@@ -56,8 +56,19 @@ void delayedInit(UpdateNotifier updateNotifier) {
             version: packageInfo.version,
             buildNumber: packageInfo.buildNumber));
 
+    // Road graph for map matching
+    final roadGraphPath =
+        MMKVUtil.getStringOpt(MMKVKey.mapMatchingRoadGraphPath);
+    if (roadGraphPath != null) {
+      try {
+        await api.setMapMatchingRoadGraph(filePath: roadGraphPath);
+      } catch (e) {
+        log.error("[delayedInit] Failed to load road graph: $e");
+      }
+    }
+
     // Db optimization check
-    const currentOptimizationCheckVersion = 1;
+    const currentOptimizationCheckVersion = 2;
     final dbOptimizeCheck = MMKVUtil.getInt(MMKVKey.dbOptimizationCheck);
     if (dbOptimizeCheck < currentOptimizationCheckVersion) {
       if (await api.mainDbRequireOptimization()) {
@@ -72,6 +83,23 @@ void delayedInit(UpdateNotifier updateNotifier) {
       }
     }
 
+    // Recording journal recovery
+    final recoveryReport = api.getRecordingRecoveryReport();
+    if (recoveryReport.recoveredPoints > 0 ||
+        recoveryReport.finalizeInterrupted) {
+      var context = navigatorKey.currentState?.context;
+      if (context != null && context.mounted) {
+        final messages = [
+          if (recoveryReport.recoveredPoints > 0)
+            context.tr("recording_health.recovered_points",
+                args: [recoveryReport.recoveredPoints.toString()]),
+          if (recoveryReport.finalizeInterrupted)
+            context.tr("recording_health.finalize_interrupted"),
+        ];
+        await showCommonDialog(context, messages.join("\n\n"));
+      }
+    }
+
     doRepeatWork() async {}
 
     await doRepeatWork();
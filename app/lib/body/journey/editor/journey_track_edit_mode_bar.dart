@@ -24,6 +24,8 @@ class ModeSwitchBar extends StatelessWidget {
   final ValueChanged<OperationMode> onModeChanged;
   final bool canUndo;
   final VoidCallback? onUndo;
+  final bool canRedo;
+  final VoidCallback? onRedo;
   final bool canSave;
   final VoidCallback? onSave;
 
@@ -33,6 +35,8 @@ class ModeSwitchBar extends StatelessWidget {
     required this.onModeChanged,
     this.canUndo = false,
     this.onUndo,
+    this.canRedo = false,
+    this.onRedo,
     this.canSave = false,
     this.onSave,
   });
@@ -61,6 +65,12 @@ class ModeSwitchBar extends StatelessWidget {
                 isEnabled: canUndo,
                 onTap: onUndo,
               ),
+              _buildActionButton(
+                icon: Icons.redo_rounded,
+                label: context.tr('journey.editor.redo'),
+                isEnabled: canRedo,
+                onTap: onRedo,
+              ),
               _buildActionButton(
                 icon: Icons.save,
                 label: context.tr('journey.editor.save'),
@@ -10,6 +10,7 @@ import 'package:memolanes/src/rust/api/api.dart' as api;
 import 'package:memolanes/src/rust/api/utils.dart';
 import 'package:memolanes/common/utils.dart';
 import 'package:memolanes/src/rust/journey_header.dart';
+import 'package:memolanes/src/rust/main_db.dart';
 import 'package:memolanes/utils/nav_helper.dart';
 
 class JourneyBody extends StatefulWidget {
@@ -25,8 +26,11 @@ class _JourneyBodyState extends State<JourneyBody> {
   static const _landscapeCalendarMinWidth = 320.0;
   static const _landscapeCalendarMaxWidth = 360.0;
   static const _landscapeListMinWidth = 280.0;
+  static const _searchResultLimit = 200;
 
   List<JourneyHeader> _journeyHeaderList = [];
+  final _searchController = TextEditingController();
+  String _searchText = '';
 
   DateTime _selectedDate = DateTime.now();
   late final DateTime? _firstDate;
@@ -43,6 +47,12 @@ class _JourneyBodyState extends State<JourneyBody> {
     _updateJourneyHeaderList();
   }
 
+  @override
+  void dispose() {
+    _searchController.dispose();
+    super.dispose();
+  }
+
   Future<void> _initialize() async {
     NaiveDate? earliestDate = await api.earliestJourneyDate();
     if (earliestDate != null) {
@@ -62,16 +72,70 @@ class _JourneyBodyState extends State<JourneyBody> {
   }
 
   void _updateJourneyHeaderList() async {
-    final journeyHeaderList = await api.listJourneyOnDate(
-        year: _selectedDate.year,
-        month: _selectedDate.month,
-        day: _selectedDate.day);
+    final List<JourneyHeader> journeyHeaderList;
+    if (_searchText.isEmpty) {
+      final journeysOnDate = await api.listJourneyOnDate(
+          year: _selectedDate.year,
+          month: _selectedDate.month,
+          day: _selectedDate.day);
+      journeyHeaderList = journeysOnDate.reversed.toList();
+    } else {
+      // words starting with `#` are tags, the rest is looked up in notes
+      final words = _searchText.split(RegExp(r'\s+'));
+      final tags = words
+          .where((word) => word.length > 1 && word.startsWith('#'))
+          .map((word) => word.substring(1))
+          .toList();
+      final text =
+          words.where((word) => !word.startsWith('#')).join(' ').trim();
+      journeyHeaderList = await api.searchJourneys(
+        query: JourneySearchQuery(
+          tags: tags,
+          text: text.isEmpty ? null : text,
+          kinds: const [],
+          offset: 0,
+          limit: _searchResultLimit,
+        ),
+      );
+    }
     if (!mounted) return;
     setState(() {
-      _journeyHeaderList = journeyHeaderList.reversed.toList();
+      _journeyHeaderList = journeyHeaderList;
     });
   }
 
+  void _search(String searchText) {
+    setState(() => _searchText = searchText.trim());
+    _updateJourneyHeaderList();
+  }
+
+  Widget _buildSearchField() {
+    return Padding(
+      padding: const EdgeInsets.symmetric(horizontal: 16.0),
+      child: TextField(
+        controller: _searchController,
+        textInputAction: TextInputAction.search,
+        onSubmitted: _search,
+        decoration: InputDecoration(
+          prefixIcon: const Icon(Icons.search),
+          suffixIcon: _searchText.isEmpty
+              ? null
+              : IconButton(
+                  icon: const Icon(Icons.clear),
+                  onPressed: () {
+                    _searchController.clear();
+                    _search('');
+                  },
+                ),
+          hintText: context.tr("journey.search_hint"),
+          hintStyle: TextStyle(
+            fontSize: 14.0,
+          ),
+        ),
+      ),
+    );
+  }
+
   Widget _buildDatePickerWithValue(DateTime firstDate) {
     final config = CalendarDatePicker2Config(
       firstDate: firstDate,
@@ -254,7 +318,13 @@ class _JourneyBodyState extends State<JourneyBody> {
               ),
               const SizedBox(width: _landscapeColumnGap),
               Expanded(
-                child: _buildJourneyHeaderList(),
+                child: Column(
+                  crossAxisAlignment: CrossAxisAlignment.stretch,
+                  children: [
+                    _buildSearchField(),
+                    Expanded(child: _buildJourneyHeaderList()),
+                  ],
+                ),
               ),
             ],
           ),
@@ -280,7 +350,9 @@ class _JourneyBodyState extends State<JourneyBody> {
       return Column(
         crossAxisAlignment: CrossAxisAlignment.stretch,
         children: [
-          _buildDatePickerWithValue(firstDate),
+          _buildSearchField(),
+          // search results are not limited to the selected date
+          if (_searchText.isEmpty) _buildDatePickerWithValue(firstDate),
           const SizedBox(height: 16.0),
           Expanded(child: _buildJourneyHeaderList()),
         ],
@@ -11,6 +11,7 @@ import 'package:memolanes/common/component/scroll_views/single_child_scroll_view
 import 'package:memolanes/common/component/tiles/label_tile.dart';
 import 'package:memolanes/common/component/tiles/label_tile_content.dart';
 import 'package:memolanes/common/utils.dart';
+import 'package:memolanes/src/rust/api/api.dart' as api;
 import 'package:memolanes/src/rust/api/import.dart' as import_api;
 import 'package:memolanes/src/rust/api/utils.dart';
 import 'package:memolanes/src/rust/journey_header.dart';
@@ -51,7 +52,7 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
   DateTime? _endTime;
   DateTime? _journeyDate;
   String? _note;
-  JourneyKind _journeyKind = JourneyKind.defaultKind;
+  JourneyKind _journeyKind = const JourneyKind.defaultKind();
   final TextEditingController _noteController = TextEditingController();
   late import_api.ImportPreprocessor _preprocessor;
 
@@ -219,6 +220,8 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
                           context.tr("preprocessor.flightTrack"),
                         import_api.ImportPreprocessor.spare =>
                           context.tr("preprocessor.spare"),
+                        import_api.ImportPreprocessor.mapMatching =>
+                          context.tr("preprocessor.mapMatching"),
                       },
                       showArrow: true,
                     ),
@@ -228,9 +231,12 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
             label: context.tr("journey.journey_kind"),
             position: LabelTilePosition.single,
             trailing: LabelTileContent(
-                content: _journeyKind == JourneyKind.defaultKind
-                    ? context.tr("journey_kind.default")
-                    : context.tr("journey_kind.flight"),
+                content: switch (_journeyKind) {
+                  JourneyKind_DefaultKind() =>
+                    context.tr("journey_kind.default"),
+                  JourneyKind_Flight() => context.tr("journey_kind.flight"),
+                  JourneyKind_Custom(:final field0) => field0,
+                },
                 showArrow: true),
             onTap: () => _showJourneyKindCard(context),
           ),
@@ -276,6 +282,7 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
   }
 
   void _showJourneyKindCard(BuildContext context) {
+    final customKinds = api.getCustomJourneyKinds();
     showBasicCard(
       context,
       child: OptionCard(
@@ -285,25 +292,76 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
             label: context.tr("journey_kind.default"),
             onTap: () {
               setState(() {
-                _journeyKind = JourneyKind.defaultKind;
+                _journeyKind = const JourneyKind.defaultKind();
               });
             },
             top: false,
           ),
           CardLabelTile(
-            position: CardLabelTilePosition.bottom,
+            position: CardLabelTilePosition.middle,
             label: context.tr("journey_kind.flight"),
             onTap: () {
               setState(() {
-                _journeyKind = JourneyKind.flight;
+                _journeyKind = const JourneyKind.flight();
               });
             },
           ),
+          for (final name in customKinds)
+            CardLabelTile(
+              position: CardLabelTilePosition.middle,
+              label: name,
+              onTap: () {
+                setState(() {
+                  _journeyKind = JourneyKind.custom(name);
+                });
+              },
+            ),
+          CardLabelTile(
+            position: CardLabelTilePosition.bottom,
+            label: context.tr("journey_kind.new_custom"),
+            onTap: () => _showNewCustomJourneyKindDialog(context),
+          ),
         ],
       ),
     );
   }
 
+  void _showNewCustomJourneyKindDialog(BuildContext context) async {
+    final controller = TextEditingController();
+    final name = await showDialog<String>(
+      context: context,
+      builder: (BuildContext context) {
+        return AlertDialog(
+          title: Text(context.tr("journey_kind.new_custom")),
+          content: TextField(
+            controller: controller,
+            autofocus: true,
+            maxLength: 32,
+            decoration: InputDecoration(
+              hintText: context.tr("journey_kind.custom_name_hint"),
+            ),
+          ),
+          actions: [
+            TextButton(
+              onPressed: () => Navigator.of(context).pop(),
+              child: Text(context.tr("common.cancel")),
+            ),
+            TextButton(
+              onPressed: () => Navigator.of(context).pop(controller.text),
+              child: Text(context.tr("common.save")),
+            ),
+          ],
+        );
+      },
+    );
+    controller.dispose();
+    final trimmed = name?.trim() ?? "";
+    if (trimmed.isEmpty) return;
+    setState(() {
+      _journeyKind = JourneyKind.custom(trimmed);
+    });
+  }
+
   void _selectPreprocessor(import_api.ImportPreprocessor processor) {
     setState(() {
       _preprocessor = processor;
@@ -345,6 +403,14 @@ class _JourneyInfoEditPageState extends State<JourneyInfoEditPage> {
               _selectPreprocessor(import_api.ImportPreprocessor.spare);
             },
           ),
+          if (api.hasMapMatchingRoadGraph())
+            CardLabelTile(
+              position: CardLabelTilePosition.middle,
+              label: context.tr("preprocessor.mapMatching"),
+              onTap: () {
+                _selectPreprocessor(import_api.ImportPreprocessor.mapMatching);
+              },
+            ),
         ],
       ),
     );
@@ -84,11 +84,8 @@ class _JourneyInfoPage extends State<JourneyInfoPage> {
       return;
     }
 
-    final allJourneys = await api.listAllJourneys();
-    final latestHeader = allJourneys
-        .where((j) => j.id == _journeyHeader.id)
-        .cast<JourneyHeader?>()
-        .firstOrNull;
+    final latestHeader =
+        await api.getJourneyHeader(journeyId: _journeyHeader.id);
 
     if (!mounted) return;
     setState(() {
@@ -102,7 +99,7 @@ class _JourneyInfoPage extends State<JourneyInfoPage> {
 
   Future<void> _deleteJourneyInfo(BuildContext context) async {
     if (await showCommonDialog(
-        context, context.tr("journey.delete_journey_message"),
+        context, context.tr("journey.trash_journey_message"),
         hasCancel: true,
         title: context.tr("journey.delete_journey_title"),
         confirmButtonText: context.tr("common.delete"),
@@ -147,13 +144,6 @@ class _JourneyInfoPage extends State<JourneyInfoPage> {
   Future<void> _trackEdit(BuildContext context) async {
     final session = await EditSession.newInstance(journeyId: _journeyHeader.id);
     if (!context.mounted) return;
-    if (session == null) {
-      await showCommonDialog(
-        context,
-        context.tr("journey.editor.bitmap_not_supported"),
-      );
-      return;
-    }
     await navigatorPush(
       context,
       page: JourneyTrackEditPage(editSession: session),
@@ -206,8 +196,9 @@ class _JourneyInfoPage extends State<JourneyInfoPage> {
       bottomOverlayHeight: _panelMaxHeight(context),
     );
     final journeyKindName = switch (_journeyHeader.journeyKind) {
-      JourneyKind.defaultKind => context.tr("journey_kind.default"),
-      JourneyKind.flight => context.tr("journey_kind.flight"),
+      JourneyKind_DefaultKind() => context.tr("journey_kind.default"),
+      JourneyKind_Flight() => context.tr("journey_kind.flight"),
+      JourneyKind_Custom(:final field0) => field0,
     };
     return Scaffold(
       body: Stack(
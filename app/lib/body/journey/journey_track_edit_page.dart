@@ -38,6 +38,7 @@ class _JourneyTrackEditPageState extends State<JourneyTrackEditPage> {
 
   OperationMode _mode = OperationMode.move;
   bool _canUndo = false;
+  bool _canRedo = false;
   bool _isLinkedDrawEnabled = false;
   String? _linkedDrawErrorTrKey;
 
@@ -135,6 +136,7 @@ class _JourneyTrackEditPageState extends State<JourneyTrackEditPage> {
         _mapRendererProxy = rendererProxy;
         _initialMapBounds = bounds;
         _canUndo = _editSession.canUndo();
+        _canRedo = _editSession.canRedo();
       });
     } catch (e) {
       log.error("[JourneyTrackEditPage] Load map error: $e");
@@ -145,9 +147,11 @@ class _JourneyTrackEditPageState extends State<JourneyTrackEditPage> {
 
   Future<void> _refreshCanUndo() async {
     final canUndo = _editSession.canUndo();
+    final canRedo = _editSession.canRedo();
     if (!mounted) return;
     setState(() {
       _canUndo = canUndo;
+      _canRedo = canRedo;
     });
   }
 
@@ -470,6 +474,13 @@ class _JourneyTrackEditPageState extends State<JourneyTrackEditPage> {
                     await _mapWebviewKey.currentState?.manualRefresh();
                     _refreshCanUndo();
                   },
+                  canRedo: _canRedo,
+                  onRedo: () async {
+                    await _editSession.redo();
+                    if (!mounted) return;
+                    await _mapWebviewKey.currentState?.manualRefresh();
+                    _refreshCanUndo();
+                  },
                   canSave: _canUndo,
                   onSave: () async {
                     if (!_canUndo) {
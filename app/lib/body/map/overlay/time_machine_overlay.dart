@@ -10,14 +10,16 @@ import 'package:memolanes/common/utils.dart';
 /// Initial layer selection for time machine: ensure at least default kind (from main map filter).
 Set<JourneyKind> _initialJourneyKindsFromMainMap() {
   final f = api.getCurrentMainMapLayerFilter();
-  final defaultOn = f.defaultKind;
-  final flightOn = f.flightKind;
-  if (!defaultOn && !flightOn) return {JourneyKind.defaultKind};
-  if (defaultOn && flightOn) {
-    return {JourneyKind.defaultKind, JourneyKind.flight};
-  }
-  if (defaultOn) return {JourneyKind.defaultKind};
-  return {JourneyKind.flight};
+  final kinds = <JourneyKind>{
+    if (f.defaultKind) const JourneyKind.defaultKind(),
+    if (f.flightKind) const JourneyKind.flight(),
+    ...api
+        .getCustomJourneyKinds()
+        .where((name) => !f.hiddenCustomKinds.contains(name))
+        .map(JourneyKind.custom),
+  };
+  if (kinds.isEmpty) return {const JourneyKind.defaultKind()};
+  return kinds;
 }
 
 class TimeMachineOverlay extends StatefulWidget {
@@ -1,11 +1,13 @@
 import 'dart:io';
 
 import 'package:easy_localization/easy_localization.dart';
+import 'package:file_picker/file_picker.dart';
 import 'package:flutter/material.dart';
 import 'package:memolanes/common/component/common_export.dart';
 import 'package:memolanes/common/component/capsule_style_app_bar.dart';
 import 'package:memolanes/common/gps_manager.dart';
 import 'package:memolanes/body/settings/raw_data_page.dart';
+import 'package:memolanes/body/settings/trash_page.dart';
 import 'package:memolanes/common/component/scroll_views/single_child_scroll_view.dart';
 import 'package:memolanes/common/component/tiles/label_tile.dart';
 import 'package:memolanes/common/component/tiles/label_tile_content.dart';
@@ -28,11 +30,16 @@ class AdvancedSettingsPage extends StatefulWidget {
 
 class _AdvancedSettingsPageState extends State<AdvancedSettingsPage> {
   late Worldview _worldview;
+  bool _gapFilling = false;
+  bool _hasRoadGraph = api.hasMapMatchingRoadGraph();
 
   @override
   void initState() {
     super.initState();
     _worldview = WorldviewManager.instance.currentWorldview;
+    api.getGapFilling().then((value) {
+      if (mounted) setState(() => _gapFilling = value);
+    });
   }
 
   Future<void> _selectWorldview() async {
@@ -49,6 +56,60 @@ class _AdvancedSettingsPageState extends State<AdvancedSettingsPage> {
     setState(() => _worldview = result);
   }
 
+  // The picked file may be a temporary copy, so we keep our own.
+  Future<File> _roadGraphFile() async {
+    final supportDir = await getApplicationSupportDirectory();
+    return File("${supportDir.path}/map_matching_road_graph.g0");
+  }
+
+  Future<void> _selectRoadGraph() async {
+    if (_hasRoadGraph) {
+      if (!await showCommonDialog(
+        context,
+        context.tr("general.advanced_settings.map_matching_road_graph_remove"),
+        hasCancel: true,
+        confirmButtonText: context.tr("common.delete"),
+        confirmGroundColor: Colors.red,
+        confirmTextColor: Colors.white,
+      )) {
+        return;
+      }
+      await api.setMapMatchingRoadGraph(filePath: null);
+      MMKVUtil.removeAppKey(MMKVKey.mapMatchingRoadGraphPath);
+      final file = await _roadGraphFile();
+      if (await file.exists()) await file.delete();
+      if (mounted) setState(() => _hasRoadGraph = false);
+      return;
+    }
+
+    final result = await FilePicker.pickFiles(type: FileType.any);
+    final path = result?.files.single.path;
+    if (path == null) return;
+
+    final file = await _roadGraphFile();
+    try {
+      await showLoadingDialog(
+        asyncTask: (() async {
+          await File(path).copy(file.path);
+          await api.setMapMatchingRoadGraph(filePath: file.path);
+        })(),
+      );
+    } catch (e) {
+      if (await file.exists()) await file.delete();
+      if (!mounted) return;
+      await showCommonDialog(
+        context,
+        context.tr(
+          "general.advanced_settings.map_matching_road_graph_failed",
+          args: [e.toString()],
+        ),
+      );
+      return;
+    }
+    MMKVUtil.putString(MMKVKey.mapMatchingRoadGraphPath, file.path);
+    if (mounted) setState(() => _hasRoadGraph = true);
+  }
+
   @override
   Widget build(BuildContext context) {
     var gpsManager = context.watch<GpsManager>();
@@ -142,11 +203,39 @@ class _AdvancedSettingsPageState extends State<AdvancedSettingsPage> {
               );
             },
           ),
+          LabelTile(
+            label: context.tr("general.advanced_settings.trash.title"),
+            position: LabelTilePosition.middle,
+            onTap: () => navigatorPush(context, page: TrashPage()),
+          ),
           LabelTile(
             label: context.tr("general.advanced_settings.raw_data_mode"),
             position: LabelTilePosition.middle,
             onTap: () => navigatorPush(context, page: RawDataPage()),
           ),
+          LabelTile(
+            label: context.tr("general.advanced_settings.gap_filling"),
+            position: LabelTilePosition.middle,
+            trailing: Switch(
+              value: _gapFilling,
+              onChanged: (bool value) async {
+                await api.setGapFilling(enable: value);
+                setState(() => _gapFilling = value);
+              },
+            ),
+          ),
+          LabelTile(
+            label: context
+                .tr("general.advanced_settings.map_matching_road_graph"),
+            position: LabelTilePosition.middle,
+            trailing: LabelTileContent(
+              content: context.tr(_hasRoadGraph
+                  ? "general.advanced_settings.map_matching_road_graph_loaded"
+                  : "general.advanced_settings.map_matching_road_graph_none"),
+              showArrow: true,
+            ),
+            onTap: _selectRoadGraph,
+          ),
           LabelTile(
             label: context.tr("general.advanced_settings.rebuild_cache"),
             position: LabelTilePosition.middle,
@@ -117,7 +117,8 @@ class _RenderDiagnosticsPageState extends State<RenderDiagnosticsPage> {
             reasonPhrase: result.status == 200 ? 'OK' : 'Not Modified',
             headers: {
               'Access-Control-Allow-Origin': '*',
-              'Access-Control-Expose-Headers': 'X-Tile-Version, X-Not-Modified',
+              'Access-Control-Expose-Headers':
+                  'X-Tile-Version, X-Not-Modified, X-Tile-Delta, X-Tile-Layers',
               'Content-Type': result.contentType,
               ...result.headers,
             },
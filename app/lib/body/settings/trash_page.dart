@@ -0,0 +1,131 @@
+import 'package:easy_localization/easy_localization.dart';
+import 'package:flutter/material.dart';
+import 'package:memolanes/common/component/capsule_style_app_bar.dart';
+import 'package:memolanes/common/component/tiles/label_tile.dart';
+import 'package:memolanes/common/utils.dart';
+import 'package:memolanes/src/rust/api/api.dart' as api;
+import 'package:memolanes/src/rust/api/utils.dart';
+import 'package:memolanes/src/rust/main_db.dart';
+
+class TrashPage extends StatefulWidget {
+  const TrashPage({super.key});
+
+  @override
+  State<TrashPage> createState() => _TrashPageState();
+}
+
+class _TrashPageState extends State<TrashPage> {
+  List<TrashedJourney> items = [];
+
+  @override
+  void initState() {
+    super.initState();
+    _loadList();
+  }
+
+  void _loadList() async {
+    var list = await api.listTrash();
+    if (!mounted) return;
+    setState(() {
+      items = list;
+    });
+  }
+
+  Future<void> _restore(BuildContext context, TrashedJourney item) async {
+    try {
+      await api.restoreJourneyFromTrash(journeyId: item.header.id);
+    } catch (e) {
+      if (!context.mounted) return;
+      await showCommonDialog(context, e.toString());
+    }
+    _loadList();
+  }
+
+  Future<void> _restoreAll(BuildContext context) async {
+    final count = await api.restoreAllJourneysFromTrash();
+    if (!context.mounted) return;
+    await showCommonDialog(
+      context,
+      context.tr("general.advanced_settings.trash.restore_all_success",
+          args: [count.toString()]),
+    );
+    _loadList();
+  }
+
+  Future<void> _emptyTrash(BuildContext context) async {
+    if (!await showCommonDialog(
+      context,
+      context.tr("general.advanced_settings.trash.empty_message"),
+      hasCancel: true,
+      title: context.tr("general.advanced_settings.trash.empty"),
+      confirmButtonText: context.tr("common.delete"),
+      confirmGroundColor: Colors.red,
+      confirmTextColor: Colors.white,
+    )) {
+      return;
+    }
+    await api.emptyTrash();
+    _loadList();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    return Scaffold(
+      appBar: CapsuleStyleAppBar(
+        title: context.tr("general.advanced_settings.trash.title"),
+      ),
+      body: Column(
+        crossAxisAlignment: CrossAxisAlignment.center,
+        children: [
+          const SizedBox(height: 8),
+          Padding(
+            padding: EdgeInsets.symmetric(horizontal: 8.0),
+            child: Column(
+              children: [
+                LabelTile(
+                  label: context
+                      .tr("general.advanced_settings.trash.restore_all"),
+                  position: LabelTilePosition.top,
+                  onTap: items.isEmpty ? null : () => _restoreAll(context),
+                ),
+                LabelTile(
+                  label: context.tr("general.advanced_settings.trash.empty"),
+                  position: LabelTilePosition.bottom,
+                  onTap: items.isEmpty ? null : () => _emptyTrash(context),
+                ),
+              ],
+            ),
+          ),
+          const SizedBox(height: 16),
+          Expanded(
+            child: items.isEmpty
+                ? Center(
+                    child: Text(
+                        context.tr("general.advanced_settings.trash.no_data")),
+                  )
+                : ListView(
+                    shrinkWrap: true,
+                    children: items.map((item) {
+                      final deletedAt = DateFormat('yyyy-MM-dd HH:mm')
+                          .format(item.deletedAt.toLocal());
+                      return ListTile(
+                        leading: const Icon(Icons.route),
+                        title: Text(
+                            naiveDateToString(date: item.header.journeyDate)),
+                        subtitle: Text(context.tr(
+                            "general.advanced_settings.trash.deleted_at",
+                            args: [deletedAt])),
+                        trailing: ElevatedButton(
+                          onPressed: () => _restore(context, item),
+                          child: Text(context
+                              .tr("general.advanced_settings.trash.restore")),
+                        ),
+                      );
+                    }).toList(),
+                  ),
+          ),
+        ],
+      ),
+    );
+  }
+}
@@ -6,6 +6,7 @@ import 'package:flutter/material.dart';
 import 'package:memolanes/common/app_haptics.dart';
 import 'package:memolanes/common/component/custom_popup.dart';
 import 'package:memolanes/constants/style_constants.dart';
+import 'package:memolanes/src/rust/api/api.dart' as api;
 import 'package:pointer_interceptor/pointer_interceptor.dart';
 
 import 'time_ruler.dart';
@@ -286,10 +287,11 @@ class _TimeMachineViewModeAndLayerMenuState
   ];
 
   static const _layerKeys = [
-    (JourneyKind.defaultKind, 'journey_kind.default'),
-    (JourneyKind.flight, 'journey_kind.flight'),
+    (JourneyKind.defaultKind(), 'journey_kind.default'),
+    (JourneyKind.flight(), 'journey_kind.flight'),
   ];
 
+  final List<String> _customJourneyKinds = api.getCustomJourneyKinds();
   late Set<JourneyKind> _localKinds;
   late TimeMachineViewMode _localViewMode;
   late TimeRulerMode _localRulerMode;
@@ -360,7 +362,10 @@ class _TimeMachineViewModeAndLayerMenuState
             crossAxisAlignment: CrossAxisAlignment.center,
             children: [
               _buildColumnTitle(context.tr('time_machine.menu_title_layer')),
-              ..._layerKeys.map((e) => _buildLayerItem(e.$1, e.$2)),
+              ..._layerKeys
+                  .map((e) => _buildLayerItem(e.$1, context.tr(e.$2))),
+              ..._customJourneyKinds.map(
+                  (name) => _buildLayerItem(JourneyKind.custom(name), name)),
             ],
           ),
         ],
@@ -389,7 +394,7 @@ class _TimeMachineViewModeAndLayerMenuState
     );
   }
 
-  Widget _buildMenuTile(BuildContext context, String labelKey, bool isSelected,
+  Widget _buildMenuTile(BuildContext context, String label, bool isSelected,
       VoidCallback onTap) {
     return InkWell(
       onTap: onTap,
@@ -405,7 +410,7 @@ class _TimeMachineViewModeAndLayerMenuState
               const SizedBox(width: 18, height: 18),
             const SizedBox(width: 8),
             Text(
-              context.tr(labelKey),
+              label,
               style: TextStyle(
                 color:
                     isSelected ? StyleConstants.defaultColor : Colors.white70,
@@ -421,7 +426,7 @@ class _TimeMachineViewModeAndLayerMenuState
   Widget _buildViewModeItem(TimeMachineViewMode mode, String labelKey) {
     return _buildMenuTile(
       context,
-      labelKey,
+      context.tr(labelKey),
       mode == _localViewMode,
       () {
         AppHaptics.selection();
@@ -439,7 +444,7 @@ class _TimeMachineViewModeAndLayerMenuState
         ignoring: disabled,
         child: _buildMenuTile(
           context,
-          labelKey,
+          context.tr(labelKey),
           rulerMode == _localRulerMode,
           () {
             AppHaptics.selection();
@@ -451,11 +456,11 @@ class _TimeMachineViewModeAndLayerMenuState
     );
   }
 
-  Widget _buildLayerItem(JourneyKind kind, String labelKey) {
+  Widget _buildLayerItem(JourneyKind kind, String label) {
     final isSelected = _localKinds.contains(kind);
     return _buildMenuTile(
       context,
-      labelKey,
+      label,
       isSelected,
       () {
         AppHaptics.selection();
@@ -185,15 +185,15 @@ class AchievementStatsStore extends ChangeNotifier {
     }
 
     return AchievementAreaStats(
-      totalKm2: km2For(AchievementLayer.all),
-      groundKm2: km2For(AchievementLayer.default_),
-      flightKm2: km2For(AchievementLayer.flight),
+      totalKm2: km2For(const AchievementLayer.all()),
+      groundKm2: km2For(const AchievementLayer.default_()),
+      flightKm2: km2For(const AchievementLayer.flight()),
     );
   }
 
   Future<List<AchievementCountryStats>> _fetchCountryStats() async {
     final countriesView = await achievement_api.regionLevelView(
-      layer: AchievementLayer.default_,
+      layer: const AchievementLayer.default_(),
       level: RegionKind.country,
     );
 
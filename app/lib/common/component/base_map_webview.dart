@@ -70,6 +70,8 @@ class BaseMapWebviewState extends State<BaseMapWebview> {
   InAppWebViewController? _webViewController;
   late GpsManager _gpsManager;
   bool _readyForDisplay = false;
+  // The journey data is still loading, the map may show a rough overview.
+  bool _journeyLoading = false;
 
   late MapStyle _selectedMapStyle;
 
@@ -273,6 +275,17 @@ class BaseMapWebviewState extends State<BaseMapWebview> {
           }
         },
       )),
+      controller.addWebMessageListener(WebMessageListener(
+        jsObjectName: 'onJourneyLoadingChanged',
+        allowedOriginRules: {'*'},
+        onPostMessage: (message, sourceOrigin, isMainFrame, replyProxy) {
+          final loading = message?.data == 'true';
+          if (loading == _journeyLoading) return;
+          _setStateIfMounted(() {
+            _journeyLoading = loading;
+          });
+        },
+      )),
       for (final channel in widget.extraJavaScriptChannels)
         controller.addWebMessageListener(WebMessageListener(
           jsObjectName: channel.name,
@@ -480,7 +493,7 @@ class BaseMapWebviewState extends State<BaseMapWebview> {
                   headers: {
                     'Access-Control-Allow-Origin': '*',
                     'Access-Control-Expose-Headers':
-                        'X-Tile-Version, X-Not-Modified',
+                        'X-Tile-Version, X-Not-Modified, X-Tile-Delta, X-Tile-Layers',
                     'Content-Type': result.contentType,
                     ...result.headers,
                   },
@@ -540,6 +553,22 @@ class BaseMapWebviewState extends State<BaseMapWebview> {
             ),
           ),
         ),
+        if (_readyForDisplay && _journeyLoading)
+          Positioned(
+            top: MediaQuery.of(context).padding.top + 8,
+            left: 0,
+            right: 0,
+            child: const IgnorePointer(
+              ignoring: true,
+              child: Center(
+                child: SizedBox(
+                  width: 16,
+                  height: 16,
+                  child: CircularProgressIndicator(strokeWidth: 2),
+                ),
+              ),
+            ),
+          ),
         // This is to prevent actions to iOS home indicator affects the
         // underlying webview. (e.g. back to home gesture moves the map)
         Positioned(
@@ -59,6 +59,7 @@ enum LayerOption {
 
 class _LayerPopupContentState extends State<LayerPopupContent> {
   final api.LayerFilter _layerFilter = api.getCurrentMainMapLayerFilter();
+  final List<String> _customJourneyKinds = api.getCustomJourneyKinds();
   Timer? _actionTimer;
 
   @override
@@ -78,10 +79,30 @@ class _LayerPopupContentState extends State<LayerPopupContent> {
             FontAwesomeIcons.shoePrints),
         _buildItem(LayerOption.flight, context.tr("journey_kind.flight"),
             FontAwesomeIcons.planeUp),
+        for (final name in _customJourneyKinds) _buildCustomItem(name),
       ],
     );
   }
 
+  void _applyLayerFilter() {
+    _actionTimer?.cancel();
+    _actionTimer = Timer(const Duration(milliseconds: 600), () {
+      _actionTimer = null;
+      api.setMainMapLayerFilter(newLayerFilter: _layerFilter);
+    });
+  }
+
+  Widget _buildCustomItem(String name) {
+    final hidden = _layerFilter.hiddenCustomKinds;
+    return _buildTile(name, FontAwesomeIcons.tag, !hidden.contains(name), () {
+      setState(() {
+        if (!hidden.remove(name)) {
+          hidden.add(name);
+        }
+      });
+    });
+  }
+
   Widget _buildItem(LayerOption layerOption, String text, FaIconData icon) {
     final isActive = switch (layerOption) {
       LayerOption.current => _layerFilter.currentJourney,
@@ -89,24 +110,27 @@ class _LayerPopupContentState extends State<LayerPopupContent> {
       LayerOption.flight => _layerFilter.flightKind,
     };
 
+    return _buildTile(text, icon, isActive, () {
+      setState(() {
+        switch (layerOption) {
+          case LayerOption.current:
+            _layerFilter.currentJourney = !_layerFilter.currentJourney;
+          case LayerOption.default_:
+            _layerFilter.defaultKind = !_layerFilter.defaultKind;
+          case LayerOption.flight:
+            _layerFilter.flightKind = !_layerFilter.flightKind;
+        }
+      });
+    });
+  }
+
+  Widget _buildTile(
+      String text, FaIconData icon, bool isActive, VoidCallback toggle) {
     return InkWell(
       onTap: () {
         AppHaptics.selection();
-        setState(() {
-          switch (layerOption) {
-            case LayerOption.current:
-              _layerFilter.currentJourney = !_layerFilter.currentJourney;
-            case LayerOption.default_:
-              _layerFilter.defaultKind = !_layerFilter.defaultKind;
-            case LayerOption.flight:
-              _layerFilter.flightKind = !_layerFilter.flightKind;
-          }
-        });
-        _actionTimer?.cancel();
-        _actionTimer = Timer(const Duration(milliseconds: 600), () {
-          _actionTimer = null;
-          api.setMainMapLayerFilter(newLayerFilter: _layerFilter);
-        });
+        toggle();
+        _applyLayerFilter();
       },
       borderRadius: BorderRadius.circular(12),
       child: Padding(
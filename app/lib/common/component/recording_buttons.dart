@@ -3,6 +3,7 @@ import 'package:flutter/material.dart';
 import 'package:memolanes/common/app_haptics.dart';
 import 'package:memolanes/common/gps_manager.dart';
 import 'package:memolanes/common/utils.dart';
+import 'package:memolanes/src/rust/api/api.dart' as api;
 import 'package:pointer_interceptor/pointer_interceptor.dart';
 import 'package:provider/provider.dart';
 
@@ -25,9 +26,18 @@ class _RecordingButtonsState extends State<RecordingButtons> {
         confirmGroundColor: Colors.red,
         confirmTextColor: Colors.white);
 
-    if (shouldEndJourney) {
-      gpsManager.changeRecordingState(GpsRecordingStatus.none);
+    if (!shouldEndJourney) return;
+
+    var mapMatching = false;
+    if (api.hasMapMatchingRoadGraph() && mounted) {
+      mapMatching = await showCommonDialog(
+          context, context.tr("home.map_matching_message"),
+          hasCancel: true,
+          confirmButtonText: context.tr("home.map_matching_confirm"),
+          cancelButtonText: context.tr("home.map_matching_skip"));
     }
+    gpsManager.changeRecordingState(GpsRecordingStatus.none,
+        mapMatching: mapMatching);
   }
 
   @override
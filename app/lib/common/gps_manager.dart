@@ -274,6 +274,7 @@ class GpsManager extends ChangeNotifier {
             speed: update.data.speed,
           ),
           receivedTimestampMs: update.receivedAt.millisecondsSinceEpoch,
+          utcOffsetSec: update.receivedAt.timeZoneOffset.inSeconds,
         );
 
         if (meaningful) {
@@ -296,7 +297,9 @@ class GpsManager extends ChangeNotifier {
     return _recordingLocationUpdatesDrained?.future ?? Future<void>.value();
   }
 
-  Future<void> changeRecordingState(GpsRecordingStatus to) async {
+  // `mapMatching` snaps the journey to roads when it gets finalized.
+  Future<void> changeRecordingState(GpsRecordingStatus to,
+      {bool mapMatching = false}) async {
     if (to == GpsRecordingStatus.recording) {
       if (!await checkAndRequestPermission()) {
         return;
@@ -319,7 +322,10 @@ class GpsManager extends ChangeNotifier {
       );
 
       if (needToFinalize) {
-        if (await api.finalizeOngoingJourney()) {
+        final saved = mapMatching
+            ? await api.finalizeOngoingJourneyWithMapMatching()
+            : await api.finalizeOngoingJourney();
+        if (saved) {
           Fluttertoast.showToast(msg: tr("journey.finalize_saved"));
         } else {
           Fluttertoast.showToast(msg: tr("journey.finalize_empty"));
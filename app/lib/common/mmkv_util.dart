@@ -12,6 +12,8 @@ class MMKVKey {
   static const String firstLaunchSetupCompletedVersion =
       "FirstLaunchSetup.completedVersion";
   static const String worldviewPreference = "Settings.worldview";
+  static const String mapMatchingRoadGraphPath =
+      "Settings.mapMatchingRoadGraphPath";
   static const String mapStyle = "mapStyle";
   static const String requestedBatteryOptimization =
       'Permission.requestedBatteryOptimization';
@@ -62,5 +62,44 @@ fn tile_buffer_creation_benchmarks(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(rendering_benches, tile_buffer_creation_benchmarks);
+// the client side of the golden-image tests: decoding what the renderer sends
+fn tile_range_decode_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tile_range_decode");
+    group.sample_size(10);
+
+    let (bitmap_data, _warnings) =
+        import_data::fow::load_fow_sync_data("./tests/data/fow_3.zip").unwrap();
+
+    let mut map_renderer = map_renderer::MapRenderer::new(bitmap_data);
+
+    // Shenzhen universiade
+    let lng = 114.212470;
+    let lat = 22.697006;
+
+    for zoom in [3, 7, 11, 15] {
+        let (tile_x, tile_y) = lng_lat_to_tile_x_y(lng, lat, zoom as i32);
+        let response = map_renderer
+            .get_tile_range_response(tile_x as i64 - 1, tile_y as i64 - 1, zoom, 3, 3, 8)
+            .unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("tile_range_decode", format!("z{zoom:02}_3x3")),
+            &response,
+            |b, response| {
+                b.iter(|| {
+                    std::hint::black_box(
+                        journey_kernel::tile_range::decode_tile_range_response(response).unwrap(),
+                    )
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    rendering_benches,
+    tile_buffer_creation_benchmarks,
+    tile_range_decode_benchmarks
+);
 criterion_main!(rendering_benches);
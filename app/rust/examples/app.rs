@@ -1,5 +1,5 @@
 use clap::Parser;
-use memolanes_core::api::api::for_testing::get_main_map_state;
+use memolanes_core::api::api::for_testing::{get_main_map_state, wait_for_main_map_loaded};
 use memolanes_core::api::api::{init, init_main_map};
 use memolanes_core::journey_data::serialize_journey_bitmap;
 mod shared;
@@ -49,6 +49,7 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     .unwrap();
 
     init_main_map()?;
+    wait_for_main_map_loaded();
 
     if let Some(output_path) = cli.export_jbm {
         let output_path = if output_path.ends_with(".jbm") {
@@ -1,15 +1,17 @@
 use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use memolanes_core::api::import::ImportPreprocessor;
 use memolanes_core::archive::MldxReader;
 use memolanes_core::gps_processor::SegmentGapRule;
 use memolanes_core::import_data;
 use memolanes_core::journey_bitmap::JourneyBitmap;
 use memolanes_core::journey_data;
+use memolanes_core::map_matching::RoadGraph;
 use memolanes_core::renderer::MapRenderer;
 
 mod shared;
@@ -24,6 +26,9 @@ use shared::MapServer;
                   journey bitmap, and optionally writes a .jbm file and/or serves it via a map server."
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Output .jbm file path.
     #[arg(short, long, value_name = "OUTPUT")]
     output: Option<String>,
@@ -41,6 +46,40 @@ struct Cli {
     serve: bool,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Build a road graph file for map matching from an OSM XML extract.
+    /// PBF extracts can be converted first with `osmium cat in.osm.pbf -o in.osm`.
+    RoadGraph {
+        /// Input .osm file path.
+        #[arg(long, value_name = "FILE")]
+        osm: String,
+
+        /// Output road graph file path.
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: String,
+    },
+}
+
+fn build_road_graph(osm: &str, output: &str) -> Result<()> {
+    println!("Reading OSM data from: {osm}");
+    let file = File::open(osm).with_context(|| format!("Failed to open OSM file: {osm}"))?;
+    let road_graph = RoadGraph::from_osm_xml(BufReader::new(file))?;
+    println!(
+        "  {} nodes, {} edges.",
+        road_graph.node_count(),
+        road_graph.edge_count()
+    );
+
+    let file =
+        File::create(output).with_context(|| format!("Failed to create output file: {output}"))?;
+    let mut writer = BufWriter::new(file);
+    road_graph.serialize(&mut writer)?;
+    writer.flush()?;
+    println!("Exported road graph to: {output}");
+    Ok(())
+}
+
 fn process_gpx_or_kml(file_path: &str) -> Result<JourneyBitmap> {
     let ext = Path::new(file_path)
         .extension()
@@ -65,6 +104,7 @@ fn process_gpx_or_kml(file_path: &str) -> Result<JourneyBitmap> {
             }
             return Ok(bm);
         }
+        ImportPreprocessor::MapMatching => bail!("Map matching is not supported here"),
     };
 
     let jv = import_data::conversion::journey_vector_from_raw_data_with_gps_preprocessor(
@@ -105,7 +145,10 @@ fn process_jbm(file_path: &str) -> Result<JourneyBitmap> {
 }
 
 fn process_data_dir(dir: &str) -> Result<JourneyBitmap> {
-    use memolanes_core::api::api::{for_testing::get_main_map_state, init, init_main_map};
+    use memolanes_core::api::api::{
+        for_testing::{get_main_map_state, wait_for_main_map_loaded},
+        init, init_main_map,
+    };
 
     init(
         dir.to_string(),
@@ -115,6 +158,7 @@ fn process_data_dir(dir: &str) -> Result<JourneyBitmap> {
     )
     .unwrap();
     init_main_map()?;
+    wait_for_main_map_loaded();
 
     let main_map_state = get_main_map_state();
     let bitmap = main_map_state
@@ -129,6 +173,10 @@ fn process_data_dir(dir: &str) -> Result<JourneyBitmap> {
 pub fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(Command::RoadGraph { osm, output }) = &cli.command {
+        return build_road_graph(osm, output);
+    }
+
     if cli.files.is_empty() && cli.data_dir.is_none() {
         bail!("No input specified. Provide --file and/or --data-dir.");
     }
@@ -180,6 +228,7 @@ pub fn main() -> Result<()> {
             .expect("Failed to start server");
 
         println!("View map at: {}", server.get_http_url());
+        println!("Raster tiles at: {}", server.get_raster_tile_url_template());
         if let Err(e) = qr2term::print_qr(server.get_http_url()) {
             eprintln!("Failed to print QR code: {e}");
         }
@@ -69,6 +69,10 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("================================================");
     println!("[Simple Map Server]:   {}", server_simple.get_http_url());
     println!("[Simple Map Local]:    {}", server_simple.get_file_url());
+    println!(
+        "[Simple Map Tiles]:    {}",
+        server_simple.get_raster_tile_url_template()
+    );
 
     // ========== Server 2: Medium Map (loaded from fow_3.zip) ==========
     let (journey_bitmap_fow, _) =
@@ -23,7 +23,7 @@ fn add_cors_headers(builder: &mut HttpResponseBuilder) -> &mut HttpResponseBuild
         ))
         .append_header((
             "Access-Control-Expose-Headers",
-            "X-Tile-Version, X-Not-Modified",
+            "X-Tile-Version, X-Not-Modified, X-Tile-Delta, X-Tile-Layers",
         ))
 }
 
@@ -85,8 +85,11 @@ impl MapServer {
             let server = HttpServer::new(move || {
                 App::new()
                     .app_data(data.clone())
-                    .route("/{path}", web::get().to(serve_request))
-                    .route("/{path}", web::method(Method::OPTIONS).to(handle_preflight))
+                    .route("/{path:.*}", web::get().to(serve_request))
+                    .route(
+                        "/{path:.*}",
+                        web::method(Method::OPTIONS).to(handle_preflight),
+                    )
             })
             .bind((host.clone(), 0))?
             .workers(1)
@@ -168,6 +171,15 @@ impl MapServer {
         }
     }
 
+    /// XYZ url template of the raster tiles, for standard map clients.
+    pub fn get_raster_tile_url_template(&self) -> String {
+        format!(
+            "http://{}:{}/tile/{{z}}/{{x}}/{{y}}.png",
+            get_dev_server_host(),
+            self.port
+        )
+    }
+
     pub fn get_file_url(&self) -> String {
         let cgi_host = get_dev_server_host();
         let mut map_renderer = self.map_renderer.lock().unwrap();
@@ -1,54 +1,69 @@
 use crate::cache_db::LayerKind;
 use crate::journey_header::JourneyKind;
 
-/// The journey layer an achievement query is computed over. Flat, closed wire
-/// enum (exposed via FRB in `api/achievement.rs`), kept separate from the
-/// structurally-open `cache_db::LayerKind` so a new `JourneyKind` variant widens
-/// the achievement surface only through a compile error in the matches below.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The journey layer an achievement query is computed over. Wire enum (exposed
+/// via FRB in `api/achievement.rs`), kept separate from the structurally-open
+/// `cache_db::LayerKind` so a new `JourneyKind` variant widens the achievement
+/// surface only through a compile error in the matches below.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AchievementLayer {
     /// Ground journeys only (`JourneyKind::DefaultKind`) — the headline
     /// layer; what "visited" means by default.
     Default,
     /// Flight journeys only ("flown over").
     Flight,
+    /// Journeys of one custom kind only (`JourneyKind::Custom`).
+    Custom(String),
     /// Union of every journey kind.
     All,
 }
 
 impl AchievementLayer {
+    /// The fixed layers, custom ones depend on the data (see `of_kind`).
     pub const ALL_LAYERS: [AchievementLayer; 3] = [
         AchievementLayer::Default,
         AchievementLayer::Flight,
         AchievementLayer::All,
     ];
 
+    /// The layer holding journeys of exactly `kind`.
+    pub fn of_kind(kind: &JourneyKind) -> AchievementLayer {
+        match kind {
+            JourneyKind::DefaultKind => AchievementLayer::Default,
+            JourneyKind::Flight => AchievementLayer::Flight,
+            JourneyKind::Custom(name) => AchievementLayer::Custom(name.clone()),
+        }
+    }
+
     /// Whether a journey of `kind` contributes to this layer's coverage.
     /// Exhaustive over BOTH enums on purpose — this is the match that
     /// makes the type-level promise above real.
-    pub fn includes_kind(self, kind: JourneyKind) -> bool {
+    pub fn includes_kind(&self, kind: &JourneyKind) -> bool {
         match (self, kind) {
             (AchievementLayer::All, _) => true,
             (AchievementLayer::Default, JourneyKind::DefaultKind) => true,
-            (AchievementLayer::Default, JourneyKind::Flight) => false,
+            (AchievementLayer::Default, JourneyKind::Flight | JourneyKind::Custom(_)) => false,
             (AchievementLayer::Flight, JourneyKind::Flight) => true,
-            (AchievementLayer::Flight, JourneyKind::DefaultKind) => false,
+            (AchievementLayer::Flight, JourneyKind::DefaultKind | JourneyKind::Custom(_)) => false,
+            (AchievementLayer::Custom(layer_name), JourneyKind::Custom(name)) => layer_name == name,
+            (AchievementLayer::Custom(_), JourneyKind::DefaultKind | JourneyKind::Flight) => false,
         }
     }
 
     /// The layers a journey of `kind` contributes to (its own kind's
     /// layer plus `All`).
-    pub fn layers_including(kind: JourneyKind) -> impl Iterator<Item = AchievementLayer> {
-        Self::ALL_LAYERS
-            .into_iter()
-            .filter(move |l| l.includes_kind(kind))
+    pub fn layers_including(kind: &JourneyKind) -> impl Iterator<Item = AchievementLayer> {
+        [Self::of_kind(kind), AchievementLayer::All].into_iter()
     }
 
     /// The cache_db layer holding this layer's merged bitmap.
-    pub fn to_layer_kind(self) -> LayerKind {
+    pub fn to_layer_kind(&self) -> LayerKind {
         match self {
             AchievementLayer::Default => LayerKind::JourneyKind(JourneyKind::DefaultKind),
             AchievementLayer::Flight => LayerKind::JourneyKind(JourneyKind::Flight),
+            AchievementLayer::Custom(name) => {
+                LayerKind::JourneyKind(JourneyKind::Custom(name.clone()))
+            }
             AchievementLayer::All => LayerKind::All,
         }
     }
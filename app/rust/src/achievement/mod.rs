@@ -14,6 +14,10 @@ use crate::geo::GeoLookup;
 pub(crate) const GEO_NOT_INSTALLED: &str = "geo not installed";
 
 pub trait AchievementReader {
+    /// `AchievementLayer::ALL_LAYERS` plus one layer per custom journey kind
+    /// in use.
+    fn layers(&mut self) -> Result<Vec<AchievementLayer>>;
+
     fn explored_area_m2(&mut self, layer: AchievementLayer) -> Result<u64>;
 
     fn region_areas(
@@ -8,6 +8,7 @@ use crate::achievement::layer::AchievementLayer;
 use crate::achievement::{AchievementReader, GEO_NOT_INSTALLED};
 use crate::geo::GeoLookup;
 use crate::journey_area_utils::{cm2_to_m2_rounded, journey_bitmap_area_m2_rounded};
+use crate::journey_header::JourneyKind;
 use crate::journey_snapshot::JourneySnapshot;
 
 pub fn explored_areas_from_snapshot(
@@ -15,9 +16,9 @@ pub fn explored_areas_from_snapshot(
     layers: &[AchievementLayer],
 ) -> Result<HashMap<AchievementLayer, u64>> {
     let mut out = HashMap::with_capacity(layers.len());
-    for &layer in layers {
+    for layer in layers {
         let bitmap = snapshot.finalized_bitmap(&layer.to_layer_kind(), None)?;
-        out.insert(layer, journey_bitmap_area_m2_rounded(&bitmap, None));
+        out.insert(layer.clone(), journey_bitmap_area_m2_rounded(&bitmap, None));
     }
     Ok(out)
 }
@@ -48,10 +49,22 @@ impl<'a, 'snap> OnDemandReader<'a, 'snap> {
 }
 
 impl AchievementReader for OnDemandReader<'_, '_> {
+    fn layers(&mut self) -> Result<Vec<AchievementLayer>> {
+        let mut layers = AchievementLayer::ALL_LAYERS.to_vec();
+        for kind in self.snapshot.journey_kinds()? {
+            if let JourneyKind::Custom(_) = kind {
+                layers.push(AchievementLayer::of_kind(&kind));
+            }
+        }
+        Ok(layers)
+    }
+
     fn explored_area_m2(&mut self, layer: AchievementLayer) -> Result<u64> {
-        Ok(explored_areas_from_snapshot(&mut self.snapshot, &[layer])?
-            .remove(&layer)
-            .unwrap_or(0))
+        Ok(
+            explored_areas_from_snapshot(&mut self.snapshot, std::slice::from_ref(&layer))?
+                .remove(&layer)
+                .unwrap_or(0),
+        )
     }
 
     fn region_areas(
@@ -92,9 +92,10 @@ pub fn get_explored_area_by_layer() -> Result<HashMap<AchievementLayer, u64>> {
     crate::api::api::get()
         .storage
         .with_achievement_read(|store| {
-            AchievementLayer::ALL_LAYERS
+            store
+                .layers()?
                 .into_iter()
-                .map(|layer| Ok((layer, store.explored_area_m2(layer)?)))
+                .map(|layer| Ok((layer.clone(), store.explored_area_m2(layer)?)))
                 .collect()
         })
 }
@@ -2,39 +2,53 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 
 use anyhow::{Context, Result};
 use auto_context::auto_context;
-use chrono::NaiveDate;
+use chrono::{FixedOffset, NaiveDate};
 use csv::Reader;
 use flutter_rust_bridge::frb;
+use journey_kernel::tile_range::{
+    TILE_RANGE_LAYER_CURRENT_JOURNEY, TILE_RANGE_LAYER_DEFAULT, TILE_RANGE_LAYER_FIRST_CUSTOM,
+    TILE_RANGE_LAYER_FLIGHT, TILE_RANGE_MAX_LAYERS,
+};
 
 use super::import::JourneyInfo;
 use crate::cache_db::LayerKind;
 use crate::frb_generated::StreamSink;
-use crate::gps_processor::{GpsPreprocessor, ProcessResult};
-use crate::journey_bitmap::JourneyBitmap;
+use crate::gps_processor::ProcessResult;
+use crate::journey_bitmap::{BlockOverview, JourneyBitmap};
 use crate::journey_data::JourneyData;
 use crate::journey_header::{JourneyHeader, JourneyKind, JourneyType};
 use crate::journey_vector::JourneyVector;
 use crate::logs;
+use crate::main_db::{
+    JourneyRevision, JourneySearchQuery, SearchArea, SplitPosition, TrashedJourney,
+};
+use crate::map_matching::RoadGraph;
+use crate::recorder::{LocationUpdateResult, Recorder};
+use crate::recording_journal::RecoveryReport;
 use crate::renderer::internal_server::{dispatch_request, WebviewResponse};
+use crate::renderer::map_renderer::{MapLayer, PendingLayers};
+use crate::renderer::raster_tile::RasterTileStyle;
+use crate::renderer::time_lapse::{FrameSequence, TimeLapse};
 use crate::renderer::MapRenderer;
 use crate::storage::{RawDataFile, Storage};
-use crate::{archive, build_info, export_data, gps_processor, main_db};
+use crate::{archive, build_info, export_data, gps_processor};
 
 use crate::utils::{db::DbError, get_bounds_from_journey_bitmap, MapBounds};
 
 use log::{error, info, warn};
 
-// TODO: we have way too many locking here and now it is hard to track.
-//  e.g. we could mess up with the order and cause a deadlock
+// Recording state is owned by `recorder`, so a location update never holds a
+// lock while waiting for storage.
 #[frb(ignore)]
 pub(super) struct MainState {
     pub storage: Storage,
-    pub gps_preprocessor: Mutex<GpsPreprocessor>,
+    recorder: Recorder,
     main_map_state: Arc<Mutex<MainMapState>>,
+    pub road_graph: Mutex<Option<Arc<RoadGraph>>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,28 +72,225 @@ pub fn short_commit_hash() -> String {
     build_info::SHORT_COMMIT_HASH.to_string()
 }
 
+/// The layer of tile-range responses for a journey kind, `None` being the
+/// ongoing journey. Custom kinds get the remaining ids in order, kinds past
+/// the last id share it.
+fn main_map_layer(kind: Option<&JourneyKind>, custom_index: usize) -> (u8, String) {
+    match kind {
+        None => (
+            TILE_RANGE_LAYER_CURRENT_JOURNEY,
+            "current_journey".to_string(),
+        ),
+        Some(JourneyKind::DefaultKind) => (TILE_RANGE_LAYER_DEFAULT, "default".to_string()),
+        Some(JourneyKind::Flight) => (TILE_RANGE_LAYER_FLIGHT, "flight".to_string()),
+        Some(JourneyKind::Custom(name)) => {
+            let last_id = TILE_RANGE_MAX_LAYERS - 1;
+            match TILE_RANGE_LAYER_FIRST_CUSTOM as usize + custom_index {
+                id if id < last_id as usize => (id as u8, name.clone()),
+                _ => (last_id, "other".to_string()),
+            }
+        }
+    }
+}
+
+/// Tiles moved into the main map per lock while it is loading, so other
+/// requests are not blocked for long in between.
+const MAIN_MAP_LOAD_BATCH_TILES: usize = 64;
+
+/// Groups the layers returned by `Storage` by tile-range layer, `merge`
+/// combines the ones sharing an id.
+fn group_main_map_layers<T>(
+    kind_layers: Vec<(Option<JourneyKind>, T)>,
+    merge: impl Fn(&mut T, T),
+) -> Vec<(u8, String, T)> {
+    let mut layers: Vec<(u8, String, T)> = Vec::new();
+    let mut custom_index = 0;
+    for (kind, content) in kind_layers {
+        let (id, name) = main_map_layer(kind.as_ref(), custom_index);
+        if let Some(JourneyKind::Custom(_)) = kind {
+            custom_index += 1;
+        }
+        match layers.iter_mut().find(|(layer_id, _, _)| *layer_id == id) {
+            Some((_, _, layer_content)) => merge(layer_content, content),
+            None => layers.push((id, name, content)),
+        }
+    }
+    layers
+}
+
+/// Groups the layers returned by `Storage` into tile-range layers.
+fn main_map_layers(kind_layers: Vec<(Option<JourneyKind>, JourneyBitmap)>) -> Vec<MapLayer> {
+    group_main_map_layers(kind_layers, |journey_bitmap, other| {
+        journey_bitmap.merge(other)
+    })
+    .into_iter()
+    .map(|(id, name, journey_bitmap)| MapLayer {
+        id,
+        name,
+        journey_bitmap,
+    })
+    .collect()
+}
+
+/// The main map as a single bitmap, `LayerKind::All` is cached as is.
 #[auto_context]
-fn reload_main_map_bitmap(storage: &Storage, main_map_state: &mut MainMapState) -> Result<()> {
-    if main_map_state.dropped_for_power_saving {
-        return Ok(());
+fn get_main_map_bitmap(
+    storage: &Storage,
+    layer_filter: &LayerFilter,
+    include_ongoing: bool,
+) -> Result<JourneyBitmap> {
+    if layer_filter.includes_all_kinds() {
+        storage.get_latest_bitmap_for_main_map_renderer(&Some(LayerKind::All), include_ongoing)
+    } else {
+        storage.get_latest_bitmap_for_main_map_renderer_by_kind(
+            |kind| layer_filter.includes_kind(kind),
+            include_ongoing,
+        )
+    }
+}
+
+/// Layers of the main map: the ongoing journey (if `include_ongoing`) and the
+/// finalized journeys, by kind if `layered` (see `MainMapState::layered`),
+/// otherwise as a single layer.
+#[auto_context]
+fn get_main_map_layers(
+    storage: &Storage,
+    layer_filter: &LayerFilter,
+    layered: bool,
+    include_ongoing: bool,
+) -> Result<Vec<MapLayer>> {
+    if layered {
+        return Ok(main_map_layers(
+            storage.get_latest_layers_for_main_map_renderer(
+                |kind| layer_filter.includes_kind(kind),
+                include_ongoing,
+            )?,
+        ));
+    }
+    let mut layers = Vec::new();
+    if include_ongoing {
+        let (id, name) = main_map_layer(None, 0);
+        layers.push(MapLayer {
+            id,
+            name,
+            journey_bitmap: storage.get_latest_bitmap_for_main_map_renderer(&None, true)?,
+        });
     }
+    layers.push(MapLayer {
+        id: TILE_RANGE_LAYER_DEFAULT,
+        name: "finalized".to_string(),
+        journey_bitmap: get_main_map_bitmap(storage, layer_filter, false)?,
+    });
+    Ok(layers)
+}
 
-    let layer_filter = main_map_state.layer_filter;
+/// Layers with the overview of each of them, see `MapRenderer::set_overviews`.
+type MainMapOverview = (Vec<MapLayer>, Vec<(u8, BlockOverview)>);
 
-    // TODO: merge layer filter with layer kind
-    let layer_kind = match (layer_filter.default_kind, layer_filter.flight_kind) {
-        (true, true) => Some(LayerKind::All),
-        (true, false) => Some(LayerKind::JourneyKind(JourneyKind::DefaultKind)),
-        (false, true) => Some(LayerKind::JourneyKind(JourneyKind::Flight)),
-        (false, false) => None,
+/// Placeholder for `get_main_map_layers`, with the same layers: the ongoing
+/// journey is complete, the finalized ones are empty and come with their
+/// cached overview instead (see `MapRenderer::set_overviews`).
+#[auto_context]
+fn get_main_map_overview(
+    storage: &Storage,
+    layer_filter: &LayerFilter,
+    layered: bool,
+    include_ongoing: bool,
+) -> Result<MainMapOverview> {
+    let mut layers = Vec::new();
+    if include_ongoing {
+        let (id, name) = main_map_layer(None, 0);
+        layers.push(MapLayer {
+            id,
+            name,
+            journey_bitmap: storage.get_latest_bitmap_for_main_map_renderer(&None, true)?,
+        });
+    }
+    let kind_overviews =
+        storage.get_overviews_for_main_map_renderer(|kind| layer_filter.includes_kind(kind))?;
+    let finalized = if layered {
+        group_main_map_layers(
+            kind_overviews
+                .into_iter()
+                .map(|(kind, overview)| (Some(kind), overview))
+                .collect(),
+            |overview, other| overview.merge(&other),
+        )
+    } else {
+        let mut overview = BlockOverview::new();
+        for (_, kind_overview) in kind_overviews {
+            overview.merge(&kind_overview);
+        }
+        vec![(TILE_RANGE_LAYER_DEFAULT, "finalized".to_string(), overview)]
     };
+    let mut overviews = Vec::new();
+    for (id, name, overview) in finalized {
+        layers.push(MapLayer {
+            id,
+            name,
+            journey_bitmap: JourneyBitmap::new(),
+        });
+        overviews.push((id, overview));
+    }
+    Ok((layers, overviews))
+}
 
-    let journey_bitmap = storage
-        .get_latest_bitmap_for_main_map_renderer(&layer_kind, layer_filter.current_journey)?;
-    main_map_state.map_renderer.replace(journey_bitmap);
+#[auto_context]
+fn reload_main_map_bitmap(storage: &Storage, main_map_state: &mut MainMapState) -> Result<()> {
+    if main_map_state.dropped_for_power_saving {
+        return Ok(());
+    }
+    // anything still loading in the background is outdated now
+    main_map_state.load_generation += 1;
+
+    let layer_filter = &main_map_state.layer_filter;
+    if main_map_state.layered {
+        let layers =
+            get_main_map_layers(storage, layer_filter, true, layer_filter.current_journey)?;
+        main_map_state.map_renderer.replace_with_layers(layers);
+    } else {
+        let journey_bitmap =
+            get_main_map_bitmap(storage, layer_filter, layer_filter.current_journey)?;
+        main_map_state.map_renderer.replace(journey_bitmap);
+    }
     Ok(())
 }
 
+/// Moves the full layers into the main map started by `init_main_map`, in
+/// batches. Stops early if the main map was reloaded or dropped meanwhile.
+#[auto_context]
+fn load_main_map_in_background(
+    load_generation: u64,
+    layer_filter: &LayerFilter,
+    layered: bool,
+) -> Result<()> {
+    let state = get();
+    // the ongoing journey is already complete in the main map
+    let layers = get_main_map_layers(&state.storage, layer_filter, layered, false)?;
+    let mut pending = if layered {
+        PendingLayers::new(layers)
+    } else {
+        PendingLayers::new_merged(layers)
+    };
+    loop {
+        let mut main_map_state = state.main_map_state.lock().unwrap();
+        if main_map_state.load_generation != load_generation
+            || !main_map_state.map_renderer.is_loading()
+        {
+            return Ok(());
+        }
+        if main_map_state
+            .map_renderer
+            .load_tiles(&mut pending, MAIN_MAP_LOAD_BATCH_TILES)
+        {
+            info!("main map loaded");
+            return Ok(());
+        }
+        drop(main_map_state);
+        std::thread::yield_now();
+    }
+}
+
 pub fn init(
     temp_dir: String,
     doc_dir: String,
@@ -123,15 +334,19 @@ pub fn init(
             current_journey: true,
             default_kind: true,
             flight_kind: false,
+            hidden_custom_kinds: vec![],
         };
 
-        // TODO: use an empty journey bitmap first, because loading could be slow (especially when we don't have cache).
-        // Ideally, we should support main map renderer being none, combine together with `dropped_for_power_saving`
+        // Empty until `init_main_map`, which loads it progressively because loading could be slow
+        // (especially when we don't have cache).
+        // TODO: Ideally, we should support main map renderer being none, combine together with `dropped_for_power_saving`
         // to be more type safe.
         let main_map_state = Arc::new(Mutex::new(MainMapState {
             map_renderer: MapRenderer::new(JourneyBitmap::new()),
             dropped_for_power_saving: false,
             layer_filter: default_layer_filter,
+            load_generation: 0,
+            layered: false,
         }));
         let main_map_state_copy = main_map_state.clone();
         // TODO: redesign the callback to better handle locks and avoid deadlocks
@@ -148,8 +363,9 @@ pub fn init(
 
         Ok(MainState {
             storage,
-            gps_preprocessor: Mutex::new(GpsPreprocessor::new()),
+            recorder: Recorder::spawn(|| &get().storage),
             main_map_state,
+            road_graph: Mutex::new(None),
         })
     });
 
@@ -255,10 +471,64 @@ fn prepare_real_cache_dir(
 }
 
 // TODO: this design is not ideal, we need this because the `init` above uses an empty one.
+/// Shows the cached overview of the main map right away (see
+/// `CacheDb::get_overview`) and returns, the full bitmap is then loaded in the
+/// background and streamed in, the area around the last viewed tile range
+/// first. Tile-range responses are marked as loading until it is done.
 pub fn init_main_map() -> Result<()> {
+    let main_map_state = get().main_map_state.lock().unwrap();
+    if main_map_state.dropped_for_power_saving {
+        return Ok(());
+    }
+    load_main_map_progressively(main_map_state)
+}
+
+/// Splits the finalized journeys of the main map by kind, so tile-range
+/// requests with `layers=1` get one layer per kind. Off by default since the
+/// layers take as much memory as the whole map again. Loads like
+/// `init_main_map`.
+pub fn set_main_map_layered(layered: bool) -> Result<()> {
+    let mut main_map_state = get().main_map_state.lock().unwrap();
+    if main_map_state.layered == layered {
+        return Ok(());
+    }
+    main_map_state.layered = layered;
+    // picked up by the next `init_main_map`
+    if main_map_state.dropped_for_power_saving {
+        return Ok(());
+    }
+    load_main_map_progressively(main_map_state)
+}
+
+#[auto_context]
+fn load_main_map_progressively(mut main_map_state: MutexGuard<MainMapState>) -> Result<()> {
     let state = get();
-    let mut main_map_state = state.main_map_state.lock().unwrap();
-    reload_main_map_bitmap(&state.storage, &mut main_map_state)
+    let layer_filter = main_map_state.layer_filter.clone();
+    let layered = main_map_state.layered;
+    let (layers, overviews) = get_main_map_overview(
+        &state.storage,
+        &layer_filter,
+        layered,
+        layer_filter.current_journey,
+    )?;
+    main_map_state.map_renderer.replace_with_layers(layers);
+    main_map_state.map_renderer.set_overviews(overviews);
+    main_map_state.map_renderer.set_loading(true);
+    main_map_state.load_generation += 1;
+    let load_generation = main_map_state.load_generation;
+    drop(main_map_state);
+
+    std::thread::spawn(move || {
+        if let Err(e) = load_main_map_in_background(load_generation, &layer_filter, layered) {
+            error!("Failed to load the main map: {e:?}");
+            // keep the overview, but stop reporting it as loading
+            let mut main_map_state = get().main_map_state.lock().unwrap();
+            if main_map_state.load_generation == load_generation {
+                main_map_state.map_renderer.set_loading(false);
+            }
+        }
+    });
+    Ok(())
 }
 
 pub fn subscribe_to_log_stream(sink: StreamSink<String>) -> Result<()> {
@@ -315,6 +585,7 @@ pub enum MapRendererProxy {
     StaticRenderer(Mutex<MapRenderer>),
     DynamicRenderer(Arc<Mutex<MapRenderer>>),
     MainMapRenderer,
+    TimeLapseRenderer(Arc<Mutex<TimeLapse>>),
 }
 
 impl MapRendererProxy {
@@ -330,6 +601,11 @@ impl MapRendererProxy {
             MapRendererProxy::DynamicRenderer(mr) => {
                 dispatch_request(&path, &query_params, &mut mr.lock().unwrap())
             }
+            MapRendererProxy::TimeLapseRenderer(time_lapse) => dispatch_request(
+                &path,
+                &query_params,
+                time_lapse.lock().unwrap().map_renderer(),
+            ),
             MapRendererProxy::MainMapRenderer => {
                 let mut main_map_state = get().main_map_state.lock().unwrap();
                 if main_map_state.dropped_for_power_saving {
@@ -348,6 +624,54 @@ impl MapRendererProxy {
         };
         Ok(resp)
     }
+
+    fn time_lapse(&self) -> Result<&Arc<Mutex<TimeLapse>>> {
+        match self {
+            MapRendererProxy::TimeLapseRenderer(time_lapse) => Ok(time_lapse),
+            _ => Err(anyhow!("Not a time-lapse map renderer")),
+        }
+    }
+
+    /// Only for proxies from `get_map_renderer_proxy_for_time_lapse`: shows
+    /// every journey on or before `date`.
+    pub fn set_time_lapse_cursor(&self, date: NaiveDate) -> Result<()> {
+        let storage = &get().storage;
+        self.time_lapse()?
+            .lock()
+            .unwrap()
+            .set_cursor(date, |journey_id| {
+                storage.with_db_txn(|txn| txn.get_journey_data(journey_id))
+            })
+    }
+
+    /// Only for proxies from `get_map_renderer_proxy_for_time_lapse`: writes a
+    /// PNG of `bounds` every `step_days` from `from_date_inclusive` to
+    /// `to_date_inclusive` into `output_dir`, returning the file paths.
+    #[allow(clippy::too_many_arguments)]
+    pub fn export_time_lapse_frames(
+        &self,
+        bounds: MapBounds,
+        zoom: i16,
+        from_date_inclusive: NaiveDate,
+        to_date_inclusive: NaiveDate,
+        step_days: u32,
+        output_dir: String,
+    ) -> Result<Vec<String>> {
+        let storage = &get().storage;
+        let frame_sequence = FrameSequence {
+            bounds,
+            zoom,
+            from_date_inclusive,
+            to_date_inclusive,
+            step_days,
+            style: RasterTileStyle::default(),
+        };
+        self.time_lapse()?.lock().unwrap().export_frames(
+            &frame_sequence,
+            &output_dir,
+            |journey_id| storage.with_db_txn(|txn| txn.get_journey_data(journey_id)),
+        )
+    }
 }
 
 #[frb(sync)]
@@ -363,7 +687,7 @@ pub fn get_empty_map_renderer_proxy() -> MapRendererProxy {
     MapRendererProxy::StaticRenderer(Mutex::new(map_renderer))
 }
 
-/// [journey_kinds]: empty = no layers; len 1 = that kind only; len 2 = both (pass None).
+/// [journey_kinds]: only journeys of these kinds are included.
 pub fn get_map_renderer_proxy_for_journey_date_range(
     from_date_inclusive: NaiveDate,
     to_date_inclusive: NaiveDate,
@@ -375,14 +699,18 @@ pub fn get_map_renderer_proxy_for_journey_date_range(
             .storage
             .get_range_bitmap(from_date_inclusive, to_date_inclusive, journey_kind)
     };
-    let journey_bitmap = match (
-        journey_kinds.contains(&JourneyKind::DefaultKind),
-        journey_kinds.contains(&JourneyKind::Flight),
-    ) {
-        (false, false) => JourneyBitmap::new(),
-        (true, false) => get(Some(&JourneyKind::DefaultKind))?,
-        (false, true) => get(Some(&JourneyKind::Flight))?,
-        (true, true) => get(None)?,
+    let all_journey_kinds = state.storage.with_db_txn(|txn| txn.journey_kinds())?;
+    let journey_bitmap = if all_journey_kinds
+        .iter()
+        .all(|kind| journey_kinds.contains(kind))
+    {
+        get(None)?
+    } else {
+        let mut journey_bitmap = JourneyBitmap::new();
+        for journey_kind in &journey_kinds {
+            journey_bitmap.merge(get(Some(journey_kind))?);
+        }
+        journey_bitmap
     };
 
     let map_renderer = MapRenderer::new(journey_bitmap);
@@ -391,15 +719,53 @@ pub fn get_map_renderer_proxy_for_journey_date_range(
     ))))
 }
 
+/// A renderer for playing back how the map grew, it starts empty and follows
+/// `MapRendererProxy::set_time_lapse_cursor`.
+/// [journey_kinds]: only journeys of these kinds are included.
+pub fn get_map_renderer_proxy_for_time_lapse(
+    journey_kinds: HashSet<JourneyKind>,
+) -> Result<MapRendererProxy> {
+    let journeys = get()
+        .storage
+        .with_db_txn(|txn| txn.query_journeys(None, None))?
+        .into_iter()
+        .filter(|header| journey_kinds.contains(&header.journey_kind))
+        .map(|header| (header.journey_date, header.id))
+        .collect();
+    Ok(MapRendererProxy::TimeLapseRenderer(Arc::new(Mutex::new(
+        TimeLapse::new(journeys),
+    ))))
+}
+
+/// Names of custom journey kinds used by at least one journey, sorted.
+#[frb(sync)]
+pub fn get_custom_journey_kinds() -> Result<Vec<String>> {
+    let journey_kinds = get().storage.with_db_txn(|txn| txn.journey_kinds())?;
+    Ok(journey_kinds
+        .into_iter()
+        .filter_map(|kind| match kind {
+            JourneyKind::Custom(name) => Some(name),
+            JourneyKind::DefaultKind | JourneyKind::Flight => None,
+        })
+        .collect())
+}
+
 fn get_map_renderer_proxy_for_journey_data_internal(
     journey_data: JourneyData,
 ) -> Result<(MapRendererProxy, Option<MapBounds>)> {
+    let route = match &journey_data {
+        JourneyData::Vector(journey_vector) => Some(journey_vector.clone()),
+        JourneyData::Bitmap(_) => None,
+    };
     let mut journey_bitmap = JourneyBitmap::new();
     journey_data.merge_into(&mut journey_bitmap);
 
     let bounds = get_bounds_from_journey_bitmap(&mut journey_bitmap);
 
-    let map_renderer = MapRenderer::new(journey_bitmap);
+    let mut map_renderer = MapRenderer::new(journey_bitmap);
+    if let Some(route) = route {
+        map_renderer.set_route(route);
+    }
     Ok((
         MapRendererProxy::DynamicRenderer(Arc::new(Mutex::new(map_renderer))),
         bounds,
@@ -426,17 +792,27 @@ pub fn get_map_renderer_proxy_for_journey_data(
 
 // Return `true` if this update contains meaningful data.
 // Meaningful data means it is not ignored by the gps preprocessor.
-pub fn on_location_update(raw_data: gps_processor::RawData, received_timestamp_ms: i64) -> bool {
+/// `utc_offset_sec` is the UTC offset of the device's timezone when the point
+/// is recorded.
+pub fn on_location_update(
+    raw_data: gps_processor::RawData,
+    received_timestamp_ms: i64,
+    utc_offset_sec: Option<i32>,
+) -> Result<bool> {
     let state = get();
     // NOTE: On Android, we might received a batch of location updates that are out of order.
     // Not very sure why yet.
 
-    // we need handle a batch in one go so we hold the lock for the whole time
-    let mut gps_preprocessor = state.gps_preprocessor.lock().unwrap();
-    let mut main_map_state = state.main_map_state.lock().unwrap();
+    let LocationUpdateResult {
+        process_result,
+        last_kept_point: last_point,
+    } = state.recorder.on_location_update(
+        raw_data.clone(),
+        received_timestamp_ms,
+        utc_offset_sec.and_then(FixedOffset::east_opt),
+    )?;
 
-    let last_point = gps_preprocessor.last_kept_point();
-    let process_result = gps_preprocessor.preprocess(&raw_data);
+    let mut main_map_state = state.main_map_state.lock().unwrap();
     if !main_map_state.dropped_for_power_saving && main_map_state.layer_filter.current_journey {
         let line_to_add = match process_result {
             ProcessResult::Ignore => None,
@@ -449,7 +825,8 @@ pub fn on_location_update(raw_data: gps_processor::RawData, received_timestamp_m
         match line_to_add {
             None => (),
             Some((start, end)) => {
-                main_map_state.map_renderer.update(
+                main_map_state.map_renderer.update_with_layer(
+                    TILE_RANGE_LAYER_CURRENT_JOURNEY,
                     |journey_bitmap: &mut crate::journey_bitmap::JourneyBitmap, tile_changed| {
                         journey_bitmap.add_line_with_change_callback(
                             start.longitude,
@@ -463,15 +840,12 @@ pub fn on_location_update(raw_data: gps_processor::RawData, received_timestamp_m
             }
         };
     };
+    drop(main_map_state);
 
-    state
-        .storage
-        .record_gps_data(&raw_data, process_result, received_timestamp_ms);
-
-    match process_result {
+    Ok(match process_result {
         ProcessResult::Ignore => false,
         ProcessResult::Append | ProcessResult::NewSegment => true,
-    }
+    })
 }
 
 pub fn list_all_raw_data() -> Result<Vec<RawDataFile>> {
@@ -486,18 +860,29 @@ pub fn delete_raw_data_file(filename: String) -> Result<()> {
     get().storage.delete_raw_data_file(filename)
 }
 
+/// Moves the journey to the trash.
 pub fn delete_journey(journey_id: &str) -> Result<()> {
     get()
         .storage
-        .with_db_txn(|txn| txn.delete_journey(journey_id))
+        .with_db_txn(|txn| txn.trash_journey(journey_id))
 }
 
 pub fn toggle_raw_data_mode(enable: bool) {
     get().storage.toggle_raw_data_mode(enable)
 }
 
+pub fn get_gap_filling() -> bool {
+    get().storage.get_gap_filling()
+}
+
+/// Whether to connect plausible gaps (e.g. tunnels, subway rides) with
+/// inferred segments when finalizing ongoing journeys.
+pub fn set_gap_filling(enable: bool) -> Result<()> {
+    get().storage.set_gap_filling(enable)
+}
+
 #[frb]
-#[derive(Eq, Clone, Copy, Debug, PartialEq)]
+#[derive(Eq, Clone, Debug, PartialEq)]
 pub struct LayerFilter {
     #[frb(non_final)]
     pub current_journey: bool,
@@ -505,6 +890,25 @@ pub struct LayerFilter {
     pub default_kind: bool,
     #[frb(non_final)]
     pub flight_kind: bool,
+    /// Custom kinds are shown unless listed here, so new ones are visible.
+    #[frb(non_final)]
+    pub hidden_custom_kinds: Vec<String>,
+}
+
+impl LayerFilter {
+    #[frb(ignore)]
+    pub fn includes_kind(&self, kind: &JourneyKind) -> bool {
+        match kind {
+            JourneyKind::DefaultKind => self.default_kind,
+            JourneyKind::Flight => self.flight_kind,
+            JourneyKind::Custom(name) => !self.hidden_custom_kinds.contains(name),
+        }
+    }
+
+    #[frb(ignore)]
+    pub fn includes_all_kinds(&self) -> bool {
+        self.default_kind && self.flight_kind && self.hidden_custom_kinds.is_empty()
+    }
 }
 
 #[frb(ignore)]
@@ -512,11 +916,17 @@ pub struct MainMapState {
     pub map_renderer: MapRenderer,
     pub dropped_for_power_saving: bool,
     pub layer_filter: LayerFilter,
+    /* bumped whenever the main map is (re)loaded, so an outdated background
+    load started by `init_main_map` stops */
+    pub load_generation: u64,
+    /* whether the finalized journeys are split by kind, see
+    `set_main_map_layered` */
+    pub layered: bool,
 }
 
 #[frb(sync)]
 pub fn get_current_main_map_layer_filter() -> LayerFilter {
-    get().main_map_state.lock().unwrap().layer_filter
+    get().main_map_state.lock().unwrap().layer_filter.clone()
 }
 
 pub fn set_main_map_layer_filter(new_layer_filter: &LayerFilter) -> Result<()> {
@@ -524,36 +934,72 @@ pub fn set_main_map_layer_filter(new_layer_filter: &LayerFilter) -> Result<()> {
     let mut main_map_state = state.main_map_state.lock().unwrap();
 
     if *new_layer_filter != main_map_state.layer_filter {
-        main_map_state.layer_filter = *new_layer_filter;
+        main_map_state.layer_filter = new_layer_filter.clone();
         reload_main_map_bitmap(&state.storage, &mut main_map_state)?;
     }
     Ok(())
 }
 
+pub fn finalize_ongoing_journey() -> Result<bool> {
+    get()
+        .recorder
+        .finalize(|txn| txn.finalize_ongoing_journey())
+}
+
+/// Same as `finalize_ongoing_journey` but the journey will be snapped to the
+/// road network loaded by `set_map_matching_road_graph`.
+pub fn finalize_ongoing_journey_with_map_matching() -> Result<bool> {
+    let road_graph = get_map_matching_road_graph()?;
+    get()
+        .recorder
+        .finalize(move |txn| txn.finalize_ongoing_journey_with_road_graph(Some(&road_graph)))
+}
+
+/// Loads the prebuilt road graph file used for map matching. `None` unloads it.
 #[auto_context]
-fn reset_gps_preprocessor_if_finalized<F>(finalize_op: F) -> Result<bool>
-where
-    F: FnOnce(&mut main_db::Txn) -> Result<bool>,
-{
-    let state = get();
-    // TODO: I think we need to hold the gps_preprocessor lock first, otherwise
-    // we might have a deadlock because the locking story in `on_location_update`
-    // is quite complex. We should fix all the locking mess.
-    let mut gps_preprocessor = state.gps_preprocessor.lock().unwrap();
-    let finalized = state.storage.with_db_txn(finalize_op)?;
-    // when journey is finalized, we should reset the gps_preprocessor to prevent old state affecting new journey
-    if finalized {
-        *gps_preprocessor = GpsPreprocessor::new();
-    }
-    Ok(finalized)
+pub fn set_map_matching_road_graph(file_path: Option<String>) -> Result<()> {
+    let road_graph = match file_path {
+        None => None,
+        Some(file_path) => {
+            let road_graph = RoadGraph::load(&file_path)?;
+            info!(
+                "Road graph loaded: nodes={}, edges={}",
+                road_graph.node_count(),
+                road_graph.edge_count()
+            );
+            Some(Arc::new(road_graph))
+        }
+    };
+    *get().road_graph.lock().unwrap() = road_graph;
+    Ok(())
 }
 
-pub fn finalize_ongoing_journey() -> Result<bool> {
-    reset_gps_preprocessor_if_finalized(|txn| txn.finalize_ongoing_journey())
+#[frb(sync)]
+pub fn has_map_matching_road_graph() -> bool {
+    get().road_graph.lock().unwrap().is_some()
+}
+
+#[frb(ignore)]
+pub(super) fn get_map_matching_road_graph() -> Result<Arc<RoadGraph>> {
+    get()
+        .road_graph
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| anyhow!("Road graph for map matching is not loaded"))
 }
 
 pub fn try_auto_finalize_journey() -> Result<bool> {
-    reset_gps_preprocessor_if_finalized(|txn| txn.try_auto_finalize_journey())
+    get()
+        .recorder
+        .finalize(|txn| txn.try_auto_finalize_journey())
+}
+
+/// What was recovered from the recording journal on startup, e.g. points not
+/// saved yet when the app was killed.
+#[frb(sync)]
+pub fn get_recording_recovery_report() -> RecoveryReport {
+    get().storage.recording_recovery_report()
 }
 
 pub fn has_ongoing_journey() -> Result<bool> {
@@ -592,6 +1038,29 @@ pub fn list_all_journeys() -> Result<Vec<JourneyHeader>> {
         .with_db_txn(|txn| txn.query_journeys(None, None))
 }
 
+pub fn search_journeys(query: JourneySearchQuery) -> Result<Vec<JourneyHeader>> {
+    get().storage.with_db_txn(|txn| txn.search_journeys(&query))
+}
+
+/// Journeys passing through the area (e.g. a tapped point), the latest first.
+pub fn find_journeys_in_area(area: SearchArea) -> Result<Vec<JourneyHeader>> {
+    search_journeys(JourneySearchQuery {
+        area: Some(area),
+        ..Default::default()
+    })
+}
+
+/// Replaces the tags of a journey.
+pub fn set_journey_tags(journey_id: &str, tags: Vec<String>) -> Result<()> {
+    get()
+        .storage
+        .with_db_txn(|txn| txn.set_journey_tags(journey_id, tags))
+}
+
+pub fn list_all_tags() -> Result<Vec<String>> {
+    get().storage.with_db_txn(|txn| txn.list_all_tags())
+}
+
 pub fn has_journeys() -> Result<bool> {
     get().storage.with_db_txn(|txn| txn.has_journeys())
 }
@@ -647,7 +1116,7 @@ pub enum ExportResult {
 }
 
 enum InternalDataForExport {
-    Mldx(JourneyHeader, JourneyData),
+    Mldx(Box<JourneyHeader>, JourneyData),
     Fwss(JourneyData),
     Gpx(JourneyVector),
     Kml(JourneyVector),
@@ -676,7 +1145,7 @@ pub fn export_journey(
                     .get_journey_header(&journey_id)?
                     .expect("header must exist because we already got the data.");
                 Ok(Some(InternalDataForExport::Mldx(
-                    journey_header,
+                    Box::new(journey_header),
                     journey_data,
                 )))
             }
@@ -702,7 +1171,7 @@ pub fn export_journey(
             match data_for_export {
                 InternalDataForExport::Mldx(header, data) => {
                     archive::export_single_journey_as_mldx(
-                        header,
+                        *header,
                         data,
                         &mut file,
                         archive::SectionVersion::V1,
@@ -767,9 +1236,104 @@ pub fn export_raw_data_gpx_file(csv_filepath: String) -> Result<String> {
     Ok(gpx_path_str)
 }
 
+/// Moves all journeys to the trash, they can be restored until the trash is
+/// emptied.
 pub fn delete_all_journeys() -> Result<()> {
     info!("Delete all journeys");
-    get().storage.with_db_txn(|txn| txn.delete_all_journeys())
+    get().storage.with_db_txn(|txn| txn.trash_all_journeys())
+}
+
+/// The latest deleted first.
+pub fn list_trash() -> Result<Vec<TrashedJourney>> {
+    get().storage.with_db_txn(|txn| txn.list_trash())
+}
+
+pub fn get_map_renderer_proxy_for_trashed_journey(
+    journey_id: &str,
+) -> Result<(MapRendererProxy, Option<MapBounds>)> {
+    let journey_data = get()
+        .storage
+        .with_db_txn(|txn| txn.get_trashed_journey_data(journey_id))?;
+    get_map_renderer_proxy_for_journey_data_internal(journey_data)
+}
+
+pub fn restore_journey_from_trash(journey_id: &str) -> Result<()> {
+    get()
+        .storage
+        .with_db_txn(|txn| txn.restore_journey_from_trash(journey_id))
+}
+
+/// Returns the number of restored journeys. Journeys with the same id as an
+/// existing one are kept in the trash.
+pub fn restore_all_journeys_from_trash() -> Result<usize> {
+    get()
+        .storage
+        .with_db_txn(|txn| txn.restore_all_journeys_from_trash())
+}
+
+pub fn empty_trash() -> Result<()> {
+    get().storage.with_db_txn(|txn| txn.empty_trash())
+}
+
+#[frb(sync)]
+pub fn get_trash_retention_in_days() -> u32 {
+    get().storage.get_trash_retention_in_days()
+}
+
+/// Trashed journeys are dropped after `retention_in_days` days, 0 keeps them
+/// until the trash is emptied.
+pub fn set_trash_retention_in_days(retention_in_days: u32) -> Result<()> {
+    get().storage.set_trash_retention_in_days(retention_in_days)
+}
+
+/// Returns the ids of the two resulting journeys, the first one keeps `id`.
+pub fn split_journey(id: &str, at: SplitPosition) -> Result<(String, String)> {
+    get().storage.with_db_txn(|txn| txn.split_journey(id, at))
+}
+
+/// Returns the id of the merged journey.
+pub fn merge_journeys(ids: Vec<String>) -> Result<String> {
+    get().storage.with_db_txn(|txn| txn.merge_journeys(&ids))
+}
+
+/// Previous revisions of a journey (including a deleted one), the latest first.
+pub fn list_journey_revisions(journey_id: &str) -> Result<Vec<JourneyRevision>> {
+    get()
+        .storage
+        .with_db_txn(|txn| txn.list_journey_revisions(journey_id))
+}
+
+pub fn get_map_renderer_proxy_for_journey_revision(
+    history_id: i64,
+) -> Result<(MapRendererProxy, Option<MapBounds>)> {
+    let journey_data = get()
+        .storage
+        .with_db_txn(|txn| txn.get_journey_revision_data(history_id))?;
+    get_map_renderer_proxy_for_journey_data_internal(journey_data)
+}
+
+pub fn restore_journey_revision(history_id: i64) -> Result<()> {
+    get()
+        .storage
+        .with_db_txn(|txn| txn.restore_journey_revision(history_id))
+}
+
+pub fn purge_journey_history() -> Result<()> {
+    get().storage.with_db_txn(|txn| txn.purge_journey_history())
+}
+
+/// Returns `(max_revisions, max_age_in_days)`.
+#[frb(sync)]
+pub fn get_journey_history_policy() -> (u32, u32) {
+    get().storage.get_journey_history_policy()
+}
+
+/// Keeps at most `max_revisions` previous revisions of each journey, for at
+/// most `max_age_in_days` days. 0 disables the history / the age limit.
+pub fn set_journey_history_policy(max_revisions: u32, max_age_in_days: u32) -> Result<()> {
+    get()
+        .storage
+        .set_journey_history_policy(max_revisions, max_age_in_days)
 }
 
 pub fn update_journey_metadata(id: &str, journey_info: JourneyInfo) -> Result<()> {
@@ -834,7 +1398,8 @@ pub fn optimize_main_db() -> Result<()> {
 pub fn area_of_main_map() -> Option<u64> {
     let state = get();
     let mut main_map_state = state.main_map_state.lock().unwrap();
-    if main_map_state.dropped_for_power_saving {
+    // only part of the main map is loaded, the rest is still an overview
+    if main_map_state.dropped_for_power_saving || main_map_state.map_renderer.is_loading() {
         None
     } else {
         Some(main_map_state.map_renderer.get_current_area())
@@ -875,6 +1440,18 @@ pub mod for_testing {
     pub fn get_main_map_state() -> Arc<Mutex<MainMapState>> {
         super::get().main_map_state.clone()
     }
+
+    /// Blocks until the background load started by `init_main_map` is done.
+    pub fn wait_for_main_map_loaded() {
+        while get_main_map_state()
+            .lock()
+            .unwrap()
+            .map_renderer
+            .is_loading()
+        {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
 }
 
 #[frb(sync)]
@@ -3,9 +3,10 @@ use std::sync::{Arc, Mutex};
 use anyhow::{anyhow, Result};
 use flutter_rust_bridge::frb;
 
-use crate::journey_bitmap::JourneyBitmap;
+use crate::journey_bitmap::{JourneyBitmap, Tile, TileKey};
 use crate::journey_data::JourneyData;
 use crate::journey_vector::{JourneyVector, TrackSegment};
+use crate::track_selection;
 
 use super::api::{get, MapRendererProxy};
 use crate::renderer::MapRenderer;
@@ -16,7 +17,25 @@ const EPS: f64 = 1e-12_f64;
 const DEDUP_EPS: f64 = 1e-9_f64;
 const LINK_SNAP_DISTANCE_RATIO_THRESHOLD: f64 = 3.0_f64;
 
-// TODO: we want some test coverage here.
+/// The journey being edited. Vector segments are shared with the edit
+/// history, so keeping a step around only costs the segments it changed.
+enum EditData {
+    Vector(Vec<Arc<TrackSegment>>),
+    Bitmap(JourneyBitmap),
+}
+
+/// A single step of the edit history.
+enum EditStep {
+    Vector {
+        before: Vec<Arc<TrackSegment>>,
+        after: Vec<Arc<TrackSegment>>,
+    },
+    /// Only the changed tiles, `None` means the tile does not exist.
+    Bitmap {
+        before: Vec<(TileKey, Option<Tile>)>,
+        after: Vec<(TileKey, Option<Tile>)>,
+    },
+}
 
 #[frb(opaque)]
 pub struct EditSession {
@@ -24,8 +43,9 @@ pub struct EditSession {
     journey_revision: String,
     map_renderer: Arc<Mutex<MapRenderer>>,
     initial_bounds: Option<MapBounds>,
-    data: JourneyVector,
-    undo_stack: Vec<JourneyVector>,
+    data: EditData,
+    undo_stack: Vec<EditStep>,
+    redo_stack: Vec<EditStep>,
 }
 
 pub enum AddLinesOutcome {
@@ -56,6 +76,13 @@ impl std::fmt::Display for PrepareTrackPointsError {
 impl std::error::Error for PrepareTrackPointsError {}
 
 impl EditSession {
+    fn track_segments(&self) -> &[Arc<TrackSegment>] {
+        match &self.data {
+            EditData::Vector(track_segments) => track_segments,
+            EditData::Bitmap(_) => &[],
+        }
+    }
+
     fn point_distance(
         a: &crate::journey_vector::TrackPoint,
         b: &crate::journey_vector::TrackPoint,
@@ -105,7 +132,7 @@ impl EditSession {
     ) -> Option<(crate::journey_vector::TrackPoint, usize)> {
         let mut best_match: Option<(f64, crate::journey_vector::TrackPoint, usize)> = None;
 
-        for (segment_index, segment) in self.data.track_segments.iter().enumerate() {
+        for (segment_index, segment) in self.track_segments().iter().enumerate() {
             let pts = &segment.track_points;
             if pts.is_empty() {
                 continue;
@@ -136,16 +163,10 @@ impl EditSession {
         points: &[(f64, f64)],
         snap_endpoints: bool,
     ) -> Result<Vec<crate::journey_vector::TrackPoint>> {
-        let mut track_points: Vec<crate::journey_vector::TrackPoint> = points
-            .iter()
-            .map(|(lat, lng)| crate::journey_vector::TrackPoint {
-                latitude: *lat,
-                longitude: *lng,
-            })
-            .collect();
+        let mut track_points = Self::to_track_points(points);
 
         if snap_endpoints {
-            if self.data.track_segments.len() < 2 {
+            if self.track_segments().len() < 2 {
                 return Err(anyhow!(PrepareTrackPointsError::NeedsMultipleTracks));
             }
 
@@ -189,200 +210,54 @@ impl EditSession {
         Ok(Self::dedup_adjacent_track_points(track_points))
     }
 
-    fn build_bitmap_from_vector(vector: &JourneyVector) -> JourneyBitmap {
+    fn build_bitmap_from_segments(track_segments: &[Arc<TrackSegment>]) -> JourneyBitmap {
         let mut bitmap = JourneyBitmap::new();
-        bitmap.merge_vector(vector);
+        for track_segment in track_segments {
+            let track_points = &track_segment.track_points;
+            for (i, point) in track_points.iter().enumerate() {
+                let prev = &track_points[i.saturating_sub(1)];
+                bitmap.add_line(
+                    prev.longitude,
+                    prev.latitude,
+                    point.longitude,
+                    point.latitude,
+                );
+            }
+        }
         bitmap
     }
 
-    fn sync_renderer_from_data(&self) -> Result<()> {
-        let bitmap = Self::build_bitmap_from_vector(&self.data);
+    fn sync_renderer_from_data(&self) {
+        let bitmap = match &self.data {
+            EditData::Vector(track_segments) => Self::build_bitmap_from_segments(track_segments),
+            EditData::Bitmap(bitmap) => bitmap.clone(),
+        };
         let mut map_renderer = self.map_renderer.lock().unwrap();
         map_renderer.replace(bitmap);
-        Ok(())
     }
 
-    fn normalize_box(
-        start_lat: f64,
-        start_lng: f64,
-        end_lat: f64,
-        end_lng: f64,
-    ) -> (f64, f64, f64, f64) {
-        (
-            start_lat.min(end_lat),
-            start_lat.max(end_lat),
-            start_lng.min(end_lng),
-            start_lng.max(end_lng),
-        )
-    }
-
-    fn point_in_box(
-        p: &crate::journey_vector::TrackPoint,
-        min_lat: f64,
-        max_lat: f64,
-        min_lng: f64,
-        max_lng: f64,
-    ) -> bool {
-        p.latitude >= min_lat
-            && p.latitude <= max_lat
-            && p.longitude >= min_lng
-            && p.longitude <= max_lng
-    }
-
-    fn segment_intersections(
-        a: &crate::journey_vector::TrackPoint,
-        b: &crate::journey_vector::TrackPoint,
-        min_lat: f64,
-        max_lat: f64,
-        min_lng: f64,
-        max_lng: f64,
-    ) -> Vec<(f64, crate::journey_vector::TrackPoint)> {
-        let x0 = a.longitude;
-        let y0 = a.latitude;
-        let x1 = b.longitude;
-        let y1 = b.latitude;
-        let dx = x1 - x0;
-        let dy = y1 - y0;
-
-        let mut hits: Vec<(f64, crate::journey_vector::TrackPoint)> = Vec::new();
-
-        let mut push_hit = |t: f64, x: f64, y: f64| {
-            if !(-EPS..=1.0 + EPS).contains(&t) {
-                return;
-            }
-            let t = t.clamp(0.0, 1.0);
-            if hits.iter().any(|(t0, _)| (*t0 - t).abs() < DEDUP_EPS) {
-                return;
-            }
-            hits.push((
-                t,
-                crate::journey_vector::TrackPoint {
-                    latitude: y,
-                    longitude: x,
-                },
-            ));
-        };
-
-        if dx.abs() > EPS {
-            for x_edge in [min_lng, max_lng] {
-                let t = (x_edge - x0) / dx;
-                let y = y0 + t * dy;
-                if y >= min_lat - EPS && y <= max_lat + EPS {
-                    push_hit(t, x_edge, y);
-                }
-            }
-        }
-
-        if dy.abs() > EPS {
-            for y_edge in [min_lat, max_lat] {
-                let t = (y_edge - y0) / dy;
-                let x = x0 + t * dx;
-                if x >= min_lng - EPS && x <= max_lng + EPS {
-                    push_hit(t, x, y_edge);
+    fn patch_tiles(bitmap: &mut JourneyBitmap, tiles: &[(TileKey, Option<Tile>)]) {
+        for (key, tile) in tiles {
+            match tile {
+                Some(tile) => bitmap.insert_tile(key, tile.clone()),
+                None => {
+                    bitmap.remove_tile(key);
                 }
             }
         }
-
-        hits.sort_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
-        hits
     }
 
-    fn delete_points_in_box_segments(
-        segments: &[crate::journey_vector::TrackSegment],
-        min_lat: f64,
-        max_lat: f64,
-        min_lng: f64,
-        max_lng: f64,
-    ) -> Vec<crate::journey_vector::TrackSegment> {
-        let mut new_segments: Vec<crate::journey_vector::TrackSegment> = Vec::new();
-
-        for segment in segments {
-            let pts = &segment.track_points;
-            if pts.len() < 2 {
-                continue;
-            }
-
-            let mut current: Vec<crate::journey_vector::TrackPoint> = Vec::new();
-            if !Self::point_in_box(&pts[0], min_lat, max_lat, min_lng, max_lng) {
-                current.push(pts[0].clone());
-            }
-
-            for i in 0..(pts.len() - 1) {
-                let a = &pts[i];
-                let b = &pts[i + 1];
-                let inside_a = Self::point_in_box(a, min_lat, max_lat, min_lng, max_lng);
-                let inside_b = Self::point_in_box(b, min_lat, max_lat, min_lng, max_lng);
-                let hits = Self::segment_intersections(a, b, min_lat, max_lat, min_lng, max_lng);
-
-                match (inside_a, inside_b) {
-                    (false, false) => {
-                        if hits.len() >= 2 {
-                            let entry = hits.first().unwrap().1.clone();
-                            let exit = hits.last().unwrap().1.clone();
-
-                            if current.last() != Some(&entry) {
-                                current.push(entry);
-                            }
-                            if current.len() >= 2 {
-                                new_segments.push(crate::journey_vector::TrackSegment {
-                                    track_points: current,
-                                });
-                            }
-
-                            current = vec![exit];
-                            if current.last() != Some(b) {
-                                current.push(b.clone());
-                            }
-                        } else {
-                            if current.is_empty() {
-                                current.push(a.clone());
-                            }
-                            if current.last() != Some(b) {
-                                current.push(b.clone());
-                            }
-                        }
-                    }
-                    (true, true) => {}
-                    (false, true) => {
-                        if let Some(hit) = hits.first().map(|x| x.1.clone()) {
-                            if current.is_empty() {
-                                current.push(a.clone());
-                            }
-                            if current.last() != Some(&hit) {
-                                current.push(hit);
-                            }
-                        }
-                        if current.len() >= 2 {
-                            new_segments.push(crate::journey_vector::TrackSegment {
-                                track_points: current,
-                            });
-                        }
-                        current = Vec::new();
-                    }
-                    (true, false) => {
-                        if let Some(hit) = hits.last().map(|x| x.1.clone()) {
-                            current = vec![hit];
-                        } else {
-                            current = Vec::new();
-                        }
-                        if current.last() != Some(b) {
-                            current.push(b.clone());
-                        }
-                    }
-                }
-            }
-
-            if current.len() >= 2 {
-                new_segments.push(crate::journey_vector::TrackSegment {
-                    track_points: current,
-                });
+    fn sync_renderer_tiles(&self, tiles: &[(TileKey, Option<Tile>)]) {
+        let mut map_renderer = self.map_renderer.lock().unwrap();
+        map_renderer.update(|journey_bitmap, tile_changed| {
+            Self::patch_tiles(journey_bitmap, tiles);
+            for (key, _) in tiles {
+                tile_changed(*key);
             }
-        }
-
-        new_segments
+        });
     }
 
-    pub fn new(journey_id: String) -> Result<Option<Self>> {
+    pub fn new(journey_id: String) -> Result<Self> {
         let state = get();
         let (journey_data, journey_revision) = state.storage.with_db_txn(|txn| {
             Ok((
@@ -392,27 +267,59 @@ impl EditSession {
                     .revision,
             ))
         })?;
+        Ok(Self::new_with_data(
+            journey_id,
+            journey_revision,
+            journey_data,
+        ))
+    }
 
-        let journey_vector = match journey_data {
-            JourneyData::Vector(vector) => vector,
-            JourneyData::Bitmap(_) => {
-                // Cannot edit bitmap journeys.
-                return Ok(None);
+    /// Starts a session on the given data, `commit` will only succeed if the
+    /// journey is still at `journey_revision`.
+    #[frb(ignore)]
+    pub fn new_with_data(
+        journey_id: String,
+        journey_revision: String,
+        journey_data: JourneyData,
+    ) -> Self {
+        let (data, mut bitmap) = match journey_data {
+            JourneyData::Vector(vector) => {
+                let track_segments: Vec<Arc<TrackSegment>> =
+                    vector.track_segments.into_iter().map(Arc::new).collect();
+                let bitmap = Self::build_bitmap_from_segments(&track_segments);
+                (EditData::Vector(track_segments), bitmap)
+            }
+            JourneyData::Bitmap(bitmap) => {
+                let bitmap_for_renderer = bitmap.clone();
+                (EditData::Bitmap(bitmap), bitmap_for_renderer)
             }
         };
-
-        let mut bitmap = Self::build_bitmap_from_vector(&journey_vector);
         let initial_bounds = get_bounds_from_journey_bitmap(&mut bitmap);
         let map_renderer = Arc::new(Mutex::new(MapRenderer::new(bitmap)));
 
-        Ok(Some(Self {
+        Self {
             journey_id,
             journey_revision,
             map_renderer,
             initial_bounds,
-            data: journey_vector,
+            data,
             undo_stack: Vec::new(),
-        }))
+            redo_stack: Vec::new(),
+        }
+    }
+
+    /// The journey data with all edits applied.
+    #[frb(ignore)]
+    pub fn journey_data(&self) -> JourneyData {
+        match &self.data {
+            EditData::Vector(track_segments) => JourneyData::Vector(JourneyVector {
+                track_segments: track_segments
+                    .iter()
+                    .map(|track_segment| (**track_segment).clone())
+                    .collect(),
+            }),
+            EditData::Bitmap(bitmap) => JourneyData::Bitmap(bitmap.clone()),
+        }
     }
 
     #[frb(sync)]
@@ -420,8 +327,32 @@ impl EditSession {
         !self.undo_stack.is_empty()
     }
 
-    fn push_undo_checkpoint(&mut self, prev_data: JourneyVector) {
-        self.undo_stack.push(prev_data);
+    #[frb(sync)]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    fn push_step(&mut self, step: EditStep) {
+        self.undo_stack.push(step);
+        self.redo_stack.clear();
+    }
+
+    /// Moves the data to the state before (`forward == false`) or after the
+    /// step.
+    fn apply_step(&mut self, step: &EditStep, forward: bool) -> Result<()> {
+        match (step, &mut self.data) {
+            (EditStep::Vector { before, after }, EditData::Vector(track_segments)) => {
+                *track_segments = if forward { after } else { before }.clone();
+                self.sync_renderer_from_data();
+            }
+            (EditStep::Bitmap { before, after }, EditData::Bitmap(bitmap)) => {
+                let tiles = if forward { after } else { before };
+                Self::patch_tiles(bitmap, tiles);
+                self.sync_renderer_tiles(tiles);
+            }
+            _ => bail!("Edit step does not match the journey type"),
+        }
+        Ok(())
     }
 
     pub fn get_map_renderer_proxy(&self) -> Result<(MapRendererProxy, Option<MapBounds>)> {
@@ -432,13 +363,78 @@ impl EditSession {
     }
 
     pub fn undo(&mut self) -> Result<()> {
-        if let Some(previous) = self.undo_stack.pop() {
-            self.data = previous;
-            self.sync_renderer_from_data()?;
+        if let Some(step) = self.undo_stack.pop() {
+            self.apply_step(&step, false)?;
+            self.redo_stack.push(step);
         }
         Ok(())
     }
 
+    pub fn redo(&mut self) -> Result<()> {
+        if let Some(step) = self.redo_stack.pop() {
+            self.apply_step(&step, true)?;
+            self.undo_stack.push(step);
+        }
+        Ok(())
+    }
+
+    /// What `commit` would change, as the (removed, added) parts.
+    #[frb(ignore)]
+    pub fn pending_changes(&self) -> (JourneyBitmap, JourneyBitmap) {
+        let (mut original, mut current) = match &self.data {
+            EditData::Vector(track_segments) => {
+                let original = match self.undo_stack.first() {
+                    Some(EditStep::Vector { before, .. }) => before,
+                    _ => track_segments,
+                };
+                (
+                    Self::build_bitmap_from_segments(original),
+                    Self::build_bitmap_from_segments(track_segments),
+                )
+            }
+            EditData::Bitmap(bitmap) => {
+                // Only the tiles touched by the history can differ, the
+                // first time a tile shows up is its original state.
+                let mut original = JourneyBitmap::new();
+                let mut current = JourneyBitmap::new();
+                let mut seen = std::collections::HashSet::new();
+                for step in &self.undo_stack {
+                    let EditStep::Bitmap { before, .. } = step else {
+                        continue;
+                    };
+                    for (key, tile) in before {
+                        if !seen.insert(*key) {
+                            continue;
+                        }
+                        if let Some(tile) = tile {
+                            original.insert_tile(key, tile.clone());
+                        }
+                        bitmap.peek_tile_without_updating_cache(key, |tile| {
+                            if let Some(tile) = tile {
+                                current.insert_tile(key, tile.clone());
+                            }
+                        });
+                    }
+                }
+                (original, current)
+            }
+        };
+        let original_copy = original.clone();
+        original.difference(&current);
+        current.difference(&original_copy);
+        (original, current)
+    }
+
+    /// What `commit` would change, as the (removed, added) parts, so they
+    /// can be highlighted.
+    pub fn get_pending_changes(&self) -> Result<(MapRendererProxy, MapRendererProxy)> {
+        let (removed, added) = self.pending_changes();
+        Ok((
+            MapRendererProxy::StaticRenderer(Mutex::new(MapRenderer::new(removed))),
+            MapRendererProxy::StaticRenderer(Mutex::new(MapRenderer::new(added))),
+        ))
+    }
+
     pub fn delete_points_in_box(
         &mut self,
         start_lat: f64,
@@ -446,28 +442,122 @@ impl EditSession {
         end_lat: f64,
         end_lng: f64,
     ) -> Result<()> {
-        // TODO: Unable to properly handle cases spanning ±180° of longitude.
-
-        let (min_lat, max_lat, min_lng, max_lng) =
-            Self::normalize_box(start_lat, start_lng, end_lat, end_lng);
-        let new_segments = Self::delete_points_in_box_segments(
-            &self.data.track_segments,
-            min_lat,
-            max_lat,
-            min_lng,
-            max_lng,
-        );
-
-        // TODO: This equality check can be very expensive.
-        if new_segments != self.data.track_segments {
-            self.push_undo_checkpoint(self.data.clone());
-            self.data.track_segments = new_segments;
-            self.sync_renderer_from_data()?;
+        self.delete_in_polygon(&[
+            (start_lat, start_lng),
+            (start_lat, end_lng),
+            (end_lat, end_lng),
+            (end_lat, start_lng),
+        ])
+    }
+
+    /// Applies `edit` to each segment of a vector journey. Unchanged segments
+    /// are shared with the previous step.
+    fn edit_vector<F>(&mut self, edit: F) -> Result<()>
+    where
+        F: Fn(&TrackSegment) -> Vec<TrackSegment>,
+    {
+        let EditData::Vector(before) = &self.data else {
+            bail!("Only vector journeys can be edited this way.")
+        };
+        let mut changed = false;
+        let mut after: Vec<Arc<TrackSegment>> = Vec::with_capacity(before.len());
+        for track_segment in before {
+            let new_segments = edit(track_segment);
+            // TODO: This equality check can be expensive for long segments.
+            if new_segments.len() == 1 && new_segments[0] == **track_segment {
+                after.push(track_segment.clone());
+            } else {
+                changed = true;
+                after.extend(new_segments.into_iter().map(Arc::new));
+            }
         }
 
+        if changed {
+            self.push_step(EditStep::Vector {
+                before: before.clone(),
+                after: after.clone(),
+            });
+            self.data = EditData::Vector(after);
+            self.sync_renderer_from_data();
+        }
         Ok(())
     }
 
+    /// Applies `edit` with `pixels` (usually the pixels to add or remove) to
+    /// a bitmap journey. Only the tiles in `pixels` may be changed.
+    fn edit_bitmap<F>(&mut self, pixels: JourneyBitmap, edit: F) -> Result<()>
+    where
+        F: FnOnce(&mut JourneyBitmap, JourneyBitmap),
+    {
+        let EditData::Bitmap(bitmap) = &mut self.data else {
+            bail!("Only bitmap journeys can be edited this way.")
+        };
+        let keys: Vec<TileKey> = pixels.all_tile_keys().cloned().collect();
+        let snapshot = |bitmap: &JourneyBitmap| -> Vec<(TileKey, Option<Tile>)> {
+            keys.iter()
+                .map(|key| {
+                    (
+                        *key,
+                        bitmap.peek_tile_without_updating_cache(key, |tile| tile.cloned()),
+                    )
+                })
+                .collect()
+        };
+        let before = snapshot(bitmap);
+        edit(bitmap, pixels);
+        let after = snapshot(bitmap);
+
+        if before != after {
+            self.sync_renderer_tiles(&after);
+            self.push_step(EditStep::Bitmap { before, after });
+        }
+        Ok(())
+    }
+
+    fn to_track_points(points: &[(f64, f64)]) -> Vec<crate::journey_vector::TrackPoint> {
+        points
+            .iter()
+            .map(|(lat, lng)| crate::journey_vector::TrackPoint {
+                latitude: *lat,
+                longitude: *lng,
+            })
+            .collect()
+    }
+
+    /// Deletes everything inside the polygon (lasso) given by `points`
+    /// (lat, lng). Tracks are split where they cross its boundary.
+    pub fn delete_in_polygon(&mut self, points: &[(f64, f64)]) -> Result<()> {
+        let polygon = Self::to_track_points(points);
+        match &self.data {
+            EditData::Vector(_) => self.edit_vector(|track_segment| {
+                track_selection::delete_in_polygon(std::slice::from_ref(track_segment), &polygon)
+            }),
+            EditData::Bitmap(bitmap) => {
+                let pixels = bitmap.visited_pixels_in_polygon(&polygon);
+                self.edit_bitmap(pixels, |bitmap, pixels| bitmap.difference(&pixels))
+            }
+        }
+    }
+
+    /// Deletes everything along the brush path given by `points` (lat, lng).
+    /// Tracks are split where they cross the edge of the brush.
+    pub fn delete_along_path(&mut self, points: &[(f64, f64)], width_in_m: f64) -> Result<()> {
+        let path = Self::to_track_points(points);
+        match &self.data {
+            EditData::Vector(_) => self.edit_vector(|track_segment| {
+                track_selection::delete_along_path(
+                    std::slice::from_ref(track_segment),
+                    &path,
+                    width_in_m,
+                )
+            }),
+            EditData::Bitmap(bitmap) => {
+                let pixels = bitmap.visited_pixels_along_path(&path, width_in_m);
+                self.edit_bitmap(pixels, |bitmap, pixels| bitmap.difference(&pixels))
+            }
+        }
+    }
+
     pub fn add_lines(
         &mut self,
         points: &[(f64, f64)],
@@ -505,8 +595,29 @@ impl EditSession {
             .map(|point| (point.latitude, point.longitude))
             .collect();
 
-        self.push_undo_checkpoint(self.data.clone());
-        self.data.track_segments.push(TrackSegment { track_points });
+        let before = match &self.data {
+            EditData::Vector(track_segments) => track_segments.clone(),
+            EditData::Bitmap(_) => {
+                let mut lines = JourneyBitmap::new();
+                for window in render_points.windows(2) {
+                    let (start_lat, start_lng) = window[0];
+                    let (end_lat, end_lng) = window[1];
+                    lines.add_line(start_lng, start_lat, end_lng, end_lat);
+                }
+                self.edit_bitmap(lines, |bitmap, lines| bitmap.merge(lines))?;
+                return Ok(AddLinesOutcome::Added);
+            }
+        };
+        let mut after = before.clone();
+        after.push(Arc::new(TrackSegment {
+            track_points,
+            inferred: false,
+        }));
+        self.push_step(EditStep::Vector {
+            before,
+            after: after.clone(),
+        });
+        self.data = EditData::Vector(after);
 
         let mut map_renderer = self.map_renderer.lock().unwrap();
         map_renderer.update(|journey_bitmap, tile_changed| {
@@ -545,8 +656,7 @@ impl EditSession {
             }
             txn.update_journey_data_with_latest_postprocessor(
                 &self.journey_id,
-                // TODO: probably we could make this function drop self to avoid the clone.
-                JourneyData::Vector(self.data.clone()),
+                self.journey_data(),
             )?;
             Ok(())
         })
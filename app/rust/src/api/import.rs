@@ -16,7 +16,7 @@ use crate::journey_header::JourneyHeader;
 use crate::journey_vector::JourneyVector;
 use crate::{
     flight_track_processor, gps_processor::RawData, import_data, journey_data::JourneyData,
-    journey_header::JourneyKind,
+    journey_header::JourneyKind, map_matching,
 };
 
 #[derive(Debug)]
@@ -131,9 +131,14 @@ pub fn import_journey_data(
 
 pub enum ImportPreprocessor {
     None,
+    /// Parts detected as flights are handled like `FlightTrack`, see
+    /// `transport_mode`.
     Generic,
     FlightTrack,
     Spare,
+    /// `Generic` and then snap the track to the road network, see
+    /// `api::set_map_matching_road_graph`.
+    MapMatching,
 }
 
 #[auto_context]
@@ -149,7 +154,7 @@ pub fn process_vector_data(
             )
         }
         ImportPreprocessor::Generic => {
-            import_data::conversion::journey_vector_from_raw_data_with_gps_preprocessor(
+            import_data::conversion::journey_vector_from_raw_data_with_transport_mode_detection(
                 &vector_data.data,
                 Some(SegmentGapRule::Default),
             )
@@ -161,6 +166,14 @@ pub fn process_vector_data(
                 Some(SegmentGapRule::Spare),
             )
         }
+        ImportPreprocessor::MapMatching => {
+            let road_graph = api::get_map_matching_road_graph()?;
+            import_data::conversion::journey_vector_from_raw_data_with_gps_preprocessor(
+                &vector_data.data,
+                Some(SegmentGapRule::Default),
+            )
+            .map(|journey_vector| map_matching::match_journey_vector(&road_graph, journey_vector))
+        }
     };
 
     let journey_vector = journey_vector_opt.unwrap_or_else(|| JourneyVector {
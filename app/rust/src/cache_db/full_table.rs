@@ -26,7 +26,7 @@ pub fn get(conn: &Connection, layer_kind: &LayerKind) -> Result<Option<JourneyBi
     read_bitmap(
         conn,
         &format!("SELECT data FROM `{TABLE}` WHERE kind = ?1;"),
-        (layer_kind.to_sql(),),
+        (layer_kind.to_sql().as_ref(),),
     )
 }
 
@@ -36,7 +36,7 @@ pub fn set(conn: &Connection, layer_kind: &LayerKind, bitmap: &mut JourneyBitmap
     let data = to_blob(bitmap)?;
     conn.execute(
         &format!("INSERT OR REPLACE INTO `{TABLE}` (kind, data) VALUES (?1, ?2)"),
-        (layer_kind_sql, &data),
+        (layer_kind_sql.as_ref(), &data),
     )?;
     Ok(())
 }
@@ -44,7 +44,7 @@ pub fn set(conn: &Connection, layer_kind: &LayerKind, bitmap: &mut JourneyBitmap
 pub fn delete(conn: &Connection, layer_kind: &LayerKind) -> Result<()> {
     conn.execute(
         &format!("DELETE FROM `{TABLE}` WHERE kind = ?1;"),
-        (layer_kind.to_sql(),),
+        (layer_kind.to_sql().as_ref(),),
     )?;
     Ok(())
 }
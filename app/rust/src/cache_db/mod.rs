@@ -1,14 +1,20 @@
 use chrono::NaiveDate;
 use rusqlite::Connection;
+use std::borrow::Cow;
 use std::path::Path;
 
 use crate::{
-    achievement::AchievementReader, geo::GeoLookup, journey_bitmap::JourneyBitmap,
-    journey_data::JourneyData, journey_header::JourneyKind, main_db, utils,
+    achievement::AchievementReader,
+    geo::GeoLookup,
+    journey_bitmap::{BlockOverview, JourneyBitmap},
+    journey_data::JourneyData,
+    journey_header::JourneyKind,
+    main_db, utils,
 };
 
 mod bitmap_io;
 mod full_table;
+mod overview_table;
 mod range;
 
 mod v1;
@@ -16,31 +22,47 @@ pub use v1::CacheDbV1;
 
 use anyhow::Result;
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct CacheEntry {
     pub date: NaiveDate,
     pub kind: JourneyKind,
 }
 
 /// flutter_rust_bridge:ignore
-#[derive(Eq, Hash, Clone, Copy, Debug, PartialEq)]
+#[derive(Eq, Hash, Clone, Debug, PartialEq)]
 pub enum LayerKind {
     All,
     JourneyKind(JourneyKind),
 }
 
 impl LayerKind {
-    fn to_sql(self) -> &'static str {
+    fn to_sql(&self) -> Cow<'static, str> {
         match self {
-            LayerKind::All => "All",
-            LayerKind::JourneyKind(kind) => match kind {
-                JourneyKind::DefaultKind => "Default",
-                JourneyKind::Flight => "Flight",
-            },
+            LayerKind::All => "All".into(),
+            LayerKind::JourneyKind(kind) => kind.to_sql(),
         }
     }
 }
 
+/// Deletes the rows of `table` (keyed by `LayerKind::to_sql` in `kind`) for
+/// custom kinds not in `kinds`, e.g. once the last journey of a kind is gone.
+fn delete_unused_custom_kinds(conn: &Connection, table: &str, kinds: &[JourneyKind]) -> Result<()> {
+    let used: std::collections::HashSet<Cow<'static, str>> =
+        kinds.iter().map(JourneyKind::to_sql).collect();
+    let cached: Vec<String> = conn
+        .prepare(&format!(
+            "SELECT kind FROM `{table}` WHERE kind LIKE 'Custom:%';"
+        ))?
+        .query_map((), |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for kind in cached {
+        if !used.contains(kind.as_str()) {
+            conn.execute(&format!("DELETE FROM `{table}` WHERE kind = ?1;"), (&kind,))?;
+        }
+    }
+    Ok(())
+}
+
 /// Open `cache_dir/cache.db` and bring it up to the latest migration. A cache
 /// left behind by a newer major version is discarded and recreated: everything
 /// in it can be recomputed from the main db.
@@ -90,6 +112,12 @@ pub trait CacheDb {
     /// the entry's kind and `LayerKind::All`. Also clears any aggregate entries.
     fn invalidate(&mut self, entries: &[CacheEntry]) -> Result<()>;
 
+    /// Block overview (see `JourneyBitmap::block_overview`) of the full-range
+    /// bitmap for `layer_kind`, if one was computed before. It is cheap to
+    /// load but may be stale: it survives `invalidate`, so it can still show
+    /// data that was since deleted until the full bitmap is recomputed.
+    fn get_overview(&mut self, layer_kind: &LayerKind) -> Result<Option<BlockOverview>>;
+
     fn clear_all(&mut self) -> Result<()>;
     fn flush(&self) -> Result<()>;
 
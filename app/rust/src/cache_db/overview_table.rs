@@ -0,0 +1,60 @@
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, Transaction};
+
+use super::{full_table, LayerKind};
+use crate::journey_bitmap::BlockOverview;
+
+/// `JourneyBitmap::block_overview` of each full bitmap, used to show the main
+/// map before the full bitmap is loaded. Unlike the full table, entries
+/// survive invalidation, so they can be stale.
+pub const TABLE: &str = "journey_cache__overview";
+
+pub fn migrate_to_1_1(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS `{TABLE}` (
+                kind TEXT PRIMARY KEY NOT NULL UNIQUE,
+                data BLOB NOT NULL
+            )"
+        ),
+        (),
+    )?;
+    // full bitmaps cached before this table existed, later ones are kept in
+    // sync by the cache
+    let full: Vec<(String, Vec<u8>)> = tx
+        .prepare(&format!("SELECT kind, data FROM `{}`;", full_table::TABLE))?
+        .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (kind, data) in full {
+        let bitmap = crate::journey_data::deserialize_journey_bitmap(&data[..], false)?;
+        tx.execute(
+            &format!("INSERT INTO `{TABLE}` (kind, data) VALUES (?1, ?2)"),
+            (&kind, &bitmap.block_overview().serialize()?),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get(conn: &Connection, layer_kind: &LayerKind) -> Result<Option<BlockOverview>> {
+    let mut stmt = conn.prepare(&format!("SELECT data FROM `{TABLE}` WHERE kind = ?1;"))?;
+    stmt.query_row((layer_kind.to_sql().as_ref(),), |row| {
+        let data = row.get_ref(0)?.as_blob()?;
+        Ok(BlockOverview::deserialize(data))
+    })
+    .optional()?
+    .transpose()
+}
+
+pub fn set(conn: &Connection, layer_kind: &LayerKind, overview: &BlockOverview) -> Result<()> {
+    let data = overview.serialize()?;
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO `{TABLE}` (kind, data) VALUES (?1, ?2)"),
+        (layer_kind.to_sql().as_ref(), &data),
+    )?;
+    Ok(())
+}
+
+pub fn clear(conn: &Connection) -> Result<()> {
+    conn.execute(&format!("DELETE FROM `{TABLE}`;"), ())?;
+    Ok(())
+}
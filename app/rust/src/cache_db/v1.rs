@@ -3,17 +3,23 @@ use auto_context::auto_context;
 use chrono::NaiveDate;
 use rusqlite::Connection;
 
-use super::{full_table, range, CacheDb, CacheEntry, LayerKind};
-use strum::IntoEnumIterator;
+use super::{full_table, overview_table, range, CacheDb, CacheEntry, LayerKind};
 
 use crate::{
-    achievement::on_demand::OnDemandReader, achievement::AchievementReader, geo::GeoLookup,
-    journey_bitmap::JourneyBitmap, journey_data::JourneyData, journey_header::JourneyKind,
-    journey_snapshot::JourneySnapshot, main_db, utils,
+    achievement::on_demand::OnDemandReader,
+    achievement::AchievementReader,
+    geo::GeoLookup,
+    journey_bitmap::{BlockOverview, JourneyBitmap},
+    journey_data::JourneyData,
+    journey_snapshot::JourneySnapshot,
+    main_db, utils,
 };
 
-fn migrations() -> [utils::db::Migration<'static>; 1] {
-    [utils::db::Migration::new(1, 0, &full_table::migrate_to_1_0)]
+fn migrations() -> [utils::db::Migration<'static>; 2] {
+    [
+        utils::db::Migration::new(1, 0, &full_table::migrate_to_1_0),
+        utils::db::Migration::new(1, 1, &overview_table::migrate_to_1_1),
+    ]
 }
 
 /// Simple SQLite-backed implementation of [`CacheDb`] using a single full-table cache.
@@ -22,6 +28,9 @@ fn migrations() -> [utils::db::Migration<'static>; 1] {
 /// - **Full** (`journey_cache__full`): one bitmap per `LayerKind`, covering all
 ///   journeys in the database.
 ///
+/// Next to it, `journey_cache__overview` keeps the block overview of every
+/// full bitmap computed so far (see [`CacheDb::get_overview`]).
+///
 /// Only full-range queries (`range: None`) are cached. Explicit date range
 /// queries are always computed directly from the main DB without caching.
 /// Nothing derived is persisted here, so achievement answers are computed per
@@ -58,10 +67,14 @@ impl CacheDb for CacheDbV1 {
                     return Ok(bm);
                 }
 
-                let mut result = match *layer_kind {
+                let mut result = match layer_kind {
                     LayerKind::All => {
+                        let journey_kinds = txn.journey_kinds()?;
+                        for table in [full_table::TABLE, overview_table::TABLE] {
+                            super::delete_unused_custom_kinds(&self.conn, table, &journey_kinds)?;
+                        }
                         let mut bm = JourneyBitmap::new();
-                        for jk in JourneyKind::iter() {
+                        for jk in journey_kinds {
                             bm.merge(self.get_or_compute(
                                 txn,
                                 &LayerKind::JourneyKind(jk),
@@ -80,6 +93,7 @@ impl CacheDb for CacheDbV1 {
                 };
 
                 full_table::set(&self.conn, layer_kind, &mut result)?;
+                overview_table::set(&self.conn, layer_kind, &result.block_overview())?;
                 Ok(result)
             }
         }
@@ -93,7 +107,7 @@ impl CacheDb for CacheDbV1 {
         // Nothing derived is stored, so there is nothing to attribute here.
         _geo: Option<&dyn GeoLookup>,
     ) -> Result<()> {
-        let layer_kind = LayerKind::JourneyKind(entry.kind);
+        let layer_kind = LayerKind::JourneyKind(entry.kind.clone());
 
         // Invalidate All aggregate.
         full_table::delete(&self.conn, &LayerKind::All)?;
@@ -104,6 +118,21 @@ impl CacheDb for CacheDbV1 {
             full_table::set(&self.conn, &layer_kind, &mut bm)?;
         }
 
+        // Overviews are kept up to date instead, there is no cheap way to
+        // recompute them.
+        let mut data_overview = None;
+        for layer_kind in [layer_kind, LayerKind::All] {
+            if let Some(mut overview) = overview_table::get(&self.conn, &layer_kind)? {
+                let data_overview = data_overview.get_or_insert_with(|| {
+                    let mut bm = JourneyBitmap::new();
+                    data.merge_into_with_partial_clone(&mut bm);
+                    bm.block_overview()
+                });
+                overview.merge(data_overview);
+                overview_table::set(&self.conn, &layer_kind, &overview)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -112,18 +141,26 @@ impl CacheDb for CacheDbV1 {
         // Delete affected kind entries and All entry.
         let mut deleted = std::collections::HashSet::new();
         for entry in entries {
-            let layer_kind = LayerKind::JourneyKind(entry.kind);
-            if deleted.insert(layer_kind) {
+            let layer_kind = LayerKind::JourneyKind(entry.kind.clone());
+            if deleted.insert(layer_kind.clone()) {
                 full_table::delete(&self.conn, &layer_kind)?;
             }
         }
         full_table::delete(&self.conn, &LayerKind::All)?;
+        // Overviews are kept: a stale one is still a good placeholder until
+        // the full bitmap is recomputed, which also replaces the overview.
         Ok(())
     }
 
     #[auto_context]
     fn clear_all(&mut self) -> Result<()> {
-        full_table::clear(&self.conn)
+        full_table::clear(&self.conn)?;
+        overview_table::clear(&self.conn)
+    }
+
+    #[auto_context]
+    fn get_overview(&mut self, layer_kind: &LayerKind) -> Result<Option<BlockOverview>> {
+        overview_table::get(&self.conn, layer_kind)
     }
 
     fn flush(&self) -> Result<()> {
@@ -18,11 +18,7 @@ pub fn process(raw_data: &[Vec<RawData>]) -> Option<JourneyVector> {
             .iter()
             .map(|data| data.point.clone())
             .collect();
-        for seg in PathInterpolator::split_trajectory_at_180(&points) {
-            if let Some(result) = PathInterpolator::interpolate_one_seg(&seg, STEP_LENGTH) {
-                track_segments.push(result)
-            }
-        }
+        track_segments.extend(interpolate_path(&points, STEP_LENGTH));
     }
 
     if track_segments.is_empty() {
@@ -32,6 +28,15 @@ pub fn process(raw_data: &[Vec<RawData>]) -> Option<JourneyVector> {
     }
 }
 
+/// Interpolates `points` every `step_length` meters, the result is split into
+/// multiple segments if it crosses the 180th meridian.
+pub(crate) fn interpolate_path(points: &[Point], step_length: f64) -> Vec<TrackSegment> {
+    PathInterpolator::split_trajectory_at_180(points)
+        .iter()
+        .filter_map(|seg| PathInterpolator::interpolate_one_seg(seg, step_length))
+        .collect()
+}
+
 struct PathInterpolator {}
 
 impl PathInterpolator {
@@ -126,7 +131,10 @@ impl PathInterpolator {
         if track_points.is_empty() {
             None
         } else {
-            Some(TrackSegment { track_points })
+            Some(TrackSegment {
+                track_points,
+                inferred: false,
+            })
         }
     }
 
@@ -0,0 +1,90 @@
+// Dead-reckoning gap filling.
+//
+// When recording underground (tunnels, subway rides), we lose GPS for a while
+// and `GpsPreprocessor` starts a new segment when the signal comes back. If the
+// two ends of such a gap imply a plausible travel speed, we connect them with
+// an interpolated path. These segments are marked as `inferred` so they can be
+// treated differently from the recorded ones.
+use crate::{
+    flight_track_processor,
+    gps_processor::{Point, PreprocessedData, ProcessResult},
+    journey_vector::{JourneyVector, TrackSegment},
+};
+
+// all speeds are in m/s
+const MIN_GAP_SPEED: f64 = 1.0;
+// fast enough for high speed trains in tunnels
+const MAX_GAP_SPEED: f64 = 70.0;
+const MIN_GAP_DISTANCE_IN_M: f64 = 20.0;
+const MAX_GAP_DISTANCE_IN_M: f64 = 100_000.0;
+const MAX_GAP_DURATION_IN_SEC: i64 = 2 * 60 * 60;
+const STEP_LENGTH_IN_M: f64 = 200.0;
+
+fn infer_gap(from: &PreprocessedData, to: &PreprocessedData) -> Option<Vec<TrackSegment>> {
+    let duration_in_sec = to.timestamp_sec? - from.timestamp_sec?;
+    if duration_in_sec <= 0 || duration_in_sec > MAX_GAP_DURATION_IN_SEC {
+        return None;
+    }
+    let from = Point::from(&from.track_point);
+    let to = Point::from(&to.track_point);
+    let distance_in_m = from.haversine_distance(&to);
+    if !(MIN_GAP_DISTANCE_IN_M..=MAX_GAP_DISTANCE_IN_M).contains(&distance_in_m) {
+        return None;
+    }
+    let speed = distance_in_m / duration_in_sec as f64;
+    if !(MIN_GAP_SPEED..=MAX_GAP_SPEED).contains(&speed) {
+        return None;
+    }
+    let track_segments: Vec<TrackSegment> =
+        flight_track_processor::interpolate_path(&[from, to], STEP_LENGTH_IN_M)
+            .into_iter()
+            .map(|track_segment| TrackSegment {
+                track_points: track_segment.track_points,
+                inferred: true,
+            })
+            .collect();
+    if track_segments.is_empty() {
+        None
+    } else {
+        Some(track_segments)
+    }
+}
+
+/// Inserts inferred segments between the segments of `journey_vector` where
+/// the gap looks like a plausible trip. `journey_vector` must be the result of
+/// `gps_processor::build_journey_vector` on `data`.
+pub fn fill_gaps(data: &[PreprocessedData], journey_vector: JourneyVector) -> JourneyVector {
+    // the same segmentation as `build_journey_vector`
+    let mut gaps = Vec::new();
+    let mut last: Option<&PreprocessedData> = None;
+    for current in data {
+        match current.process_result {
+            ProcessResult::Ignore => (),
+            ProcessResult::Append => last = Some(current),
+            ProcessResult::NewSegment => {
+                if let Some(last) = last {
+                    gaps.push(infer_gap(last, current));
+                }
+                last = Some(current);
+            }
+        }
+    }
+    if gaps.len() + 1 != journey_vector.track_segments.len() {
+        warn!(
+            "[gap_filling] unexpected number of gaps: {}, number of segments: {}",
+            gaps.len(),
+            journey_vector.track_segments.len()
+        );
+        return journey_vector;
+    }
+
+    let mut track_segments = Vec::new();
+    let mut gaps = gaps.into_iter();
+    for track_segment in journey_vector.track_segments {
+        track_segments.push(track_segment);
+        if let Some(Some(inferred)) = gaps.next() {
+            track_segments.extend(inferred);
+        }
+    }
+    JourneyVector { track_segments }
+}
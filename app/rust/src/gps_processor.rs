@@ -1,4 +1,5 @@
 use crate::{
+    journey_bitmap::{BITMAP_WIDTH_OFFSET, MAP_WIDTH_OFFSET, TILE_WIDTH_OFFSET},
     journey_date_picker::JourneyDatePicker,
     journey_header::{JourneyHeader, JourneyType},
     journey_vector::{JourneyVector, TrackPoint, TrackSegment},
@@ -14,6 +15,15 @@ pub struct Point {
     pub longitude: f64,
 }
 
+impl From<&TrackPoint> for Point {
+    fn from(track_point: &TrackPoint) -> Self {
+        Point {
+            latitude: track_point.latitude,
+            longitude: track_point.longitude,
+        }
+    }
+}
+
 impl Point {
     pub fn haversine_distance(&self, other: &Point) -> f64 {
         use std::f64::consts::PI;
@@ -472,6 +482,7 @@ impl GpsPreprocessor {
     }
 }
 
+#[derive(Clone)]
 pub struct PreprocessedData {
     pub timestamp_sec: Option<i64>,
     pub track_point: TrackPoint,
@@ -492,6 +503,7 @@ pub fn build_journey_vector(
         if need_break && !current_segment.is_empty() {
             segments.push(TrackSegment {
                 track_points: current_segment,
+                inferred: false,
             });
             current_segment = Vec::new();
         }
@@ -501,7 +513,7 @@ pub fn build_journey_vector(
                     .timestamp_sec
                     .map(|x| DateTime::from_timestamp(x, 0).unwrap())
                 {
-                    journey_date_picker.add_point(time, &data.track_point);
+                    journey_date_picker.add_point(time, None, &data.track_point);
                 }
             }
             current_segment.push(data.track_point);
@@ -510,6 +522,7 @@ pub fn build_journey_vector(
     if !current_segment.is_empty() {
         segments.push(TrackSegment {
             track_points: current_segment,
+            inferred: false,
         });
     }
 
@@ -522,15 +535,205 @@ pub fn build_journey_vector(
     }
 }
 
+/// A track point projected into the pixel space of `JourneyBitmap` (i.e. the
+/// finest zoom level we ever rasterize at). Working in this space lets us
+/// express "this does not change the bitmap" directly as a tolerance in pixels.
+/// `x` is unwrapped along a segment so crossing the antimeridian does not
+/// produce a jump.
+#[derive(Clone, Debug)]
+struct ProjectedPoint {
+    x: f64,
+    y: f64,
+}
+
+impl ProjectedPoint {
+    fn map_size() -> f64 {
+        f64::powi(
+            2.0,
+            (MAP_WIDTH_OFFSET + TILE_WIDTH_OFFSET + BITMAP_WIDTH_OFFSET) as i32,
+        )
+    }
+
+    fn of_track_point(track_point: &TrackPoint) -> Self {
+        use std::f64::consts::PI;
+        let n = Self::map_size();
+        let lat_rad = track_point.latitude.to_radians();
+        ProjectedPoint {
+            x: (track_point.longitude + 180.0) / 360.0 * n,
+            y: (1.0 - ((lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI)) / 2.0 * n,
+        }
+    }
+
+    fn to_track_point(&self) -> TrackPoint {
+        use std::f64::consts::PI;
+        let n = Self::map_size();
+        let longitude = (self.x / n * 360.0).rem_euclid(360.0) - 180.0;
+        let latitude = f64::atan(f64::sinh(PI * (1.0 - 2.0 * self.y / n))).to_degrees();
+        TrackPoint {
+            latitude,
+            longitude,
+        }
+    }
+
+    fn distance(&self, other: &ProjectedPoint) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+
+    fn distance_to_segment(&self, start: &ProjectedPoint, end: &ProjectedPoint) -> f64 {
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let length_squared = dx * dx + dy * dy;
+        if length_squared == 0.0 {
+            return self.distance(start);
+        }
+        let t =
+            (((self.x - start.x) * dx + (self.y - start.y) * dy) / length_squared).clamp(0.0, 1.0);
+        self.distance(&ProjectedPoint {
+            x: start.x + t * dx,
+            y: start.y + t * dy,
+        })
+    }
+}
+
+fn project_track_points(track_points: &[TrackPoint]) -> Vec<ProjectedPoint> {
+    let map_size = ProjectedPoint::map_size();
+    let mut projected: Vec<ProjectedPoint> = Vec::with_capacity(track_points.len());
+    for track_point in track_points {
+        let mut point = ProjectedPoint::of_track_point(track_point);
+        if let Some(prev) = projected.last() {
+            // keep the segment continuous across the antimeridian
+            point.x += ((prev.x - point.x) / map_size).round() * map_size;
+        }
+        projected.push(point);
+    }
+    projected
+}
+
+// Removes single points that shoot away and immediately come back. This is the
+// typical shape of GPS noise when we are not moving (e.g. indoor), and a real
+// movement would have more than one point on the way out.
+fn remove_spikes(track_points: Vec<TrackPoint>) -> Vec<TrackPoint> {
+    const SPIKE_MIN_LEG_IN_M: f64 = 15.0;
+    const SPIKE_MAX_LEG_IN_M: f64 = 300.0;
+    const SPIKE_MAX_RETURN_RATIO: f64 = 0.25;
+
+    if track_points.len() < 3 {
+        return track_points;
+    }
+    let to_point = |track_point: &TrackPoint| Point {
+        latitude: track_point.latitude,
+        longitude: track_point.longitude,
+    };
+    let last_index = track_points.len() - 1;
+    let mut result: Vec<TrackPoint> = Vec::with_capacity(track_points.len());
+    for (i, track_point) in track_points.iter().enumerate() {
+        if let Some(prev) = result.last().filter(|_| i < last_index) {
+            let (prev, curr) = (to_point(prev), to_point(track_point));
+            let next = to_point(&track_points[i + 1]);
+            let leg_out = prev.haversine_distance(&curr);
+            let leg_back = curr.haversine_distance(&next);
+            let shorter_leg = leg_out.min(leg_back);
+            let is_spike = shorter_leg >= SPIKE_MIN_LEG_IN_M
+                && leg_out.max(leg_back) <= SPIKE_MAX_LEG_IN_M
+                && prev.haversine_distance(&next) < shorter_leg * SPIKE_MAX_RETURN_RATIO;
+            if is_spike {
+                continue;
+            }
+        }
+        result.push(track_point.clone());
+    }
+    result
+}
+
+// A 3-point median filter for GPS jitter. A point is only moved if it stays
+// within `SMOOTHING_MAX_SHIFT_IN_PX` so this never changes the shape of the
+// track in a way that is visible on the bitmap.
+fn smooth(track_points: Vec<TrackPoint>) -> Vec<TrackPoint> {
+    const SMOOTHING_MAX_SHIFT_IN_PX: f64 = 1.0;
+
+    if track_points.len() < 3 {
+        return track_points;
+    }
+    let projected = project_track_points(&track_points);
+    let median = |a: f64, b: f64, c: f64| a.max(b).min(a.min(b).max(c));
+    let last_index = track_points.len() - 1;
+    track_points
+        .into_iter()
+        .enumerate()
+        .map(|(i, track_point)| {
+            if i == 0 || i == last_index {
+                return track_point;
+            }
+            let (prev, curr, next) = (&projected[i - 1], &projected[i], &projected[i + 1]);
+            let smoothed = ProjectedPoint {
+                x: median(prev.x, curr.x, next.x),
+                y: median(prev.y, curr.y, next.y),
+            };
+            let shift = smoothed.distance(curr);
+            if shift == 0.0 || shift > SMOOTHING_MAX_SHIFT_IN_PX {
+                track_point
+            } else {
+                smoothed.to_track_point()
+            }
+        })
+        .collect()
+}
+
+// Douglas-Peucker in bitmap pixel space. The tolerance is below one pixel so the
+// simplified track covers (almost) exactly the same pixels as the original one.
+fn simplify(track_points: Vec<TrackPoint>) -> Vec<TrackPoint> {
+    const SIMPLIFICATION_TOLERANCE_IN_PX: f64 = 0.5;
+
+    if track_points.len() < 3 {
+        return track_points;
+    }
+    let projected = project_track_points(&track_points);
+    let mut keep = vec![false; track_points.len()];
+    keep[0] = true;
+    keep[track_points.len() - 1] = true;
+    // using an explicit stack instead of recursion, a track can be very long.
+    let mut stack = vec![(0, track_points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+        let (farthest_index, farthest_distance) = (start + 1..end)
+            .map(|i| {
+                (
+                    i,
+                    projected[i].distance_to_segment(&projected[start], &projected[end]),
+                )
+            })
+            .fold((start, -1.0), |acc, x| if x.1 > acc.1 { x } else { acc });
+        if farthest_distance > SIMPLIFICATION_TOLERANCE_IN_PX {
+            keep[farthest_index] = true;
+            stack.push((start, farthest_index));
+            stack.push((farthest_index, end));
+        }
+    }
+    track_points
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(track_point, keep)| if keep { Some(track_point) } else { None })
+        .collect()
+}
+
 pub struct GpsPostprocessor {}
 
 impl GpsPostprocessor {
     pub fn process(journey_vector: JourneyVector) -> JourneyVector {
-        journey_vector
+        let track_segments = journey_vector
+            .track_segments
+            .into_iter()
+            .map(|track_segment| TrackSegment {
+                track_points: simplify(smooth(remove_spikes(track_segment.track_points))),
+                inferred: track_segment.inferred,
+            })
+            .collect();
+        JourneyVector { track_segments }
     }
 
     pub fn current_algo() -> String {
-        "0".to_string()
+        "1".to_string()
     }
 
     // When introducing a new algorithm, remember to update the
@@ -538,10 +741,9 @@ impl GpsPostprocessor {
     pub fn outdated_algo(journey_header: &JourneyHeader) -> bool {
         match journey_header.journey_type {
             JourneyType::Bitmap => false,
-            #[allow(clippy::redundant_pattern_matching)]
-            JourneyType::Vector => match journey_header.postprocessor_algo {
+            JourneyType::Vector => match &journey_header.postprocessor_algo {
                 None => true,
-                Some(_) => false,
+                Some(algo) => *algo != Self::current_algo(),
             },
         }
     }
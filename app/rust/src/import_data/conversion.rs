@@ -6,6 +6,7 @@ use crate::gps_processor::{
 use crate::journey_date_picker::JourneyDatePicker;
 use crate::journey_header::JourneyKind;
 use crate::journey_vector::{JourneyVector, TrackPoint};
+use crate::transport_mode;
 use chrono::{Local, TimeZone, Utc};
 
 /// `segment_gap_rule_for_preprocessor = None` meaning disable preprocessor
@@ -47,6 +48,43 @@ pub fn journey_vector_from_raw_data_with_gps_preprocessor(
         .expect("Impossible, `preprocessed_data` does not contain error")
 }
 
+/// Same as `journey_vector_from_raw_data_with_gps_preprocessor`, except that
+/// parts detected as flights are handled by the flight track processor.
+pub fn journey_vector_from_raw_data_with_transport_mode_detection(
+    raw_data: &[Vec<RawData>],
+    segment_gap_rule_for_preprocessor: Option<SegmentGapRule>,
+) -> Option<JourneyVector> {
+    let parts = transport_mode::split_by_journey_kind(raw_data);
+    if parts.iter().all(|(kind, _)| *kind != JourneyKind::Flight) {
+        return journey_vector_from_raw_data_with_gps_preprocessor(
+            raw_data,
+            segment_gap_rule_for_preprocessor,
+        );
+    }
+
+    let track_segments: Vec<_> = parts
+        .into_iter()
+        .filter_map(|(kind, raw_data)| {
+            let raw_data = [raw_data];
+            match kind {
+                JourneyKind::Flight => flight_track_processor::process(&raw_data),
+                JourneyKind::DefaultKind | JourneyKind::Custom(_) => {
+                    journey_vector_from_raw_data_with_gps_preprocessor(
+                        &raw_data,
+                        segment_gap_rule_for_preprocessor,
+                    )
+                }
+            }
+        })
+        .flat_map(|journey_vector| journey_vector.track_segments)
+        .collect();
+    if track_segments.is_empty() {
+        None
+    } else {
+        Some(JourneyVector { track_segments })
+    }
+}
+
 pub fn journey_vector_from_raw_data_with_flight_track_processor(
     raw_data: &[Vec<RawData>],
 ) -> Option<JourneyVector> {
@@ -66,6 +104,7 @@ pub fn journey_info_from_raw_vector_data(raw_vector_data: &[Vec<RawData>]) -> Jo
             if let Some(timestamp) = time_from_raw_data(raw_data) {
                 journey_date_picker.add_point(
                     timestamp,
+                    None,
                     &TrackPoint {
                         latitude: raw_data.point.latitude,
                         longitude: raw_data.point.longitude,
@@ -84,6 +123,6 @@ pub fn journey_info_from_raw_vector_data(raw_vector_data: &[Vec<RawData>]) -> Jo
         start_time: journey_date_picker.min_time(),
         end_time: journey_date_picker.max_time(),
         note: None,
-        journey_kind: JourneyKind::DefaultKind,
+        journey_kind: transport_mode::suggest_journey_kind(raw_vector_data),
     }
 }
@@ -1,3 +1,4 @@
+use crate::journey_vector::TrackPoint;
 use crate::utils;
 use anyhow::Result;
 use bitvec::prelude::*;
@@ -30,12 +31,113 @@ pub const MIPMAP_BIT_SIZE: usize = {
 const ALL_OFFSET: i16 = TILE_WIDTH_OFFSET + BITMAP_WIDTH_OFFSET;
 const TILE_ZSTD_COMPRESS_LEVEL: i32 = 3;
 
+// Erasing works on global pixel coordinates, i.e. the pixels of the map at the
+// zoom level where a single pixel is a single bit of a block.
+const PIXEL_ZOOM: i32 = (ALL_OFFSET + MAP_WIDTH_OFFSET) as i32;
+const PIXEL_MAP_WIDTH: f64 = (MAP_WIDTH << ALL_OFFSET) as f64;
+const MAX_MERCATOR_LAT: f64 = 85.051_128_78;
+const EARTH_CIRCUMFERENCE_IN_M: f64 = 40_075_016.686;
+
 fn interpolate_x_at_y(x0: i32, y0: i32, x1: i32, y1: i32, y: i32) -> i32 {
     debug_assert_ne!(y0, y1);
     let t = (y as f64 - y0 as f64) / (y1 as f64 - y0 as f64);
     (x0 as f64 + (x1 as f64 - x0 as f64) * t).round() as i32
 }
 
+fn lng_lat_to_pixel(lng: f64, lat: f64) -> (f64, f64) {
+    let wrapped_lng = (lng + 180.0).rem_euclid(360.0) - 180.0;
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT);
+    let (x, y) = utils::lng_lat_to_tile_x_y(wrapped_lng, lat, PIXEL_ZOOM);
+    (x as f64, y as f64)
+}
+
+/// Projects `points` to pixel coordinates. `x` is unwrapped so every edge
+/// takes the shorter way, which means it may fall outside of the map when the
+/// path crosses the antimeridian.
+fn project_path(points: &[TrackPoint]) -> Vec<(f64, f64)> {
+    let mut projected: Vec<(f64, f64)> = Vec::with_capacity(points.len());
+    for point in points {
+        let (mut x, y) = lng_lat_to_pixel(point.longitude, point.latitude);
+        if let Some((prev_x, _)) = projected.last() {
+            if x - prev_x > PIXEL_MAP_WIDTH / 2.0 {
+                x -= PIXEL_MAP_WIDTH;
+            } else if prev_x - x > PIXEL_MAP_WIDTH / 2.0 {
+                x += PIXEL_MAP_WIDTH;
+            }
+        }
+        projected.push((x, y));
+    }
+    projected
+}
+
+fn meters_to_pixels(meters: f64, lat: f64) -> f64 {
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT);
+    meters * PIXEL_MAP_WIDTH / (EARTH_CIRCUMFERENCE_IN_M * lat.to_radians().cos())
+}
+
+// even-odd rule
+fn point_in_polygon(polygon: &[(f64, f64)], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (xi, yi) = polygon[i];
+        let (xj, yj) = polygon[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// start, end and the brush radius, in pixels
+type BrushEdge = ((f64, f64), (f64, f64), f64);
+
+fn distance_sq_to_edge(a: (f64, f64), b: (f64, f64), x: f64, y: f64) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((x - a.0) * dx + (y - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (px, py) = (a.0 + t * dx - x, a.1 + t * dy - y);
+    px * px + py * py
+}
+
+#[derive(Clone, Copy)]
+struct PixelBounds {
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+impl PixelBounds {
+    fn of_points(points: &[(f64, f64)], margin: f64) -> Self {
+        let mut bounds = PixelBounds {
+            min_x: f64::INFINITY,
+            min_y: f64::INFINITY,
+            max_x: f64::NEG_INFINITY,
+            max_y: f64::NEG_INFINITY,
+        };
+        for (x, y) in points {
+            bounds.min_x = bounds.min_x.min(x - margin);
+            bounds.min_y = bounds.min_y.min(y - margin);
+            bounds.max_x = bounds.max_x.max(x + margin);
+            bounds.max_y = bounds.max_y.max(y + margin);
+        }
+        bounds
+    }
+
+    fn intersects(&self, min_x: f64, min_y: f64, width: f64) -> bool {
+        min_x <= self.max_x
+            && min_x + width >= self.min_x
+            && min_y <= self.max_y
+            && min_y + width >= self.min_y
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, PartialOrd, Ord)]
 pub struct TileKey {
     pub x: u16,
@@ -144,6 +246,10 @@ impl JourneyBitmap {
         self.tiles.keys()
     }
 
+    pub fn remove_tile(&mut self, key: &TileKey) -> Option<Tile> {
+        self.tiles.remove(key)
+    }
+
     pub fn insert_tile(&mut self, key: &TileKey, tile: Tile) {
         if !key.is_in_bounds() {
             warn!(
@@ -338,6 +444,26 @@ impl JourneyBitmap {
         }
     }
 
+    /// The non-empty blocks of every tile, a cheap placeholder while the
+    /// bitmap is loading. A block is a single pixel at zoom +
+    /// buffer_size_power = 16, so up to that level it renders exactly like
+    /// `self` (see `TileShader2::add_overview_bits`).
+    pub fn block_overview(&self) -> BlockOverview {
+        let mut overview = BlockOverview::new();
+        for (key, tile) in &self.tiles {
+            let mut block_keys = BlockKeyBitset::new();
+            for (block_key, block) in tile.iter() {
+                if !block.is_empty() {
+                    block_keys.set(block_key);
+                }
+            }
+            if !block_keys.is_empty() {
+                overview.tiles.insert(*key, block_keys);
+            }
+        }
+        overview
+    }
+
     pub fn difference(&mut self, other_journey_bitmap: &JourneyBitmap) {
         for (tile_key, other_tile) in &other_journey_bitmap.tiles {
             if let Some(tile) = self.tiles.get_mut(tile_key) {
@@ -388,6 +514,129 @@ impl JourneyBitmap {
         );
     }
 
+    /// The visited pixels for which `selected` returns true. It is called with
+    /// the pixel center, only for blocks intersecting `bounds`.
+    fn visited_pixels_where<F>(&self, bounds: PixelBounds, mut selected: F) -> JourneyBitmap
+    where
+        F: FnMut(f64, f64) -> bool,
+    {
+        let block_width = BITMAP_WIDTH as f64;
+        let mut pixels = JourneyBitmap::new();
+        for (tile_key, tile) in &self.tiles {
+            for (block_key, block) in tile.iter() {
+                let block_x = ((((tile_key.x as i64) << TILE_WIDTH_OFFSET) + block_key.x() as i64)
+                    << BITMAP_WIDTH_OFFSET) as f64;
+                let block_y = ((((tile_key.y as i64) << TILE_WIDTH_OFFSET) + block_key.y() as i64)
+                    << BITMAP_WIDTH_OFFSET) as f64;
+                // `bounds` is unwrapped, so it may overlap with a copy of the block.
+                for shift in [-PIXEL_MAP_WIDTH, 0.0, PIXEL_MAP_WIDTH] {
+                    let block_x = block_x + shift;
+                    if !bounds.intersects(block_x, block_y, block_width) {
+                        continue;
+                    }
+                    for y in 0..BITMAP_WIDTH as u8 {
+                        for x in 0..BITMAP_WIDTH as u8 {
+                            if block.is_visited(x, y)
+                                && selected(block_x + x as f64 + 0.5, block_y + y as f64 + 0.5)
+                            {
+                                pixels.get_tile_mut_or_insert_empty(tile_key).blocks
+                                    [block_key.index()]
+                                .get_or_insert_with(|| Box::new(Block::new()))
+                                .set_point(x, y, true);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    fn erase(&mut self, pixels: &JourneyBitmap) -> bool {
+        if pixels.is_empty() {
+            return false;
+        }
+        self.difference(pixels);
+        true
+    }
+
+    /// The visited pixels inside `polygon` (even-odd rule). Edges are straight
+    /// lines on the web mercator map and take the shorter way around the
+    /// antimeridian.
+    pub fn visited_pixels_in_polygon(&self, polygon: &[TrackPoint]) -> JourneyBitmap {
+        if polygon.len() < 3 {
+            return JourneyBitmap::new();
+        }
+        let polygon = project_path(polygon);
+        let bounds = PixelBounds::of_points(&polygon, 0.0);
+        self.visited_pixels_where(bounds, |x, y| point_in_polygon(&polygon, x, y))
+    }
+
+    /// The visited pixels within `width_in_m / 2` of `path`, like a round
+    /// brush moving along it.
+    pub fn visited_pixels_along_path(&self, path: &[TrackPoint], width_in_m: f64) -> JourneyBitmap {
+        if path.is_empty() || width_in_m.is_nan() || width_in_m <= 0.0 {
+            return JourneyBitmap::new();
+        }
+        let projected = project_path(path);
+        let radius_of = |a: &TrackPoint, b: &TrackPoint| {
+            meters_to_pixels(width_in_m / 2.0, a.latitude.abs().max(b.latitude.abs()))
+        };
+        let edges: Vec<BrushEdge> = if path.len() == 1 {
+            vec![(projected[0], projected[0], radius_of(&path[0], &path[0]))]
+        } else {
+            projected
+                .windows(2)
+                .zip(path.windows(2))
+                .map(|(edge, points)| (edge[0], edge[1], radius_of(&points[0], &points[1])))
+                .collect()
+        };
+        let max_radius = edges
+            .iter()
+            .fold(0.0_f64, |max, (_, _, radius)| max.max(*radius));
+        let bounds = PixelBounds::of_points(&projected, max_radius);
+        self.visited_pixels_where(bounds, |x, y| {
+            edges
+                .iter()
+                .any(|(a, b, radius)| distance_sq_to_edge(*a, *b, x, y) <= radius * radius)
+        })
+    }
+
+    /// Erases everything inside `polygon`, see `visited_pixels_in_polygon`.
+    /// Returns whether anything was erased.
+    pub fn erase_polygon(&mut self, polygon: &[TrackPoint]) -> bool {
+        let pixels = self.visited_pixels_in_polygon(polygon);
+        self.erase(&pixels)
+    }
+
+    /// Erases the box with the given corners. Returns whether anything was
+    /// erased.
+    pub fn erase_rect(
+        &mut self,
+        start_lng: f64,
+        start_lat: f64,
+        end_lng: f64,
+        end_lat: f64,
+    ) -> bool {
+        let corner = |longitude, latitude| TrackPoint {
+            latitude,
+            longitude,
+        };
+        self.erase_polygon(&[
+            corner(start_lng, start_lat),
+            corner(end_lng, start_lat),
+            corner(end_lng, end_lat),
+            corner(start_lng, end_lat),
+        ])
+    }
+
+    /// Erases everything along `path`, see `visited_pixels_along_path`.
+    /// Returns whether anything was erased.
+    pub fn erase_along_path(&mut self, path: &[TrackPoint], width_in_m: f64) -> bool {
+        let pixels = self.visited_pixels_along_path(path, width_in_m);
+        self.erase(&pixels)
+    }
+
     pub fn check_invariant_and_debug_log(&mut self) {
         let total_tiles = self.tiles.len();
         info!("total tiles: {}", total_tiles);
@@ -424,7 +673,7 @@ impl BlockKey {
 
 const BLOCK_KEYS_SIZE: usize = (TILE_WIDTH * TILE_WIDTH / 8) as usize;
 
-#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct BlockKeyBitset([u8; BLOCK_KEYS_SIZE]);
 
 impl BlockKeyBitset {
@@ -437,7 +686,21 @@ impl BlockKeyBitset {
         self.0[i / 8] |= 1 << (i % 8);
     }
 
-    fn iter(&self) -> impl Iterator<Item = BlockKey> + '_ {
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&byte| byte == 0)
+    }
+
+    pub fn merge(&mut self, other: &BlockKeyBitset) {
+        for (byte, other_byte) in self.0.iter_mut().zip(other.0) {
+            *byte |= other_byte;
+        }
+    }
+
+    pub fn raw_data(&self) -> &[u8; BLOCK_KEYS_SIZE] {
+        &self.0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BlockKey> + '_ {
         self.0.iter().enumerate().flat_map(|(byte_index, &byte)| {
             (0..8_usize).filter_map(move |offset| {
                 if byte & (1 << offset) != 0 {
@@ -449,6 +712,15 @@ impl BlockKeyBitset {
         })
     }
 
+    // zstd compressed on its own, a few blocks only take a few bytes.
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(zstd::encode_all(&self.0[..], TILE_ZSTD_COMPRESS_LEVEL)?)
+    }
+
+    pub fn deserialize(data: &[u8]) -> anyhow::Result<Self> {
+        Self::read_from(&mut zstd::Decoder::new(data)?)
+    }
+
     fn write_to<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
         writer.write_all(&self.0)?;
         Ok(())
@@ -461,6 +733,82 @@ impl BlockKeyBitset {
     }
 }
 
+/// The non-empty blocks of each tile of a `JourneyBitmap`, see
+/// `JourneyBitmap::block_overview`.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct BlockOverview {
+    tiles: HashMap<TileKey, BlockKeyBitset>,
+}
+
+impl BlockOverview {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn get_tile(&self, key: &TileKey) -> Option<&BlockKeyBitset> {
+        self.tiles.get(key)
+    }
+
+    pub fn remove_tile(&mut self, key: &TileKey) -> Option<BlockKeyBitset> {
+        self.tiles.remove(key)
+    }
+
+    pub fn all_tile_keys(&self) -> impl Iterator<Item = &TileKey> {
+        self.tiles.keys()
+    }
+
+    pub fn merge(&mut self, other: &BlockOverview) {
+        for (key, other_block_keys) in &other.tiles {
+            match self.tiles.get_mut(key) {
+                None => {
+                    self.tiles.insert(*key, other_block_keys.clone());
+                }
+                Some(block_keys) => block_keys.merge(other_block_keys),
+            }
+        }
+    }
+
+    // zstd of the tile count, then the key and `BlockKeyBitset` of each tile.
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut encoder = zstd::Encoder::new(&mut buf, TILE_ZSTD_COMPRESS_LEVEL)?.auto_finish();
+        encoder.write_all(&(self.tiles.len() as u32).to_le_bytes())?;
+        for (key, block_keys) in &self.tiles {
+            encoder.write_all(&key.x.to_le_bytes())?;
+            encoder.write_all(&key.y.to_le_bytes())?;
+            block_keys.write_to(&mut encoder)?;
+        }
+        drop(encoder);
+        Ok(buf)
+    }
+
+    pub fn deserialize(data: &[u8]) -> anyhow::Result<BlockOverview> {
+        let mut decoder = zstd::Decoder::new(data)?;
+        let mut count_bytes = [0_u8; 4];
+        decoder.read_exact(&mut count_bytes)?;
+        let mut overview = BlockOverview::new();
+        for _ in 0..u32::from_le_bytes(count_bytes) {
+            let mut key_bytes = [0_u8; 4];
+            decoder.read_exact(&mut key_bytes)?;
+            let key = TileKey::new(
+                u16::from_le_bytes([key_bytes[0], key_bytes[1]]),
+                u16::from_le_bytes([key_bytes[2], key_bytes[3]]),
+            );
+            let block_keys = BlockKeyBitset::read_from(&mut decoder)?;
+            overview.tiles.insert(key, block_keys);
+        }
+        Ok(overview)
+    }
+}
+
 /// A lightweight tile that only provides information about which blocks are not empty.
 pub enum TileSummary<'a> {
     Tile(&'a Tile),
@@ -541,7 +889,7 @@ impl Tile {
         }
     }
 
-    fn merge_with_partial_clone(&mut self, other_tile: &Tile) {
+    pub fn merge_with_partial_clone(&mut self, other_tile: &Tile) {
         for i in 0..other_tile.blocks.len() {
             match &other_tile.blocks[i] {
                 None => (),
@@ -22,6 +22,9 @@ pub enum JourneyData {
 pub const ZSTD_COMPRESS_LEVEL: i32 = 3;
 
 const JOURNEY_VECTOR_MAGIC_HEADER: [u8; 2] = *b"V0";
+// Same as `V0` with an extra flag per segment. Only used when there are
+// inferred segments so data without them stays readable by older versions.
+const JOURNEY_VECTOR_WITH_FLAGS_MAGIC_HEADER: [u8; 2] = *b"V1";
 const JOURNEY_BITMAP_MAGIC_HEADER: [u8; 2] = *b"B0";
 
 pub fn validate_magic_header<T: Read>(reader: &mut T, expected_header: &[u8; 2]) -> Result<()> {
@@ -44,13 +47,24 @@ pub fn serialize_journey_vector<T: Write>(
     journey_vector: &JourneyVector,
     mut writer: T,
 ) -> Result<()> {
+    let with_flags = journey_vector
+        .track_segments
+        .iter()
+        .any(|track_segment| track_segment.inferred);
     // magic header
-    writer.write_all(&JOURNEY_VECTOR_MAGIC_HEADER)?;
+    writer.write_all(if with_flags {
+        &JOURNEY_VECTOR_WITH_FLAGS_MAGIC_HEADER
+    } else {
+        &JOURNEY_VECTOR_MAGIC_HEADER
+    })?;
 
     // data is compressed as a whole
     let mut encoder = zstd::Encoder::new(writer, ZSTD_COMPRESS_LEVEL)?.auto_finish();
     encoder.write_all(&(journey_vector.track_segments.len() as u64).encode_var_vec())?;
     for track_segmant in &journey_vector.track_segments {
+        if with_flags {
+            encoder.write_all(&[track_segmant.inferred as u8])?;
+        }
         encoder.write_all(&(track_segmant.track_points.len() as u64).encode_var_vec())?;
         for track_point in &track_segmant.track_points {
             encoder.write_all(&track_point.latitude.to_be_bytes())?;
@@ -62,13 +76,31 @@ pub fn serialize_journey_vector<T: Write>(
 
 #[auto_context]
 pub fn deserialize_journey_vector<T: Read>(mut reader: T) -> Result<JourneyVector> {
-    validate_magic_header(&mut reader, &JOURNEY_VECTOR_MAGIC_HEADER)?;
+    let mut magic_header: [u8; 2] = [0; 2];
+    reader.read_exact(&mut magic_header)?;
+    let with_flags = match magic_header {
+        JOURNEY_VECTOR_MAGIC_HEADER => false,
+        JOURNEY_VECTOR_WITH_FLAGS_MAGIC_HEADER => true,
+        _ => bail!(
+            "Invalid magic header, expect: {:?} or {:?}, got: {:?}",
+            JOURNEY_VECTOR_MAGIC_HEADER,
+            JOURNEY_VECTOR_WITH_FLAGS_MAGIC_HEADER,
+            magic_header
+        ),
+    };
 
     // data is compressed as a whole
     let mut decoder = zstd::Decoder::new(reader)?;
     let segments_count: u64 = decoder.read_varint()?;
     let mut track_segments = Vec::with_capacity(segments_count as usize);
     for _ in 0..segments_count {
+        let inferred = if with_flags {
+            let mut flags: [u8; 1] = [0; 1];
+            decoder.read_exact(&mut flags)?;
+            flags[0] != 0
+        } else {
+            false
+        };
         let points_count: u64 = decoder.read_varint()?;
         let mut track_points = Vec::with_capacity(points_count as usize);
         for _ in 0..points_count {
@@ -82,7 +114,10 @@ pub fn deserialize_journey_vector<T: Read>(mut reader: T) -> Result<JourneyVecto
                 longitude,
             })
         }
-        track_segments.push(TrackSegment { track_points });
+        track_segments.push(TrackSegment {
+            track_points,
+            inferred,
+        });
     }
     Ok(JourneyVector { track_segments })
 }
@@ -1,23 +1,28 @@
 use std::collections::HashMap;
 
-use chrono::{DateTime, Local, NaiveDate, Utc};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc};
 
 use crate::{gps_processor::Point, journey_vector::TrackPoint};
 
-// TODO: we don't have test for this because mocking timezone in test is annoying.
-
-// TODO: I think using `chrono::Local` might be problematic. I think on mobile
-// devices, the timezone might change as the user travels. We should probably
-// use the flutter side to get the current timezone and pass it in here.
+/// The UTC offset of the device's timezone at `time`. This is what we use when
+/// we don't know where a point was recorded.
+pub fn local_utc_offset(time: DateTime<Utc>) -> FixedOffset {
+    Local.offset_from_utc_datetime(&time.naive_utc()).fix()
+}
 
 // Tools for picking the journey date based on a series of GPS data with timestamp.
 // We track the two furthest points of each day and use the distance between
 // to measure how "big" each day is. The we pick the latest day after filtering out
 // days that are too small.
+//
+// Dates are in the local time of each point. The UTC offset of a point is
+// provided by the caller (e.g. the flutter side knows the timezone of the device
+// when a point is recorded), we don't have the timezone boundary data to look it
+// up by location. Points without one fall back to the device's timezone.
 pub struct JourneyDatePicker {
     furthest_point_tracker_per_day: HashMap<NaiveDate, FurthestPointTracker>,
-    min_time: Option<DateTime<Utc>>,
-    max_time: Option<DateTime<Utc>>,
+    min_time: Option<(DateTime<Utc>, Option<FixedOffset>)>,
+    max_time: Option<(DateTime<Utc>, Option<FixedOffset>)>,
 }
 
 impl JourneyDatePicker {
@@ -29,14 +34,25 @@ impl JourneyDatePicker {
         }
     }
 
-    pub fn add_point(&mut self, time: DateTime<Utc>, point: &TrackPoint) {
-        let date = time.with_timezone(&Local).date_naive();
+    pub fn add_point(
+        &mut self,
+        time: DateTime<Utc>,
+        utc_offset: Option<FixedOffset>,
+        point: &TrackPoint,
+    ) {
+        let date = time
+            .with_timezone(&utc_offset.unwrap_or_else(|| local_utc_offset(time)))
+            .date_naive();
         self.furthest_point_tracker_per_day
             .entry(date)
             .and_modify(|x| x.update(point))
             .or_insert(FurthestPointTracker::new(point));
-        self.min_time = Some(self.min_time.map_or(time, |t| t.min(time)));
-        self.max_time = Some(self.max_time.map_or(time, |t| t.max(time)));
+        if self.min_time.is_none_or(|(t, _)| time < t) {
+            self.min_time = Some((time, utc_offset));
+        }
+        if self.max_time.is_none_or(|(t, _)| time > t) {
+            self.max_time = Some((time, utc_offset));
+        }
     }
 
     pub fn pick_journey_date(&self) -> Option<NaiveDate> {
@@ -60,11 +76,21 @@ impl JourneyDatePicker {
     }
 
     pub fn min_time(&self) -> Option<DateTime<Utc>> {
-        self.min_time
+        self.min_time.map(|(time, _)| time)
     }
 
     pub fn max_time(&self) -> Option<DateTime<Utc>> {
-        self.max_time
+        self.max_time.map(|(time, _)| time)
+    }
+
+    /// The UTC offset provided with the point at `min_time`.
+    pub fn min_time_utc_offset(&self) -> Option<FixedOffset> {
+        self.min_time.and_then(|(_, utc_offset)| utc_offset)
+    }
+
+    /// The UTC offset provided with the point at `max_time`.
+    pub fn max_time_utc_offset(&self) -> Option<FixedOffset> {
+        self.max_time.and_then(|(_, utc_offset)| utc_offset)
     }
 }
 
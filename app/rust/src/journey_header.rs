@@ -3,6 +3,8 @@ use auto_context::auto_context;
 use chrono::{DateTime, NaiveDate, Utc};
 use flutter_rust_bridge::frb;
 use protobuf::EnumOrUnknown;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
 use strum_macros::EnumIter;
 
 use crate::{protos, utils};
@@ -60,28 +62,65 @@ mod tests {
     }
 }
 
-#[derive(Eq, Hash, Clone, Copy, Debug, PartialEq, EnumIter)]
+#[derive(Eq, Hash, Clone, Debug, PartialEq)]
 pub enum JourneyKind {
     DefaultKind,
     Flight,
+    /// User defined kind (e.g. "Cycling"), identified by its name.
+    Custom(String),
 }
 
 impl JourneyKind {
-    pub fn to_proto(self) -> protos::journey::header::Kind {
+    pub const BUILT_IN: [JourneyKind; 2] = [JourneyKind::DefaultKind, JourneyKind::Flight];
+
+    pub fn validate(&self) -> Result<()> {
+        if let JourneyKind::Custom(name) = self {
+            if name.trim().is_empty() {
+                bail!("Custom journey kind name cannot be empty");
+            }
+            if name.trim() != name {
+                bail!("Custom journey kind name cannot have leading or trailing spaces: {name:?}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_proto(&self) -> protos::journey::header::Kind {
         use protos::journey::header::{kind, Kind};
         let mut kind = Kind::new();
         match self {
             JourneyKind::DefaultKind => kind.set_build_in(kind::BuiltIn::DEFAULT),
             JourneyKind::Flight => kind.set_build_in(kind::BuiltIn::FLIGHT),
+            JourneyKind::Custom(name) => kind.set_custom_kind(name.clone()),
         };
         kind
     }
 
+    /// Name used in the databases (e.g. the `kind` column of `journey`).
+    pub(crate) fn to_sql(&self) -> Cow<'static, str> {
+        match self {
+            JourneyKind::DefaultKind => "Default".into(),
+            JourneyKind::Flight => "Flight".into(),
+            // prefixed so it never collides with the built-in ones
+            JourneyKind::Custom(name) => format!("Custom:{name}").into(),
+        }
+    }
+
+    pub(crate) fn of_sql(kind: &str) -> Result<Self> {
+        match kind {
+            "Default" => Ok(JourneyKind::DefaultKind),
+            "Flight" => Ok(JourneyKind::Flight),
+            _ => match kind.strip_prefix("Custom:") {
+                Some(name) => Ok(JourneyKind::Custom(name.to_string())),
+                None => bail!("Invalid journey kind: {kind}"),
+            },
+        }
+    }
+
     pub fn of_proto(proto: protos::journey::header::Kind) -> Self {
         use protos::journey::header::kind;
         if proto.has_custom_kind() {
-            let custom_kind = proto.custom_kind();
-            panic!("custom journey kind is not supported, custom_kind = {custom_kind}")
+            return JourneyKind::Custom(proto.custom_kind().to_string());
         }
         match proto.build_in() {
             kind::BuiltIn::DEFAULT => JourneyKind::DefaultKind,
@@ -90,6 +129,16 @@ impl JourneyKind {
     }
 }
 
+/// Trims tags, drops empty ones and duplicates, and sorts them.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let tags: BTreeSet<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.into_iter().collect()
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[frb(non_opaque)]
 pub struct JourneyHeader {
@@ -100,10 +149,15 @@ pub struct JourneyHeader {
     pub updated_at: Option<DateTime<Utc>>,
     pub start: Option<DateTime<Utc>>,
     pub end: Option<DateTime<Utc>>,
+    /// UTC offset of the local time at `start`, `None` for journeys recorded
+    /// before we kept track of it.
+    pub start_utc_offset_sec: Option<i32>,
+    pub end_utc_offset_sec: Option<i32>,
     pub journey_type: JourneyType,
     pub journey_kind: JourneyKind,
     pub note: Option<String>,
     pub postprocessor_algo: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl JourneyHeader {
@@ -127,6 +181,8 @@ impl JourneyHeader {
             start: proto
                 .start__timestamp_sec
                 .and_then(|sec| DateTime::from_timestamp(sec, 0)),
+            start_utc_offset_sec: proto.start__utc_offset_sec,
+            end_utc_offset_sec: proto.end__utc_offset_sec,
             journey_type: JourneyType::of_proto(journey_type),
             journey_kind: JourneyKind::of_proto(match proto.kind.take() {
                 None => bail!("Missing `kind`"),
@@ -134,6 +190,7 @@ impl JourneyHeader {
             }),
             note: proto.note,
             postprocessor_algo: proto.postprocessor_algo,
+            tags: proto.tags,
         })
     }
 
@@ -146,10 +203,13 @@ impl JourneyHeader {
             updated_at,
             start,
             end,
+            start_utc_offset_sec,
+            end_utc_offset_sec,
             journey_type,
             journey_kind,
             note,
             postprocessor_algo,
+            tags,
         } = self;
         let mut proto = protos::journey::Header::new();
         proto.id = id;
@@ -159,10 +219,13 @@ impl JourneyHeader {
         proto.updated_at__timestamp_sec = updated_at.map(|x| x.timestamp());
         proto.end__timestamp_sec = end.map(|x| x.timestamp());
         proto.start__timestamp_sec = start.map(|x| x.timestamp());
+        proto.start__utc_offset_sec = start_utc_offset_sec;
+        proto.end__utc_offset_sec = end_utc_offset_sec;
         proto.type_ = EnumOrUnknown::new(journey_type.to_proto());
         proto.kind.0 = Some(Box::new(journey_kind.to_proto()));
         proto.note = note;
         proto.postprocessor_algo = postprocessor_algo;
+        proto.tags = tags;
         proto
     }
 }
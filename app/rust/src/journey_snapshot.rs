@@ -1,6 +1,7 @@
 use crate::{
     cache_db::{CacheDb, LayerKind},
-    journey_bitmap::JourneyBitmap,
+    journey_bitmap::{BlockOverview, JourneyBitmap},
+    journey_header::JourneyKind,
     journey_vector::JourneyVector,
     main_db,
 };
@@ -28,6 +29,17 @@ impl<'a, 'txn> JourneySnapshot<'a, 'txn> {
         self.cache_db.get_or_compute(self.txn, layer, range)
     }
 
+    /// Cached block overview of the all-time `finalized_bitmap`, see
+    /// `CacheDb::get_overview`. Never computed here.
+    pub fn finalized_overview(&mut self, layer: &LayerKind) -> Result<Option<BlockOverview>> {
+        self.cache_db.get_overview(layer)
+    }
+
+    /// See `main_db::Txn::journey_kinds`.
+    pub fn journey_kinds(&self) -> Result<Vec<JourneyKind>> {
+        self.txn.journey_kinds()
+    }
+
     /// The not-yet-finalized ongoing journey, if any. Read through the
     /// same snapshot as `finalized_bitmap`, so a caller merging the two
     /// (e.g. the live map renderer) sees one consistent state.
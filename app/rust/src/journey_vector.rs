@@ -6,6 +6,9 @@ pub struct JourneyVector {
 #[derive(Debug, PartialEq, Clone)]
 pub struct TrackSegment {
     pub track_points: Vec<TrackPoint>,
+    /// Not recorded but inferred from its surroundings (e.g. a gap filled by
+    /// `gap_filling`).
+    pub inferred: bool,
 }
 
 #[derive(Debug, PartialEq, Clone)]
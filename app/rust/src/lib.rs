@@ -18,6 +18,7 @@ pub mod archive;
 pub mod cache_db;
 pub mod export_data;
 pub mod flight_track_processor;
+pub mod gap_filling;
 pub mod geo;
 pub mod gps_processor;
 pub mod gpx_file_utils;
@@ -31,7 +32,12 @@ pub mod journey_snapshot;
 pub mod journey_vector;
 mod logs;
 pub mod main_db;
+pub mod map_matching;
 mod protos;
+pub mod recorder;
+pub mod recording_journal;
 pub mod renderer;
 pub mod storage;
+pub mod track_selection;
+pub mod transport_mode;
 pub mod utils;
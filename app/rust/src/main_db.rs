@@ -1,33 +1,56 @@
 extern crate simplelog;
 use anyhow::{Context, Result};
 use auto_context::auto_context;
-use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, Timelike, Utc};
 use protobuf::Message;
 use rusqlite::{Connection, OptionalExtension, Transaction};
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::str::FromStr;
 use uuid::Uuid;
 
 pub use crate::cache_db::CacheEntry;
-use crate::gps_processor::{self, GpsPostprocessor, PreprocessedData, ProcessResult};
+use crate::gps_processor::{
+    self, GpsPostprocessor, Point, PreprocessedData, ProcessResult, RawData,
+};
+use crate::journey_bitmap::{
+    BlockKeyBitset, JourneyBitmap, MAP_WIDTH_OFFSET, TILE_WIDTH, TILE_WIDTH_OFFSET,
+};
 use crate::journey_data::JourneyData;
-use crate::journey_date_picker::JourneyDatePicker;
-use crate::journey_header::{JourneyHeader, JourneyKind, JourneyType};
-use crate::journey_vector::{JourneyVector, TrackPoint};
-use crate::{protos, utils};
+use crate::journey_date_picker::{local_utc_offset, JourneyDatePicker};
+use crate::journey_header::{normalize_tags, JourneyHeader, JourneyKind, JourneyType};
+use crate::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+use crate::map_matching::{self, RoadGraph};
+use crate::recording_journal::{JournalPoint, RecordingJournal, RecoveryReport};
+use crate::utils::MapBounds;
+use crate::{flight_track_processor, gap_filling, protos, transport_mode, utils};
 
 /* The main database, we are likely to store a lot of protobuf bytes in it,
 less relational stuff. Basically we will use it as a file system with better
 transaction support.
 
 `ongoing_journey` contains structured gps data for the current ongoing journey.
-Note that it contains detailed timestamp, but these timestamp will be removed
-when finalizing the journey.
+Note that it contains detailed timestamp (and altitude / speed for transport
+mode detection), but these will be removed when finalizing the journey. New
+points go through `recording_journal` first and are added here in batches.
 
 `journey` keeps all finalized journeys. It stores most data as raw protobuf
 bytes and some index for faster lookup. Instead of storing a single blob, it has
 two parts: header and data, so most common operation only need to fetch and
 deserialize the header.
+
+`journey_history` keeps previous revisions of journeys, so overwriting or
+deleting a journey can be reverted. It is bounded by a number of revisions per
+journey and a maximum age. Revisions only changing the metadata keep an empty
+`data`, meaning the data of the next full revision, or of the journey itself.
+Re-running the postprocessor is not a revision.
+
+`journey_tag`, `journey_note_fts` and `journey_footprint` index journeys for
+searching, they are derived from `journey` (see `index_journey`).
+
+`journey_trash` keeps journeys deleted by the user. They are moved there as is,
+so they are out of all queries and caches, and can be restored until the trash
+is emptied or they expire.
 */
 
 // 3 is the zstd default
@@ -35,6 +58,7 @@ pub const ZSTD_COMPRESS_LEVEL: i32 = 3;
 
 pub struct Txn<'a> {
     db_txn: rusqlite::Transaction<'a>,
+    journal: &'a mut RecordingJournal,
     pub action: Option<Action>,
 }
 
@@ -55,6 +79,412 @@ fn generate_random_revision() -> String {
     random_string::generate(8, random_string::charsets::ALPHANUMERIC)
 }
 
+pub const DEFAULT_JOURNEY_HISTORY_MAX_REVISIONS: u32 = 5;
+pub const DEFAULT_JOURNEY_HISTORY_MAX_AGE_IN_DAYS: u32 = 90;
+
+pub const DEFAULT_TRASH_RETENTION_IN_DAYS: u32 = 30;
+
+/// A journey in the trash.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrashedJourney {
+    pub deleted_at: DateTime<Utc>,
+    pub header: JourneyHeader,
+}
+
+/// A previous revision of a journey kept in the history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JourneyRevision {
+    pub history_id: i64,
+    pub archived_at: DateTime<Utc>,
+    pub header: JourneyHeader,
+}
+
+/// Where to split a vector journey. Point indices count all points of all
+/// segments in order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitPosition {
+    /// We don't keep timestamps of finalized journeys, so the position is
+    /// estimated from `start` / `end` assuming a constant pace.
+    Timestamp(DateTime<Utc>),
+    /// The point is shared by both journeys, unless it starts or ends a
+    /// segment.
+    PointIndex(usize),
+    /// The index of the first segment of the second journey.
+    SegmentBoundary(usize),
+}
+
+/// An area a journey should pass through.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchArea {
+    /// `east` may be less than `west` when crossing the antimeridian.
+    Bounds(MapBounds),
+    Point {
+        lng: f64,
+        lat: f64,
+        tolerance_in_m: f64,
+    },
+}
+
+/// All filters must match, empty / `None` ones are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JourneySearchQuery {
+    /// Journeys having all these tags.
+    pub tags: Vec<String>,
+    /// Text in the note.
+    pub text: Option<String>,
+    pub from_date_inclusive: Option<NaiveDate>,
+    pub to_date_inclusive: Option<NaiveDate>,
+    /// Journeys of any of these kinds.
+    pub kinds: Vec<JourneyKind>,
+    pub area: Option<SearchArea>,
+    pub offset: u32,
+    pub limit: Option<u32>,
+}
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+const MAX_MERCATOR_LAT: f64 = 85.051_128_78;
+// the zoom level where a tile is a block
+const FOOTPRINT_ZOOM: i32 = (TILE_WIDTH_OFFSET + MAP_WIDTH_OFFSET) as i32;
+
+// the range of `y` and ranges of `x` of footprint blocks
+type BlockRanges = ((i64, i64), Vec<(i64, i64)>);
+
+impl SearchArea {
+    fn bounds(&self) -> MapBounds {
+        match self {
+            SearchArea::Bounds(bounds) => *bounds,
+            SearchArea::Point {
+                lng,
+                lat,
+                tolerance_in_m,
+            } => {
+                let lat_delta = tolerance_in_m / METERS_PER_DEGREE;
+                let lng_delta =
+                    tolerance_in_m / (METERS_PER_DEGREE * lat.abs().min(89.0).to_radians().cos());
+                MapBounds {
+                    west: lng - lng_delta.min(180.0),
+                    south: lat - lat_delta,
+                    east: lng + lng_delta.min(180.0),
+                    north: lat + lat_delta,
+                }
+            }
+        }
+    }
+
+    /// The footprint blocks that may be in the area, or with `inner`, the
+    /// blocks entirely in it (`None` if there is none).
+    fn block_ranges(&self, inner: bool) -> Option<BlockRanges> {
+        let bounds = match *self {
+            // the square inscribed in the circle
+            SearchArea::Point {
+                lng,
+                lat,
+                tolerance_in_m,
+            } if inner => SearchArea::Point {
+                lng,
+                lat,
+                tolerance_in_m: tolerance_in_m / std::f64::consts::SQRT_2,
+            }
+            .bounds(),
+            _ => self.bounds(),
+        };
+        let n = 1_i64 << FOOTPRINT_ZOOM;
+        let to_x_y = |lng: f64, lat: f64| {
+            let (x, y) = utils::lng_lat_to_tile_x_y(
+                lng,
+                lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT),
+                FOOTPRINT_ZOOM,
+            );
+            (i64::from(x), i64::from(y).clamp(0, n - 1))
+        };
+        let mut span = bounds.east - bounds.west;
+        if span < 0.0 {
+            span += 360.0;
+        }
+        let west = (bounds.west + 180.0).rem_euclid(360.0) - 180.0;
+        let (mut west_x, mut north_y) = to_x_y(west, bounds.north);
+        let (mut east_x, mut south_y) = to_x_y(west + span, bounds.south);
+        if inner {
+            // blocks on the border are only partly in the area
+            west_x += 1;
+            north_y += 1;
+            east_x -= 1;
+            south_y -= 1;
+            if west_x > east_x || north_y > south_y {
+                return None;
+            }
+        }
+        let x_ranges = if span >= 360.0 || east_x - west_x >= n {
+            vec![(0, n - 1)]
+        } else if east_x >= n {
+            vec![(west_x, n - 1), (0, east_x - n)]
+        } else {
+            vec![(west_x, east_x)]
+        };
+        Some(((north_y, south_y), x_ranges))
+    }
+
+    /// SQL condition on `journey_footprint` matching the tiles of the blocks
+    /// in `block_ranges`.
+    fn footprint_condition(
+        ((min_y, max_y), x_ranges): &BlockRanges,
+        params: &mut Vec<rusqlite::types::Value>,
+    ) -> String {
+        use rusqlite::types::Value;
+
+        let x_conditions = vec!["x BETWEEN ? AND ?"; x_ranges.len()].join(" OR ");
+        params.push(Value::Integer(min_y / TILE_WIDTH));
+        params.push(Value::Integer(max_y / TILE_WIDTH));
+        for (min_x, max_x) in x_ranges {
+            params.push(Value::Integer(min_x / TILE_WIDTH));
+            params.push(Value::Integer(max_x / TILE_WIDTH));
+        }
+        format!("y BETWEEN ? AND ? AND ({x_conditions})")
+    }
+
+    fn contains_block(((min_y, max_y), x_ranges): &BlockRanges, x: i64, y: i64) -> bool {
+        (*min_y..=*max_y).contains(&y)
+            && x_ranges
+                .iter()
+                .any(|(min_x, max_x)| (*min_x..=*max_x).contains(&x))
+    }
+
+    fn is_visited(&self, journey_data: &JourneyData) -> bool {
+        let mut journey_bitmap = JourneyBitmap::new();
+        journey_data.merge_into_with_partial_clone(&mut journey_bitmap);
+        let visited = match self {
+            SearchArea::Bounds(bounds) => {
+                let mut east = bounds.east;
+                if east < bounds.west {
+                    east += 360.0;
+                }
+                // polygon edges take the shorter way, so keep them short
+                let steps = ((east - bounds.west) / 90.0).ceil().max(1.0) as usize;
+                let lngs: Vec<f64> = (0..=steps)
+                    .map(|i| bounds.west + (east - bounds.west) * i as f64 / steps as f64)
+                    .collect();
+                let polygon: Vec<TrackPoint> = lngs
+                    .iter()
+                    .map(|lng| TrackPoint {
+                        latitude: bounds.south,
+                        longitude: *lng,
+                    })
+                    .chain(lngs.iter().rev().map(|lng| TrackPoint {
+                        latitude: bounds.north,
+                        longitude: *lng,
+                    }))
+                    .collect();
+                journey_bitmap.visited_pixels_in_polygon(&polygon)
+            }
+            SearchArea::Point {
+                lng,
+                lat,
+                tolerance_in_m,
+            } => journey_bitmap.visited_pixels_along_path(
+                &[TrackPoint {
+                    latitude: *lat,
+                    longitude: *lng,
+                }],
+                tolerance_in_m * 2.0,
+            ),
+        };
+        !visited.is_empty()
+    }
+}
+
+fn parse_header(header_bytes: &[u8], journey_type: JourneyType) -> Result<JourneyHeader> {
+    let header = JourneyHeader::of_proto(protos::journey::Header::parse_from_bytes(header_bytes)?)?;
+    if header.journey_type != journey_type {
+        bail!(
+            "Invalid DB state, `journey_type` miss match. id: {}.",
+            header.id
+        );
+    }
+    Ok(header)
+}
+
+/// Keeps the search index (`journey_tag`, `journey_note_fts` and
+/// `journey_footprint`) in sync with `journey`. `journey_data` is only needed
+/// when it changed.
+#[auto_context]
+fn index_journey(
+    conn: &Connection,
+    header: &JourneyHeader,
+    journey_data: Option<&JourneyData>,
+) -> Result<()> {
+    let id = &header.id;
+    conn.execute("DELETE FROM journey_tag WHERE journey_id = ?1;", (id,))?;
+    for tag in &header.tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO journey_tag (journey_id, tag) VALUES (?1, ?2);",
+            (id, tag),
+        )?;
+    }
+    conn.execute("DELETE FROM journey_note_fts WHERE journey_id = ?1;", (id,))?;
+    if let Some(note) = header.note.as_ref().filter(|note| !note.is_empty()) {
+        conn.execute(
+            "INSERT INTO journey_note_fts (journey_id, note) VALUES (?1, ?2);",
+            (id, note),
+        )?;
+    }
+    if let Some(journey_data) = journey_data {
+        index_journey_footprint(conn, id, journey_data)?;
+    }
+    Ok(())
+}
+
+/// One row per visited tile with its visited blocks, so a recorded track
+/// only takes a few rows, and a FoW bitmap about as many as it has tiles.
+#[auto_context]
+fn index_journey_footprint(conn: &Connection, id: &str, journey_data: &JourneyData) -> Result<()> {
+    conn.execute(
+        "DELETE FROM journey_footprint WHERE journey_id = ?1;",
+        (id,),
+    )?;
+    let mut journey_bitmap = JourneyBitmap::new();
+    journey_data.merge_into_with_partial_clone(&mut journey_bitmap);
+    let overview = journey_bitmap.block_overview();
+    let mut insert = conn.prepare(
+        "INSERT INTO journey_footprint (journey_id, x, y, blocks) VALUES (?1, ?2, ?3, ?4);",
+    )?;
+    for tile_key in overview.all_tile_keys() {
+        if let Some(block_keys) = overview.get_tile(tile_key) {
+            insert.execute((id, tile_key.x, tile_key.y, block_keys.serialize()?))?;
+        }
+    }
+    Ok(())
+}
+
+/// `None` removes every journey from the search index.
+#[auto_context]
+fn unindex_journey(conn: &Connection, id: Option<&str>) -> Result<()> {
+    for table in ["journey_tag", "journey_note_fts", "journey_footprint"] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE ?1 IS NULL OR journey_id = ?1;"),
+            (id,),
+        )?;
+    }
+    Ok(())
+}
+
+// Cumulative distance of every point, continuing across segments.
+fn cumulative_distances(journey_vector: &JourneyVector) -> Vec<f64> {
+    let mut result = Vec::new();
+    let mut total = 0.;
+    for track_segment in &journey_vector.track_segments {
+        for (i, track_point) in track_segment.track_points.iter().enumerate() {
+            if i > 0 {
+                let prev = &track_segment.track_points[i - 1];
+                total += Point::from(prev).haversine_distance(&Point::from(track_point));
+            }
+            result.push(total);
+        }
+    }
+    result
+}
+
+// Estimates the time of every point assuming a constant pace between `start`
+// and `end`.
+fn estimate_point_times(
+    journey_vector: &JourneyVector,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let distances = cumulative_distances(journey_vector);
+    let total = distances.last().copied().unwrap_or(0.);
+    let duration_ms = (end - start).num_milliseconds();
+    distances
+        .into_iter()
+        .map(|distance| {
+            let ratio = if total > 0. { distance / total } else { 0. };
+            start + chrono::Duration::milliseconds((duration_ms as f64 * ratio) as i64)
+        })
+        .collect()
+}
+
+fn utc_offset_of_sec(utc_offset_sec: Option<i32>) -> Option<FixedOffset> {
+    utc_offset_sec.and_then(FixedOffset::east_opt)
+}
+
+// We only know the UTC offsets at both ends of a journey, a point in between
+// takes the one of the closer end.
+fn estimate_utc_offset_sec(header: &JourneyHeader, time: DateTime<Utc>) -> Option<i32> {
+    match (header.start, header.end) {
+        (Some(start), Some(end)) if time - start > end - time => {
+            header.end_utc_offset_sec.or(header.start_utc_offset_sec)
+        }
+        _ => header.start_utc_offset_sec.or(header.end_utc_offset_sec),
+    }
+}
+
+fn add_points_with_estimated_times(
+    journey_date_picker: &mut JourneyDatePicker,
+    journey_vector: &JourneyVector,
+    header: &JourneyHeader,
+) {
+    if let (Some(start), Some(end)) = (header.start, header.end) {
+        let track_points = journey_vector
+            .track_segments
+            .iter()
+            .flat_map(|track_segment| &track_segment.track_points);
+        for (time, track_point) in estimate_point_times(journey_vector, start, end)
+            .into_iter()
+            .zip(track_points)
+        {
+            journey_date_picker.add_point(
+                time,
+                utc_offset_of_sec(estimate_utc_offset_sec(header, time)),
+                track_point,
+            );
+        }
+    }
+}
+
+fn pick_journey_date(journey_vector: &JourneyVector, header: &JourneyHeader) -> Option<NaiveDate> {
+    let mut journey_date_picker = JourneyDatePicker::new();
+    add_points_with_estimated_times(&mut journey_date_picker, journey_vector, header);
+    journey_date_picker.pick_journey_date()
+}
+
+// Splits `journey_vector` so the second part starts at the `index`-th point.
+fn split_journey_vector(
+    journey_vector: JourneyVector,
+    index: usize,
+) -> (JourneyVector, JourneyVector) {
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    let mut offset = 0;
+    for track_segment in journey_vector.track_segments {
+        let len = track_segment.track_points.len();
+        // a segment ending at `index` is not split, so there is no single point
+        // segment
+        if offset + len <= index + 1 {
+            first.push(track_segment);
+        } else if offset >= index {
+            second.push(track_segment);
+        } else {
+            let local_index = index - offset;
+            first.push(TrackSegment {
+                track_points: track_segment.track_points[..=local_index].to_vec(),
+                inferred: track_segment.inferred,
+            });
+            second.push(TrackSegment {
+                track_points: track_segment.track_points[local_index..].to_vec(),
+                inferred: track_segment.inferred,
+            });
+        }
+        offset += len;
+    }
+    (
+        JourneyVector {
+            track_segments: first,
+        },
+        JourneyVector {
+            track_segments: second,
+        },
+    )
+}
+
 // NOTE: the `Txn` here is not only for making operation atomic, the `storage`
 // will also use this to make sure the `cache_db` is in sync.
 impl Txn<'_> {
@@ -79,32 +509,16 @@ impl Txn<'_> {
         Ok(())
     }
 
+    /// The ongoing journey as it will be finalized, see
+    /// `build_ongoing_journey`.
+    #[auto_context]
     pub fn get_ongoing_journey(
         &self,
         journey_date_picker: Option<&mut JourneyDatePicker>,
     ) -> Result<Option<JourneyVector>> {
-        // `id` in `ongoing_journey` is auto incremented.
-        let mut query = self.db_txn.prepare(
-            "SELECT timestamp_sec, lat, lng, process_result FROM ongoing_journey ORDER BY id;",
-        )?;
-        let results = query
-            .query_map((), |row| {
-                let timestamp_sec: Option<i64> = row.get(0)?;
-                let process_result: i8 = row.get(3)?;
-                Ok(PreprocessedData {
-                    timestamp_sec,
-                    track_point: TrackPoint {
-                        latitude: row.get(1)?,
-                        longitude: row.get(2)?,
-                    },
-                    process_result: process_result.into(),
-                })
-            })
-            .context("get_onging_journey")?;
-        gps_processor::build_journey_vector(
-            results.map(|x| x.map_err(|x| x.into())),
-            journey_date_picker,
-        )
+        Ok(self
+            .build_ongoing_journey(journey_date_picker)?
+            .map(|(journey_vector, _)| journey_vector))
     }
 
     // the fist timestamp is the start time, the second is the end time
@@ -134,12 +548,31 @@ impl Txn<'_> {
         }
     }
 
+    // The UTC offsets of the first and last point of the ongoing journey.
+    #[auto_context]
+    fn get_ongoing_journey_utc_offsets(
+        &self,
+    ) -> Result<(Option<FixedOffset>, Option<FixedOffset>)> {
+        let mut query = self
+            .db_txn
+            .prepare("SELECT * FROM (SELECT utc_offset_sec FROM ongoing_journey ORDER BY id ASC LIMIT 1) UNION ALL SELECT * FROM (SELECT utc_offset_sec FROM ongoing_journey ORDER BY id DESC LIMIT 1);")?;
+        let utc_offsets = query
+            .query_map((), |row| row.get(0))?
+            .map(|utc_offset_sec| Ok(utc_offset_of_sec(utc_offset_sec?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok((
+            utc_offsets.first().copied().flatten(),
+            utc_offsets.last().copied().flatten(),
+        ))
+    }
+
     #[auto_context]
     pub fn delete_all_journeys(&mut self) -> Result<()> {
         info!("Deleting all journeys");
         self.db_txn.execute("DELETE FROM journey;", ())?;
+        unindex_journey(&self.db_txn, None)?;
         self.action = Some(Action::CompleteRebuilt);
-        Ok(())
+        self.prune_journey_history(None)
     }
 
     #[auto_context]
@@ -148,12 +581,14 @@ impl Txn<'_> {
         let header = self
             .get_journey_header(id)?
             .ok_or_else(|| anyhow!("Failed to find journey with id = {id}"))?;
+        self.archive_journey(id, false)?;
         let changes = self
             .db_txn
             .execute("DELETE FROM journey WHERE id = ?1;", (id,))?;
         if changes != 1 {
             return Err(anyhow!("Failed to delete journey with id = {id}"));
         }
+        unindex_journey(&self.db_txn, Some(id))?;
         self.set_invalidate_action(vec![CacheEntry {
             date: header.journey_date,
             kind: header.journey_kind,
@@ -161,13 +596,165 @@ impl Txn<'_> {
         Ok(())
     }
 
+    /// Moves a journey to the trash, unlike `delete_journey` it can be
+    /// restored by `restore_journey_from_trash`.
+    #[auto_context]
+    pub fn trash_journey(&mut self, id: &str) -> Result<()> {
+        info!("Moving journey to trash: id={id}");
+        let header = self
+            .get_journey_header(id)?
+            .ok_or_else(|| anyhow!("Failed to find journey with id = {id}"))?;
+        let sql = "INSERT OR REPLACE INTO journey_trash (id, journey_date, timestamp_for_ordering, type, kind, header, data, deleted_at) SELECT id, journey_date, timestamp_for_ordering, type, kind, header, data, ?2 FROM journey WHERE id = ?1;";
+        self.db_txn.execute(sql, (id, Utc::now().timestamp()))?;
+        self.db_txn
+            .execute("DELETE FROM journey WHERE id = ?1;", (id,))?;
+        unindex_journey(&self.db_txn, Some(id))?;
+        self.set_invalidate_action(vec![CacheEntry {
+            date: header.journey_date,
+            kind: header.journey_kind,
+        }])?;
+        self.purge_trash()
+    }
+
+    #[auto_context]
+    pub fn trash_all_journeys(&mut self) -> Result<()> {
+        info!("Moving all journeys to trash");
+        let sql = "INSERT OR REPLACE INTO journey_trash (id, journey_date, timestamp_for_ordering, type, kind, header, data, deleted_at) SELECT id, journey_date, timestamp_for_ordering, type, kind, header, data, ?1 FROM journey;";
+        self.db_txn.execute(sql, (Utc::now().timestamp(),))?;
+        self.db_txn.execute("DELETE FROM journey;", ())?;
+        unindex_journey(&self.db_txn, None)?;
+        self.action = Some(Action::CompleteRebuilt);
+        self.purge_trash()
+    }
+
+    /// The latest deleted first.
+    #[auto_context]
+    pub fn list_trash(&self) -> Result<Vec<TrashedJourney>> {
+        let mut query = self.db_txn.prepare(
+            "SELECT header, deleted_at FROM journey_trash ORDER BY deleted_at DESC, timestamp_for_ordering DESC;",
+        )?;
+        let mut rows = query.query(())?;
+        let mut trashed_journeys = Vec::new();
+        while let Some(row) = rows.next()? {
+            let deleted_at: i64 = row.get(1)?;
+            trashed_journeys.push(TrashedJourney {
+                deleted_at: DateTime::from_timestamp(deleted_at, 0)
+                    .ok_or_else(|| anyhow!("Invalid timestamp: {deleted_at}"))?,
+                header: JourneyHeader::of_proto(protos::journey::Header::parse_from_bytes(
+                    row.get_ref(0)?.as_blob()?,
+                )?)?,
+            });
+        }
+        Ok(trashed_journeys)
+    }
+
+    #[auto_context]
+    pub fn get_trashed_journey_data(&self, id: &str) -> Result<JourneyData> {
+        let (type_, data_bytes): (i64, Vec<u8>) = self
+            .db_txn
+            .query_row(
+                "SELECT type, data FROM journey_trash WHERE id = ?1;",
+                (id,),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("Failed to find trashed journey with id = {id}"))?;
+        let journey_type = JourneyType::of_int(i8::try_from(type_)?)?;
+        JourneyData::deserialize(data_bytes.as_slice(), journey_type, false)
+    }
+
+    /// Fails if a journey with the same id exists again (e.g. re-imported).
+    #[auto_context]
+    pub fn restore_journey_from_trash(&mut self, id: &str) -> Result<()> {
+        info!("Restoring journey from trash: id={id}");
+        if self.get_journey_header(id)?.is_some() {
+            bail!("Journey with ID {id} already exists");
+        }
+        let changes = self.db_txn.execute(
+            "INSERT INTO journey (id, journey_date, timestamp_for_ordering, type, kind, header, data) SELECT id, journey_date, timestamp_for_ordering, type, kind, header, data FROM journey_trash WHERE id = ?1;",
+            (id,),
+        )?;
+        if changes != 1 {
+            bail!("Failed to find trashed journey with id = {id}");
+        }
+        self.db_txn
+            .execute("DELETE FROM journey_trash WHERE id = ?1;", (id,))?;
+        let header = self
+            .get_journey_header(id)?
+            .ok_or_else(|| anyhow!("Failed to find journey with id = {id}"))?;
+        index_journey(&self.db_txn, &header, Some(&self.get_journey_data(id)?))?;
+        self.set_invalidate_action(vec![CacheEntry {
+            date: header.journey_date,
+            kind: header.journey_kind,
+        }])
+    }
+
+    /// Journeys that exist again are left in the trash. Returns the number of
+    /// restored journeys.
+    #[auto_context]
+    pub fn restore_all_journeys_from_trash(&mut self) -> Result<usize> {
+        info!("Restoring all journeys from trash");
+        let ids: Vec<String> = self
+            .db_txn
+            .prepare("SELECT id FROM journey_trash WHERE id NOT IN (SELECT id FROM journey);")?
+            .query_map((), |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let changes = self.db_txn.execute(
+            "INSERT INTO journey (id, journey_date, timestamp_for_ordering, type, kind, header, data) SELECT id, journey_date, timestamp_for_ordering, type, kind, header, data FROM journey_trash WHERE id NOT IN (SELECT id FROM journey) ORDER BY deleted_at;",
+            (),
+        )?;
+        // a restored journey has the same header bytes as the trashed one
+        self.db_txn.execute(
+            "DELETE FROM journey_trash WHERE EXISTS (SELECT 1 FROM journey WHERE journey.id = journey_trash.id AND journey.header = journey_trash.header);",
+            (),
+        )?;
+        for id in &ids {
+            if let Some(header) = self.get_journey_header(id)? {
+                index_journey(&self.db_txn, &header, Some(&self.get_journey_data(id)?))?;
+            }
+        }
+        if changes > 0 {
+            self.action = Some(Action::CompleteRebuilt);
+        }
+        Ok(changes)
+    }
+
+    #[auto_context]
+    pub fn empty_trash(&mut self) -> Result<()> {
+        info!("Emptying trash");
+        self.db_txn.execute("DELETE FROM journey_trash;", ())?;
+        self.prune_journey_history(None)
+    }
+
+    /// Drops journeys that have been in the trash for longer than the
+    /// retention period.
+    #[auto_context]
+    pub fn purge_trash(&mut self) -> Result<()> {
+        let retention_in_days: u32 = query_setting(&self.db_txn, Setting::TrashRetentionInDays)?
+            .unwrap_or(DEFAULT_TRASH_RETENTION_IN_DAYS);
+        if retention_in_days > 0 {
+            let cutoff = Utc::now().timestamp() - i64::from(retention_in_days) * 24 * 60 * 60;
+            self.db_txn.execute(
+                "DELETE FROM journey_trash WHERE deleted_at < ?1;",
+                (cutoff,),
+            )?;
+        }
+        self.prune_journey_history(None)
+    }
+
     // TODO: consider return structured result so the caller know if it is skipped or other cases
     #[auto_context]
-    pub fn insert_journey(&mut self, header: JourneyHeader, mut data: JourneyData) -> Result<()> {
+    pub fn insert_journey(
+        &mut self,
+        mut header: JourneyHeader,
+        mut data: JourneyData,
+    ) -> Result<()> {
+        header.tags = normalize_tags(header.tags);
         let journey_type = header.journey_type;
         if journey_type != data.type_() {
             bail!("[insert_journey] Mismatch journey type")
         }
+        header.journey_kind.validate()?;
         let id = header.id.clone();
 
         match self.get_journey_header(&id)? {
@@ -191,16 +778,17 @@ impl Txn<'_> {
         }
 
         let insert_date = header.journey_date;
-        let insert_kind = header.journey_kind;
+        let insert_kind = header.journey_kind.clone();
         let journey_date = utils::date_to_days_since_epoch(insert_date);
         // use start time first, then fallback to endtime
         let timestamp_for_ordering = header.start.or(header.end).map(|x| x.timestamp());
 
+        index_journey(&self.db_txn, &header, Some(&data))?;
         let header_bytes = header.to_proto().write_to_bytes()?;
         let mut data_bytes = Vec::new();
         data.serialize(&mut data_bytes)?;
 
-        let sql = "INSERT INTO journey (id, journey_date, timestamp_for_ordering, type, header, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6);";
+        let sql = "INSERT INTO journey (id, journey_date, timestamp_for_ordering, type, kind, header, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);";
         self.db_txn.execute(
             sql,
             (
@@ -208,6 +796,7 @@ impl Txn<'_> {
                 journey_date,
                 timestamp_for_ordering,
                 journey_type.to_int(),
+                insert_kind.to_sql(),
                 header_bytes,
                 data_bytes,
             ),
@@ -232,7 +821,6 @@ impl Txn<'_> {
     }
 
     #[allow(clippy::too_many_arguments)]
-    #[auto_context]
     pub fn create_and_insert_journey(
         &mut self,
         journey_date: NaiveDate,
@@ -242,6 +830,31 @@ impl Txn<'_> {
         journey_kind: JourneyKind,
         note: Option<String>,
         journey_data: JourneyData,
+    ) -> Result<String> {
+        self.create_and_insert_journey_with_utc_offsets(
+            journey_date,
+            (start, None),
+            (end, None),
+            created_at,
+            journey_kind,
+            note,
+            journey_data,
+        )
+    }
+
+    /// `start` and `end` come with the UTC offset of the local time where they
+    /// were recorded.
+    #[allow(clippy::too_many_arguments)]
+    #[auto_context]
+    pub fn create_and_insert_journey_with_utc_offsets(
+        &mut self,
+        journey_date: NaiveDate,
+        (start, start_utc_offset): (Option<DateTime<Utc>>, Option<FixedOffset>),
+        (end, end_utc_offset): (Option<DateTime<Utc>>, Option<FixedOffset>),
+        created_at: Option<DateTime<Utc>>,
+        journey_kind: JourneyKind,
+        note: Option<String>,
+        journey_data: JourneyData,
     ) -> Result<String> {
         let (journey_data, postprocessor_algo) = match journey_data {
             JourneyData::Vector(journey_vector) => (
@@ -264,10 +877,13 @@ impl Txn<'_> {
             updated_at: None,
             end,
             start,
+            start_utc_offset_sec: start_utc_offset.map(|x| x.local_minus_utc()),
+            end_utc_offset_sec: end_utc_offset.map(|x| x.local_minus_utc()),
             journey_type,
             journey_kind,
             note,
             postprocessor_algo,
+            tags: Vec::new(),
         };
         self.insert_journey(header, journey_data)?;
         Ok(id)
@@ -284,31 +900,40 @@ impl Txn<'_> {
         new_journey_kind: JourneyKind,
     ) -> Result<()> {
         info!("Updating journey with ID {}", id);
+        new_journey_kind.validate()?;
 
         let mut header = self
             .get_journey_header(id)?
             .ok_or_else(|| anyhow!("Updating non existent journey, journey id = {id}"))?;
+        self.archive_journey(id, true)?;
 
         // must change during update
         header.updated_at = Some(Utc::now());
         header.revision = generate_random_revision();
 
         let old_journey_date = header.journey_date;
-        let old_journey_kind = header.journey_kind;
+        let old_journey_kind = header.journey_kind.clone();
         header.journey_date = new_journey_date;
         header.start = start;
         header.end = end;
         header.note = note;
-        header.journey_kind = new_journey_kind;
+        header.journey_kind = new_journey_kind.clone();
 
         // update
         let journey_date = utils::date_to_days_since_epoch(header.journey_date);
         let timestamp_for_ordering = header.start.or(header.end).map(|x| x.timestamp());
+        index_journey(&self.db_txn, &header, None)?;
         let header_bytes = header.to_proto().write_to_bytes()?;
-        let sql = "UPDATE journey SET journey_date = ?1, timestamp_for_ordering = ?2, header = ?3 WHERE id = ?4;";
+        let sql = "UPDATE journey SET journey_date = ?1, timestamp_for_ordering = ?2, kind = ?3, header = ?4 WHERE id = ?5;";
         self.db_txn.execute(
             sql,
-            (journey_date, timestamp_for_ordering, header_bytes, &id),
+            (
+                journey_date,
+                timestamp_for_ordering,
+                new_journey_kind.to_sql(),
+                header_bytes,
+                &id,
+            ),
         )?;
 
         if old_journey_date != new_journey_date || old_journey_kind != new_journey_kind {
@@ -327,12 +952,46 @@ impl Txn<'_> {
         Ok(())
     }
 
+    #[auto_context]
+    pub fn set_journey_tags(&mut self, id: &str, tags: Vec<String>) -> Result<()> {
+        info!("Setting tags of journey with ID {}", id);
+        let mut header = self
+            .get_journey_header(id)?
+            .ok_or_else(|| anyhow!("Updating non existent journey, journey id = {id}"))?;
+        let tags = normalize_tags(tags);
+        if header.tags == tags {
+            return Ok(());
+        }
+        self.archive_journey(id, true)?;
+
+        // must change during update
+        header.updated_at = Some(Utc::now());
+        header.revision = generate_random_revision();
+        header.tags = tags;
+
+        index_journey(&self.db_txn, &header, None)?;
+        let header_bytes = header.to_proto().write_to_bytes()?;
+        self.db_txn.execute(
+            "UPDATE journey SET header = ?1 WHERE id = ?2;",
+            (header_bytes, &id),
+        )?;
+        Ok(())
+    }
+
     #[auto_context]
     pub fn update_journey_data_with_latest_postprocessor(
         &mut self,
         id: &str,
         journey_data: JourneyData,
     ) -> Result<()> {
+        self.archive_journey(id, false)?;
+        self.replace_journey_data(id, journey_data)
+    }
+
+    /// Same as `update_journey_data_with_latest_postprocessor`, without
+    /// keeping the current revision.
+    #[auto_context]
+    fn replace_journey_data(&mut self, id: &str, journey_data: JourneyData) -> Result<()> {
         info!("Updating journey data with ID {}", id);
 
         let mut header = self
@@ -354,7 +1013,8 @@ impl Txn<'_> {
         header.journey_type = journey_data.type_();
 
         let journey_date = header.journey_date;
-        let journey_kind = header.journey_kind;
+        let journey_kind = header.journey_kind.clone();
+        index_journey(&self.db_txn, &header, Some(&journey_data))?;
         let header_bytes = header.to_proto().write_to_bytes()?;
         let mut data_bytes = Vec::new();
         journey_data.serialize(&mut data_bytes)?;
@@ -372,23 +1032,553 @@ impl Txn<'_> {
         Ok(())
     }
 
+    /// Keeps the current revision of the journey in `journey_history`, if
+    /// there is one. With `header_only`, the data is left out since it is
+    /// not going to change.
+    #[auto_context]
+    fn archive_journey(&mut self, id: &str, header_only: bool) -> Result<()> {
+        let max_revisions: u32 = query_setting(&self.db_txn, Setting::JourneyHistoryMaxRevisions)?
+            .unwrap_or(DEFAULT_JOURNEY_HISTORY_MAX_REVISIONS);
+        if max_revisions == 0 {
+            return Ok(());
+        }
+        let row: Option<(i64, Vec<u8>, Vec<u8>)> = self
+            .db_txn
+            .query_row(
+                "SELECT type, header, CASE WHEN ?2 THEN x'' ELSE data END FROM journey WHERE id = ?1;",
+                (id, header_only),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((type_, header_bytes, data_bytes)) = row else {
+            return Ok(());
+        };
+        // `data` is already compressed
+        let header_bytes = zstd::encode_all(header_bytes.as_slice(), ZSTD_COMPRESS_LEVEL)?;
+        let sql = "INSERT INTO journey_history (journey_id, archived_at, type, header, data) VALUES (?1, ?2, ?3, ?4, ?5);";
+        self.db_txn.execute(
+            sql,
+            (id, Utc::now().timestamp(), type_, header_bytes, data_bytes),
+        )?;
+        self.prune_journey_history(Some(id))
+    }
+
+    /// Applies the retention policy. The number of revisions is only checked
+    /// for `journey_id` if it is given, the age is always checked.
+    #[auto_context]
+    pub fn prune_journey_history(&mut self, journey_id: Option<&str>) -> Result<()> {
+        let max_revisions: u32 = query_setting(&self.db_txn, Setting::JourneyHistoryMaxRevisions)?
+            .unwrap_or(DEFAULT_JOURNEY_HISTORY_MAX_REVISIONS);
+        let max_age_in_days: u32 =
+            query_setting(&self.db_txn, Setting::JourneyHistoryMaxAgeInDays)?
+                .unwrap_or(DEFAULT_JOURNEY_HISTORY_MAX_AGE_IN_DAYS);
+
+        let sql = "DELETE FROM journey_history AS h WHERE (?1 IS NULL OR journey_id = ?1) AND (SELECT COUNT(*) FROM journey_history WHERE journey_id = h.journey_id AND id > h.id) >= ?2;";
+        self.db_txn.execute(sql, (journey_id, max_revisions))?;
+        if max_age_in_days > 0 {
+            let cutoff = Utc::now().timestamp() - i64::from(max_age_in_days) * 24 * 60 * 60;
+            self.db_txn.execute(
+                "DELETE FROM journey_history WHERE archived_at < ?1;",
+                (cutoff,),
+            )?;
+        }
+        // metadata only revisions whose data is gone
+        let sql = "DELETE FROM journey_history AS h WHERE length(data) = 0 AND NOT EXISTS (SELECT 1 FROM journey_history WHERE journey_id = h.journey_id AND id > h.id AND length(data) > 0) AND journey_id NOT IN (SELECT id FROM journey) AND journey_id NOT IN (SELECT id FROM journey_trash);";
+        self.db_txn.execute(sql, ())?;
+        Ok(())
+    }
+
+    #[auto_context]
+    pub fn purge_journey_history(&mut self) -> Result<()> {
+        info!("Purging journey history");
+        self.db_txn.execute("DELETE FROM journey_history;", ())?;
+        Ok(())
+    }
+
+    /// Previous revisions of a journey, the latest first. It also works for
+    /// deleted journeys.
+    #[auto_context]
+    pub fn list_journey_revisions(&self, journey_id: &str) -> Result<Vec<JourneyRevision>> {
+        let mut query = self.db_txn.prepare(
+            "SELECT id, archived_at, header FROM journey_history WHERE journey_id = ?1 ORDER BY id DESC;",
+        )?;
+        let mut rows = query.query((journey_id,))?;
+        let mut revisions = Vec::new();
+        while let Some(row) = rows.next()? {
+            let archived_at: i64 = row.get(1)?;
+            let header_bytes = zstd::decode_all(row.get_ref(2)?.as_blob()?)?;
+            revisions.push(JourneyRevision {
+                history_id: row.get(0)?,
+                archived_at: DateTime::from_timestamp(archived_at, 0)
+                    .ok_or_else(|| anyhow!("Invalid timestamp: {archived_at}"))?,
+                header: JourneyHeader::of_proto(protos::journey::Header::parse_from_bytes(
+                    &header_bytes,
+                )?)?,
+            });
+        }
+        Ok(revisions)
+    }
+
+    #[auto_context]
+    fn get_journey_revision(&self, history_id: i64) -> Result<(JourneyHeader, JourneyData)> {
+        let (journey_id, mut type_, header_bytes, mut data_bytes): (String, i64, Vec<u8>, Vec<u8>) =
+            self.db_txn
+                .query_row(
+                    "SELECT journey_id, type, header, data FROM journey_history WHERE id = ?1;",
+                    (history_id,),
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )
+                .optional()?
+                .ok_or_else(|| anyhow!("Failed to find journey revision with id = {history_id}"))?;
+        let header_bytes = zstd::decode_all(header_bytes.as_slice())?;
+        let header =
+            JourneyHeader::of_proto(protos::journey::Header::parse_from_bytes(&header_bytes)?)?;
+        if data_bytes.is_empty() {
+            // only the metadata changed, the data is the same as the one
+            // of the next full revision or of the journey
+            let mut full_revision = self
+                .db_txn
+                .query_row(
+                    "SELECT type, data FROM journey_history WHERE journey_id = ?1 AND id > ?2 AND length(data) > 0 ORDER BY id LIMIT 1;",
+                    (&journey_id, history_id),
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            for table in ["journey", "journey_trash"] {
+                if full_revision.is_some() {
+                    break;
+                }
+                full_revision = self
+                    .db_txn
+                    .query_row(
+                        &format!("SELECT type, data FROM {table} WHERE id = ?1;"),
+                        (&journey_id,),
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?;
+            }
+            (type_, data_bytes) = full_revision.ok_or_else(|| {
+                anyhow!("Failed to find data of journey revision with id = {history_id}")
+            })?;
+        }
+        let journey_type = JourneyType::of_int(i8::try_from(type_)?)?;
+        let journey_data = JourneyData::deserialize(data_bytes.as_slice(), journey_type, false)?;
+        Ok((header, journey_data))
+    }
+
+    pub fn get_journey_revision_data(&self, history_id: i64) -> Result<JourneyData> {
+        Ok(self.get_journey_revision(history_id)?.1)
+    }
+
+    /// Brings back a previous revision (with a new revision id). The current
+    /// one, if any, goes to the history.
+    #[auto_context]
+    pub fn restore_journey_revision(&mut self, history_id: i64) -> Result<()> {
+        let (mut header, journey_data) = self.get_journey_revision(history_id)?;
+        info!(
+            "Restoring journey revision: id={}, history_id={history_id}",
+            header.id
+        );
+        if self.get_journey_header(&header.id)?.is_some() {
+            self.delete_journey(&header.id)?;
+        }
+        header.revision = generate_random_revision();
+        header.updated_at = Some(Utc::now());
+        self.insert_journey(header, journey_data)
+    }
+
+    /// Splits a vector journey into two. The first one keeps the original id.
+    /// Returns the ids of both journeys.
+    #[auto_context]
+    pub fn split_journey(&mut self, id: &str, at: SplitPosition) -> Result<(String, String)> {
+        info!("Splitting journey: id={id}, at={at:?}");
+        let header = self
+            .get_journey_header(id)?
+            .ok_or_else(|| anyhow!("Failed to find journey with id = {id}"))?;
+        let journey_vector = match self.get_journey_data(id)? {
+            JourneyData::Vector(journey_vector) => journey_vector,
+            JourneyData::Bitmap(_) => bail!("Cannot split bitmap journeys"),
+        };
+        let distances = cumulative_distances(&journey_vector);
+        let num_of_points = distances.len();
+
+        let index = match at {
+            SplitPosition::PointIndex(index) => index,
+            SplitPosition::SegmentBoundary(segment_index) => {
+                if segment_index == 0 || segment_index >= journey_vector.track_segments.len() {
+                    bail!("Invalid segment boundary: {segment_index}");
+                }
+                journey_vector.track_segments[..segment_index]
+                    .iter()
+                    .map(|track_segment| track_segment.track_points.len())
+                    .sum()
+            }
+            SplitPosition::Timestamp(time) => {
+                let (start, end) = match (header.start, header.end) {
+                    (Some(start), Some(end)) if start < time && time < end => (start, end),
+                    _ => bail!("Timestamp is not within the journey: {time}"),
+                };
+                let times = estimate_point_times(&journey_vector, start, end);
+                times
+                    .iter()
+                    .position(|x| *x >= time)
+                    .unwrap_or(num_of_points)
+            }
+        };
+        if index == 0 || index >= num_of_points {
+            bail!("Invalid split position: {at:?}");
+        }
+        let split_time = match at {
+            SplitPosition::Timestamp(time) => Some(time),
+            SplitPosition::PointIndex(_) | SplitPosition::SegmentBoundary(_) => {
+                match (header.start, header.end) {
+                    (Some(start), Some(end)) => {
+                        Some(estimate_point_times(&journey_vector, start, end)[index])
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        let (first, second) = split_journey_vector(journey_vector, index);
+        if first.track_segments.is_empty() || second.track_segments.is_empty() {
+            bail!("Invalid split position: {at:?}");
+        }
+
+        let now = Utc::now();
+        let split_utc_offset_sec =
+            split_time.and_then(|split_time| estimate_utc_offset_sec(&header, split_time));
+        let make_header = |id: String,
+                           journey_vector: &JourneyVector,
+                           (start, start_utc_offset_sec),
+                           (end, end_utc_offset_sec)| {
+            let mut new_header = JourneyHeader {
+                id,
+                revision: generate_random_revision(),
+                journey_date: header.journey_date,
+                created_at: header.created_at,
+                updated_at: Some(now),
+                start,
+                end,
+                start_utc_offset_sec,
+                end_utc_offset_sec,
+                journey_type: JourneyType::Vector,
+                journey_kind: header.journey_kind.clone(),
+                note: header.note.clone(),
+                postprocessor_algo: header.postprocessor_algo.clone(),
+                tags: header.tags.clone(),
+            };
+            if let Some(journey_date) = pick_journey_date(journey_vector, &new_header) {
+                new_header.journey_date = journey_date;
+            }
+            new_header
+        };
+        let first_header = make_header(
+            header.id.clone(),
+            &first,
+            (header.start, header.start_utc_offset_sec),
+            (split_time, split_utc_offset_sec),
+        );
+        let second_header = make_header(
+            Uuid::new_v4().as_hyphenated().to_string(),
+            &second,
+            (split_time, split_utc_offset_sec),
+            (header.end, header.end_utc_offset_sec),
+        );
+        let second_id = second_header.id.clone();
+
+        self.delete_journey(id)?;
+        self.insert_journey(first_header, JourneyData::Vector(first))?;
+        self.insert_journey(second_header, JourneyData::Vector(second))?;
+        Ok((header.id, second_id))
+    }
+
+    /// Merges journeys of the same kind into one, which keeps the id of the
+    /// earliest journey. The result is a bitmap journey if any of the input is.
     #[auto_context]
+    pub fn merge_journeys(&mut self, ids: &[String]) -> Result<String> {
+        info!("Merging journeys: ids={ids:?}");
+        if ids.len() < 2 {
+            bail!("Need at least two journeys to merge");
+        }
+        let mut journeys = Vec::with_capacity(ids.len());
+        for id in ids {
+            if journeys
+                .iter()
+                .any(|(header, _): &(JourneyHeader, JourneyData)| header.id == *id)
+            {
+                bail!("Duplicated journey id: {id}");
+            }
+            let header = self
+                .get_journey_header(id)?
+                .ok_or_else(|| anyhow!("Failed to find journey with id = {id}"))?;
+            let journey_data = self.get_journey_data(id)?;
+            journeys.push((header, journey_data));
+        }
+        let journey_kind = journeys[0].0.journey_kind.clone();
+        if journeys
+            .iter()
+            .any(|(header, _)| header.journey_kind != journey_kind)
+        {
+            bail!("Cannot merge journeys of different kinds");
+        }
+        journeys.sort_by_key(|(header, _)| {
+            (
+                header
+                    .start
+                    .or(header.end)
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC),
+                header.journey_date,
+            )
+        });
+
+        let (start, start_utc_offset_sec) = journeys
+            .iter()
+            .filter_map(|(header, _)| Some((header.start?, header.start_utc_offset_sec)))
+            .min_by_key(|(start, _)| *start)
+            .unzip();
+        let (end, end_utc_offset_sec) = journeys
+            .iter()
+            .filter_map(|(header, _)| Some((header.end?, header.end_utc_offset_sec)))
+            .max_by_key(|(end, _)| *end)
+            .unzip();
+        let created_at = journeys
+            .iter()
+            .map(|(header, _)| header.created_at)
+            .min()
+            .unwrap_or(Utc::now());
+        let notes: Vec<String> = journeys
+            .iter()
+            .filter_map(|(header, _)| header.note.clone())
+            .filter(|note| !note.is_empty())
+            .collect();
+        let note = if notes.is_empty() {
+            None
+        } else {
+            Some(notes.join("\n"))
+        };
+        let tags = normalize_tags(
+            journeys
+                .iter()
+                .flat_map(|(header, _)| header.tags.clone())
+                .collect(),
+        );
+        let postprocessor_algo = journeys[0].0.postprocessor_algo.clone();
+        let same_postprocessor_algo = journeys
+            .iter()
+            .all(|(header, _)| header.postprocessor_algo == postprocessor_algo);
+        let fallback_journey_date = journeys[0].0.journey_date;
+        let id = journeys[0].0.id.clone();
+
+        let all_vector = journeys
+            .iter()
+            .all(|(_, journey_data)| matches!(journey_data, JourneyData::Vector(_)));
+        let (journey_data, journey_date, postprocessor_algo) = if all_vector {
+            let mut journey_date_picker = JourneyDatePicker::new();
+            let mut track_segments = Vec::new();
+            for (header, journey_data) in journeys {
+                if let JourneyData::Vector(journey_vector) = journey_data {
+                    add_points_with_estimated_times(
+                        &mut journey_date_picker,
+                        &journey_vector,
+                        &header,
+                    );
+                    track_segments.extend(journey_vector.track_segments);
+                }
+            }
+            (
+                JourneyData::Vector(JourneyVector { track_segments }),
+                journey_date_picker
+                    .pick_journey_date()
+                    .unwrap_or(fallback_journey_date),
+                // otherwise it will be picked up by the next optimization
+                if same_postprocessor_algo {
+                    postprocessor_algo
+                } else {
+                    None
+                },
+            )
+        } else {
+            let mut journey_bitmap = JourneyBitmap::new();
+            for (_, journey_data) in journeys {
+                journey_data.merge_into(&mut journey_bitmap);
+            }
+            (
+                JourneyData::Bitmap(journey_bitmap),
+                fallback_journey_date,
+                None,
+            )
+        };
+
+        let header = JourneyHeader {
+            id: id.clone(),
+            revision: generate_random_revision(),
+            journey_date,
+            created_at,
+            updated_at: Some(Utc::now()),
+            start,
+            end,
+            start_utc_offset_sec: start_utc_offset_sec.flatten(),
+            end_utc_offset_sec: end_utc_offset_sec.flatten(),
+            journey_type: journey_data.type_(),
+            journey_kind,
+            note,
+            postprocessor_algo,
+            tags,
+        };
+        for id in ids {
+            self.delete_journey(id)?;
+        }
+        self.insert_journey(header, journey_data)?;
+        Ok(id)
+    }
+
+    #[auto_context]
+    fn get_ongoing_journey_raw_data(
+        &self,
+    ) -> Result<Vec<(RawData, ProcessResult, Option<FixedOffset>)>> {
+        let mut query = self.db_txn.prepare(
+            "SELECT timestamp_sec, lat, lng, process_result, altitude, speed, utc_offset_sec FROM ongoing_journey ORDER BY id;",
+        )?;
+        let results = query.query_map((), |row| {
+            let timestamp_sec: Option<i64> = row.get(0)?;
+            let process_result: i8 = row.get(3)?;
+            Ok((
+                RawData {
+                    point: Point {
+                        latitude: row.get(1)?,
+                        longitude: row.get(2)?,
+                    },
+                    timestamp_ms: timestamp_sec.map(|x| x * 1000),
+                    accuracy: None,
+                    altitude: row.get(4)?,
+                    speed: row.get(5)?,
+                },
+                process_result.into(),
+                utc_offset_of_sec(row.get(6)?),
+            ))
+        })?;
+        Ok(results.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    // Parts detected as flights (see `transport_mode`) are processed by
+    // `flight_track_processor`, the rest keeps what we decided while
+    // recording. Also returns the suggested kind of the journey.
+    #[auto_context]
+    fn build_ongoing_journey(
+        &self,
+        journey_date_picker: Option<&mut JourneyDatePicker>,
+    ) -> Result<Option<(JourneyVector, JourneyKind)>> {
+        let rows = self.get_ongoing_journey_raw_data()?;
+        if let Some(journey_date_picker) = journey_date_picker {
+            for (raw_data, _, utc_offset) in &rows {
+                if let Some(time) = raw_data
+                    .timestamp_ms
+                    .and_then(DateTime::from_timestamp_millis)
+                {
+                    journey_date_picker.add_point(
+                        time,
+                        *utc_offset,
+                        &TrackPoint {
+                            latitude: raw_data.point.latitude,
+                            longitude: raw_data.point.longitude,
+                        },
+                    );
+                }
+            }
+        }
+
+        let raw_data: Vec<RawData> = rows
+            .iter()
+            .map(|(raw_data, _, _)| raw_data.clone())
+            .collect();
+        let gap_filling_enabled: bool =
+            query_setting(&self.db_txn, Setting::GapFilling)?.unwrap_or(false);
+        let (kind_ranges, journey_kind) = transport_mode::classify_journey_kind(&raw_data);
+        let mut track_segments = Vec::new();
+        for (kind, range) in kind_ranges {
+            let journey_vector = match kind {
+                JourneyKind::Flight => flight_track_processor::process(&[raw_data[range].to_vec()]),
+                JourneyKind::DefaultKind | JourneyKind::Custom(_) => {
+                    let start = range.start;
+                    let data: Vec<PreprocessedData> = rows[range]
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (raw_data, process_result, _))| PreprocessedData {
+                            timestamp_sec: raw_data.timestamp_ms.map(|x| x / 1000),
+                            track_point: TrackPoint {
+                                latitude: raw_data.point.latitude,
+                                longitude: raw_data.point.longitude,
+                            },
+                            // always break after a flight
+                            process_result: if i == 0 && start > 0 {
+                                ProcessResult::NewSegment
+                            } else {
+                                *process_result
+                            },
+                        })
+                        .collect();
+                    gps_processor::build_journey_vector(data.iter().cloned().map(Ok), None)?.map(
+                        |journey_vector| {
+                            if gap_filling_enabled {
+                                gap_filling::fill_gaps(&data, journey_vector)
+                            } else {
+                                journey_vector
+                            }
+                        },
+                    )
+                }
+            };
+            if let Some(journey_vector) = journey_vector {
+                track_segments.extend(journey_vector.track_segments);
+            }
+        }
+
+        if track_segments.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((JourneyVector { track_segments }, journey_kind)))
+        }
+    }
+
     pub fn finalize_ongoing_journey(&mut self) -> Result<bool> {
+        self.finalize_ongoing_journey_with_road_graph(None)
+    }
+
+    /// With `road_graph`, the journey will be snapped to the road network
+    /// before being saved.
+    #[auto_context]
+    pub fn finalize_ongoing_journey_with_road_graph(
+        &mut self,
+        road_graph: Option<&RoadGraph>,
+    ) -> Result<bool> {
+        self.journal.begin_finalize()?;
         let mut journey_date_picker = JourneyDatePicker::new();
-        let new_journey_added = match self.get_ongoing_journey(Some(&mut journey_date_picker))? {
+        let new_journey_added = match self.build_ongoing_journey(Some(&mut journey_date_picker))? {
             None => false,
-            Some(journey_vector) => {
-                // TODO: allow user to set this when recording?
-                let journey_kind = JourneyKind::DefaultKind;
+            Some((journey_vector, journey_kind)) => {
+                let journey_vector = match road_graph {
+                    None => journey_vector,
+                    Some(road_graph) => {
+                        map_matching::match_journey_vector(road_graph, journey_vector)
+                    }
+                };
 
-                self.create_and_insert_journey(
+                self.create_and_insert_journey_with_utc_offsets(
                     // In practice, `end` could never be none but just in case ...
                     // TODO: Maybe we want better journey date strategy
-                    journey_date_picker
-                        .pick_journey_date()
-                        .unwrap_or_else(|| Local::now().date_naive()),
-                    journey_date_picker.min_time(),
-                    journey_date_picker.max_time(),
+                    journey_date_picker.pick_journey_date().unwrap_or_else(|| {
+                        let now = Utc::now();
+                        now.with_timezone(&local_utc_offset(now)).date_naive()
+                    }),
+                    (
+                        journey_date_picker.min_time(),
+                        journey_date_picker.min_time_utc_offset(),
+                    ),
+                    (
+                        journey_date_picker.max_time(),
+                        journey_date_picker.max_time_utc_offset(),
+                    ),
                     None,
                     journey_kind,
                     None,
@@ -433,21 +1623,167 @@ impl Txn<'_> {
         let mut rows = query.query((from, to))?;
         let mut results = Vec::new();
         while let Some(row) = rows.next()? {
-            let header_bytes = row.get_ref(0)?.as_blob()?;
-            let journey_type = JourneyType::of_int(row.get(1)?)?;
-            let header =
-                JourneyHeader::of_proto(protos::journey::Header::parse_from_bytes(header_bytes)?)?;
-            if header.journey_type != journey_type {
-                bail!(
-                    "Invalid DB state, `journey_type` miss match. id: {}.",
-                    header.id
-                );
+            results.push(parse_header(
+                row.get_ref(0)?.as_blob()?,
+                JourneyType::of_int(row.get(1)?)?,
+            )?);
+        }
+        Ok(results)
+    }
+
+    /// Same order as `query_journeys`.
+    #[auto_context]
+    pub fn search_journeys(&self, search_query: &JourneySearchQuery) -> Result<Vec<JourneyHeader>> {
+        use rusqlite::types::Value;
+
+        let mut params: Vec<Value> = Vec::new();
+        let mut sql =
+            "SELECT header, type, id FROM journey WHERE journey_date >= ? AND journey_date <= ?"
+                .to_string();
+        params.extend([
+            Value::Integer(
+                search_query
+                    .from_date_inclusive
+                    .map_or(i32::MIN, utils::date_to_days_since_epoch)
+                    .into(),
+            ),
+            Value::Integer(
+                search_query
+                    .to_date_inclusive
+                    .map_or(i32::MAX, utils::date_to_days_since_epoch)
+                    .into(),
+            ),
+        ]);
+        if !search_query.kinds.is_empty() {
+            let placeholders = vec!["?"; search_query.kinds.len()].join(", ");
+            sql += &format!(" AND kind IN ({placeholders})");
+            for kind in &search_query.kinds {
+                params.push(Value::Text(kind.to_sql().into_owned()));
+            }
+        }
+        for tag in normalize_tags(search_query.tags.clone()) {
+            sql += " AND id IN (SELECT journey_id FROM journey_tag WHERE tag = ?)";
+            params.push(Value::Text(tag));
+        }
+        if let Some(text) = search_query
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+        {
+            // the trigram tokenizer needs at least 3 characters
+            if text.chars().count() >= 3 {
+                sql += " AND id IN (SELECT journey_id FROM journey_note_fts WHERE note MATCH ?)";
+                params.push(Value::Text(format!("\"{}\"", text.replace('"', "\"\""))));
+            } else {
+                sql += " AND id IN (SELECT journey_id FROM journey_note_fts WHERE note LIKE ? ESCAPE '\\')";
+                let escaped = text
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                params.push(Value::Text(format!("%{escaped}%")));
+            }
+        }
+        // (area, its blocks, the blocks entirely in it)
+        let area_blocks = match &search_query.area {
+            None => None,
+            Some(area) => match area.block_ranges(false) {
+                None => None,
+                Some(block_ranges) => {
+                    sql += " AND id IN (SELECT journey_id FROM journey_footprint WHERE ";
+                    sql += &SearchArea::footprint_condition(&block_ranges, &mut params);
+                    sql += ")";
+                    Some((area, block_ranges, area.block_ranges(true)))
+                }
+            },
+        };
+        // use `id` to break tie
+        sql += " ORDER BY journey_date DESC, timestamp_for_ordering DESC, id";
+        if area_blocks.is_none() {
+            sql += " LIMIT ? OFFSET ?";
+            params.push(Value::Integer(search_query.limit.map_or(-1, i64::from)));
+            params.push(Value::Integer(search_query.offset.into()));
+        }
+
+        let mut query = self.db_txn.prepare(&sql)?;
+        let mut rows = query.query(rusqlite::params_from_iter(params))?;
+        let mut to_skip = search_query.offset;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            if let Some((area, block_ranges, inner_block_ranges)) = &area_blocks {
+                if search_query
+                    .limit
+                    .is_some_and(|limit| results.len() >= limit as usize)
+                {
+                    break;
+                }
+                let id = row.get_ref(2)?.as_str()?;
+                let (in_blocks, in_inner_blocks) =
+                    self.footprint_in_blocks(id, block_ranges, inner_block_ranges.as_ref())?;
+                // the footprint is only as precise as a block, so check the
+                // journeys only having blocks on the border of the area
+                if !in_blocks || (!in_inner_blocks && !area.is_visited(&self.get_journey_data(id)?))
+                {
+                    continue;
+                }
+                if to_skip > 0 {
+                    to_skip -= 1;
+                    continue;
+                }
             }
-            results.push(header);
+            results.push(parse_header(
+                row.get_ref(0)?.as_blob()?,
+                JourneyType::of_int(row.get(1)?)?,
+            )?);
         }
         Ok(results)
     }
 
+    /// Whether the footprint of the journey has blocks in `block_ranges`, and
+    /// whether some of them are in `inner_block_ranges`.
+    #[auto_context]
+    fn footprint_in_blocks(
+        &self,
+        id: &str,
+        block_ranges: &BlockRanges,
+        inner_block_ranges: Option<&BlockRanges>,
+    ) -> Result<(bool, bool)> {
+        use rusqlite::types::Value;
+
+        let mut params = vec![Value::Text(id.to_string())];
+        let condition = SearchArea::footprint_condition(block_ranges, &mut params);
+        let mut query = self.db_txn.prepare_cached(&format!(
+            "SELECT x, y, blocks FROM journey_footprint WHERE journey_id = ? AND {condition};"
+        ))?;
+        let mut rows = query.query(rusqlite::params_from_iter(params))?;
+        let mut in_blocks = false;
+        while let Some(row) = rows.next()? {
+            let (tile_x, tile_y): (i64, i64) = (row.get(0)?, row.get(1)?);
+            for block_key in BlockKeyBitset::deserialize(row.get_ref(2)?.as_blob()?)?.iter() {
+                let x = tile_x * TILE_WIDTH + i64::from(block_key.x());
+                let y = tile_y * TILE_WIDTH + i64::from(block_key.y());
+                if inner_block_ranges.is_some_and(|ranges| SearchArea::contains_block(ranges, x, y))
+                {
+                    return Ok((true, true));
+                }
+                in_blocks |= SearchArea::contains_block(block_ranges, x, y);
+            }
+        }
+        Ok((in_blocks, false))
+    }
+
+    /// All tags in use, sorted.
+    #[auto_context]
+    pub fn list_all_tags(&self) -> Result<Vec<String>> {
+        let mut query = self
+            .db_txn
+            .prepare("SELECT DISTINCT tag FROM journey_tag ORDER BY tag;")?;
+        let tags = query
+            .query_map((), |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tags)
+    }
+
     pub fn get_journey_header(&self, id: &str) -> Result<Option<JourneyHeader>> {
         let mut query = self
             .db_txn
@@ -542,7 +1878,13 @@ impl Txn<'_> {
             Some((start, end)) => {
                 // NOTE: this logic is not called very frequently
 
-                let now = Local::now();
+                let (start_utc_offset, end_utc_offset) = self.get_ongoing_journey_utc_offsets()?;
+                let now = Utc::now();
+                // the user is most likely still where the last point was recorded
+                let now =
+                    now.with_timezone(&end_utc_offset.unwrap_or_else(|| local_utc_offset(now)));
+                let start = start
+                    .with_timezone(&start_utc_offset.unwrap_or_else(|| local_utc_offset(start)));
                 let recording_length_hours = (now.timestamp() - start.timestamp()) / 60 / 60;
                 let required_gap_mins = if recording_length_hours >= 48 {
                     0 // let's just finalize it
@@ -550,7 +1892,7 @@ impl Txn<'_> {
                     2
                 } else {
                     // if the local date changed since start, we should try to finalize it, otherwise we don't want that unless there is a huge gap (6h)
-                    if start.with_timezone(&Local).date_naive() == now.date_naive() {
+                    if start.date_naive() == now.date_naive() {
                         6 * 60
                     } else if now.hour() <= 4 || recording_length_hours <= 8 {
                         20
@@ -585,6 +1927,24 @@ impl Txn<'_> {
             .context("earliest_journey_date")
     }
 
+    /// All built-in kinds followed by custom kinds used by at least one
+    /// journey, sorted by name.
+    #[auto_context]
+    pub fn journey_kinds(&self) -> Result<Vec<JourneyKind>> {
+        let mut query = self.db_txn.prepare("SELECT DISTINCT kind FROM journey;")?;
+        let mut rows = query.query(())?;
+        let mut custom_kinds = BTreeSet::new();
+        while let Some(row) = rows.next()? {
+            if let JourneyKind::Custom(name) = JourneyKind::of_sql(row.get_ref(0)?.as_str()?)? {
+                custom_kinds.insert(name);
+            }
+        }
+        Ok(JourneyKind::BUILT_IN
+            .into_iter()
+            .chain(custom_kinds.into_iter().map(JourneyKind::Custom))
+            .collect())
+    }
+
     pub fn journey_date_range(&self) -> Result<Option<(NaiveDate, NaiveDate)>> {
         let mut query = self
             .db_txn
@@ -623,8 +1983,7 @@ impl Txn<'_> {
                 match self.get_journey_data(&journey_header.id)? {
                     JourneyData::Bitmap(_) => (),
                     JourneyData::Vector(journey_vector) => {
-                        let journey_vector = GpsPostprocessor::process(journey_vector);
-                        self.update_journey_data_with_latest_postprocessor(
+                        self.replace_journey_data(
                             &journey_header.id,
                             JourneyData::Vector(journey_vector),
                         )?;
@@ -637,11 +1996,19 @@ impl Txn<'_> {
     }
 }
 
+// Points are added to `ongoing_journey` once there are this many of them in the
+// journal, or before any transaction so they are always visible to readers.
+const JOURNAL_BATCH_SIZE: usize = 32;
+
 pub struct MainDb {
     conn: Connection,
+    journal: RecordingJournal,
+    // points in the journal but not in `ongoing_journey` yet
+    pending_points: Vec<JournalPoint>,
+    recovery_report: RecoveryReport,
 }
 
-fn migrations() -> [utils::db::Migration<'static>; 1] {
+fn migrations() -> [utils::db::Migration<'static>; 6] {
     fn migrate_to_1_0(tx: &Transaction) -> Result<()> {
         let sql = "
         CREATE TABLE ongoing_journey (
@@ -680,7 +2047,148 @@ fn migrations() -> [utils::db::Migration<'static>; 1] {
         Ok(())
     }
 
-    [utils::db::Migration::new(1, 0, &migrate_to_1_0)]
+    fn migrate_to_1_1(tx: &Transaction) -> Result<()> {
+        // used by transport mode detection
+        let sql = "
+        ALTER TABLE ongoing_journey ADD COLUMN altitude REAL;
+        ALTER TABLE ongoing_journey ADD COLUMN speed REAL;
+        ";
+        for statement in sql_split::split(sql) {
+            tx.execute(&statement, ())?;
+        }
+        Ok(())
+    }
+
+    fn migrate_to_1_2(tx: &Transaction) -> Result<()> {
+        let sql = "
+        CREATE TABLE journey_history (
+            id                INTEGER PRIMARY KEY AUTOINCREMENT
+                                      UNIQUE
+                                      NOT NULL,
+            journey_id        TEXT    NOT NULL,
+            archived_at       INTEGER NOT NULL, -- unix timestamp in seconds
+            type              INTEGER NOT NULL,
+            header            BLOB    NOT NULL, -- zstd compressed
+            data              BLOB    NOT NULL  -- same as `journey.data`
+        );
+        CREATE INDEX journey_history_journey_id_index ON journey_history (
+            journey_id
+        );
+        CREATE INDEX journey_history_archived_at_index ON journey_history (
+            archived_at
+        );
+        ";
+        for statement in sql_split::split(sql) {
+            tx.execute(&statement, ())?;
+        }
+        Ok(())
+    }
+
+    fn migrate_to_1_3(tx: &Transaction) -> Result<()> {
+        // same as `journey`, with the `kind` it gets in 1.4
+        let sql = "
+        CREATE TABLE journey_trash (
+            id                TEXT    PRIMARY KEY
+                                      NOT NULL
+                                      UNIQUE,
+            journey_date      INTEGER NOT NULL,
+            timestamp_for_ordering
+                              INTEGER,
+            type              INTEGER NOT NULL,
+            header            BLOB    NOT NULL,
+            data              BLOB    NOT NULL,
+            deleted_at        INTEGER NOT NULL, -- unix timestamp in seconds
+            kind              TEXT    NOT NULL DEFAULT 'Default'
+        );
+        CREATE INDEX journey_trash_deleted_at_index ON journey_trash (
+            deleted_at
+        );
+        ";
+        for statement in sql_split::split(sql) {
+            tx.execute(&statement, ())?;
+        }
+        Ok(())
+    }
+
+    fn migrate_to_1_4(tx: &Transaction) -> Result<()> {
+        // search index, see `index_journey`
+        let sql = "
+        CREATE TABLE journey_tag (
+            journey_id        TEXT    NOT NULL,
+            tag               TEXT    NOT NULL,
+            PRIMARY KEY (journey_id, tag)
+        );
+        CREATE INDEX journey_tag_tag_index ON journey_tag (
+            tag
+        );
+        CREATE VIRTUAL TABLE journey_note_fts USING fts5 (
+            journey_id UNINDEXED,
+            note,
+            tokenize = 'trigram'
+        );
+        -- `JourneyKind::to_sql` of the header, so kinds can be queried
+        -- without parsing every header
+        ALTER TABLE journey ADD COLUMN kind TEXT NOT NULL DEFAULT 'Default';
+        CREATE INDEX journey_kind_index ON journey (
+            kind
+        );
+        -- visited blocks (see `journey_bitmap`) of each journey, per tile
+        CREATE TABLE journey_footprint (
+            journey_id        TEXT    NOT NULL,
+            x                 INTEGER NOT NULL,
+            y                 INTEGER NOT NULL,
+            blocks            BLOB    NOT NULL, -- `BlockKeyBitset::serialize`
+            PRIMARY KEY (journey_id, x, y)
+        ) WITHOUT ROWID;
+        CREATE INDEX journey_footprint_x_y_index ON journey_footprint (
+            x,
+            y
+        );
+        ";
+        for statement in sql_split::split(sql) {
+            tx.execute(&statement, ())?;
+        }
+
+        let mut kinds = Vec::new();
+        let mut query = tx.prepare("SELECT header, type, data FROM journey;")?;
+        let mut rows = query.query(())?;
+        while let Some(row) = rows.next()? {
+            let journey_type = JourneyType::of_int(row.get(1)?)?;
+            let header = parse_header(row.get_ref(0)?.as_blob()?, journey_type)?;
+            let journey_data =
+                JourneyData::deserialize(row.get_ref(2)?.as_blob()?, journey_type, false)?;
+            index_journey(tx, &header, Some(&journey_data))?;
+            if header.journey_kind != JourneyKind::DefaultKind {
+                kinds.push((header.id, header.journey_kind));
+            }
+        }
+        // not updating `journey` while reading it
+        for (id, kind) in kinds {
+            tx.execute(
+                "UPDATE journey SET kind = ?1 WHERE id = ?2;",
+                (kind.to_sql(), id),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn migrate_to_1_5(tx: &Transaction) -> Result<()> {
+        // used for picking the journey date in the local time of each point
+        tx.execute(
+            "ALTER TABLE ongoing_journey ADD COLUMN utc_offset_sec INTEGER;",
+            (),
+        )?;
+        Ok(())
+    }
+
+    [
+        utils::db::Migration::new(1, 0, &migrate_to_1_0),
+        utils::db::Migration::new(1, 1, &migrate_to_1_1),
+        utils::db::Migration::new(1, 2, &migrate_to_1_2),
+        utils::db::Migration::new(1, 3, &migrate_to_1_3),
+        utils::db::Migration::new(1, 4, &migrate_to_1_4),
+        utils::db::Migration::new(1, 5, &migrate_to_1_5),
+    ]
 }
 
 #[cfg(test)]
@@ -694,9 +2202,39 @@ mod migration_tests {
 }
 
 impl MainDb {
+    #[auto_context]
     pub fn open(support_dir: &str) -> Result<MainDb> {
         let conn = utils::db::open_and_migrate(support_dir, "main.db", &migrations())?;
-        Ok(MainDb { conn })
+        let committed_seq = query_setting(&conn, Setting::JournalCommittedSeq)?.unwrap_or(0);
+        let (journal, pending_points, finalize_interrupted) =
+            RecordingJournal::open(support_dir, committed_seq)?;
+        let recovery_report = RecoveryReport {
+            recovered_points: pending_points.len() as u32,
+            last_timestamp: pending_points
+                .iter()
+                .filter_map(|point| point.raw_data.timestamp_ms)
+                .max()
+                .and_then(DateTime::from_timestamp_millis),
+            finalize_interrupted,
+        };
+        if recovery_report != RecoveryReport::default() {
+            warn!("Recovered from recording journal: {recovery_report:?}");
+        }
+        let mut main_db = MainDb {
+            conn,
+            journal,
+            pending_points,
+            recovery_report,
+        };
+        main_db.commit_pending_points()?;
+        // nothing to recover from an interrupted finalization
+        main_db.journal.clear()?;
+        Ok(main_db)
+    }
+
+    /// What was recovered from the recording journal when opening.
+    pub fn recovery_report(&self) -> &RecoveryReport {
+        &self.recovery_report
     }
 
     #[auto_context]
@@ -704,13 +2242,21 @@ impl MainDb {
     where
         F: FnOnce(&mut Txn) -> Result<O>,
     {
-        let mut txn = Txn {
-            db_txn: self.conn.transaction()?,
-            action: None,
+        self.commit_pending_points()?;
+        let output = {
+            let mut txn = Txn {
+                db_txn: self.conn.transaction()?,
+                journal: &mut self.journal,
+                action: None,
+            };
+            match f(&mut txn) {
+                Ok(output) => txn.db_txn.commit().map(|()| output).map_err(Into::into),
+                Err(error) => Err(error),
+            }
         };
-        let output = f(&mut txn)?;
-        txn.db_txn.commit()?;
-        Ok(output)
+        // the ongoing journey is either finalized or rolled back by now
+        self.journal.end_finalize()?;
+        output
     }
 
     #[auto_context]
@@ -725,35 +2271,57 @@ impl MainDb {
     */
 
     #[auto_context]
-    fn append_ongoing_journey(
-        &mut self,
-        raw_data: &gps_processor::RawData,
-        process_result: ProcessResult,
-    ) -> Result<()> {
-        let process_result = process_result.to_int();
-        assert!(process_result >= 0);
+    fn commit_pending_points(&mut self) -> Result<()> {
+        let last_seq = match self.pending_points.last() {
+            None => return Ok(()),
+            Some(point) => point.seq,
+        };
         let tx = self.conn.transaction()?;
-        let sql = "INSERT INTO ongoing_journey (timestamp_sec, lat, lng, process_result) VALUES (?1, ?2, ?3, ?4);";
-        tx.prepare_cached(sql)?.execute((
-            raw_data.timestamp_ms.map(|x| x / 1000),
-            raw_data.point.latitude,
-            raw_data.point.longitude,
-            process_result,
-        ))?;
+        let sql = "INSERT INTO ongoing_journey (timestamp_sec, lat, lng, process_result, altitude, speed, utc_offset_sec) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);";
+        for point in &self.pending_points {
+            let process_result = point.process_result.to_int();
+            assert!(process_result >= 0);
+            tx.prepare_cached(sql)?.execute((
+                point.raw_data.timestamp_ms.map(|x| x / 1000),
+                point.raw_data.point.latitude,
+                point.raw_data.point.longitude,
+                process_result,
+                point.raw_data.altitude,
+                point.raw_data.speed,
+                point.utc_offset.map(|x| x.local_minus_utc()),
+            ))?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO setting (key, value) VALUES (?1, ?2);",
+            (
+                Setting::JournalCommittedSeq.to_db_key(),
+                last_seq.to_string(),
+            ),
+        )?;
         tx.commit()?;
+        self.pending_points.clear();
+        self.journal.clear()?;
         Ok(())
     }
 
+    /// `utc_offset` is the UTC offset of the local time where the point was
+    /// recorded, it decides the date of the journey. `None` means the device's
+    /// timezone.
     #[auto_context]
     pub fn record(
         &mut self,
         raw_data: &gps_processor::RawData,
         process_result: ProcessResult,
+        utc_offset: Option<FixedOffset>,
     ) -> Result<()> {
         match process_result {
             ProcessResult::Ignore => (),
             ProcessResult::Append | ProcessResult::NewSegment => {
-                self.append_ongoing_journey(raw_data, process_result)?;
+                let point = self.journal.append(raw_data, process_result, utc_offset)?;
+                self.pending_points.push(point);
+                if self.pending_points.len() >= JOURNAL_BATCH_SIZE {
+                    self.commit_pending_points()?;
+                }
             }
         }
         Ok(())
@@ -765,17 +2333,7 @@ impl MainDb {
         <T as FromStr>::Err: Error + Send + Sync + 'static,
     {
         let tx = self.conn.transaction()?;
-        let mut query = tx.prepare("SELECT value FROM setting WHERE key = ?1;")?;
-        let result: Option<String> = query
-            .query_row([setting.to_db_key()], |row| row.get(0))
-            .optional()?;
-        match result {
-            None => Ok(None),
-            Some(s) => {
-                let v = FromStr::from_str(&s)?;
-                Ok(Some(v))
-            }
-        }
+        query_setting(&tx, setting)
     }
 
     pub fn get_setting_with_default<T: FromStr>(&mut self, setting: Setting, default: T) -> T
@@ -807,12 +2365,45 @@ pub enum Setting {
     // TODO: We should consider making the flutter part handle this, similar to
     // `GpsManager.isRecording`.
     RawDataMode,
+    /// Fill plausible gaps (e.g. tunnels) when finalizing ongoing journeys.
+    GapFilling,
+    /// Number of previous revisions kept for each journey, 0 disables history.
+    JourneyHistoryMaxRevisions,
+    /// Previous revisions older than this are dropped, 0 keeps them forever.
+    JourneyHistoryMaxAgeInDays,
+    /// Trashed journeys older than this are dropped, 0 keeps them forever.
+    TrashRetentionInDays,
+    /// The last `recording_journal` entry added to `ongoing_journey`.
+    JournalCommittedSeq,
 }
 
 impl Setting {
     fn to_db_key(self) -> &'static str {
         match self {
             Self::RawDataMode => "RAW_DATA_MODE",
+            Self::GapFilling => "GAP_FILLING",
+            Self::JourneyHistoryMaxRevisions => "JOURNEY_HISTORY_MAX_REVISIONS",
+            Self::JourneyHistoryMaxAgeInDays => "JOURNEY_HISTORY_MAX_AGE_IN_DAYS",
+            Self::TrashRetentionInDays => "TRASH_RETENTION_IN_DAYS",
+            Self::JournalCommittedSeq => "JOURNAL_COMMITTED_SEQ",
+        }
+    }
+}
+
+#[auto_context]
+fn query_setting<T: FromStr>(conn: &Connection, setting: Setting) -> Result<Option<T>>
+where
+    <T as FromStr>::Err: Error + Send + Sync + 'static,
+{
+    let mut query = conn.prepare("SELECT value FROM setting WHERE key = ?1;")?;
+    let result: Option<String> = query
+        .query_row([setting.to_db_key()], |row| row.get(0))
+        .optional()?;
+    match result {
+        None => Ok(None),
+        Some(s) => {
+            let v = FromStr::from_str(&s)?;
+            Ok(Some(v))
         }
     }
 }
@@ -0,0 +1,600 @@
+// Offline map matching: snapping a noisy `JourneyVector` onto a road/path
+// graph, based on the HMM approach from "Hidden Markov Map Matching Through
+// Noise and Sparseness" (Newson & Krumm, 2009).
+//
+// The road graph is loaded from a compact prebuilt file (see
+// `RoadGraph::serialize`). Such a file is built from an OSM XML extract with
+// `RoadGraph::from_osm_xml` (exposed as `jbm_tool road-graph`), which is done
+// outside of the app. Points that are too far away from any road are kept as
+// they are, so matching never loses data.
+//
+// NOTE: the graph is assumed to not cross the antimeridian.
+use crate::gps_processor::Point;
+use crate::journey_data::{validate_magic_header, ZSTD_COMPRESS_LEVEL};
+use crate::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+use anyhow::{Context, Result};
+use auto_context::auto_context;
+use integer_encoding::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::str::FromStr;
+
+const ROAD_GRAPH_MAGIC_HEADER: [u8; 2] = *b"G0";
+const COORDINATE_SCALE: f64 = 1e7;
+const GRID_CELL_SIZE_IN_DEGREE: f64 = 0.01;
+const METERS_PER_DEGREE: f64 = 6371e3 * std::f64::consts::PI / 180.0;
+
+// Standard deviation of GPS noise.
+const EMISSION_SIGMA_IN_M: f64 = 10.0;
+// How much we tolerate the route distance being different from the distance
+// between two GPS points.
+const TRANSITION_BETA_IN_M: f64 = 20.0;
+const SEARCH_RADIUS_IN_M: f64 = 50.0;
+const MAX_NUM_OF_CANDIDATES: usize = 8;
+const MAX_ROUTE_DISTANCE_FACTOR: f64 = 4.0;
+const MIN_ROUTE_DISTANCE_LIMIT_IN_M: f64 = 200.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoadGraph {
+    nodes: Vec<TrackPoint>,
+    // undirected edges
+    edges: Vec<(u32, u32)>,
+    edge_lengths_in_m: Vec<f64>,
+    adjacency: Vec<Vec<u32>>,
+    grid: HashMap<(i32, i32), Vec<u32>>,
+}
+
+fn grid_cell(latitude: f64, longitude: f64) -> (i32, i32) {
+    (
+        (latitude / GRID_CELL_SIZE_IN_DEGREE).floor() as i32,
+        (longitude / GRID_CELL_SIZE_IN_DEGREE).floor() as i32,
+    )
+}
+
+// `highway` values that are not (yet or anymore) usable roads.
+const IGNORED_OSM_HIGHWAY_VALUES: [&[u8]; 4] =
+    [b"proposed", b"construction", b"abandoned", b"razed"];
+
+#[auto_context]
+fn osm_attribute<T: FromStr>(element: &BytesStart, name: &str) -> Result<Option<T>>
+where
+    <T as FromStr>::Err: std::error::Error + Send + Sync + 'static,
+{
+    match element.try_get_attribute(name)? {
+        None => Ok(None),
+        Some(attribute) => Ok(Some(std::str::from_utf8(&attribute.value)?.parse()?)),
+    }
+}
+
+fn distance_in_m(a: &TrackPoint, b: &TrackPoint) -> f64 {
+    let to_point = |p: &TrackPoint| Point {
+        latitude: p.latitude,
+        longitude: p.longitude,
+    };
+    to_point(a).haversine_distance(&to_point(b))
+}
+
+impl RoadGraph {
+    pub fn new(nodes: Vec<TrackPoint>, edges: Vec<(u32, u32)>) -> Result<Self> {
+        let mut adjacency = vec![Vec::new(); nodes.len()];
+        let mut edge_lengths_in_m = Vec::with_capacity(edges.len());
+        let mut grid: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+        for (edge_index, (from, to)) in edges.iter().enumerate() {
+            let (start, end) = match (nodes.get(*from as usize), nodes.get(*to as usize)) {
+                (Some(start), Some(end)) => (start, end),
+                _ => bail!("Invalid edge: {from} -> {to}"),
+            };
+            adjacency[*from as usize].push(edge_index as u32);
+            adjacency[*to as usize].push(edge_index as u32);
+            edge_lengths_in_m.push(distance_in_m(start, end));
+
+            let (min_y, min_x) = grid_cell(
+                start.latitude.min(end.latitude),
+                start.longitude.min(end.longitude),
+            );
+            let (max_y, max_x) = grid_cell(
+                start.latitude.max(end.latitude),
+                start.longitude.max(end.longitude),
+            );
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    grid.entry((y, x)).or_default().push(edge_index as u32);
+                }
+            }
+        }
+        Ok(RoadGraph {
+            nodes,
+            edges,
+            edge_lengths_in_m,
+            adjacency,
+            grid,
+        })
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    #[auto_context]
+    pub fn load(file_path: &str) -> Result<Self> {
+        Self::deserialize(BufReader::new(File::open(file_path)?))
+    }
+
+    #[auto_context]
+    pub fn serialize<T: Write>(&self, mut writer: T) -> Result<()> {
+        writer.write_all(&ROAD_GRAPH_MAGIC_HEADER)?;
+
+        // data is compressed as a whole
+        let mut encoder = zstd::Encoder::new(writer, ZSTD_COMPRESS_LEVEL)?.auto_finish();
+        encoder.write_all(&(self.nodes.len() as u64).encode_var_vec())?;
+        for node in &self.nodes {
+            encoder
+                .write_all(&((node.latitude * COORDINATE_SCALE).round() as i32).to_be_bytes())?;
+            encoder
+                .write_all(&((node.longitude * COORDINATE_SCALE).round() as i32).to_be_bytes())?;
+        }
+        encoder.write_all(&(self.edges.len() as u64).encode_var_vec())?;
+        for (from, to) in &self.edges {
+            encoder.write_all(&from.encode_var_vec())?;
+            encoder.write_all(&to.encode_var_vec())?;
+        }
+        Ok(())
+    }
+
+    #[auto_context]
+    pub fn deserialize<T: Read>(mut reader: T) -> Result<Self> {
+        validate_magic_header(&mut reader, &ROAD_GRAPH_MAGIC_HEADER)?;
+
+        let mut decoder = zstd::Decoder::new(reader)?;
+        let nodes_count: u64 = decoder.read_varint()?;
+        let mut nodes = Vec::with_capacity(nodes_count as usize);
+        for _ in 0..nodes_count {
+            let mut buf: [u8; 4] = [0; 4];
+            decoder.read_exact(&mut buf)?;
+            let latitude = i32::from_be_bytes(buf) as f64 / COORDINATE_SCALE;
+            decoder.read_exact(&mut buf)?;
+            let longitude = i32::from_be_bytes(buf) as f64 / COORDINATE_SCALE;
+            nodes.push(TrackPoint {
+                latitude,
+                longitude,
+            });
+        }
+        let edges_count: u64 = decoder.read_varint()?;
+        let mut edges = Vec::with_capacity(edges_count as usize);
+        for _ in 0..edges_count {
+            let from: u32 = decoder.read_varint()?;
+            let to: u32 = decoder.read_varint()?;
+            edges.push((from, to));
+        }
+        Self::new(nodes, edges)
+    }
+
+    // Builds the graph from OSM XML (e.g. an extract exported from
+    // openstreetmap.org, or a PBF file converted with `osmium cat`). Every way
+    // with a `highway` tag becomes a chain of edges, and only the nodes used by
+    // those ways are kept. References to nodes missing from the extract are
+    // skipped.
+    #[auto_context]
+    pub fn from_osm_xml<T: BufRead>(reader: T) -> Result<Self> {
+        let mut reader = Reader::from_reader(reader);
+        let mut buf = Vec::new();
+        let mut osm_nodes: HashMap<i64, TrackPoint> = HashMap::new();
+        let mut ways: Vec<Vec<i64>> = Vec::new();
+        // (node refs, is a road) of the way being read
+        let mut current_way: Option<(Vec<i64>, bool)> = None;
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                    b"node" => {
+                        if let (Some(id), Some(latitude), Some(longitude)) = (
+                            osm_attribute(&e, "id")?,
+                            osm_attribute(&e, "lat")?,
+                            osm_attribute(&e, "lon")?,
+                        ) {
+                            osm_nodes.insert(
+                                id,
+                                TrackPoint {
+                                    latitude,
+                                    longitude,
+                                },
+                            );
+                        }
+                    }
+                    b"way" => current_way = Some((Vec::new(), false)),
+                    b"nd" => {
+                        if let (Some((refs, _)), Some(id)) =
+                            (current_way.as_mut(), osm_attribute(&e, "ref")?)
+                        {
+                            refs.push(id);
+                        }
+                    }
+                    b"tag" => {
+                        if let Some((_, is_road)) = current_way.as_mut() {
+                            let key = e.try_get_attribute("k")?;
+                            let value = e.try_get_attribute("v")?;
+                            if let (Some(key), Some(value)) = (key, value) {
+                                if key.value.as_ref() == b"highway"
+                                    && !IGNORED_OSM_HIGHWAY_VALUES.contains(&value.value.as_ref())
+                                {
+                                    *is_road = true;
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                Event::End(e) if e.name().as_ref() == b"way" => {
+                    if let Some((refs, true)) = current_way.take() {
+                        ways.push(refs);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut node_indices: HashMap<i64, u32> = HashMap::new();
+        let mut node_index = |id: i64| -> Option<u32> {
+            let point = osm_nodes.get(&id)?;
+            Some(*node_indices.entry(id).or_insert_with(|| {
+                nodes.push(point.clone());
+                (nodes.len() - 1) as u32
+            }))
+        };
+        for refs in ways {
+            for pair in refs.windows(2) {
+                if pair[0] == pair[1] {
+                    continue;
+                }
+                if let (Some(from), Some(to)) = (node_index(pair[0]), node_index(pair[1])) {
+                    edges.push((from, to));
+                }
+            }
+        }
+        Self::new(nodes, edges)
+    }
+
+    fn candidates(&self, point: &TrackPoint) -> Vec<Candidate> {
+        let lng_scale = point.latitude.to_radians().cos().max(0.01);
+        let radius_lat = SEARCH_RADIUS_IN_M / METERS_PER_DEGREE;
+        let radius_lng = radius_lat / lng_scale;
+        let (min_y, min_x) = grid_cell(point.latitude - radius_lat, point.longitude - radius_lng);
+        let (max_y, max_x) = grid_cell(point.latitude + radius_lat, point.longitude + radius_lng);
+
+        // local equirectangular projection around `point`, good enough for
+        // distances within the search radius.
+        let to_xy = |p: &TrackPoint| {
+            (
+                (p.longitude - point.longitude) * lng_scale * METERS_PER_DEGREE,
+                (p.latitude - point.latitude) * METERS_PER_DEGREE,
+            )
+        };
+
+        let mut candidates = Vec::new();
+        let mut visited_edges = HashSet::new();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                for edge_index in self.grid.get(&(y, x)).into_iter().flatten() {
+                    if !visited_edges.insert(*edge_index) {
+                        continue;
+                    }
+
+                    let (from, to) = self.edges[*edge_index as usize];
+                    let (start, end) = (&self.nodes[from as usize], &self.nodes[to as usize]);
+                    let ((x0, y0), (x1, y1)) = (to_xy(start), to_xy(end));
+                    let (dx, dy) = (x1 - x0, y1 - y0);
+                    let length_squared = dx * dx + dy * dy;
+                    let t = if length_squared == 0.0 {
+                        0.0
+                    } else {
+                        (-(x0 * dx + y0 * dy) / length_squared).clamp(0.0, 1.0)
+                    };
+                    let distance_in_m = (x0 + t * dx).hypot(y0 + t * dy);
+                    if distance_in_m <= SEARCH_RADIUS_IN_M {
+                        candidates.push(Candidate {
+                            edge: *edge_index as usize,
+                            offset_in_m: t * self.edge_lengths_in_m[*edge_index as usize],
+                            point: TrackPoint {
+                                latitude: start.latitude + t * (end.latitude - start.latitude),
+                                longitude: start.longitude + t * (end.longitude - start.longitude),
+                            },
+                            distance_in_m,
+                        });
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.distance_in_m.total_cmp(&b.distance_in_m));
+        candidates.truncate(MAX_NUM_OF_CANDIDATES);
+        candidates
+    }
+
+    // Dijkstra from a candidate, returning the distance and the previous node
+    // of every node reachable within `limit_in_m`.
+    fn shortest_paths(
+        &self,
+        candidate: &Candidate,
+        limit_in_m: f64,
+    ) -> HashMap<u32, (f64, Option<u32>)> {
+        let mut result: HashMap<u32, (f64, Option<u32>)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let (from, to) = self.edges[candidate.edge];
+        let edge_length = self.edge_lengths_in_m[candidate.edge];
+        heap.push(HeapEntry {
+            distance_in_m: candidate.offset_in_m,
+            node: from,
+            prev: None,
+        });
+        heap.push(HeapEntry {
+            distance_in_m: edge_length - candidate.offset_in_m,
+            node: to,
+            prev: None,
+        });
+        while let Some(HeapEntry {
+            distance_in_m,
+            node,
+            prev,
+        }) = heap.pop()
+        {
+            if distance_in_m > limit_in_m || result.contains_key(&node) {
+                continue;
+            }
+            result.insert(node, (distance_in_m, prev));
+            for edge_index in &self.adjacency[node as usize] {
+                let (a, b) = self.edges[*edge_index as usize];
+                let next = if a == node { b } else { a };
+                if !result.contains_key(&next) {
+                    heap.push(HeapEntry {
+                        distance_in_m: distance_in_m + self.edge_lengths_in_m[*edge_index as usize],
+                        node: next,
+                        prev: Some(node),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    // Route from `a` to `b`, returning the distance and the nodes in between.
+    fn route(
+        &self,
+        a: &Candidate,
+        b: &Candidate,
+        shortest_paths_from_a: &HashMap<u32, (f64, Option<u32>)>,
+    ) -> Option<(f64, Vec<u32>)> {
+        if a.edge == b.edge {
+            return Some(((a.offset_in_m - b.offset_in_m).abs(), Vec::new()));
+        }
+        let (from, to) = self.edges[b.edge];
+        let edge_length = self.edge_lengths_in_m[b.edge];
+        let via_from = shortest_paths_from_a
+            .get(&from)
+            .map(|(d, _)| (d + b.offset_in_m, from));
+        let via_to = shortest_paths_from_a
+            .get(&to)
+            .map(|(d, _)| (d + edge_length - b.offset_in_m, to));
+        let (distance_in_m, last_node) = match (via_from, via_to) {
+            (Some(x), Some(y)) => {
+                if x.0 <= y.0 {
+                    x
+                } else {
+                    y
+                }
+            }
+            (Some(x), None) | (None, Some(x)) => x,
+            (None, None) => return None,
+        };
+        let mut nodes = vec![last_node];
+        let mut current = last_node;
+        while let Some((_, Some(prev))) = shortest_paths_from_a.get(&current) {
+            nodes.push(*prev);
+            current = *prev;
+        }
+        nodes.reverse();
+        Some((distance_in_m, nodes))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    edge: usize,
+    // distance from the start node of the edge
+    offset_in_m: f64,
+    point: TrackPoint,
+    distance_in_m: f64,
+}
+
+struct HeapEntry {
+    distance_in_m: f64,
+    node: u32,
+    prev: Option<u32>,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance_in_m == other.distance_in_m
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    // reversed so `BinaryHeap` pops the closest node first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance_in_m.total_cmp(&self.distance_in_m)
+    }
+}
+
+struct ViterbiState {
+    candidate: Candidate,
+    log_probability: f64,
+    // index of the previous state and the road nodes leading to this state
+    prev: Option<(usize, Vec<u32>)>,
+}
+
+fn emission_log_probability(candidate: &Candidate) -> f64 {
+    let x = candidate.distance_in_m / EMISSION_SIGMA_IN_M;
+    -0.5 * x * x
+}
+
+// Finishes a run of matched points by backtracking the best path.
+fn flush_run(
+    road_graph: &RoadGraph,
+    run: &mut Vec<Vec<ViterbiState>>,
+    output: &mut Vec<TrackPoint>,
+) {
+    let last = match run.last() {
+        None => return,
+        Some(last) => last,
+    };
+    let mut best = (0..last.len()).max_by(|a, b| {
+        last[*a]
+            .log_probability
+            .total_cmp(&last[*b].log_probability)
+    });
+    let mut matched = Vec::with_capacity(run.len());
+    for states in run.iter().rev() {
+        let index = match best {
+            None => break,
+            Some(index) => index,
+        };
+        let state = &states[index];
+        matched.push(state.candidate.point.clone());
+        if let Some((prev_index, nodes)) = &state.prev {
+            for node in nodes.iter().rev() {
+                matched.push(road_graph.nodes[*node as usize].clone());
+            }
+            best = Some(*prev_index);
+        } else {
+            best = None;
+        }
+    }
+    matched.reverse();
+    output.append(&mut matched);
+    run.clear();
+}
+
+fn match_track_points(road_graph: &RoadGraph, track_points: &[TrackPoint]) -> Vec<TrackPoint> {
+    let mut output = Vec::with_capacity(track_points.len());
+    let mut run: Vec<Vec<ViterbiState>> = Vec::new();
+    let mut prev_point: Option<&TrackPoint> = None;
+
+    for track_point in track_points {
+        let candidates = road_graph.candidates(track_point);
+        if candidates.is_empty() {
+            // off-network, keep the raw point
+            flush_run(road_graph, &mut run, &mut output);
+            output.push(track_point.clone());
+            prev_point = Some(track_point);
+            continue;
+        }
+
+        let next_states = match (run.last(), prev_point) {
+            (Some(prev_states), Some(prev_point)) => {
+                let great_circle_distance_in_m = distance_in_m(prev_point, track_point);
+                let limit_in_m = (great_circle_distance_in_m * MAX_ROUTE_DISTANCE_FACTOR)
+                    .max(MIN_ROUTE_DISTANCE_LIMIT_IN_M);
+                let shortest_paths: Vec<_> = prev_states
+                    .iter()
+                    .map(|state| road_graph.shortest_paths(&state.candidate, limit_in_m))
+                    .collect();
+                let states: Vec<ViterbiState> = candidates
+                    .iter()
+                    .filter_map(|candidate| {
+                        let mut best: Option<ViterbiState> = None;
+                        for (prev_index, prev_state) in prev_states.iter().enumerate() {
+                            let (route_distance_in_m, nodes) = match road_graph.route(
+                                &prev_state.candidate,
+                                candidate,
+                                &shortest_paths[prev_index],
+                            ) {
+                                None => continue,
+                                Some(route) => route,
+                            };
+                            let log_probability = prev_state.log_probability
+                                - (route_distance_in_m - great_circle_distance_in_m).abs()
+                                    / TRANSITION_BETA_IN_M
+                                + emission_log_probability(candidate);
+                            if best
+                                .as_ref()
+                                .is_none_or(|best| log_probability > best.log_probability)
+                            {
+                                best = Some(ViterbiState {
+                                    candidate: candidate.clone(),
+                                    log_probability,
+                                    prev: Some((prev_index, nodes)),
+                                });
+                            }
+                        }
+                        best
+                    })
+                    .collect();
+                if states.is_empty() {
+                    None
+                } else {
+                    Some(states)
+                }
+            }
+            _ => None,
+        };
+
+        let next_states = match next_states {
+            Some(states) => states,
+            None => {
+                // no way to reach any of the candidates, start a new run
+                flush_run(road_graph, &mut run, &mut output);
+                candidates
+                    .into_iter()
+                    .map(|candidate| ViterbiState {
+                        log_probability: emission_log_probability(&candidate),
+                        candidate,
+                        prev: None,
+                    })
+                    .collect()
+            }
+        };
+        run.push(next_states);
+        prev_point = Some(track_point);
+    }
+    flush_run(road_graph, &mut run, &mut output);
+    output
+}
+
+pub fn match_journey_vector(
+    road_graph: &RoadGraph,
+    journey_vector: JourneyVector,
+) -> JourneyVector {
+    JourneyVector {
+        track_segments: journey_vector
+            .track_segments
+            .into_iter()
+            .map(|track_segment| TrackSegment {
+                // inferred segments are not where the roads are
+                track_points: if track_segment.inferred {
+                    track_segment.track_points
+                } else {
+                    match_track_points(road_graph, &track_segment.track_points)
+                },
+                inferred: track_segment.inferred,
+            })
+            .collect(),
+    }
+}
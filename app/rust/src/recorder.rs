@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use chrono::FixedOffset;
+use log::warn;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use crate::gps_processor::{GpsPreprocessor, Point, ProcessResult, RawData};
+use crate::main_db;
+use crate::storage::Storage;
+
+/* The recorder is the single owner of the recording state: the gps
+preprocessor and writing points of the ongoing journey to storage. It runs on
+its own thread and handles one message at a time, so a location update and a
+finalization are always processed in order and never need to hold a lock on
+each other.
+
+If the thread panics, the message fails and a new thread is spawned. Only the
+gps preprocessor state is lost, the points are already in the recording
+journal.
+*/
+
+pub struct LocationUpdateResult {
+    pub process_result: ProcessResult,
+    /// The last point kept by the gps preprocessor before this update.
+    pub last_kept_point: Option<Point>,
+}
+
+type FinalizeOp = Box<dyn FnOnce(&mut main_db::Txn) -> Result<bool> + Send>;
+
+enum Message {
+    LocationUpdate {
+        raw_data: RawData,
+        received_timestamp_ms: i64,
+        utc_offset: Option<FixedOffset>,
+        reply: mpsc::Sender<LocationUpdateResult>,
+    },
+    Finalize {
+        finalize_op: FinalizeOp,
+        reply: mpsc::Sender<Result<bool>>,
+    },
+}
+
+pub struct Recorder {
+    storage: fn() -> &'static Storage,
+    // (times the thread was respawned, sender to the thread)
+    sender: Mutex<(u64, mpsc::Sender<Message>)>,
+}
+
+fn spawn_recorder_thread(
+    storage: fn() -> &'static Storage,
+) -> std::io::Result<mpsc::Sender<Message>> {
+    let (sender, receiver) = mpsc::channel::<Message>();
+    thread::Builder::new()
+        .name("recorder".to_string())
+        .spawn(move || {
+            let mut gps_preprocessor = GpsPreprocessor::new();
+            for message in receiver {
+                match message {
+                    Message::LocationUpdate {
+                        raw_data,
+                        received_timestamp_ms,
+                        utc_offset,
+                        reply,
+                    } => {
+                        let last_kept_point = gps_preprocessor.last_kept_point();
+                        let process_result = gps_preprocessor.preprocess(&raw_data);
+                        storage().record_gps_data(
+                            &raw_data,
+                            process_result,
+                            received_timestamp_ms,
+                            utc_offset,
+                        );
+                        let _ = reply.send(LocationUpdateResult {
+                            process_result,
+                            last_kept_point,
+                        });
+                    }
+                    Message::Finalize { finalize_op, reply } => {
+                        let finalized = storage().with_db_txn(finalize_op);
+                        // when journey is finalized, we should reset the
+                        // gps_preprocessor to prevent old state affecting
+                        // new journey
+                        if let Ok(true) = finalized {
+                            gps_preprocessor = GpsPreprocessor::new();
+                        }
+                        let _ = reply.send(finalized);
+                    }
+                }
+            }
+        })?;
+    Ok(sender)
+}
+
+impl Recorder {
+    /// `storage` is only called on the recorder thread when handling a
+    /// message, so it can point to a storage initialized after this.
+    pub fn spawn(storage: fn() -> &'static Storage) -> Self {
+        let sender = spawn_recorder_thread(storage).expect("failed to spawn the recorder thread");
+        Recorder {
+            storage,
+            sender: Mutex::new((0, sender)),
+        }
+    }
+
+    fn respawn(&self, sender: &mut (u64, mpsc::Sender<Message>)) -> Result<()> {
+        warn!("The recorder thread is gone, respawning it");
+        *sender = (sender.0 + 1, spawn_recorder_thread(self.storage)?);
+        Ok(())
+    }
+
+    fn request<T>(&self, message: impl FnOnce(mpsc::Sender<T>) -> Message) -> Result<T> {
+        let (reply, result) = mpsc::channel();
+        let generation = {
+            let mut sender = self.sender.lock().unwrap();
+            if let Err(mpsc::SendError(message)) = sender.1.send(message(reply)) {
+                self.respawn(&mut sender)?;
+                sender
+                    .1
+                    .send(message)
+                    .map_err(|_| anyhow!("The recorder thread is gone"))?;
+            }
+            sender.0
+        };
+        result.recv().or_else(|_| {
+            // it panicked, messages sent meanwhile are dropped with it
+            let mut sender = self.sender.lock().unwrap();
+            if sender.0 == generation {
+                self.respawn(&mut sender)?;
+            }
+            Err(anyhow!(
+                "The recorder thread stopped while handling a message"
+            ))
+        })
+    }
+
+    pub fn on_location_update(
+        &self,
+        raw_data: RawData,
+        received_timestamp_ms: i64,
+        utc_offset: Option<FixedOffset>,
+    ) -> Result<LocationUpdateResult> {
+        self.request(|reply| Message::LocationUpdate {
+            raw_data,
+            received_timestamp_ms,
+            utc_offset,
+            reply,
+        })
+    }
+
+    /// Runs `finalize_op` in a db transaction, the gps preprocessor is reset if
+    /// it returns `true`, i.e. the ongoing journey is finalized.
+    pub fn finalize<F>(&self, finalize_op: F) -> Result<bool>
+    where
+        F: FnOnce(&mut main_db::Txn) -> Result<bool> + Send + 'static,
+    {
+        self.request(|reply| Message::Finalize {
+            finalize_op: Box::new(finalize_op),
+            reply,
+        })?
+    }
+}
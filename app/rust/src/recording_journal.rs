@@ -0,0 +1,208 @@
+use anyhow::Result;
+use auto_context::auto_context;
+use chrono::{DateTime, FixedOffset, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::gps_processor::{Point, ProcessResult, RawData};
+
+/* An append-only journal for incoming GPS points of the ongoing journey.
+
+Writing every point to `ongoing_journey` means a sqlite transaction per point,
+so `MainDb` only appends points here and moves them to `ongoing_journey` in
+batches. If the app gets killed in between, the points are replayed from the
+journal the next time the main db is opened.
+
+It is a text file with one entry per line:
+- `P <seq> <timestamp_ms> <lat> <lng> <process_result> <altitude> <speed> <utc_offset_sec>`
+  for a point (fields are tab separated, missing values are empty).
+- `F` when finalizing the ongoing journey begins. The journal is cleared once
+  the finalization is done, so seeing it when opening means it was interrupted.
+
+`seq` keeps increasing across batches, the last one moved to `ongoing_journey`
+is stored in the same db transaction, so points that are already committed
+but not cleared from the journal yet are not added twice.
+
+Every write is synced to disk before returning, otherwise it could still be
+lost with the device (not just the app) going down. Points arrive about once a
+second, so this is cheap compared to a transaction per point.
+*/
+
+const JOURNAL_FILENAME: &str = "ongoing_journey.journal";
+const FINALIZE_MARKER: &str = "F";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalPoint {
+    pub seq: u64,
+    pub raw_data: RawData,
+    pub process_result: ProcessResult,
+    pub utc_offset: Option<FixedOffset>,
+}
+
+/// What was left in the journal when the main db is opened, i.e. what a crash
+/// (or the app being killed) would have lost otherwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecoveryReport {
+    /// Points added back to the ongoing journey.
+    pub recovered_points: u32,
+    /// Timestamp of the last recovered point.
+    pub last_timestamp: Option<DateTime<Utc>>,
+    /// Finalizing the ongoing journey did not finish. The finalization is
+    /// rolled back as a whole, so the ongoing journey is kept as is.
+    pub finalize_interrupted: bool,
+}
+
+pub struct RecordingJournal {
+    file: File,
+    next_seq: u64,
+    finalize_began: bool,
+}
+
+fn parse_optional<T: FromStr>(field: &str) -> Option<Option<T>> {
+    if field.is_empty() {
+        Some(None)
+    } else {
+        field.parse().ok().map(Some)
+    }
+}
+
+fn parse_point(line: &str) -> Option<JournalPoint> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [seq, timestamp_ms, latitude, longitude, process_result, altitude, speed, utc_offset_sec] =
+        fields[..]
+    else {
+        return None;
+    };
+    let process_result = match process_result.parse::<i8>().ok()? {
+        x if x == ProcessResult::Append.to_int() => ProcessResult::Append,
+        x if x == ProcessResult::NewSegment.to_int() => ProcessResult::NewSegment,
+        _ => return None,
+    };
+    Some(JournalPoint {
+        seq: seq.parse().ok()?,
+        raw_data: RawData {
+            point: Point {
+                latitude: latitude.parse().ok()?,
+                longitude: longitude.parse().ok()?,
+            },
+            timestamp_ms: parse_optional(timestamp_ms)?,
+            accuracy: None,
+            altitude: parse_optional(altitude)?,
+            speed: parse_optional(speed)?,
+        },
+        process_result,
+        utc_offset: parse_optional(utc_offset_sec)?.and_then(FixedOffset::east_opt),
+    })
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|x| x.to_string()).unwrap_or_default()
+}
+
+impl RecordingJournal {
+    /// Opens the journal in `dir`, returning the points after `committed_seq`
+    /// and whether a finalization was interrupted.
+    #[auto_context]
+    pub fn open(dir: &str, committed_seq: u64) -> Result<(Self, Vec<JournalPoint>, bool)> {
+        let path = Path::new(dir).join(JOURNAL_FILENAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+
+        let mut points = Vec::new();
+        let mut finalize_interrupted = false;
+        let mut last_seq = committed_seq;
+        // the last line may be partially written, only complete lines count
+        let complete_lines = match content.rfind('\n') {
+            None => "",
+            Some(end) => &content[..end],
+        };
+        for line in complete_lines.lines() {
+            if line == FINALIZE_MARKER {
+                finalize_interrupted = true;
+            } else if let Some(point) = line.strip_prefix("P\t").and_then(parse_point) {
+                last_seq = last_seq.max(point.seq);
+                if point.seq > committed_seq {
+                    points.push(point);
+                }
+            } else {
+                warn!("[recording_journal] Skipping invalid entry: {line:?}");
+            }
+        }
+
+        Ok((
+            RecordingJournal {
+                file,
+                next_seq: last_seq + 1,
+                finalize_began: false,
+            },
+            points,
+            finalize_interrupted,
+        ))
+    }
+
+    #[auto_context]
+    pub fn append(
+        &mut self,
+        raw_data: &RawData,
+        process_result: ProcessResult,
+        utc_offset: Option<FixedOffset>,
+    ) -> Result<JournalPoint> {
+        let point = JournalPoint {
+            seq: self.next_seq,
+            raw_data: raw_data.clone(),
+            process_result,
+            utc_offset,
+        };
+        // written in one go so a crash can only leave a partial last line
+        let line = format!(
+            "P\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            point.seq,
+            format_optional(raw_data.timestamp_ms),
+            raw_data.point.latitude,
+            raw_data.point.longitude,
+            process_result.to_int(),
+            format_optional(raw_data.altitude),
+            format_optional(raw_data.speed),
+            format_optional(utc_offset.map(|x| x.local_minus_utc())),
+        );
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        self.next_seq += 1;
+        Ok(point)
+    }
+
+    #[auto_context]
+    pub fn begin_finalize(&mut self) -> Result<()> {
+        self.file
+            .write_all(format!("{FINALIZE_MARKER}\n").as_bytes())?;
+        self.file.sync_data()?;
+        self.finalize_began = true;
+        Ok(())
+    }
+
+    /// Called after the transaction that may have finalized the ongoing
+    /// journey is either committed or rolled back.
+    #[auto_context]
+    pub fn end_finalize(&mut self) -> Result<()> {
+        if self.finalize_began {
+            self.clear()?;
+            self.finalize_began = false;
+        }
+        Ok(())
+    }
+
+    /// Everything in the journal is committed to the main db.
+    #[auto_context]
+    pub fn clear(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use super::raster_tile::{RasterTileFormat, RasterTileStyle};
+use super::route_line::RouteLineFormat;
 use super::MapRenderer;
 
 use rand::Rng;
@@ -12,6 +14,10 @@ struct TileRangeQuery {
     height: i64,
     buffer_size_power: i16,
     cached_version: Option<String>,
+    /// Only send the tiles changed since `cached_version` when possible.
+    delta: bool,
+    /// Split the tiles by layer, if the renderer has layers.
+    layers: bool,
 }
 
 struct TileRangeResponse {
@@ -24,17 +30,88 @@ fn handle_tile_range_query(
     query: &TileRangeQuery,
     map_renderer: &mut MapRenderer,
 ) -> Result<TileRangeResponse, String> {
-    let (_, version) =
-        match map_renderer.get_latest_bitmap_if_changed(query.cached_version.as_deref()) {
-            None => {
+    let layered = query.layers && map_renderer.has_layers();
+    let cached_version = query
+        .cached_version
+        .as_deref()
+        .and_then(|v| map_renderer.version_of_tile_range_version(v));
+    let version = map_renderer.get_tile_range_version(
+        query.x,
+        query.y,
+        query.z,
+        query.width,
+        query.height,
+        query.buffer_size_power,
+        layered,
+    );
+    if query.cached_version.as_ref() == Some(&version) {
+        return Ok(TileRangeResponse {
+            status: 304,
+            headers: HashMap::new(),
+            body: Vec::new(),
+        });
+    }
+
+    if layered {
+        let since_version = cached_version.filter(|_| query.delta);
+        let get_layers = |map_renderer: &mut MapRenderer, since_version| {
+            map_renderer
+                .get_tile_range_layers(
+                    query.x,
+                    query.y,
+                    query.z,
+                    query.width,
+                    query.height,
+                    query.buffer_size_power,
+                    since_version,
+                )
+                .map_err(|e| format!("Failed to generate tile buffer: {e}"))
+        };
+        let mut headers = HashMap::from([
+            ("version".to_string(), version),
+            ("layers".to_string(), "true".to_string()),
+        ]);
+        let body = match get_layers(map_renderer, since_version)? {
+            Some(body) => {
+                if since_version.is_some() {
+                    headers.insert("delta".to_string(), "true".to_string());
+                }
+                body
+            }
+            // the changes are unknown, fall back to a full response
+            None => get_layers(map_renderer, None)?
+                .ok_or_else(|| "Failed to generate tile buffer".to_string())?,
+        };
+        return Ok(TileRangeResponse {
+            status: 200,
+            headers,
+            body,
+        });
+    }
+    if let (true, Some(cached_version)) = (query.delta, cached_version) {
+        match map_renderer.get_tile_range_delta(
+            query.x,
+            query.y,
+            query.z,
+            query.width,
+            query.height,
+            query.buffer_size_power,
+            cached_version,
+        ) {
+            Ok(None) => (),
+            Ok(Some(buffer)) => {
                 return Ok(TileRangeResponse {
-                    status: 304,
-                    headers: HashMap::new(),
-                    body: Vec::new(),
+                    status: 200,
+                    headers: HashMap::from([
+                        ("version".to_string(), version),
+                        ("delta".to_string(), "true".to_string()),
+                    ]),
+                    body: buffer,
                 });
             }
-            Some((journey_bitmap, version)) => (journey_bitmap, version),
-        };
+            Err(e) => return Err(format!("Failed to generate tile buffer delta: {e}")),
+        }
+    }
 
     let tile_range_response = match map_renderer.get_tile_range_response(
         query.x,
@@ -85,6 +162,16 @@ pub struct WebviewResponse {
 /// parses query params, and returns a fully-formed response.
 /// Always returns status 200 or 500 -- "not modified" is signaled via
 /// X-Not-Modified header (Android WebResourceResponse rejects 3xx codes).
+/// The X-Tile-Version of `tile_range` is derived from the covered content, so a
+/// `cached_version` stays valid across app restarts.
+/// For `tile_range` with `mode=delta`, a body with only the tiles changed since
+/// `cached_version` is signaled via X-Tile-Delta header.
+/// With `layers=1`, the tiles are split by layer (if the renderer has layers),
+/// signaled via X-Tile-Layers header.
+/// `tile/{z}/{x}/{y}.png` (or `.webp`) serves plain XYZ raster tiles, and
+/// `mvt/{z}/{x}/{y}` (optionally with `.mvt` or `.pbf`) serves vector tiles.
+/// `route.geojson` (or `.bin`) serves the polyline of a single vector journey,
+/// simplified for zoom `z`.
 pub fn dispatch_request(
     path: &str,
     query_params: &HashMap<String, String>,
@@ -93,6 +180,9 @@ pub fn dispatch_request(
     match path {
         "tile_range" => dispatch_tile_range(query_params, map_renderer),
         "random_data" => dispatch_random_data(query_params),
+        _ if path.starts_with("tile/") => dispatch_raster_tile(path, query_params, map_renderer),
+        _ if path.starts_with("mvt/") => dispatch_vector_tile(path, query_params, map_renderer),
+        _ if path.starts_with("route.") => dispatch_route_line(path, query_params, map_renderer),
         _ => WebviewResponse {
             status: 500,
             content_type: "text/plain".to_string(),
@@ -120,7 +210,11 @@ fn dispatch_tile_range(
         width: parse_or(params, "width", 1),
         height: parse_or(params, "height", 1),
         buffer_size_power: parse_or(params, "buffer_size_power", 8),
-        cached_version: params.get("cached_version").cloned(),
+        cached_version: params
+            .get("cached_version")
+            .map(|v| v.trim_matches('"').to_string()),
+        delta: params.get("mode").map(String::as_str) == Some("delta"),
+        layers: params.get("layers").map(String::as_str) == Some("1"),
     };
 
     match handle_tile_range_query(&query, map_renderer) {
@@ -136,6 +230,12 @@ fn dispatch_tile_range(
                 if let Some(version) = resp.headers.get("version") {
                     headers.insert("X-Tile-Version".to_string(), version.clone());
                 }
+                if let Some(delta) = resp.headers.get("delta") {
+                    headers.insert("X-Tile-Delta".to_string(), delta.clone());
+                }
+                if let Some(layers) = resp.headers.get("layers") {
+                    headers.insert("X-Tile-Layers".to_string(), layers.clone());
+                }
                 WebviewResponse {
                     status: 200,
                     content_type: "application/octet-stream".to_string(),
@@ -159,6 +259,140 @@ fn dispatch_tile_range(
     }
 }
 
+/// Parses `{prefix}/{z}/{x}/{y}` with an optional `.{extension}`.
+fn parse_xyz_path<'a>(path: &'a str, prefix: &str) -> Option<(i16, i64, i64, Option<&'a str>)> {
+    let rest = path.strip_prefix(prefix)?.strip_prefix('/')?;
+    let mut parts = rest.split('/');
+    let (z, x, y_and_extension) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let (y, extension) = match y_and_extension.split_once('.') {
+        Some((y, extension)) => (y, Some(extension)),
+        None => (y_and_extension, None),
+    };
+    Some((z.parse().ok()?, x.parse().ok()?, y.parse().ok()?, extension))
+}
+
+fn parse_raster_tile_style(params: &HashMap<String, String>) -> Result<RasterTileStyle, String> {
+    let mut style = RasterTileStyle::default();
+    if let Some(color) = params.get("fog_color") {
+        let hex = color.trim_start_matches('#');
+        let rgb = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)
+            .ok_or_else(|| format!("Invalid fog_color: {color} (expected RRGGBB)"))?;
+        let [_, r, g, b] = rgb.to_be_bytes();
+        style.fog_color = [r, g, b];
+    }
+    if let Some(alpha) = params.get("fog_alpha") {
+        style.fog_alpha = alpha
+            .parse()
+            .map_err(|_| format!("Invalid fog_alpha: {alpha} (must be 0-255)"))?;
+    }
+    if let Some(edge_softening) = params.get("edge_softening") {
+        style.edge_softening = edge_softening
+            .parse()
+            .map_err(|_| format!("Invalid edge_softening: {edge_softening}"))?;
+    }
+    Ok(style)
+}
+
+fn dispatch_raster_tile(
+    path: &str,
+    params: &HashMap<String, String>,
+    map_renderer: &mut MapRenderer,
+) -> WebviewResponse {
+    let result = parse_xyz_path(path, "tile")
+        .ok_or_else(|| format!("Invalid tile path: {path}"))
+        .and_then(|(z, x, y, extension)| {
+            let format = extension
+                .and_then(RasterTileFormat::from_extension)
+                .ok_or_else(|| format!("Unsupported tile format: {path}"))?;
+            let style = parse_raster_tile_style(params)?;
+            let buffer_size_power = parse_or(params, "buffer_size_power", 8);
+            let body = map_renderer.get_raster_tile(x, y, z, buffer_size_power, &style, format)?;
+            Ok((format, body))
+        });
+    match result {
+        Ok((format, body)) => WebviewResponse {
+            status: 200,
+            content_type: format.content_type().to_string(),
+            body,
+            headers: HashMap::from([(
+                "X-Tile-Version".to_string(),
+                map_renderer.get_version_string(),
+            )]),
+        },
+        Err(e) => WebviewResponse {
+            status: 500,
+            content_type: "text/plain".to_string(),
+            body: e.into_bytes(),
+            headers: HashMap::new(),
+        },
+    }
+}
+
+fn dispatch_vector_tile(
+    path: &str,
+    params: &HashMap<String, String>,
+    map_renderer: &mut MapRenderer,
+) -> WebviewResponse {
+    let result = parse_xyz_path(path, "mvt")
+        .filter(|(_, _, _, extension)| matches!(extension, None | Some("mvt") | Some("pbf")))
+        .ok_or_else(|| format!("Invalid vector tile path: {path}"))
+        .and_then(|(z, x, y, _)| {
+            let buffer_size_power = parse_or(params, "buffer_size_power", 8);
+            map_renderer.get_vector_tile(x, y, z, buffer_size_power)
+        });
+    match result {
+        Ok(body) => WebviewResponse {
+            status: 200,
+            content_type: "application/vnd.mapbox-vector-tile".to_string(),
+            body,
+            headers: HashMap::from([(
+                "X-Tile-Version".to_string(),
+                map_renderer.get_version_string(),
+            )]),
+        },
+        Err(e) => WebviewResponse {
+            status: 500,
+            content_type: "text/plain".to_string(),
+            body: e.into_bytes(),
+            headers: HashMap::new(),
+        },
+    }
+}
+
+fn dispatch_route_line(
+    path: &str,
+    params: &HashMap<String, String>,
+    map_renderer: &mut MapRenderer,
+) -> WebviewResponse {
+    let result = path
+        .strip_prefix("route.")
+        .and_then(RouteLineFormat::from_extension)
+        .ok_or_else(|| format!("Unsupported route format: {path}"))
+        .and_then(|format| {
+            let body = map_renderer.get_route_line(parse_or(params, "z", 16), format)?;
+            Ok((format, body))
+        });
+    match result {
+        Ok((format, body)) => WebviewResponse {
+            status: 200,
+            content_type: format.content_type().to_string(),
+            body,
+            headers: HashMap::new(),
+        },
+        Err(e) => WebviewResponse {
+            status: 500,
+            content_type: "text/plain".to_string(),
+            body: e.into_bytes(),
+            headers: HashMap::new(),
+        },
+    }
+}
+
 fn dispatch_random_data(params: &HashMap<String, String>) -> WebviewResponse {
     let size: u64 = parse_or(params, "size", 1_048_576);
     match generate_random_data(size) {
@@ -208,7 +442,7 @@ mod tests {
     fn test_dispatch_tile_range_not_modified() {
         let jb = JourneyBitmap::new();
         let mut mr = MapRenderer::new(jb);
-        let version = mr.get_version_string();
+        let version = mr.get_tile_range_version(0, 0, 0, 1, 1, 6, false);
 
         let params: HashMap<String, String> = [
             ("x", "0"),
@@ -232,6 +466,350 @@ mod tests {
         );
     }
 
+    fn tile_range_params(extra: &[(&str, &str)]) -> HashMap<String, String> {
+        [
+            ("x", "0"),
+            ("y", "0"),
+            ("z", "0"),
+            ("width", "1"),
+            ("height", "1"),
+            ("buffer_size_power", "6"),
+        ]
+        .iter()
+        .chain(extra)
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    fn add_line(mr: &mut MapRenderer, start_lng: f64, end_lng: f64) {
+        mr.update(|journey_bitmap, tile_changed| {
+            journey_bitmap.add_line_with_change_callback(
+                start_lng,
+                30.0,
+                end_lng,
+                30.0,
+                tile_changed,
+            );
+        });
+    }
+
+    #[test]
+    fn test_dispatch_tile_range_delta() {
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        add_line(&mut mr, 120.0, 120.1);
+        let version = mr.get_tile_range_version(0, 0, 1, 2, 2, 6, false);
+        add_line(&mut mr, 120.1, 120.2);
+
+        let params = tile_range_params(&[
+            ("x", "0"),
+            ("y", "0"),
+            ("z", "1"),
+            ("width", "2"),
+            ("height", "2"),
+            ("cached_version", &version),
+            ("mode", "delta"),
+        ]);
+        let resp = dispatch_request("tile_range", &params, &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.headers.get("X-Tile-Delta"), Some(&"true".to_string()));
+        assert_eq!(
+            resp.headers.get("X-Tile-Version"),
+            Some(&mr.get_tile_range_version(0, 0, 1, 2, 2, 6, false))
+        );
+        let (header, tiles) =
+            journey_kernel::tile_range::decode_tile_range_delta(&resp.body).unwrap();
+        assert_eq!((header.range_w, header.range_h), (2, 2));
+        // only the north-east quarter of the world is changed
+        assert_eq!(tiles.len(), 1);
+        let (x, y, bitmap) = &tiles[0];
+        assert_eq!((*x, *y), (1, 0));
+        assert!(bitmap.as_ref().is_some_and(|bitmap| !bitmap.is_empty()));
+    }
+
+    #[test]
+    fn test_tile_range_version_is_stable() {
+        let mut journey_bitmap = JourneyBitmap::new();
+        journey_bitmap.add_line(120.0, 30.0, 120.1, 30.0);
+        // the eastern half of the world
+        let range = [("z", "1"), ("x", "1"), ("height", "2")];
+        let resp = dispatch_request(
+            "tile_range",
+            &tile_range_params(&range),
+            &mut MapRenderer::new(journey_bitmap.clone()),
+        );
+        let version = resp.headers["X-Tile-Version"].clone();
+        let params = tile_range_params(&[&range[..], &[("cached_version", &version)]].concat());
+
+        // e.g. after an app restart
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        mr.replace(journey_bitmap);
+        let not_modified = |mr: &mut MapRenderer| {
+            dispatch_request("tile_range", &params, mr)
+                .headers
+                .contains_key("X-Not-Modified")
+        };
+        assert!(not_modified(&mut mr));
+
+        // changes outside of the range do not matter
+        add_line(&mut mr, -120.0, -119.9);
+        assert!(not_modified(&mut mr));
+        add_line(&mut mr, 120.1, 120.2);
+        assert!(!not_modified(&mut mr));
+    }
+
+    #[test]
+    fn test_dispatch_tile_range_delta_falls_back_to_full() {
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        add_line(&mut mr, 120.0, 120.1);
+        let version = mr.get_tile_range_version(0, 0, 0, 1, 1, 6, false);
+        mr.replace(JourneyBitmap::new());
+
+        for params in [
+            // the changes since `cached_version` are unknown
+            tile_range_params(&[("cached_version", &version), ("mode", "delta")]),
+            // no delta requested
+            tile_range_params(&[("cached_version", &version)]),
+        ] {
+            let resp = dispatch_request("tile_range", &params, &mut mr);
+            assert_eq!(resp.status, 200);
+            assert!(!resp.headers.contains_key("X-Tile-Delta"));
+            assert!(journey_kernel::tile_range::decode_tile_range_response(&resp.body).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_dispatch_tile_range_layers() {
+        use crate::renderer::map_renderer::MapLayer;
+        use journey_kernel::tile_range::{
+            decode_tile_range_layers, TILE_RANGE_LAYER_CURRENT_JOURNEY, TILE_RANGE_LAYER_DEFAULT,
+        };
+
+        let mut default_bitmap = JourneyBitmap::new();
+        // west of the antimeridian, in view tile (0, 0) at z1
+        default_bitmap.add_line(-120.0, 30.0, -119.9, 30.0);
+        let mut mr = MapRenderer::new_with_layers(vec![
+            MapLayer {
+                id: TILE_RANGE_LAYER_CURRENT_JOURNEY,
+                name: "current_journey".to_string(),
+                journey_bitmap: JourneyBitmap::new(),
+            },
+            MapLayer {
+                id: TILE_RANGE_LAYER_DEFAULT,
+                name: "default".to_string(),
+                journey_bitmap: default_bitmap,
+            },
+        ]);
+        let version = mr.get_tile_range_version(0, 0, 1, 2, 2, 6, true);
+        mr.update_with_layer(
+            TILE_RANGE_LAYER_CURRENT_JOURNEY,
+            |journey_bitmap, tile_changed| {
+                journey_bitmap.add_line_with_change_callback(
+                    120.0,
+                    30.0,
+                    120.1,
+                    30.0,
+                    tile_changed,
+                );
+            },
+        );
+        let range = [("z", "1"), ("width", "2"), ("height", "2"), ("layers", "1")];
+
+        let resp = dispatch_request("tile_range", &tile_range_params(&range), &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.headers.get("X-Tile-Layers"), Some(&"true".to_string()));
+        let (header, layers) = decode_tile_range_layers(&resp.body).unwrap();
+        assert!(!header.is_delta());
+        let layers: Vec<_> = layers
+            .iter()
+            .map(|layer| {
+                let tiles: Vec<_> = layer.tiles.iter().map(|(x, y, _)| (*x, *y)).collect();
+                (layer.id, layer.name.as_str(), tiles)
+            })
+            .collect();
+        assert_eq!(
+            layers,
+            vec![
+                (
+                    TILE_RANGE_LAYER_CURRENT_JOURNEY,
+                    "current_journey",
+                    vec![(1, 0)]
+                ),
+                (TILE_RANGE_LAYER_DEFAULT, "default", vec![(0, 0)]),
+            ]
+        );
+
+        let delta_range = [
+            &range[..],
+            &[("cached_version", &version), ("mode", "delta")],
+        ]
+        .concat();
+        let resp = dispatch_request("tile_range", &tile_range_params(&delta_range), &mut mr);
+        assert_eq!(resp.headers.get("X-Tile-Delta"), Some(&"true".to_string()));
+        let (header, layers) = decode_tile_range_layers(&resp.body).unwrap();
+        assert!(header.is_delta());
+        // every layer has the changed view tile, the default one is empty there
+        assert!(layers.iter().all(|layer| layer.tiles.len() == 1));
+        assert!(layers[0].tiles[0].2.is_some());
+        assert!(layers[1].tiles[0].2.is_none());
+
+        // without `layers=1` the response is the same as before
+        let resp = dispatch_request("tile_range", &tile_range_params(&range[..3]), &mut mr);
+        assert!(!resp.headers.contains_key("X-Tile-Layers"));
+        let tiles = journey_kernel::tile_range::decode_tile_range_response(&resp.body).unwrap();
+        assert_eq!(tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_dispatch_route_line() {
+        use crate::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        let params = HashMap::from([("z".to_string(), "12".to_string())]);
+        assert_eq!(
+            dispatch_request("route.geojson", &params, &mut mr).status,
+            500
+        );
+
+        mr.set_route(JourneyVector {
+            track_segments: vec![TrackSegment {
+                track_points: vec![
+                    TrackPoint {
+                        latitude: 30.0,
+                        longitude: 120.0,
+                    },
+                    TrackPoint {
+                        latitude: 30.0,
+                        longitude: 120.1,
+                    },
+                ],
+                inferred: false,
+            }],
+        });
+        let resp = dispatch_request("route.geojson", &params, &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.content_type, "application/geo+json");
+        let geojson: serde_json::Value = serde_json::from_slice(&resp.body).unwrap();
+        assert_eq!(
+            geojson["features"][0]["geometry"]["coordinates"],
+            serde_json::json!([[120.0, 30.0], [120.1, 30.0]])
+        );
+        assert_eq!(
+            geojson["features"][0]["properties"]["progress"],
+            serde_json::json!([0.0, 1.0])
+        );
+
+        let resp = dispatch_request("route.bin", &params, &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.content_type, "application/octet-stream");
+        assert_eq!(dispatch_request("route.gpx", &params, &mut mr).status, 500);
+    }
+
+    #[test]
+    fn test_dispatch_raster_tile() {
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        add_line(&mut mr, 120.0, 120.1);
+
+        let params: HashMap<String, String> = [("fog_color", "#102030"), ("fog_alpha", "200")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let resp = dispatch_request("tile/1/1/0.png", &params, &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.content_type, "image/png");
+        let image = image::load_from_memory(&resp.body).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (256, 256));
+        assert_eq!(image.get_pixel(0, 0).0, [0x10, 0x20, 0x30, 200]);
+        assert!(image.pixels().any(|pixel| pixel.0[3] == 0));
+
+        let resp = dispatch_request("tile/1/0/0.webp", &HashMap::new(), &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.content_type, "image/webp");
+        let image = image::load_from_memory(&resp.body).unwrap().to_rgba8();
+        assert!(image.pixels().all(|pixel| pixel.0 == [0, 0, 0, 127]));
+    }
+
+    #[test]
+    fn test_dispatch_raster_tile_edge_softening() {
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        add_line(&mut mr, 100.0, 150.0);
+        let count_alpha_levels = |mr: &mut MapRenderer, edge_softening: &str| {
+            let params =
+                HashMap::from([("edge_softening".to_string(), edge_softening.to_string())]);
+            let resp = dispatch_request("tile/1/1/0.png", &params, mr);
+            let image = image::load_from_memory(&resp.body).unwrap().to_rgba8();
+            image
+                .pixels()
+                .map(|pixel| pixel.0[3])
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+        assert_eq!(count_alpha_levels(&mut mr, "0"), 2);
+        assert!(count_alpha_levels(&mut mr, "4") > 2);
+    }
+
+    #[test]
+    fn test_dispatch_raster_tile_invalid() {
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        for (path, params) in [
+            ("tile/1/2/0.png", vec![]),
+            ("tile/1/0/0.jpg", vec![]),
+            ("tile/1/0.png", vec![]),
+            ("tile/1/0/0.png", vec![("fog_color", "red")]),
+            ("tile/1/0/0.png", vec![("edge_softening", "100")]),
+        ] {
+            let params = params
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let resp = dispatch_request(path, &params, &mut mr);
+            assert_eq!(resp.status, 500, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_dispatch_vector_tile() {
+        use crate::protos::vector_tile::{tile::GeomType, Tile};
+        use protobuf::Message;
+
+        let mut mr = MapRenderer::new(JourneyBitmap::new());
+        add_line(&mut mr, 100.0, 150.0);
+
+        let resp = dispatch_request("mvt/1/1/0.pbf", &HashMap::new(), &mut mr);
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.content_type, "application/vnd.mapbox-vector-tile");
+        let tile = Tile::parse_from_bytes(&resp.body).unwrap();
+        assert_eq!(tile.layers.len(), 1);
+        let layer = &tile.layers[0];
+        assert_eq!(layer.name(), "explored");
+        assert_eq!(layer.extent(), 4096);
+        assert_eq!(layer.features.len(), 1);
+        assert_eq!(layer.features[0].type_(), GeomType::POLYGON);
+        // starts with a MoveTo
+        assert_eq!(layer.features[0].geometry[0], 9);
+
+        // served from the cache until the next update
+        assert_eq!(
+            dispatch_request("mvt/1/1/0", &HashMap::new(), &mut mr).body,
+            resp.body
+        );
+        mr.update(|journey_bitmap, tile_changed| {
+            journey_bitmap.add_line_with_change_callback(100.0, 20.0, 150.0, 20.0, tile_changed);
+        });
+        assert_ne!(
+            dispatch_request("mvt/1/1/0", &HashMap::new(), &mut mr).body,
+            resp.body
+        );
+
+        let resp = dispatch_request("mvt/1/0/0", &HashMap::new(), &mut mr);
+        let tile = Tile::parse_from_bytes(&resp.body).unwrap();
+        assert!(tile.layers[0].features.is_empty());
+
+        for path in ["mvt/1/2/0", "mvt/1/0/0.png", "mvt/1/0"] {
+            let resp = dispatch_request(path, &HashMap::new(), &mut mr);
+            assert_eq!(resp.status, 500, "{path}");
+        }
+    }
+
     #[test]
     fn test_dispatch_random_data() {
         let jb = JourneyBitmap::new();
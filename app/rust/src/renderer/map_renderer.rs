@@ -1,14 +1,83 @@
 use flutter_rust_bridge::frb;
+use image::RgbaImage;
+use journey_kernel::encode_tile_range_delta_from_tiles;
+use journey_kernel::encode_tile_range_layers_from_tiles;
 use journey_kernel::encode_tile_range_response_from_tiles;
+use journey_kernel::tile_range::mark_tile_range_loading;
+use journey_kernel::TileLayerPixelData;
 use journey_kernel::TilePixelData;
 use journey_kernel::FTA_COMPRESSION_ZSTD;
 
 use crate::journey_area_utils;
-use crate::journey_bitmap::{JourneyBitmap, TileKey};
+use crate::journey_bitmap::{
+    BlockOverview, JourneyBitmap, Tile, TileKey, MAP_WIDTH, MAP_WIDTH_OFFSET,
+};
+use crate::journey_vector::JourneyVector;
+use crate::renderer::raster_tile::{self, RasterTileFormat, RasterTileStyle};
+use crate::renderer::route_line::{self, RouteLineFormat};
 use crate::renderer::tile_shader2::TileShader2;
+use crate::renderer::vector_tile;
 use crate::utils;
 use crate::utils::MapBounds;
-use std::collections::HashMap;
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/* how many versions of changed tiles are kept for delta responses, clients
+with an older version get a full response instead */
+const MAX_CHANGELOG_VERSIONS: usize = 256;
+
+const MAX_CACHED_VECTOR_TILES: usize = 1024;
+
+/* bump when the tile-range response changes for the same content, so
+versions from older builds are not mistaken for current ones */
+const TILE_RANGE_VERSION_SALT: &[u8] = b"tile_range/1";
+const MAX_TILE_RANGE_VERSIONS: usize = 1024;
+
+/// Part of the rendered bitmap that can be styled on its own (e.g. a journey
+/// kind), see `journey_kernel::tile_range::TILE_RANGE_FLAG_LAYERED`.
+#[frb(ignore)]
+pub struct MapLayer {
+    /// See `journey_kernel::tile_range::TILE_RANGE_LAYER_*`.
+    pub id: u8,
+    pub name: String,
+    pub journey_bitmap: JourneyBitmap,
+}
+
+/// Full layers to be moved into a renderer that shows a placeholder for them
+/// in the meantime (see `MapRenderer::set_overviews`), see
+/// `MapRenderer::load_tiles`.
+#[frb(ignore)]
+pub struct PendingLayers {
+    layers: Vec<MapLayer>,
+    /* the tiles not moved yet, collected by the first `load_tiles`, the
+    closest to `sorted_for` last */
+    tiles: Option<Vec<TileKey>>,
+    /* the view `tiles` is sorted for */
+    sorted_for: Option<(i64, i64, i16, i64, i64)>,
+    /* only the union is kept once all tiles are moved */
+    merged: bool,
+}
+
+impl PendingLayers {
+    pub fn new(layers: Vec<MapLayer>) -> Self {
+        Self {
+            layers,
+            tiles: None,
+            sorted_for: None,
+            merged: false,
+        }
+    }
+
+    /// Same as `new`, but the renderer drops its layers once all tiles are
+    /// moved. They are only needed meanwhile, so the placeholder of a layer
+    /// is replaced without losing the others (e.g. the ongoing journey).
+    pub fn new_merged(layers: Vec<MapLayer>) -> Self {
+        Self {
+            merged: true,
+            ..Self::new(layers)
+        }
+    }
+}
 
 #[frb(ignore)]
 pub struct MapRenderer {
@@ -17,6 +86,28 @@ pub struct MapRenderer {
     tile_area_cache: HashMap<TileKey, i64>,
     version: u64,
     current_area: Option<u64>,
+    /* the tiles changed by each version, oldest first */
+    changelog: VecDeque<(u64, Vec<TileKey>)>,
+    /* encoded vector tiles keyed by (z, x, y, buffer_size_power), only valid
+    for the version they are rendered from */
+    vector_tile_cache: (u64, HashMap<(i16, i64, i64, i16), Vec<u8>>),
+    /* `journey_bitmap` is the union of these, if the renderer has layers */
+    layers: Vec<MapLayer>,
+    /* shown for the tiles not loaded yet, keyed by layer id (`None` for
+    `journey_bitmap`), see `set_overviews` */
+    overviews: HashMap<Option<u8>, BlockOverview>,
+    /* the polyline of a single vector journey, for `get_route_line` */
+    route: Option<JourneyVector>,
+    /* content hash of each tile, keyed by layer id (`None` for
+    `journey_bitmap`) */
+    tile_hash_cache: HashMap<(Option<u8>, TileKey), u64>,
+    /* the `version` each tile-range version was last seen at, so deltas can
+    be computed from it */
+    tile_range_versions: HashMap<String, u64>,
+    /* the content is a placeholder, see `set_loading` */
+    loading: bool,
+    /* (x, y, z, width, height) of the last tile range asked for */
+    last_view: Option<(i64, i64, i16, i64, i64)>,
 }
 
 impl MapRenderer {
@@ -26,9 +117,30 @@ impl MapRenderer {
             tile_area_cache: HashMap::new(),
             version: 0,
             current_area: None,
+            changelog: VecDeque::new(),
+            vector_tile_cache: (0, HashMap::new()),
+            layers: Vec::new(),
+            overviews: HashMap::new(),
+            route: None,
+            tile_hash_cache: HashMap::new(),
+            tile_range_versions: HashMap::new(),
+            loading: false,
+            last_view: None,
         }
     }
 
+    /// Keeps the journey `journey_bitmap` is rendered from, so its actual
+    /// polyline can be served too.
+    pub fn set_route(&mut self, journey_vector: JourneyVector) {
+        self.route = Some(journey_vector);
+    }
+
+    pub fn new_with_layers(layers: Vec<MapLayer>) -> Self {
+        let mut map_renderer = Self::new(JourneyBitmap::new());
+        map_renderer.replace_with_layers(layers);
+        map_renderer
+    }
+
     pub fn update<F>(&mut self, mut f: F)
     where
         F: FnMut(&mut JourneyBitmap, &mut dyn FnMut(TileKey)),
@@ -42,20 +154,199 @@ impl MapRenderer {
         // Apply the update function
         f(&mut self.journey_bitmap, &mut tile_changed);
 
-        for tile_pos in changed_tiles {
-            self.tile_area_cache.remove(&tile_pos);
+        self.record_changes(changed_tiles);
+    }
+
+    fn record_changes(&mut self, changed_tiles: Vec<TileKey>) {
+        for tile_pos in &changed_tiles {
+            self.tile_area_cache.remove(tile_pos);
+            self.tile_hash_cache.remove(&(None, *tile_pos));
+            for layer in &self.layers {
+                self.tile_hash_cache.remove(&(Some(layer.id), *tile_pos));
+            }
         }
 
-        // TODO: we should improve the cache invalidation rule
         self.reset();
+        if self.changelog.len() == MAX_CHANGELOG_VERSIONS {
+            self.changelog.pop_front();
+        }
+        self.changelog.push_back((self.version, changed_tiles));
+    }
+
+    /// Same as `update`, but `f` is also applied to the layer `layer_id` (if
+    /// the renderer has it), so both stay in sync.
+    pub fn update_with_layer<F>(&mut self, layer_id: u8, mut f: F)
+    where
+        F: FnMut(&mut JourneyBitmap, &mut dyn FnMut(TileKey)),
+    {
+        let mut changed_tiles = Vec::new();
+        let mut tile_changed = |tile_pos: TileKey| {
+            changed_tiles.push(tile_pos);
+        };
+        f(&mut self.journey_bitmap, &mut tile_changed);
+        if let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == layer_id) {
+            f(&mut layer.journey_bitmap, &mut tile_changed);
+        }
+        self.record_changes(changed_tiles);
     }
 
     pub fn replace(&mut self, journey_bitmap: JourneyBitmap) {
         self.journey_bitmap = journey_bitmap;
+        self.layers.clear();
+        self.overviews.clear();
         self.tile_area_cache.clear();
+        self.tile_hash_cache.clear();
+        // everything may have changed, there is no delta from older versions
+        self.changelog.clear();
+        self.loading = false;
         self.reset();
     }
 
+    /// Replaces the bitmap with the union of `layers`, which are kept so
+    /// tile-range responses can be split by layer.
+    pub fn replace_with_layers(&mut self, layers: Vec<MapLayer>) {
+        let mut journey_bitmap = JourneyBitmap::new();
+        for layer in &layers {
+            journey_bitmap.merge(layer.journey_bitmap.clone());
+        }
+        self.replace(journey_bitmap);
+        self.layers = layers;
+    }
+
+    /// Shows the overview of each layer (by id) in the tiles `load_tiles` has
+    /// not moved yet, as if their non-empty blocks were completely visited.
+    /// The renderer must have these layers.
+    pub fn set_overviews(&mut self, overviews: Vec<(u8, BlockOverview)>) {
+        let mut union = BlockOverview::new();
+        self.overviews.clear();
+        for (layer_id, overview) in overviews {
+            union.merge(&overview);
+            self.overviews.insert(Some(layer_id), overview);
+        }
+        self.overviews.insert(None, union);
+        self.tile_area_cache.clear();
+        self.tile_hash_cache.clear();
+        self.changelog.clear();
+        self.reset();
+    }
+
+    pub fn has_layers(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Marks the content as a placeholder while the real one is loaded, which
+    /// tile-range responses report to clients (see
+    /// `journey_kernel::tile_range::TILE_RANGE_FLAG_LOADING`). Cleared by
+    /// `replace` and once `load_tiles` is done.
+    pub fn set_loading(&mut self, loading: bool) {
+        self.loading = loading;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Moves up to `max_tiles` journey bitmap tiles of `pending` into the
+    /// layers with the same id (adding missing layers), the ones closest to
+    /// the last requested tile range first. A tile `pending` does not have is
+    /// removed from these layers, and the overviews no longer cover moved
+    /// tiles. Returns whether all tiles are moved, the renderer is no longer
+    /// loading then.
+    pub fn load_tiles(&mut self, pending: &mut PendingLayers, max_tiles: usize) -> bool {
+        for pending_layer in &pending.layers {
+            if !self.layers.iter().any(|layer| layer.id == pending_layer.id) {
+                self.layers.push(MapLayer {
+                    id: pending_layer.id,
+                    name: pending_layer.name.clone(),
+                    journey_bitmap: JourneyBitmap::new(),
+                });
+            }
+        }
+        let tiles = pending.tiles.get_or_insert_with(|| {
+            let tiles: HashSet<TileKey> = self
+                .layers
+                .iter()
+                .filter(|layer| pending.layers.iter().any(|p| p.id == layer.id))
+                .chain(&pending.layers)
+                .flat_map(|layer| layer.journey_bitmap.all_tile_keys().cloned())
+                .chain(
+                    self.overviews
+                        .values()
+                        .flat_map(|overview| overview.all_tile_keys().cloned()),
+                )
+                .collect();
+            tiles.into_iter().collect()
+        });
+        // sorted again only once the view moves
+        if let Some(view) = self.last_view {
+            if pending.sorted_for != Some(view) {
+                tiles.sort_by(|a, b| view_distance(b, view).total_cmp(&view_distance(a, view)));
+                pending.sorted_for = Some(view);
+            }
+        }
+
+        let batch = tiles.split_off(tiles.len().saturating_sub(max_tiles));
+        for key in &batch {
+            for overview in self.overviews.values_mut() {
+                overview.remove_tile(key);
+            }
+            for pending_layer in &mut pending.layers {
+                let tile = pending_layer.journey_bitmap.remove_tile(key);
+                let Some(layer) = self
+                    .layers
+                    .iter_mut()
+                    .find(|layer| layer.id == pending_layer.id)
+                else {
+                    continue;
+                };
+                match tile {
+                    Some(tile) => layer.journey_bitmap.insert_tile(key, tile),
+                    None => {
+                        layer.journey_bitmap.remove_tile(key);
+                    }
+                }
+            }
+            // `journey_bitmap` stays the union of the layers
+            let mut merged: Option<Tile> = None;
+            for layer in &mut self.layers {
+                if let Some(tile) = layer.journey_bitmap.get_tile(key) {
+                    match &mut merged {
+                        None => merged = Some(tile.clone()),
+                        Some(merged) => merged.merge_with_partial_clone(tile),
+                    }
+                }
+            }
+            match merged {
+                Some(tile) => self.journey_bitmap.insert_tile(key, tile),
+                None => {
+                    self.journey_bitmap.remove_tile(key);
+                }
+            }
+        }
+
+        let done = tiles.is_empty();
+        if !batch.is_empty() {
+            self.record_changes(batch);
+        }
+        if done {
+            self.loading = false;
+            self.overviews.clear();
+            if pending.merged {
+                self.layers.clear();
+                self.tile_hash_cache
+                    .retain(|(layer_id, _), _| layer_id.is_none());
+            }
+        }
+        done
+    }
+
+    fn finish_tile_range_response(&self, mut data: Vec<u8>) -> Result<Vec<u8>, String> {
+        if self.loading {
+            mark_tile_range_loading(&mut data)?;
+        }
+        Ok(data)
+    }
+
     fn reset(&mut self) {
         self.version = self.version.wrapping_add(1);
         self.current_area = None;
@@ -69,21 +360,92 @@ impl MapRenderer {
         format!("{:x}", self.version)
     }
 
-    pub fn parse_version_string(version_str: &str) -> Option<u64> {
-        // Remove quotes if present
-        let cleaned = version_str.trim_matches('"');
-        u64::from_str_radix(cleaned, 16).ok()
+    /// A version of the tile range derived from the content it covers, unlike
+    /// `get_version_string` it stays the same across app restarts (and
+    /// `replace`) as long as the content does not change.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_tile_range_version(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        width: i64,
+        height: i64,
+        buffer_size_power: i16,
+        layered: bool,
+    ) -> String {
+        // every tile-range request asks for its version first
+        self.last_view = Some((x, y, z, width, height));
+
+        let mut hasher = Sha1::new();
+        hasher.update(TILE_RANGE_VERSION_SALT);
+        for value in [x, y, z as i64, width, height, buffer_size_power as i64] {
+            hasher.update(value.to_le_bytes());
+        }
+        hasher.update([self.loading as u8]);
+        let mut bitmaps = vec![(None, &self.journey_bitmap)];
+        if layered {
+            bitmaps = self
+                .layers
+                .iter()
+                .map(|layer| (Some(layer.id), &layer.journey_bitmap))
+                .collect();
+        }
+        for (layer_id, journey_bitmap) in bitmaps {
+            hasher.update([layer_id.map_or(0xff, |id| id)]);
+            let overview = self.overviews.get(&layer_id);
+            let mut keys: Vec<TileKey> = journey_bitmap
+                .all_tile_keys()
+                .chain(overview.into_iter().flat_map(|o| o.all_tile_keys()))
+                .filter(|key| view_range_covers(key, x, y, z, width, height))
+                .cloned()
+                .collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let tile_hash = *self
+                    .tile_hash_cache
+                    .entry((layer_id, key))
+                    .or_insert_with(|| tile_content_hash(journey_bitmap, overview, &key));
+                hasher.update(key.x.to_le_bytes());
+                hasher.update(key.y.to_le_bytes());
+                hasher.update(tile_hash.to_le_bytes());
+            }
+        }
+        let version = hex::encode(&hasher.finalize()[..8]);
+
+        if self.tile_range_versions.len() >= MAX_TILE_RANGE_VERSIONS {
+            self.tile_range_versions.clear();
+        }
+        self.tile_range_versions
+            .insert(version.clone(), self.version);
+        version
+    }
+
+    /// The `version` a tile-range version from `get_tile_range_version` was
+    /// seen at, if it is still known.
+    pub fn version_of_tile_range_version(&self, tile_range_version: &str) -> Option<u64> {
+        self.tile_range_versions.get(tile_range_version).copied()
     }
 
-    // TODO: deprecate this method and merge it with `get_tile_buffer`.
-    pub fn get_latest_bitmap_if_changed(
-        &self,
-        client_version: Option<&str>,
-    ) -> Option<(&JourneyBitmap, String)> {
-        match client_version {
-            Some(v_str) if (Self::parse_version_string(v_str) == Some(self.version)) => None,
-            _ => Some((&self.journey_bitmap, self.get_version_string())),
+    /// The tiles changed after `version`, or `None` if that is unknown (e.g.
+    /// too old, or there was a `replace` since then).
+    pub fn changed_tiles_since(&self, version: u64) -> Option<HashSet<TileKey>> {
+        if version == self.version {
+            return Some(HashSet::new());
+        }
+        // the changelog has to cover every version after `version`
+        let (oldest_version, _) = self.changelog.front()?;
+        if version >= self.version || version.checked_add(1)? < *oldest_version {
+            return None;
         }
+        Some(
+            self.changelog
+                .iter()
+                .filter(|(v, _)| *v > version)
+                .flat_map(|(_, tiles)| tiles.iter().cloned())
+                .collect(),
+        )
     }
 
     pub fn peek_latest_bitmap(&self) -> &JourneyBitmap {
@@ -112,27 +474,299 @@ impl MapRenderer {
         height: i64,
         buffer_size_power: i16,
     ) -> Result<Vec<u8>, String> {
-        tile_range_response_from_journey_bitmap(
+        let tiles = render_tile_range(
             &mut self.journey_bitmap,
+            self.overviews.get(&None),
             x,
             y,
             z,
             width,
             height,
             buffer_size_power,
+            None,
+        )?;
+        let data = encode_tile_range_response_from_tiles(
+            z as u8,
+            x as i32,
+            y as i32,
+            width as u32,
+            height as u32,
+            buffer_size_power as u8,
+            FTA_COMPRESSION_ZSTD,
+            tiles,
+        )?;
+        self.finish_tile_range_response(data)
+    }
+
+    pub fn get_raster_tile(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        buffer_size_power: i16,
+        style: &RasterTileStyle,
+        format: RasterTileFormat,
+    ) -> Result<Vec<u8>, String> {
+        raster_tile::render_raster_tile(
+            &mut self.journey_bitmap,
+            self.overviews.get(&None),
+            x,
+            y,
+            z,
+            buffer_size_power,
+            style,
+            format,
         )
     }
+
+    /// Same as `get_raster_tile`, without encoding the image.
+    pub fn get_raster_tile_image(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        buffer_size_power: i16,
+        style: &RasterTileStyle,
+    ) -> Result<RgbaImage, String> {
+        raster_tile::render_raster_tile_image(
+            &mut self.journey_bitmap,
+            self.overviews.get(&None),
+            x,
+            y,
+            z,
+            buffer_size_power,
+            style,
+        )
+    }
+
+    pub fn get_route_line(&self, zoom: i16, format: RouteLineFormat) -> Result<Vec<u8>, String> {
+        let route = self
+            .route
+            .as_ref()
+            .ok_or_else(|| "The map renderer has no route".to_string())?;
+        route_line::render_route_line(route, zoom, format)
+    }
+
+    pub fn get_vector_tile(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        buffer_size_power: i16,
+    ) -> Result<Vec<u8>, String> {
+        let (cached_version, cache) = &mut self.vector_tile_cache;
+        if *cached_version != self.version || cache.len() >= MAX_CACHED_VECTOR_TILES {
+            *cached_version = self.version;
+            cache.clear();
+        }
+        let key = (z, x, y, buffer_size_power);
+        if let Some(data) = cache.get(&key) {
+            return Ok(data.clone());
+        }
+        let data = vector_tile::render_vector_tile(
+            &mut self.journey_bitmap,
+            self.overviews.get(&None),
+            x,
+            y,
+            z,
+            buffer_size_power,
+        )?;
+        cache.insert(key, data.clone());
+        Ok(data)
+    }
+
+    /// Same as `get_tile_range_response`, but only with the tiles changed
+    /// after `since_version`. Returns `Ok(None)` if the changes since then are
+    /// unknown, the caller should send a full response instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_tile_range_delta(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        width: i64,
+        height: i64,
+        buffer_size_power: i16,
+        since_version: u64,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let Some(changed_tiles) = self.changed_tiles_since(since_version) else {
+            return Ok(None);
+        };
+        let tiles = render_tile_range(
+            &mut self.journey_bitmap,
+            self.overviews.get(&None),
+            x,
+            y,
+            z,
+            width,
+            height,
+            buffer_size_power,
+            Some(&changed_tiles),
+        )?;
+        let data = encode_tile_range_delta_from_tiles(
+            z as u8,
+            x as i32,
+            y as i32,
+            width as u32,
+            height as u32,
+            buffer_size_power as u8,
+            FTA_COMPRESSION_ZSTD,
+            tiles,
+        )?;
+        self.finish_tile_range_response(data).map(Some)
+    }
+
+    /// Same as `get_tile_range_response` (or `get_tile_range_delta` with
+    /// `since_version`), but with the tiles of each layer separately. The
+    /// renderer must have layers.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_tile_range_layers(
+        &mut self,
+        x: i64,
+        y: i64,
+        z: i16,
+        width: i64,
+        height: i64,
+        buffer_size_power: i16,
+        since_version: Option<u64>,
+    ) -> Result<Option<Vec<u8>>, String> {
+        if self.layers.is_empty() {
+            return Err("The map renderer has no layers".to_string());
+        }
+        let changed_tiles = match since_version {
+            None => None,
+            Some(since_version) => match self.changed_tiles_since(since_version) {
+                None => return Ok(None),
+                Some(changed_tiles) => Some(changed_tiles),
+            },
+        };
+        let mut layers = Vec::new();
+        for layer in &mut self.layers {
+            let tiles = render_tile_range(
+                &mut layer.journey_bitmap,
+                self.overviews.get(&Some(layer.id)),
+                x,
+                y,
+                z,
+                width,
+                height,
+                buffer_size_power,
+                changed_tiles.as_ref(),
+            )?;
+            layers.push(TileLayerPixelData {
+                id: layer.id,
+                name: layer.name.clone(),
+                tiles,
+            });
+        }
+        let data = encode_tile_range_layers_from_tiles(
+            z as u8,
+            x as i32,
+            y as i32,
+            width as u32,
+            height as u32,
+            buffer_size_power as u8,
+            FTA_COMPRESSION_ZSTD,
+            changed_tiles.is_some(),
+            layers,
+        )?;
+        self.finish_tile_range_response(data).map(Some)
+    }
+}
+
+fn tile_content_hash(
+    journey_bitmap: &JourneyBitmap,
+    overview: Option<&BlockOverview>,
+    key: &TileKey,
+) -> u64 {
+    journey_bitmap.peek_tile_without_updating_cache(key, |tile| {
+        let mut hasher = Sha1::new();
+        for (block_key, block) in tile.into_iter().flat_map(|tile| tile.iter()) {
+            hasher.update((block_key.index() as u32).to_le_bytes());
+            hasher.update(block.raw_data());
+        }
+        if let Some(block_keys) = overview.and_then(|overview| overview.get_tile(key)) {
+            hasher.update(b"overview");
+            hasher.update(block_keys.raw_data());
+        }
+        u64::from_le_bytes(hasher.finalize()[..8].try_into().unwrap())
+    })
+}
+
+/// Squared distance between the journey bitmap tile `key` and the center of
+/// `view` (as `MapRenderer::last_view`), in journey bitmap tiles.
+fn view_distance(key: &TileKey, view: (i64, i64, i16, i64, i64)) -> f64 {
+    let (x, y, z, width, height) = view;
+    let scale = (2.0f64).powi((MAP_WIDTH_OFFSET - z) as i32);
+    let center_x = (x as f64 + width as f64 / 2.0) * scale;
+    let center_y = (y as f64 + height as f64 / 2.0) * scale;
+    // the map wraps around horizontally
+    let dx = (key.x as f64 + 0.5 - center_x).rem_euclid(MAP_WIDTH as f64);
+    let dx = dx.min(MAP_WIDTH as f64 - dx);
+    let dy = key.y as f64 + 0.5 - center_y;
+    dx * dx + dy * dy
 }
 
-fn tile_range_response_from_journey_bitmap(
+/// Whether the journey bitmap tile `key` is (partly) in the view tiles of the
+/// range, `x` may be out of `0..2^z` as in `get_tile_range_response`.
+fn view_range_covers(key: &TileKey, x: i64, y: i64, z: i16, width: i64, height: i64) -> bool {
+    // the view tiles `key` is in, `start..end` on both axes
+    let zoom_diff = z - MAP_WIDTH_OFFSET;
+    let span = |k: u16| {
+        if zoom_diff >= 0 {
+            ((k as i64) << zoom_diff, (k as i64 + 1) << zoom_diff)
+        } else {
+            let view = k as i64 >> -zoom_diff;
+            (view, view + 1)
+        }
+    };
+    let (x_start, x_end) = span(key.x);
+    let (y_start, y_end) = span(key.y);
+    let zoom_coefficient = 1i64 << z;
+    y_start < y + height
+        && y < y_end
+        && (x..x + width).any(|view_x| {
+            let view_x = view_x.rem_euclid(zoom_coefficient);
+            x_start <= view_x && view_x < x_end
+        })
+}
+
+/// Whether the view tile `(view_x, view_y)` at `zoom` covers any of
+/// `changed_tiles`.
+fn view_tile_changed(
+    changed_tiles: &HashSet<TileKey>,
+    view_x: i64,
+    view_y: i64,
+    zoom: i16,
+) -> bool {
+    let zoom_diff = zoom - MAP_WIDTH_OFFSET;
+    if zoom_diff >= 0 {
+        changed_tiles.contains(&TileKey::new(
+            (view_x >> zoom_diff) as u16,
+            (view_y >> zoom_diff) as u16,
+        ))
+    } else {
+        changed_tiles.iter().any(|key| {
+            (key.x as i64) >> -zoom_diff == view_x && (key.y as i64) >> -zoom_diff == view_y
+        })
+    }
+}
+
+/// Renders the view tiles of a tile range (with `overview`, see
+/// `MapRenderer::set_overviews`), with `changed_tiles` only the ones covering
+/// them.
+#[allow(clippy::too_many_arguments)]
+fn render_tile_range(
     journey_bitmap: &mut JourneyBitmap,
+    overview: Option<&BlockOverview>,
     x: i64,
     y: i64,
     z: i16,
     width: i64,
     height: i64,
     buffer_size_power: i16,
-) -> Result<Vec<u8>, String> {
+    changed_tiles: Option<&HashSet<TileKey>>,
+) -> Result<Vec<TilePixelData>, String> {
     // Validate parameters to prevent overflow and invalid operations
     if width <= 0 || height <= 0 {
         return Err(format!(
@@ -177,8 +811,15 @@ fn tile_range_response_from_journey_bitmap(
             let tile_x_rounded =
                 ((tile_x % zoom_coefficient) + zoom_coefficient) % zoom_coefficient;
 
-            let bitmap = TileShader2::render_tile_bitmap(
+            if let Some(changed_tiles) = changed_tiles {
+                if !view_tile_changed(changed_tiles, tile_x_rounded, tile_y, z) {
+                    continue;
+                }
+            }
+
+            let bitmap = TileShader2::render_tile_bitmap_with_overview(
                 journey_bitmap,
+                overview,
                 tile_x_rounded,
                 tile_y,
                 z,
@@ -195,14 +836,5 @@ fn tile_range_response_from_journey_bitmap(
         }
     }
 
-    encode_tile_range_response_from_tiles(
-        z as u8,
-        x as i32,
-        y as i32,
-        width as u32,
-        height as u32,
-        buffer_size_power as u8,
-        FTA_COMPRESSION_ZSTD,
-        tiles,
-    )
+    Ok(tiles)
 }
@@ -3,4 +3,12 @@ pub use map_renderer::MapRenderer;
 
 pub mod internal_server;
 
+pub mod raster_tile;
+
+pub mod route_line;
+
+pub mod time_lapse;
+
+pub mod vector_tile;
+
 mod tile_shader2;
@@ -0,0 +1,204 @@
+use image::{ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+use crate::journey_bitmap::{BlockOverview, JourneyBitmap};
+use crate::renderer::tile_shader2::TileShader2;
+
+/* Plain XYZ raster tiles of the fog, for standard map clients that cannot
+decode `tile_range` responses (those need a `journey_kernel::TileBuffer`).
+Explored area is fully transparent and everything else is covered by the fog
+color.
+
+Edge softening is done within a single tile, so there can be a small seam at
+tile borders when it is enabled. */
+
+pub const MAX_EDGE_SOFTENING: u8 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RasterTileFormat {
+    Png,
+    Webp,
+}
+
+impl RasterTileFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "png" => Some(RasterTileFormat::Png),
+            "webp" => Some(RasterTileFormat::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RasterTileFormat::Png => "image/png",
+            RasterTileFormat::Webp => "image/webp",
+        }
+    }
+
+    fn image_format(&self) -> ImageFormat {
+        match self {
+            RasterTileFormat::Png => ImageFormat::Png,
+            RasterTileFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterTileStyle {
+    pub fog_color: [u8; 3],
+    pub fog_alpha: u8,
+    /// Radius in pixels over which the fog fades out at the edges of explored
+    /// area, `0` for hard edges.
+    pub edge_softening: u8,
+}
+
+impl Default for RasterTileStyle {
+    fn default() -> Self {
+        Self {
+            fog_color: [0, 0, 0],
+            fog_alpha: 127,
+            edge_softening: 0,
+        }
+    }
+}
+
+/// Blurs `values` (a `side` * `side` grid) with a box of `radius` in place,
+/// pixels outside of the grid count as the nearest edge pixel.
+fn box_blur(values: &mut [f32], side: usize, radius: usize) {
+    let mut line = vec![0.0f32; side];
+    let mut prefix = vec![0.0f32; side + 1];
+    let mut blur_line = |line: &mut [f32]| {
+        for (i, value) in line.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + value;
+        }
+        for (i, value) in line.iter_mut().enumerate() {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius).min(side - 1);
+            // clamped pixels on both sides
+            let before = (radius - (i - start)) as f32 * prefix[1];
+            let after = (radius - (end - i)) as f32 * (prefix[side] - prefix[side - 1]);
+            *value = (prefix[end + 1] - prefix[start] + before + after) / (2 * radius + 1) as f32;
+        }
+    };
+    for y in 0..side {
+        blur_line(&mut values[y * side..(y + 1) * side]);
+    }
+    for x in 0..side {
+        for y in 0..side {
+            line[y] = values[y * side + x];
+        }
+        blur_line(&mut line);
+        for y in 0..side {
+            values[y * side + x] = line[y];
+        }
+    }
+}
+
+pub(super) fn validate_xyz_tile(
+    x: i64,
+    y: i64,
+    zoom: i16,
+    buffer_size_power: i16,
+) -> Result<(), String> {
+    if !(0..=16).contains(&zoom) {
+        return Err(format!("Invalid zoom level: {zoom} (must be 0-16)"));
+    }
+    let zoom_coefficient = 1i64 << zoom;
+    if !(0..zoom_coefficient).contains(&x) || !(0..zoom_coefficient).contains(&y) {
+        return Err(format!(
+            "Invalid tile coordinate: ({x}, {y}) (must be 0-{})",
+            zoom_coefficient - 1
+        ));
+    }
+    if !(6..=11).contains(&buffer_size_power) {
+        return Err(format!(
+            "Invalid buffer_size_power: {buffer_size_power} (must be 6-11, corresponding to 64-2048 pixel tiles)"
+        ));
+    }
+    Ok(())
+}
+
+/// Renders the view tile `(x, y)` at `zoom`, the tile is
+/// `2^buffer_size_power` pixels wide. `overview` is shown as well, see
+/// `MapRenderer::set_overviews`.
+#[allow(clippy::too_many_arguments)]
+pub fn render_raster_tile(
+    journey_bitmap: &mut JourneyBitmap,
+    overview: Option<&BlockOverview>,
+    x: i64,
+    y: i64,
+    zoom: i16,
+    buffer_size_power: i16,
+    style: &RasterTileStyle,
+    format: RasterTileFormat,
+) -> Result<Vec<u8>, String> {
+    let image = render_raster_tile_image(
+        journey_bitmap,
+        overview,
+        x,
+        y,
+        zoom,
+        buffer_size_power,
+        style,
+    )?;
+    encode_image(&image, format)
+}
+
+pub fn encode_image(image: &RgbaImage, format: RasterTileFormat) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), format.image_format())
+        .map_err(|e| format!("Failed to encode raster tile: {e}"))?;
+    Ok(data)
+}
+
+/// Same as `render_raster_tile`, without encoding the image.
+pub fn render_raster_tile_image(
+    journey_bitmap: &mut JourneyBitmap,
+    overview: Option<&BlockOverview>,
+    x: i64,
+    y: i64,
+    zoom: i16,
+    buffer_size_power: i16,
+    style: &RasterTileStyle,
+) -> Result<RgbaImage, String> {
+    validate_xyz_tile(x, y, zoom, buffer_size_power)?;
+    if style.edge_softening > MAX_EDGE_SOFTENING {
+        return Err(format!(
+            "Invalid edge_softening: {} (must be 0-{MAX_EDGE_SOFTENING})",
+            style.edge_softening
+        ));
+    }
+
+    let bitmap = TileShader2::render_tile_bitmap_with_overview(
+        journey_bitmap,
+        overview,
+        x,
+        y,
+        zoom,
+        buffer_size_power,
+    );
+    let side = bitmap.side();
+
+    let mut explored = vec![0.0f32; side * side];
+    for py in 0..side {
+        for px in 0..side {
+            if bitmap.get(px, py) {
+                explored[py * side + px] = 1.0;
+            }
+        }
+    }
+    if style.edge_softening > 0 {
+        box_blur(&mut explored, side, style.edge_softening as usize);
+    }
+
+    let [r, g, b] = style.fog_color;
+    let mut pixels = Vec::with_capacity(side * side * 4);
+    for coverage in explored {
+        let alpha = (style.fog_alpha as f32 * (1.0 - coverage)).round() as u8;
+        pixels.extend_from_slice(&[r, g, b, alpha]);
+    }
+    RgbaImage::from_raw(side as u32, side as u32, pixels)
+        .ok_or_else(|| "Invalid raster tile buffer".to_string())
+}
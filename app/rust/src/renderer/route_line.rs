@@ -0,0 +1,314 @@
+use integer_encoding::*;
+use serde_json::json;
+use std::f64::consts::PI;
+
+use crate::gps_processor::Point;
+use crate::journey_vector::JourneyVector;
+
+/* The actual polyline of a vector journey, so a journey detail view can show
+the route instead of the fog. Points are simplified for the zoom level
+(Douglas-Peucker with a tolerance of about a pixel) and every point has a
+`progress`: the distance traveled so far as a fraction of the whole journey,
+which clients can color by and use for direction arrows. Finalized journeys
+do not keep per-point time or speed.
+
+The binary format is:
+- `ROUTE_LINE_BINARY_VERSION` (`u8`)
+- segment count (varint)
+- for each segment:
+  - flags (`u8`, bit 0: inferred, e.g. filled by gap filling)
+  - point count (varint)
+  - for each point: longitude and latitude in 1e-6 degrees as zigzag varint
+    deltas from the previous point (of any segment, starting from 0), then
+    `progress * 65535` (`u16`, little-endian)
+Longitudes are unwrapped so a route crossing the antimeridian stays
+continuous, they can be out of -180..180. */
+
+pub const ROUTE_LINE_BINARY_VERSION: u8 = 1;
+const SEGMENT_FLAG_INFERRED: u8 = 1;
+const TILE_SIZE: f64 = 256.0;
+const MAX_ZOOM: i16 = 22;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RouteLineFormat {
+    GeoJson,
+    Binary,
+}
+
+impl RouteLineFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "geojson" => Some(RouteLineFormat::GeoJson),
+            "bin" => Some(RouteLineFormat::Binary),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RouteLineFormat::GeoJson => "application/geo+json",
+            RouteLineFormat::Binary => "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoutePoint {
+    pub longitude: f64,
+    pub latitude: f64,
+    pub progress: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteSegment {
+    pub points: Vec<RoutePoint>,
+    pub inferred: bool,
+}
+
+fn mercator(point: &RoutePoint, world_size: f64) -> (f64, f64) {
+    let lat_rad = point.latitude.clamp(-85.05112878, 85.05112878).to_radians();
+    (
+        (point.longitude + 180.0) / 360.0 * world_size,
+        (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0 * world_size,
+    )
+}
+
+fn distance_sq_to_edge(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (x, y) = (a.0 + dx * t, a.1 + dy * t);
+    (x - p.0) * (x - p.0) + (y - p.1) * (y - p.1)
+}
+
+/// Douglas-Peucker on mercator pixels, `tolerance` in pixels.
+fn simplify(points: &[RoutePoint], world_size: f64, tolerance: f64) -> Vec<RoutePoint> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+    let pixels: Vec<_> = points.iter().map(|x| mercator(x, world_size)).collect();
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| {
+                (
+                    i,
+                    distance_sq_to_edge(pixels[i], pixels[start], pixels[end]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance_sq)) = farthest {
+            if distance_sq > tolerance * tolerance {
+                keep[i] = true;
+                stack.push((start, i));
+                stack.push((i, end));
+            }
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// The route of `journey_vector` simplified for `zoom` (256 pixel tiles).
+pub fn route_segments(
+    journey_vector: &JourneyVector,
+    zoom: i16,
+) -> Result<Vec<RouteSegment>, String> {
+    if !(0..=MAX_ZOOM).contains(&zoom) {
+        return Err(format!("Invalid zoom level: {zoom} (must be 0-{MAX_ZOOM})"));
+    }
+
+    let mut total = 0.0;
+    let mut prev_lng: Option<f64> = None;
+    let mut segments: Vec<RouteSegment> = Vec::new();
+    for track_segment in &journey_vector.track_segments {
+        let mut points: Vec<RoutePoint> = Vec::new();
+        for track_point in &track_segment.track_points {
+            if let Some(prev) = points.last() {
+                total += Point {
+                    latitude: prev.latitude,
+                    longitude: prev.longitude,
+                }
+                .haversine_distance(&Point::from(track_point));
+            }
+            // unwrap longitudes so every edge takes the shorter way
+            let mut longitude = track_point.longitude;
+            if let Some(prev_lng) = prev_lng {
+                longitude += ((prev_lng - longitude) / 360.0).round() * 360.0;
+            }
+            prev_lng = Some(longitude);
+            points.push(RoutePoint {
+                longitude,
+                latitude: track_point.latitude,
+                progress: total,
+            });
+        }
+        segments.push(RouteSegment {
+            points,
+            inferred: track_segment.inferred,
+        });
+    }
+
+    let world_size = TILE_SIZE * (1u64 << zoom) as f64;
+    for segment in &mut segments {
+        for point in &mut segment.points {
+            point.progress = if total > 0.0 {
+                point.progress / total
+            } else {
+                0.0
+            };
+        }
+        segment.points = simplify(&segment.points, world_size, 1.0);
+    }
+    segments.retain(|segment| !segment.points.is_empty());
+    Ok(segments)
+}
+
+fn encode_geojson(segments: &[RouteSegment]) -> Vec<u8> {
+    let features: Vec<_> = segments
+        .iter()
+        .map(|segment| {
+            let coordinates: Vec<_> = segment
+                .points
+                .iter()
+                .map(|point| [point.longitude, point.latitude])
+                .collect();
+            let progress: Vec<_> = segment.points.iter().map(|point| point.progress).collect();
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": coordinates },
+                "properties": { "inferred": segment.inferred, "progress": progress },
+            })
+        })
+        .collect();
+    json!({ "type": "FeatureCollection", "features": features })
+        .to_string()
+        .into_bytes()
+}
+
+fn encode_binary(segments: &[RouteSegment]) -> Vec<u8> {
+    let mut data = vec![ROUTE_LINE_BINARY_VERSION];
+    data.extend((segments.len() as u64).encode_var_vec());
+    let (mut prev_lng, mut prev_lat) = (0i64, 0i64);
+    for segment in segments {
+        data.push(if segment.inferred {
+            SEGMENT_FLAG_INFERRED
+        } else {
+            0
+        });
+        data.extend((segment.points.len() as u64).encode_var_vec());
+        for point in &segment.points {
+            let lng = (point.longitude * 1e6).round() as i64;
+            let lat = (point.latitude * 1e6).round() as i64;
+            data.extend((lng - prev_lng).encode_var_vec());
+            data.extend((lat - prev_lat).encode_var_vec());
+            data.extend(((point.progress * 65535.0).round() as u16).to_le_bytes());
+            (prev_lng, prev_lat) = (lng, lat);
+        }
+    }
+    data
+}
+
+pub fn render_route_line(
+    journey_vector: &JourneyVector,
+    zoom: i16,
+    format: RouteLineFormat,
+) -> Result<Vec<u8>, String> {
+    let segments = route_segments(journey_vector, zoom)?;
+    Ok(match format {
+        RouteLineFormat::GeoJson => encode_geojson(&segments),
+        RouteLineFormat::Binary => encode_binary(&segments),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journey_vector::{TrackPoint, TrackSegment};
+
+    fn journey_vector(segments: &[&[(f64, f64)]]) -> JourneyVector {
+        JourneyVector {
+            track_segments: segments
+                .iter()
+                .map(|points| TrackSegment {
+                    track_points: points
+                        .iter()
+                        .map(|(longitude, latitude)| TrackPoint {
+                            latitude: *latitude,
+                            longitude: *longitude,
+                        })
+                        .collect(),
+                    inferred: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn simplified_per_zoom() {
+        // a small zigzag along a straight line
+        let points: Vec<_> = (0..=100)
+            .map(|i| (120.0 + i as f64 * 0.001, 30.0 + (i % 2) as f64 * 0.0001))
+            .collect();
+        let journey_vector = journey_vector(&[&points]);
+
+        let low = route_segments(&journey_vector, 5).unwrap();
+        assert_eq!(low[0].points.len(), 2);
+        let high = route_segments(&journey_vector, 20).unwrap();
+        assert_eq!(high[0].points.len(), 101);
+
+        let first = low[0].points[0];
+        let last = low[0].points[1];
+        assert_eq!((first.longitude, first.progress), (120.0, 0.0));
+        assert_eq!((last.longitude, last.progress), (120.1, 1.0));
+    }
+
+    #[test]
+    fn progress_continues_across_segments() {
+        let journey_vector = journey_vector(&[
+            &[(120.0, 30.0), (120.1, 30.0)],
+            &[(120.3, 30.0), (120.4, 30.0)],
+        ]);
+        let segments = route_segments(&journey_vector, 10).unwrap();
+        let progress: Vec<_> = segments
+            .iter()
+            .flat_map(|segment| segment.points.iter().map(|point| point.progress))
+            .collect();
+        // the gap between segments is not traveled
+        assert_eq!(progress[0], 0.0);
+        assert!((progress[1] - 0.5).abs() < 1e-9);
+        assert_eq!(progress[1], progress[2]);
+        assert_eq!(progress[3], 1.0);
+    }
+
+    #[test]
+    fn unwrap_across_antimeridian() {
+        let journey_vector = journey_vector(&[&[(179.9, 0.0), (-179.9, 0.0)]]);
+        let segments = route_segments(&journey_vector, 10).unwrap();
+        assert!((segments[0].points[1].longitude - 180.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn binary_format() {
+        let journey_vector = journey_vector(&[&[(120.0, 30.0), (120.000001, 29.999999)]]);
+        let data = render_route_line(&journey_vector, 22, RouteLineFormat::Binary).unwrap();
+        let mut expected = vec![ROUTE_LINE_BINARY_VERSION, 1, 0, 2];
+        expected.extend(120_000_000i64.encode_var_vec());
+        expected.extend(30_000_000i64.encode_var_vec());
+        expected.extend([0, 0]);
+        expected.extend(1i64.encode_var_vec());
+        expected.extend((-1i64).encode_var_vec());
+        expected.extend([255, 255]);
+        assert_eq!(data, expected);
+    }
+}
@@ -1,6 +1,6 @@
 use journey_kernel::bitmap2d::BitMap2D;
 
-use crate::journey_bitmap::{Block, BlockKey, JourneyBitmap, TileKey};
+use crate::journey_bitmap::{Block, BlockKey, BlockOverview, JourneyBitmap, TileKey};
 use crate::journey_bitmap::{BITMAP_WIDTH, BITMAP_WIDTH_OFFSET, TILE_WIDTH_OFFSET};
 
 const TILE_ZOOM: i16 = 9;
@@ -77,6 +77,90 @@ impl TileShader2 {
         bitmap
     }
 
+    /// Same as `render_tile_bitmap`, plus `add_overview_bits` for `overview`.
+    pub fn render_tile_bitmap_with_overview(
+        journey_bitmap: &mut JourneyBitmap,
+        overview: Option<&BlockOverview>,
+        view_x: i64,
+        view_y: i64,
+        zoom: i16,
+        buffer_size_power: i16,
+    ) -> BitMap2D {
+        let mut bitmap =
+            Self::render_tile_bitmap(journey_bitmap, view_x, view_y, zoom, buffer_size_power);
+        if let Some(overview) = overview {
+            Self::add_overview_bits(
+                &mut bitmap,
+                overview,
+                view_x,
+                view_y,
+                zoom,
+                buffer_size_power,
+            );
+        }
+        bitmap
+    }
+
+    /// Sets the pixels of the view tile covered by the blocks of `overview`,
+    /// as if they were completely visited. Below zoom + buffer_size_power = 16
+    /// a block is a single pixel, which is what `render_tile_bitmap` sets for
+    /// any non-empty block too.
+    pub fn add_overview_bits(
+        bitmap: &mut BitMap2D,
+        overview: &BlockOverview,
+        view_x: i64,
+        view_y: i64,
+        zoom: i16,
+        buffer_size_power: i16,
+    ) {
+        let side = bitmap.side() as i64;
+        // blocks are `2^block_size_power` pixels wide, or smaller than a pixel
+        let block_size_power = zoom + buffer_size_power - (TILE_ZOOM + TILE_WIDTH_OFFSET);
+        let (origin_x, origin_y) = (view_x << buffer_size_power, view_y << buffer_size_power);
+
+        let zoom_diff_view_to_tile = zoom - TILE_ZOOM;
+        let (tile_x, tile_y) = if zoom_diff_view_to_tile > 0 {
+            (
+                view_x >> zoom_diff_view_to_tile,
+                view_y >> zoom_diff_view_to_tile,
+            )
+        } else {
+            (
+                view_x << -zoom_diff_view_to_tile,
+                view_y << -zoom_diff_view_to_tile,
+            )
+        };
+        let tile_num = 1 << std::cmp::max(-zoom_diff_view_to_tile, 0);
+        for i in 0..tile_num {
+            for j in 0..tile_num {
+                let tile_key = TileKey::new((tile_x + i) as u16, (tile_y + j) as u16);
+                let Some(block_keys) = overview.get_tile(&tile_key) else {
+                    continue;
+                };
+                for block_key in block_keys.iter() {
+                    let block_x = ((tile_key.x as i64) << TILE_WIDTH_OFFSET) + block_key.x() as i64;
+                    let block_y = ((tile_key.y as i64) << TILE_WIDTH_OFFSET) + block_key.y() as i64;
+                    if block_size_power >= 0 {
+                        Self::set_rect_bits(
+                            bitmap,
+                            side,
+                            (block_x << block_size_power) - origin_x,
+                            (block_y << block_size_power) - origin_y,
+                            1 << block_size_power,
+                            1 << block_size_power,
+                        );
+                    } else {
+                        let x = (block_x >> -block_size_power) - origin_x;
+                        let y = (block_y >> -block_size_power) - origin_y;
+                        if x >= 0 && x < side && y >= 0 && y < side {
+                            bitmap.set(x as usize, y as usize, true);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn add_tile_bits(
         bitmap: &mut BitMap2D,
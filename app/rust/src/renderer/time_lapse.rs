@@ -0,0 +1,291 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Days, NaiveDate};
+use image::{imageops, RgbaImage};
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::journey_bitmap::JourneyBitmap;
+use crate::journey_data::JourneyData;
+use crate::renderer::raster_tile::{self, RasterTileFormat, RasterTileStyle};
+use crate::renderer::MapRenderer;
+use crate::utils::MapBounds;
+
+/* Playback of how the explored area grew over time. The map renderer shows
+every journey up to a date cursor: moving the cursor forward only merges the
+journeys in between (so clients keep getting small tile-range deltas), moving
+it back starts over from the first journey. */
+
+pub const MAX_FRAME_SIZE: u32 = 4096;
+const FRAME_BUFFER_SIZE_POWER: i16 = 8;
+
+pub struct FrameSequence {
+    pub bounds: MapBounds,
+    pub zoom: i16,
+    pub from_date_inclusive: NaiveDate,
+    pub to_date_inclusive: NaiveDate,
+    /// Days between two frames, the last frame is always `to_date_inclusive`.
+    pub step_days: u32,
+    pub style: RasterTileStyle,
+}
+
+impl FrameSequence {
+    pub fn dates(&self) -> Result<Vec<NaiveDate>> {
+        if self.step_days == 0 {
+            bail!("step_days must be positive");
+        }
+        if self.from_date_inclusive > self.to_date_inclusive {
+            bail!(
+                "Invalid date range: {} - {}",
+                self.from_date_inclusive,
+                self.to_date_inclusive
+            );
+        }
+        let mut dates = Vec::new();
+        let mut date = Some(self.from_date_inclusive);
+        while let Some(d) = date.filter(|d| *d < self.to_date_inclusive) {
+            dates.push(d);
+            date = d.checked_add_days(Days::new(self.step_days as u64));
+        }
+        dates.push(self.to_date_inclusive);
+        Ok(dates)
+    }
+}
+
+pub struct TimeLapse {
+    /* (journey_date, journey_id), oldest first */
+    journeys: Vec<(NaiveDate, String)>,
+    /* journeys[..merged] are in the map renderer */
+    merged: usize,
+    map_renderer: MapRenderer,
+}
+
+impl TimeLapse {
+    pub fn new(mut journeys: Vec<(NaiveDate, String)>) -> Self {
+        journeys.sort();
+        Self {
+            journeys,
+            merged: 0,
+            map_renderer: MapRenderer::new(JourneyBitmap::new()),
+        }
+    }
+
+    pub fn map_renderer(&mut self) -> &mut MapRenderer {
+        &mut self.map_renderer
+    }
+
+    /// Shows every journey on or before `date`.
+    pub fn set_cursor<F>(&mut self, date: NaiveDate, mut load_journey: F) -> Result<()>
+    where
+        F: FnMut(&str) -> Result<JourneyData>,
+    {
+        let target = self
+            .journeys
+            .partition_point(|(journey_date, _)| *journey_date <= date);
+        if target < self.merged {
+            self.map_renderer.replace(JourneyBitmap::new());
+            self.merged = 0;
+        }
+        if target == self.merged {
+            return Ok(());
+        }
+
+        let mut added = JourneyBitmap::new();
+        for (_, journey_id) in &self.journeys[self.merged..target] {
+            load_journey(journey_id)?.merge_into(&mut added);
+        }
+        self.map_renderer.update(|journey_bitmap, tile_changed| {
+            for key in added.all_tile_keys() {
+                tile_changed(*key);
+            }
+            journey_bitmap.merge(std::mem::take(&mut added));
+        });
+        self.merged = target;
+        Ok(())
+    }
+
+    /// Renders `bounds` at `zoom` with what the cursor currently shows.
+    pub fn render_frame(
+        &mut self,
+        bounds: &MapBounds,
+        zoom: i16,
+        style: &RasterTileStyle,
+    ) -> Result<RgbaImage> {
+        if !(0..=16).contains(&zoom) {
+            bail!("Invalid zoom level: {zoom} (must be 0-16)");
+        }
+        let tile_size = 1i64 << FRAME_BUFFER_SIZE_POWER;
+        let world_size = (tile_size << zoom) as f64;
+        let pixel_x = |lng: f64| (lng + 180.0) / 360.0 * world_size;
+        let pixel_y = |lat: f64| {
+            let lat_rad = lat.clamp(-85.05112878, 85.05112878).to_radians();
+            (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0 * world_size
+        };
+        let left = pixel_x(bounds.west).floor() as i64;
+        let right = pixel_x(bounds.east).ceil() as i64;
+        let top = pixel_y(bounds.north).floor() as i64;
+        let bottom = pixel_y(bounds.south).ceil() as i64;
+        let (width, height) = (right - left, bottom - top);
+        if !(1..=MAX_FRAME_SIZE as i64).contains(&width)
+            || !(1..=MAX_FRAME_SIZE as i64).contains(&height)
+        {
+            bail!("Invalid frame size: {width}x{height} (must be 1-{MAX_FRAME_SIZE} pixels wide)");
+        }
+
+        let mut frame = RgbaImage::new(width as u32, height as u32);
+        for tile_y in top.div_euclid(tile_size)..=(bottom - 1).div_euclid(tile_size) {
+            for tile_x in left.div_euclid(tile_size)..=(right - 1).div_euclid(tile_size) {
+                // the bounds may cross the antimeridian
+                let tile = self
+                    .map_renderer
+                    .get_raster_tile_image(
+                        tile_x.rem_euclid(1 << zoom),
+                        tile_y,
+                        zoom,
+                        FRAME_BUFFER_SIZE_POWER,
+                        style,
+                    )
+                    .map_err(|e| anyhow!(e))?;
+                imageops::replace(
+                    &mut frame,
+                    &tile,
+                    tile_x * tile_size - left,
+                    tile_y * tile_size - top,
+                );
+            }
+        }
+        Ok(frame)
+    }
+
+    /// Writes a PNG per frame into `output_dir` (as `frame_00000.png`...),
+    /// returning their paths. The cursor is left at the last frame.
+    pub fn export_frames<F>(
+        &mut self,
+        frame_sequence: &FrameSequence,
+        output_dir: &str,
+        mut load_journey: F,
+    ) -> Result<Vec<String>>
+    where
+        F: FnMut(&str) -> Result<JourneyData>,
+    {
+        let mut paths = Vec::new();
+        for (i, date) in frame_sequence.dates()?.into_iter().enumerate() {
+            self.set_cursor(date, &mut load_journey)?;
+            let frame = self.render_frame(
+                &frame_sequence.bounds,
+                frame_sequence.zoom,
+                &frame_sequence.style,
+            )?;
+            let data =
+                raster_tile::encode_image(&frame, RasterTileFormat::Png).map_err(|e| anyhow!(e))?;
+            let path = Path::new(output_dir).join(format!("frame_{i:05}.png"));
+            std::fs::write(&path, data)?;
+            paths.push(path.to_string_lossy().into_owned());
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn load_journey(journey_id: &str) -> Result<JourneyData> {
+        // each journey is a short line, further east for bigger ids
+        let lng = journey_id.parse::<f64>()?;
+        Ok(JourneyData::Vector(JourneyVector {
+            track_segments: vec![TrackSegment {
+                track_points: vec![
+                    TrackPoint {
+                        latitude: 30.0,
+                        longitude: lng,
+                    },
+                    TrackPoint {
+                        latitude: 30.0,
+                        longitude: lng + 0.1,
+                    },
+                ],
+                inferred: false,
+            }],
+        }))
+    }
+
+    fn time_lapse() -> TimeLapse {
+        TimeLapse::new(vec![
+            (date(3), "120".to_string()),
+            (date(1), "100".to_string()),
+            (date(2), "110".to_string()),
+        ])
+    }
+
+    #[test]
+    fn moving_forward_only_merges_new_journeys() {
+        let mut time_lapse = time_lapse();
+        let mut loaded = Vec::new();
+        let mut load = |journey_id: &str| {
+            loaded.push(journey_id.to_string());
+            load_journey(journey_id)
+        };
+        time_lapse.set_cursor(date(1), &mut load).unwrap();
+        let version = time_lapse.map_renderer().get_current_version();
+        time_lapse.set_cursor(date(3), &mut load).unwrap();
+        time_lapse.set_cursor(date(3), &mut load).unwrap();
+        assert_eq!(loaded, vec!["100", "110", "120"]);
+        // clients can get a delta from the previous frame
+        let changed_tiles = time_lapse.map_renderer().changed_tiles_since(version);
+        assert!(changed_tiles.is_some_and(|tiles| !tiles.is_empty()));
+
+        let mut expected = JourneyBitmap::new();
+        for journey_id in ["100", "110", "120"] {
+            load_journey(journey_id).unwrap().merge_into(&mut expected);
+        }
+        assert_eq!(time_lapse.map_renderer().peek_latest_bitmap(), &expected);
+    }
+
+    #[test]
+    fn moving_back_starts_over() {
+        let mut time_lapse = time_lapse();
+        time_lapse.set_cursor(date(3), load_journey).unwrap();
+        time_lapse.set_cursor(date(1), load_journey).unwrap();
+        let mut expected = JourneyBitmap::new();
+        load_journey("100").unwrap().merge_into(&mut expected);
+        assert_eq!(time_lapse.map_renderer().peek_latest_bitmap(), &expected);
+    }
+
+    #[test]
+    fn export_frames() {
+        let dir = tempdir::TempDir::new("time_lapse").unwrap();
+        let mut time_lapse = time_lapse();
+        let frame_sequence = FrameSequence {
+            bounds: MapBounds {
+                west: 95.0,
+                south: 20.0,
+                east: 125.0,
+                north: 40.0,
+            },
+            zoom: 3,
+            from_date_inclusive: date(1),
+            to_date_inclusive: date(3),
+            step_days: 2,
+            style: RasterTileStyle::default(),
+        };
+        let paths = time_lapse
+            .export_frames(&frame_sequence, dir.path().to_str().unwrap(), load_journey)
+            .unwrap();
+        assert_eq!(paths.len(), 2);
+        let explored_pixels: Vec<usize> = paths
+            .iter()
+            .map(|path| {
+                let frame = image::open(path).unwrap().to_rgba8();
+                // 30 degrees of longitude at z3 is 30 / 360 * 2048 pixels
+                assert_eq!(frame.width(), 172);
+                frame.pixels().filter(|pixel| pixel.0[3] == 0).count()
+            })
+            .collect();
+        assert!(0 < explored_pixels[0] && explored_pixels[0] < explored_pixels[1]);
+    }
+}
@@ -0,0 +1,286 @@
+use protobuf::Message;
+use std::collections::BTreeMap;
+
+use crate::journey_bitmap::{BlockOverview, JourneyBitmap};
+use crate::protos::vector_tile::{tile, Tile};
+use crate::renderer::raster_tile::validate_xyz_tile;
+use crate::renderer::tile_shader2::TileShader2;
+
+/* Mapbox Vector Tiles of explored area, so map clients can style it with plain
+layers (fill, extrusion, outline...) instead of our custom shaders.
+
+The explored area of a view tile is rendered into a bitmap first (the same way
+as raster tiles), then the boundary of explored pixels is traced into polygons
+with holes. A tile has a single `explored` layer with one polygon feature. */
+
+pub const LAYER_NAME: &str = "explored";
+pub const EXTENT: u32 = 4096;
+
+type Point = (i32, i32);
+
+fn right_of(direction: Point) -> Point {
+    // y points down
+    (-direction.1, direction.0)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn signed_area2(ring: &[Point]) -> i64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum()
+}
+
+fn contains(ring: &[Point], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        let (ax, ay, bx, by) = (a.0 as f64, a.1 as f64, b.0 as f64, b.1 as f64);
+        if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Traces the boundary of `explored` pixels of a `side` * `side` grid into
+/// rings of pixel corners. Every edge has explored pixels on its right, so
+/// exterior rings are clockwise (y pointing down) and holes counterclockwise,
+/// which is what MVT expects. Pixels touching only at a corner are kept apart.
+fn trace_rings(side: usize, explored: impl Fn(usize, usize) -> bool) -> Vec<Vec<Point>> {
+    let is_explored = |x: i32, y: i32| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < side
+            && (y as usize) < side
+            && explored(x as usize, y as usize)
+    };
+    let mut edges: BTreeMap<Point, Vec<Point>> = BTreeMap::new();
+    let mut add_edge = |from: Point, to: Point| edges.entry(from).or_default().push(to);
+    for y in 0..side as i32 {
+        for x in 0..side as i32 {
+            if !is_explored(x, y) {
+                continue;
+            }
+            if !is_explored(x, y - 1) {
+                add_edge((x, y), (x + 1, y));
+            }
+            if !is_explored(x + 1, y) {
+                add_edge((x + 1, y), (x + 1, y + 1));
+            }
+            if !is_explored(x, y + 1) {
+                add_edge((x + 1, y + 1), (x, y + 1));
+            }
+            if !is_explored(x - 1, y) {
+                add_edge((x, y + 1), (x, y));
+            }
+        }
+    }
+
+    let mut rings = Vec::new();
+    while let Some((&start, _)) = edges.first_key_value() {
+        let mut ring = vec![start];
+        let mut from = start;
+        let mut to = edges.get_mut(&start).unwrap().pop().unwrap();
+        loop {
+            if edges.get(&from).is_some_and(Vec::is_empty) {
+                edges.remove(&from);
+            }
+            if to == start {
+                break;
+            }
+            ring.push(to);
+            let direction = sub(to, from);
+            let right = right_of(direction);
+            let left = (-right.0, -right.1);
+            let outgoing = edges.get_mut(&to).unwrap();
+            // prefer turning right so pixels touching at a corner are split
+            let next = [right, direction, left]
+                .iter()
+                .find_map(|turn| outgoing.iter().position(|x| sub(*x, to) == *turn))
+                .unwrap();
+            from = to;
+            to = outgoing.swap_remove(next);
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+/// Removes the vertices in the middle of straight lines.
+fn simplify_ring(ring: &[Point]) -> Vec<Point> {
+    let n = ring.len();
+    (0..n)
+        .filter(|i| {
+            let prev = ring[(i + n - 1) % n];
+            let next = ring[(i + 1) % n];
+            sub(ring[*i], prev) != sub(next, ring[*i])
+        })
+        .map(|i| ring[i])
+        .collect()
+}
+
+/// Groups rings into polygons, each is an exterior ring followed by its holes.
+fn group_polygons(rings: Vec<Vec<Point>>) -> Vec<Vec<Vec<Point>>> {
+    let (exteriors, holes): (Vec<_>, Vec<_>) =
+        rings.into_iter().partition(|ring| signed_area2(ring) > 0);
+    let mut polygons: Vec<Vec<Vec<Point>>> = exteriors.into_iter().map(|x| vec![x]).collect();
+    for hole in holes {
+        // the center of the explored pixel on the right of the first edge
+        let (dx, dy) = sub(hole[1], hole[0]);
+        let direction = (dx.signum(), dy.signum());
+        let right = right_of(direction);
+        let point = (
+            hole[0].0 as f64 + (direction.0 + right.0) as f64 * 0.5,
+            hole[0].1 as f64 + (direction.1 + right.1) as f64 * 0.5,
+        );
+        // with islands in holes, the innermost exterior is the smallest one
+        if let Some(polygon) = polygons
+            .iter_mut()
+            .filter(|polygon| contains(&polygon[0], point))
+            .min_by_key(|polygon| signed_area2(&polygon[0]))
+        {
+            polygon.push(hole);
+        }
+    }
+    polygons
+}
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+fn zigzag(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+fn encode_geometry(polygons: &[Vec<Vec<Point>>], scale: i32) -> Vec<u32> {
+    const MOVE_TO: u32 = 1;
+    const LINE_TO: u32 = 2;
+    const CLOSE_PATH: u32 = 7;
+    let mut geometry = Vec::new();
+    let mut cursor = (0, 0);
+    for ring in polygons.iter().flatten() {
+        for (i, point) in ring.iter().enumerate() {
+            match i {
+                0 => geometry.push(command(MOVE_TO, 1)),
+                1 => geometry.push(command(LINE_TO, ring.len() as u32 - 1)),
+                _ => (),
+            }
+            let point = (point.0 * scale, point.1 * scale);
+            let (dx, dy) = sub(point, cursor);
+            geometry.push(zigzag(dx));
+            geometry.push(zigzag(dy));
+            cursor = point;
+        }
+        geometry.push(command(CLOSE_PATH, 1));
+    }
+    geometry
+}
+
+/// Renders the view tile `(x, y)` at `zoom`, the explored area is traced at a
+/// resolution of `2^buffer_size_power` pixels per tile. `overview` is shown as
+/// well, see `MapRenderer::set_overviews`.
+pub fn render_vector_tile(
+    journey_bitmap: &mut JourneyBitmap,
+    overview: Option<&BlockOverview>,
+    x: i64,
+    y: i64,
+    zoom: i16,
+    buffer_size_power: i16,
+) -> Result<Vec<u8>, String> {
+    validate_xyz_tile(x, y, zoom, buffer_size_power)?;
+    let bitmap = TileShader2::render_tile_bitmap_with_overview(
+        journey_bitmap,
+        overview,
+        x,
+        y,
+        zoom,
+        buffer_size_power,
+    );
+    let side = bitmap.side();
+
+    let rings = trace_rings(side, |x, y| bitmap.get(x, y))
+        .iter()
+        .map(|ring| simplify_ring(ring))
+        .collect();
+    let polygons = group_polygons(rings);
+
+    let mut layer = tile::Layer::new();
+    layer.set_version(2);
+    layer.set_name(LAYER_NAME.to_string());
+    layer.set_extent(EXTENT);
+    if !polygons.is_empty() {
+        let mut feature = tile::Feature::new();
+        feature.set_id(1);
+        feature.set_type(tile::GeomType::POLYGON);
+        feature.geometry = encode_geometry(&polygons, (EXTENT as usize / side) as i32);
+        layer.features.push(feature);
+    }
+    let mut vector_tile = Tile::new();
+    vector_tile.layers.push(layer);
+    vector_tile
+        .write_to_bytes()
+        .map_err(|e| format!("Failed to encode vector tile: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(rows: &[&str]) -> Vec<Vec<Vec<Point>>> {
+        let rings = trace_rings(rows.len(), |x, y| rows[y].as_bytes()[x] == b'#')
+            .iter()
+            .map(|ring| simplify_ring(ring))
+            .collect();
+        group_polygons(rings)
+    }
+
+    #[test]
+    fn square_with_hole() {
+        let polygons = trace(&["####", "#..#", "####", "...."]);
+        assert_eq!(polygons.len(), 1);
+        let [exterior, hole] = &polygons[0][..] else {
+            panic!("expected an exterior ring and a hole");
+        };
+        assert_eq!(exterior.len(), 4);
+        assert_eq!(signed_area2(exterior), 2 * 12);
+        assert_eq!(hole.len(), 4);
+        assert_eq!(signed_area2(hole), -2 * 2);
+    }
+
+    #[test]
+    fn island_in_hole() {
+        let polygons = trace(&["#####", "#...#", "#.#.#", "#...#", "#####"]);
+        assert_eq!(polygons.len(), 2);
+        let mut areas: Vec<_> = polygons
+            .iter()
+            .map(|polygon| {
+                polygon
+                    .iter()
+                    .map(|ring| signed_area2(ring))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        areas.sort();
+        assert_eq!(areas, vec![vec![2], vec![50, -18]]);
+    }
+
+    #[test]
+    fn pixels_touching_at_a_corner() {
+        let polygons = trace(&["#.", ".#"]);
+        assert_eq!(polygons.len(), 2);
+        assert!(polygons.iter().all(|polygon| polygon.len() == 1));
+    }
+
+    #[test]
+    fn geometry_commands() {
+        let polygons = trace(&["#.", ".."]);
+        assert_eq!(
+            encode_geometry(&polygons, 2048),
+            vec![9, 0, 0, 26, 4096, 0, 0, 4096, 4095, 0, 15]
+        );
+    }
+}
@@ -3,13 +3,14 @@ use crate::achievement::AchievementReader;
 use crate::cache_db::{self, CacheDb, LayerKind};
 use crate::geo::{GeoIndex, GeoLookup};
 use crate::gps_processor::{self, ProcessResult};
-use crate::journey_bitmap::JourneyBitmap;
+use crate::journey_bitmap::{BlockOverview, JourneyBitmap};
 use crate::journey_header::JourneyKind;
 use crate::journey_snapshot::JourneySnapshot;
 use crate::main_db::{self, Action, MainDb};
+use crate::recording_journal::RecoveryReport;
 use anyhow::{Context, Ok, Result};
 use auto_context::auto_context;
-use chrono::{Local, NaiveDate};
+use chrono::{FixedOffset, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::fs::{remove_file, File};
 use std::path::{Path, PathBuf};
@@ -152,6 +153,7 @@ impl Storage {
         cache_dir: String,
     ) -> Result<Self> {
         let mut main_db = MainDb::open(&support_dir)?;
+        main_db.with_txn(|txn| txn.purge_trash())?;
         let cache_db: Box<dyn CacheDb + Send> = Box::new(cache_db::new(&cache_dir));
         let raw_data_recorder =
             if main_db.get_setting_with_default(crate::main_db::Setting::RawDataMode, false) {
@@ -242,6 +244,69 @@ impl Storage {
         raw_data_recorder.is_some()
     }
 
+    pub fn get_gap_filling(&self) -> bool {
+        let main_db = &mut self.dbs.lock().unwrap().main_db;
+        main_db.get_setting_with_default(crate::main_db::Setting::GapFilling, false)
+    }
+
+    pub fn set_gap_filling(&self, enable: bool) -> Result<()> {
+        let main_db = &mut self.dbs.lock().unwrap().main_db;
+        main_db.set_setting(crate::main_db::Setting::GapFilling, enable)
+    }
+
+    /// Returns `(max_revisions, max_age_in_days)`.
+    pub fn get_journey_history_policy(&self) -> (u32, u32) {
+        let main_db = &mut self.dbs.lock().unwrap().main_db;
+        (
+            main_db.get_setting_with_default(
+                crate::main_db::Setting::JourneyHistoryMaxRevisions,
+                crate::main_db::DEFAULT_JOURNEY_HISTORY_MAX_REVISIONS,
+            ),
+            main_db.get_setting_with_default(
+                crate::main_db::Setting::JourneyHistoryMaxAgeInDays,
+                crate::main_db::DEFAULT_JOURNEY_HISTORY_MAX_AGE_IN_DAYS,
+            ),
+        )
+    }
+
+    pub fn set_journey_history_policy(
+        &self,
+        max_revisions: u32,
+        max_age_in_days: u32,
+    ) -> Result<()> {
+        {
+            let main_db = &mut self.dbs.lock().unwrap().main_db;
+            main_db.set_setting(
+                crate::main_db::Setting::JourneyHistoryMaxRevisions,
+                max_revisions,
+            )?;
+            main_db.set_setting(
+                crate::main_db::Setting::JourneyHistoryMaxAgeInDays,
+                max_age_in_days,
+            )?;
+        }
+        self.with_db_txn(|txn| txn.prune_journey_history(None))
+    }
+
+    pub fn get_trash_retention_in_days(&self) -> u32 {
+        let main_db = &mut self.dbs.lock().unwrap().main_db;
+        main_db.get_setting_with_default(
+            crate::main_db::Setting::TrashRetentionInDays,
+            crate::main_db::DEFAULT_TRASH_RETENTION_IN_DAYS,
+        )
+    }
+
+    pub fn set_trash_retention_in_days(&self, retention_in_days: u32) -> Result<()> {
+        {
+            let main_db = &mut self.dbs.lock().unwrap().main_db;
+            main_db.set_setting(
+                crate::main_db::Setting::TrashRetentionInDays,
+                retention_in_days,
+            )?;
+        }
+        self.with_db_txn(|txn| txn.purge_trash())
+    }
+
     #[auto_context]
     pub fn delete_raw_data_file(&self, filename: String) -> Result<()> {
         let filename = if Path::new(&filename).extension().is_some() {
@@ -275,6 +340,7 @@ impl Storage {
         raw_data: &gps_processor::RawData,
         process_result: ProcessResult,
         received_timestamp_ms: i64,
+        utc_offset: Option<FixedOffset>,
     ) {
         let mut raw_data_recorder = self.raw_data_recorder.lock().unwrap();
         if let Some(ref mut x) = *raw_data_recorder {
@@ -283,7 +349,13 @@ impl Storage {
         drop(raw_data_recorder);
 
         let main_db = &mut self.dbs.lock().unwrap().main_db;
-        main_db.record(raw_data, process_result).unwrap();
+        main_db
+            .record(raw_data, process_result, utc_offset)
+            .unwrap();
+    }
+
+    pub fn recording_recovery_report(&self) -> RecoveryReport {
+        self.dbs.lock().unwrap().main_db.recovery_report().clone()
     }
 
     pub fn list_all_raw_data(&self) -> Result<Vec<RawDataFile>> {
@@ -426,6 +498,90 @@ impl Storage {
         })
     }
 
+    /// Same as `get_latest_bitmap_for_main_map_renderer`, but the finalized
+    /// base is the union of every journey kind accepted by `include_kind`.
+    #[auto_context]
+    pub fn get_latest_bitmap_for_main_map_renderer_by_kind<F>(
+        &self,
+        include_kind: F,
+        include_ongoing: bool,
+    ) -> Result<JourneyBitmap>
+    where
+        F: Fn(&JourneyKind) -> bool,
+    {
+        self.with_journey_snapshot(|snapshot| {
+            let mut bitmap = JourneyBitmap::new();
+            for kind in snapshot.journey_kinds()? {
+                if include_kind(&kind) {
+                    bitmap.merge(snapshot.finalized_bitmap(&LayerKind::JourneyKind(kind), None)?);
+                }
+            }
+            if include_ongoing {
+                if let Some(journey_vector) = snapshot.ongoing_journey()? {
+                    bitmap.merge_vector(&journey_vector);
+                }
+            }
+            Ok(bitmap)
+        })
+    }
+
+    /// Same as `get_latest_bitmap_for_main_map_renderer_by_kind`, but split
+    /// into the finalized bitmap of each kind and the ongoing journey (the
+    /// `None` entry, present whenever `include_ongoing`).
+    #[auto_context]
+    pub fn get_latest_layers_for_main_map_renderer<F>(
+        &self,
+        include_kind: F,
+        include_ongoing: bool,
+    ) -> Result<Vec<(Option<JourneyKind>, JourneyBitmap)>>
+    where
+        F: Fn(&JourneyKind) -> bool,
+    {
+        self.with_journey_snapshot(|snapshot| {
+            let mut layers = Vec::new();
+            if include_ongoing {
+                let mut bitmap = JourneyBitmap::new();
+                if let Some(journey_vector) = snapshot.ongoing_journey()? {
+                    bitmap.merge_vector(&journey_vector);
+                }
+                layers.push((None, bitmap));
+            }
+            for kind in snapshot.journey_kinds()? {
+                if include_kind(&kind) {
+                    let bitmap =
+                        snapshot.finalized_bitmap(&LayerKind::JourneyKind(kind.clone()), None)?;
+                    layers.push((Some(kind), bitmap));
+                }
+            }
+            Ok(layers)
+        })
+    }
+
+    /// Cheap placeholder for the finalized layers of
+    /// `get_latest_layers_for_main_map_renderer`: the cached overview of each
+    /// kind (empty if there is none yet).
+    #[auto_context]
+    pub fn get_overviews_for_main_map_renderer<F>(
+        &self,
+        include_kind: F,
+    ) -> Result<Vec<(JourneyKind, BlockOverview)>>
+    where
+        F: Fn(&JourneyKind) -> bool,
+    {
+        self.with_journey_snapshot(|snapshot| {
+            let mut overviews = Vec::new();
+            for kind in snapshot.journey_kinds()? {
+                if include_kind(&kind) {
+                    let overview = snapshot
+                        .finalized_overview(&LayerKind::JourneyKind(kind.clone()))?
+                        .unwrap_or_default();
+                    overviews.push((kind, overview));
+                }
+            }
+            Ok(overviews)
+        })
+    }
+
     /// Finalized coverage within `[from, to]`, optionally filtered to one
     /// journey kind (`None` → all kinds). Used by the time machine.
     #[auto_context]
@@ -436,7 +592,7 @@ impl Storage {
         kind: Option<&JourneyKind>,
     ) -> Result<JourneyBitmap> {
         let layer_kind = match kind {
-            Some(kind) => LayerKind::JourneyKind(*kind),
+            Some(kind) => LayerKind::JourneyKind(kind.clone()),
             None => LayerKind::All,
         };
         self.with_journey_snapshot(|snapshot| {
@@ -0,0 +1,329 @@
+// Deleting the parts of tracks inside a selection (a lasso polygon or a brush
+// corridor). Segments are split where they cross the selection boundary.
+//
+// Everything is computed on a local plane around the selection, in meters,
+// with longitudes unwrapped so that a selection crossing the antimeridian is
+// continuous.
+use crate::journey_vector::{TrackPoint, TrackSegment};
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+// in the local plane (meters)
+const EPS: f64 = 1e-6;
+
+type PlanePoint = (f64, f64);
+
+enum Shape {
+    Polygon(Vec<PlanePoint>),
+    // start, end and radius of each brush stroke
+    Corridor(Vec<(PlanePoint, PlanePoint, f64)>),
+}
+
+struct Selection {
+    lng_scale: f64,
+    center_lng: f64,
+    shape: Shape,
+}
+
+fn wrap_lng(lng: f64) -> f64 {
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Longitudes of `points`, unwrapped so every edge takes the shorter way.
+fn unwrapped_lngs(points: &[TrackPoint]) -> Vec<f64> {
+    let mut lngs: Vec<f64> = Vec::with_capacity(points.len());
+    for point in points {
+        let mut lng = wrap_lng(point.longitude);
+        if let Some(prev) = lngs.last() {
+            lng += ((prev - lng) / 360.0).round() * 360.0;
+        }
+        lngs.push(lng);
+    }
+    lngs
+}
+
+fn lerp(a: PlanePoint, b: PlanePoint, t: f64) -> PlanePoint {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn distance_sq_to_edge(p: PlanePoint, a: PlanePoint, b: PlanePoint) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (x, y) = lerp(a, b, t);
+    (x - p.0) * (x - p.0) + (y - p.1) * (y - p.1)
+}
+
+/// Parameter `t` on `a -> b` where it crosses `c -> d`, if it does.
+fn edge_crossing(a: PlanePoint, b: PlanePoint, c: PlanePoint, d: PlanePoint) -> Option<f64> {
+    let r = (b.0 - a.0, b.1 - a.1);
+    let s = (d.0 - c.0, d.1 - c.1);
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    if denominator.abs() < EPS * EPS {
+        return None;
+    }
+    let q = (c.0 - a.0, c.1 - a.1);
+    let t = (q.0 * s.1 - q.1 * s.0) / denominator;
+    let u = (q.0 * r.1 - q.1 * r.0) / denominator;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Parameters `t` on `a -> b` where it crosses the circle.
+fn circle_crossings(a: PlanePoint, b: PlanePoint, center: PlanePoint, radius: f64) -> Vec<f64> {
+    let d = (b.0 - a.0, b.1 - a.1);
+    let f = (a.0 - center.0, a.1 - center.1);
+    let qa = d.0 * d.0 + d.1 * d.1;
+    if qa == 0.0 {
+        return Vec::new();
+    }
+    let qb = 2.0 * (f.0 * d.0 + f.1 * d.1);
+    let qc = f.0 * f.0 + f.1 * f.1 - radius * radius;
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    let root = discriminant.sqrt();
+    [(-qb - root) / (2.0 * qa), (-qb + root) / (2.0 * qa)]
+        .into_iter()
+        .filter(|t| (0.0..=1.0).contains(t))
+        .collect()
+}
+
+impl Selection {
+    /// `shape` builds the shape from `points` projected to the local plane.
+    fn new<F>(points: &[TrackPoint], shape: F) -> Self
+    where
+        F: FnOnce(Vec<PlanePoint>) -> Shape,
+    {
+        let lngs = unwrapped_lngs(points);
+        let max_abs_lat = points
+            .iter()
+            .fold(0.0_f64, |max, point| max.max(point.latitude.abs()));
+        let min_lng = lngs.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max_lng = lngs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mut selection = Selection {
+            lng_scale: max_abs_lat.min(89.0).to_radians().cos(),
+            center_lng: (min_lng + max_lng) / 2.0,
+            shape: Shape::Polygon(Vec::new()),
+        };
+        let projected = lngs
+            .iter()
+            .zip(points)
+            .map(|(lng, point)| selection.project(*lng, point.latitude))
+            .collect();
+        selection.shape = shape(projected);
+        selection
+    }
+
+    fn polygon(polygon: &[TrackPoint]) -> Option<Self> {
+        if polygon.len() < 3 {
+            return None;
+        }
+        Some(Self::new(polygon, Shape::Polygon))
+    }
+
+    fn corridor(path: &[TrackPoint], width_in_m: f64) -> Option<Self> {
+        if path.is_empty() || width_in_m.is_nan() || width_in_m <= 0.0 {
+            return None;
+        }
+        let radius = width_in_m / 2.0;
+        Some(Self::new(path, |projected| {
+            Shape::Corridor(if projected.len() == 1 {
+                vec![(projected[0], projected[0], radius)]
+            } else {
+                projected
+                    .windows(2)
+                    .map(|edge| (edge[0], edge[1], radius))
+                    .collect()
+            })
+        }))
+    }
+
+    fn project(&self, lng: f64, lat: f64) -> PlanePoint {
+        (
+            lng * self.lng_scale * METERS_PER_DEGREE,
+            lat * METERS_PER_DEGREE,
+        )
+    }
+
+    fn unproject(&self, (x, y): PlanePoint) -> TrackPoint {
+        TrackPoint {
+            latitude: y / METERS_PER_DEGREE,
+            longitude: wrap_lng(x / self.lng_scale / METERS_PER_DEGREE),
+        }
+    }
+
+    /// Projects the track edge `a -> b` to the copy of the world closest to
+    /// the selection.
+    fn project_edge(&self, a: &TrackPoint, b: &TrackPoint) -> (PlanePoint, PlanePoint) {
+        let lngs = unwrapped_lngs(&[a.clone(), b.clone()]);
+        let shift = ((self.center_lng - (lngs[0] + lngs[1]) / 2.0) / 360.0).round() * 360.0;
+        (
+            self.project(lngs[0] + shift, a.latitude),
+            self.project(lngs[1] + shift, b.latitude),
+        )
+    }
+
+    fn contains(&self, p: PlanePoint) -> bool {
+        match &self.shape {
+            // even-odd rule
+            Shape::Polygon(polygon) => {
+                let mut inside = false;
+                let mut j = polygon.len() - 1;
+                for i in 0..polygon.len() {
+                    let (xi, yi) = polygon[i];
+                    let (xj, yj) = polygon[j];
+                    if (yi > p.1) != (yj > p.1) && p.0 < (xj - xi) * (p.1 - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+            Shape::Corridor(strokes) => strokes
+                .iter()
+                .any(|(c, d, radius)| distance_sq_to_edge(p, *c, *d) <= radius * radius),
+        }
+    }
+
+    /// Candidate parameters on `a -> b` where it may cross the selection
+    /// boundary. Extra candidates are fine, they are merged by `pieces`.
+    fn crossings(&self, a: PlanePoint, b: PlanePoint) -> Vec<f64> {
+        let mut crossings = Vec::new();
+        match &self.shape {
+            Shape::Polygon(polygon) => {
+                let mut j = polygon.len() - 1;
+                for i in 0..polygon.len() {
+                    crossings.extend(edge_crossing(a, b, polygon[j], polygon[i]));
+                    j = i;
+                }
+            }
+            Shape::Corridor(strokes) => {
+                for (c, d, radius) in strokes {
+                    crossings.extend(circle_crossings(a, b, *c, *radius));
+                    crossings.extend(circle_crossings(a, b, *d, *radius));
+                    let (dx, dy) = (d.0 - c.0, d.1 - c.1);
+                    let length = (dx * dx + dy * dy).sqrt();
+                    if length > 0.0 {
+                        let normal = (-dy / length * radius, dx / length * radius);
+                        for side in [1.0, -1.0] {
+                            let offset = (normal.0 * side, normal.1 * side);
+                            crossings.extend(edge_crossing(
+                                a,
+                                b,
+                                (c.0 + offset.0, c.1 + offset.1),
+                                (d.0 + offset.0, d.1 + offset.1),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        crossings
+    }
+
+    /// Splits `a -> b` into `(t_start, t_end, inside)` pieces, neighbouring
+    /// pieces are never both inside or both outside.
+    fn pieces(&self, a: PlanePoint, b: PlanePoint) -> Vec<(f64, f64, bool)> {
+        let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        if length < EPS {
+            return vec![(0.0, 1.0, self.contains(a))];
+        }
+        let mut ts = self.crossings(a, b);
+        ts.push(0.0);
+        ts.push(1.0);
+        ts.sort_by(|t1, t2| t1.total_cmp(t2));
+        ts.dedup_by(|t2, t1| (*t2 - *t1) * length < EPS);
+        if let Some(last) = ts.last_mut() {
+            *last = 1.0;
+        }
+
+        let mut pieces: Vec<(f64, f64, bool)> = Vec::new();
+        for window in ts.windows(2) {
+            let (t0, t1) = (window[0], window[1]);
+            let inside = self.contains(lerp(a, b, (t0 + t1) / 2.0));
+            match pieces.last_mut() {
+                Some(last) if last.2 == inside => last.1 = t1,
+                _ => pieces.push((t0, t1, inside)),
+            }
+        }
+        pieces
+    }
+
+    fn delete_from(&self, segments: &[TrackSegment]) -> Vec<TrackSegment> {
+        let mut new_segments: Vec<TrackSegment> = Vec::new();
+        for segment in segments {
+            let pts = &segment.track_points;
+            if pts.len() < 2 {
+                continue;
+            }
+
+            let mut current: Vec<TrackPoint> = Vec::new();
+            let mut finish = |current: &mut Vec<TrackPoint>| {
+                let track_points = std::mem::take(current);
+                if track_points.len() >= 2 {
+                    new_segments.push(TrackSegment {
+                        track_points,
+                        inferred: segment.inferred,
+                    });
+                }
+            };
+            for edge in pts.windows(2) {
+                let (a, b) = (&edge[0], &edge[1]);
+                let (pa, pb) = self.project_edge(a, b);
+                for (t0, t1, inside) in self.pieces(pa, pb) {
+                    if inside {
+                        finish(&mut current);
+                        continue;
+                    }
+                    let start = if t0 == 0.0 {
+                        a.clone()
+                    } else {
+                        self.unproject(lerp(pa, pb, t0))
+                    };
+                    let end = if t1 == 1.0 {
+                        b.clone()
+                    } else {
+                        self.unproject(lerp(pa, pb, t1))
+                    };
+                    if current.last() != Some(&start) {
+                        current.push(start);
+                    }
+                    if current.last() != Some(&end) {
+                        current.push(end);
+                    }
+                }
+            }
+            finish(&mut current);
+        }
+        new_segments
+    }
+}
+
+/// Removes the parts of `segments` inside `polygon` (even-odd rule). Edges of
+/// the polygon take the shorter way around the antimeridian.
+pub fn delete_in_polygon(segments: &[TrackSegment], polygon: &[TrackPoint]) -> Vec<TrackSegment> {
+    match Selection::polygon(polygon) {
+        Some(selection) => selection.delete_from(segments),
+        None => segments.to_vec(),
+    }
+}
+
+/// Removes the parts of `segments` within `width_in_m / 2` of `path`.
+pub fn delete_along_path(
+    segments: &[TrackSegment],
+    path: &[TrackPoint],
+    width_in_m: f64,
+) -> Vec<TrackSegment> {
+    match Selection::corridor(path, width_in_m) {
+        Some(selection) => selection.delete_from(segments),
+        None => segments.to_vec(),
+    }
+}
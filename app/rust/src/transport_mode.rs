@@ -0,0 +1,292 @@
+// Rough transport mode detection for raw GPS data.
+//
+// This is a rule based classifier: speed does most of the work, acceleration
+// is used to tell cycling from driving at similar speed, and altitude is used
+// to catch slow flights (e.g. takeoff / landing). Per point results are noisy,
+// so they are grouped into spans and spans that are too short are merged into
+// their neighbors.
+//
+// The main consumer is the flight detection: flight spans are handled by
+// `flight_track_processor` and affect the suggested `JourneyKind`.
+use std::ops::Range;
+
+use crate::{gps_processor::RawData, journey_header::JourneyKind};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TransportMode {
+    Walking,
+    Cycling,
+    Driving,
+    Train,
+    Flight,
+}
+
+impl TransportMode {
+    pub fn journey_kind(self) -> JourneyKind {
+        match self {
+            TransportMode::Flight => JourneyKind::Flight,
+            TransportMode::Walking
+            | TransportMode::Cycling
+            | TransportMode::Driving
+            | TransportMode::Train => JourneyKind::DefaultKind,
+        }
+    }
+}
+
+/// A range of the input data (`start` inclusive, `end` exclusive) that shares
+/// the same transport mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransportModeSpan {
+    pub mode: TransportMode,
+    pub start: usize,
+    pub end: usize,
+}
+
+// all speeds are in m/s
+const MAX_WALKING_SPEED: f64 = 2.5;
+const MAX_CYCLING_SPEED: f64 = 8.5;
+const MIN_CYCLING_ACCELERATION_FOR_DRIVING: f64 = 1.5;
+const MIN_TRAIN_SPEED: f64 = 42.0;
+const MIN_FLIGHT_SPEED: f64 = 100.0;
+const MIN_FLIGHT_SPEED_AT_HIGH_ALTITUDE: f64 = 40.0;
+const MIN_FLIGHT_ALTITUDE_IN_M: f32 = 3000.0;
+// Faster than any airliner, must be bad data.
+const MAX_PLAUSIBLE_SPEED: f64 = 350.0;
+
+const SPEED_SMOOTHING_RADIUS: usize = 3;
+
+const MIN_FLIGHT_SPAN_DURATION_MS: i64 = 5 * 60 * 1000;
+const MIN_SPAN_DURATION_MS: i64 = 2 * 60 * 1000;
+// used when we don't have timestamps
+const MIN_SPAN_NUM_OF_POINTS: usize = 10;
+
+fn duration_ms(raw_data: &[RawData], start: usize, end: usize) -> Option<i64> {
+    Some(raw_data[end - 1].timestamp_ms? - raw_data[start].timestamp_ms?)
+}
+
+// Use the speed reported by the device if there is one, otherwise estimate it
+// from the neighboring points.
+fn estimate_speeds(raw_data: &[RawData]) -> Vec<Option<f64>> {
+    (0..raw_data.len())
+        .map(|i| {
+            if let Some(speed) = raw_data[i].speed {
+                if speed >= 0.0 {
+                    return Some(speed as f64);
+                }
+            }
+            let prev = i.saturating_sub(1);
+            let next = (i + 1).min(raw_data.len() - 1);
+            let time_ms = duration_ms(raw_data, prev, next + 1)?;
+            if time_ms <= 0 {
+                return None;
+            }
+            let distance = raw_data[prev].point.haversine_distance(&raw_data[i].point)
+                + raw_data[i].point.haversine_distance(&raw_data[next].point);
+            Some(distance / (time_ms as f64 / 1000.0))
+        })
+        .map(|speed| speed.filter(|speed| *speed <= MAX_PLAUSIBLE_SPEED))
+        .collect()
+}
+
+fn median_smooth(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    (0..values.len())
+        .map(|i| {
+            values[i]?;
+            let start = i.saturating_sub(SPEED_SMOOTHING_RADIUS);
+            let end = (i + SPEED_SMOOTHING_RADIUS + 1).min(values.len());
+            let mut window: Vec<f64> = values[start..end].iter().flatten().copied().collect();
+            window.sort_by(f64::total_cmp);
+            Some(window[window.len() / 2])
+        })
+        .collect()
+}
+
+fn classify_point(speed: f64, acceleration: Option<f64>, altitude: Option<f32>) -> TransportMode {
+    let high_altitude = altitude.is_some_and(|altitude| altitude >= MIN_FLIGHT_ALTITUDE_IN_M);
+    if speed >= MIN_FLIGHT_SPEED || (high_altitude && speed >= MIN_FLIGHT_SPEED_AT_HIGH_ALTITUDE) {
+        TransportMode::Flight
+    } else if speed >= MIN_TRAIN_SPEED {
+        TransportMode::Train
+    } else if speed > MAX_CYCLING_SPEED {
+        TransportMode::Driving
+    } else if speed > MAX_WALKING_SPEED {
+        if acceleration.is_some_and(|a| a >= MIN_CYCLING_ACCELERATION_FOR_DRIVING) {
+            TransportMode::Driving
+        } else {
+            TransportMode::Cycling
+        }
+    } else {
+        TransportMode::Walking
+    }
+}
+
+fn classify_points(raw_data: &[RawData]) -> Vec<TransportMode> {
+    let speeds = median_smooth(&estimate_speeds(raw_data));
+    let modes: Vec<Option<TransportMode>> = (0..raw_data.len())
+        .map(|i| {
+            let speed = speeds[i]?;
+            let prev = i.saturating_sub(1);
+            let next = (i + 1).min(raw_data.len() - 1);
+            let acceleration = match (
+                speeds[prev],
+                speeds[next],
+                duration_ms(raw_data, prev, next + 1),
+            ) {
+                (Some(prev_speed), Some(next_speed), Some(time_ms)) if time_ms > 0 => {
+                    Some((next_speed - prev_speed).abs() / (time_ms as f64 / 1000.0))
+                }
+                _ => None,
+            };
+            Some(classify_point(speed, acceleration, raw_data[i].altitude))
+        })
+        .collect();
+
+    // Points we know nothing about follow the previous one (or the next one if
+    // they are at the beginning).
+    let first_known = modes.iter().flatten().next().copied();
+    let mut current = first_known.unwrap_or(TransportMode::Walking);
+    modes
+        .into_iter()
+        .map(|mode| {
+            if let Some(mode) = mode {
+                current = mode;
+            }
+            current
+        })
+        .collect()
+}
+
+fn is_too_short(raw_data: &[RawData], span: &TransportModeSpan) -> bool {
+    match duration_ms(raw_data, span.start, span.end) {
+        Some(duration_ms) => {
+            let min_duration_ms = match span.mode {
+                TransportMode::Flight => MIN_FLIGHT_SPAN_DURATION_MS,
+                _ => MIN_SPAN_DURATION_MS,
+            };
+            duration_ms < min_duration_ms
+        }
+        None => span.end - span.start < MIN_SPAN_NUM_OF_POINTS,
+    }
+}
+
+fn span_weight(raw_data: &[RawData], span: &TransportModeSpan) -> i64 {
+    duration_ms(raw_data, span.start, span.end).unwrap_or((span.end - span.start) as i64)
+}
+
+fn merge_same_mode_neighbors(spans: &mut Vec<TransportModeSpan>) {
+    spans.dedup_by(|next, prev| {
+        if next.mode == prev.mode {
+            prev.end = next.end;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Splits `raw_data` into spans covering all the input. The result is empty
+/// iff the input is empty.
+pub fn classify(raw_data: &[RawData]) -> Vec<TransportModeSpan> {
+    let mut spans: Vec<TransportModeSpan> = Vec::new();
+    for (i, mode) in classify_points(raw_data).into_iter().enumerate() {
+        spans.push(TransportModeSpan {
+            mode,
+            start: i,
+            end: i + 1,
+        });
+    }
+    merge_same_mode_neighbors(&mut spans);
+
+    // Absorb the shortest span that is too short into its bigger neighbor until
+    // every span is long enough.
+    while spans.len() > 1 {
+        let shortest = (0..spans.len())
+            .filter(|i| is_too_short(raw_data, &spans[*i]))
+            .min_by_key(|i| span_weight(raw_data, &spans[*i]));
+        let i = match shortest {
+            None => break,
+            Some(i) => i,
+        };
+        let span = spans.remove(i);
+        let merge_into_prev = if i == 0 {
+            false
+        } else if i == spans.len() {
+            true
+        } else {
+            span_weight(raw_data, &spans[i - 1]) >= span_weight(raw_data, &spans[i])
+        };
+        if merge_into_prev {
+            spans[i - 1].end = span.end;
+        } else {
+            spans[i].start = span.start;
+        }
+        merge_same_mode_neighbors(&mut spans);
+    }
+    spans
+}
+
+// `JourneyKind::Flight` if flights weigh more than half of `total_weight`.
+fn suggest_journey_kind_by_weight(flight_weight: i64, total_weight: i64) -> JourneyKind {
+    if total_weight > 0 && flight_weight * 2 > total_weight {
+        JourneyKind::Flight
+    } else {
+        JourneyKind::DefaultKind
+    }
+}
+
+/// `JourneyKind::Flight` if most of the journey (by time, or by number of
+/// points when there are no timestamps) is a flight.
+pub fn suggest_journey_kind(raw_data: &[Vec<RawData>]) -> JourneyKind {
+    let mut flight_weight = 0;
+    let mut total_weight = 0;
+    for segment in raw_data {
+        for span in classify(segment) {
+            let weight = span_weight(segment, &span);
+            if span.mode == TransportMode::Flight {
+                flight_weight += weight;
+            }
+            total_weight += weight;
+        }
+    }
+    suggest_journey_kind_by_weight(flight_weight, total_weight)
+}
+
+/// Groups the result of `classify` by `JourneyKind`, along with
+/// `suggest_journey_kind` of `raw_data` so it is only classified once.
+pub fn classify_journey_kind(
+    raw_data: &[RawData],
+) -> (Vec<(JourneyKind, Range<usize>)>, JourneyKind) {
+    let mut result: Vec<(JourneyKind, Range<usize>)> = Vec::new();
+    let mut flight_weight = 0;
+    let mut total_weight = 0;
+    for span in classify(raw_data) {
+        let weight = span_weight(raw_data, &span);
+        if span.mode == TransportMode::Flight {
+            flight_weight += weight;
+        }
+        total_weight += weight;
+        let kind = span.mode.journey_kind();
+        match result.last_mut() {
+            Some((last_kind, range)) if *last_kind == kind => range.end = span.end,
+            _ => result.push((kind, span.start..span.end)),
+        }
+    }
+    (
+        result,
+        suggest_journey_kind_by_weight(flight_weight, total_weight),
+    )
+}
+
+/// Splits every segment of `raw_data` into parts of different `JourneyKind`,
+/// in their original order.
+pub fn split_by_journey_kind(raw_data: &[Vec<RawData>]) -> Vec<(JourneyKind, Vec<RawData>)> {
+    raw_data
+        .iter()
+        .flat_map(|segment| {
+            classify_journey_kind(segment)
+                .0
+                .into_iter()
+                .map(|(kind, range)| (kind, segment[range].to_vec()))
+        })
+        .collect()
+}
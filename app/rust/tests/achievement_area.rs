@@ -98,7 +98,11 @@ fn explored_areas_match_direct_fold() {
         // Map result equals the direct fold for every layer.
         let map = explored_areas(&storage, &layers);
         for layer in layers {
-            assert_eq!(map[&layer], oracle_area(&storage, layer), "layer {layer:?}");
+            assert_eq!(
+                map[&layer],
+                oracle_area(&storage, layer.clone()),
+                "layer {layer:?}"
+            );
         }
 
         // Non-empty; `All` is a superset of each component layer.
@@ -37,7 +37,7 @@ fn import_gpx_as_journey(path: &str) {
         .collect();
     assert!(!points.is_empty(), "no timestamped points in {path}");
     for p in &points {
-        api::on_location_update(p.clone(), p.timestamp_ms.unwrap());
+        api::on_location_update(p.clone(), p.timestamp_ms.unwrap(), None).unwrap();
     }
     assert!(api::finalize_ongoing_journey().unwrap(), "finalize {path}");
 }
@@ -95,7 +95,7 @@ fn api_achievement_explored_area_and_region_contract() {
     // Both entry points return the same numbers under one read.
     for l in [Default, Flight, All] {
         assert_eq!(
-            get_explored_area(l).unwrap(),
+            get_explored_area(l.clone()).unwrap(),
             a[&l],
             "two APIs disagree {l:?}"
         );
@@ -26,7 +26,7 @@ fn add_vector_journeys(main_db: &mut MainDb) {
                 .unwrap();
         }
         main_db
-            .record(raw_data, gps_processor::ProcessResult::Append)
+            .record(raw_data, gps_processor::ProcessResult::Append, None)
             .unwrap();
     }
     main_db
@@ -77,10 +77,13 @@ fn sample_journey() -> (JourneyHeader, JourneyData) {
         updated_at: Some(ts(2)),
         start: Some(ts(3)),
         end: Some(ts(4)),
+        start_utc_offset_sec: Some(8 * 3600),
+        end_utc_offset_sec: Some(9 * 3600),
         journey_type: JourneyType::Vector,
         journey_kind: JourneyKind::DefaultKind,
         note: Some("test note".to_owned()),
         postprocessor_algo: None,
+        tags: vec!["test tag".to_owned()],
     };
     let data = JourneyData::Vector(JourneyVector {
         track_segments: vec![TrackSegment {
@@ -94,6 +97,7 @@ fn sample_journey() -> (JourneyHeader, JourneyData) {
                     longitude: 121.4747,
                 },
             ],
+            inferred: false,
         }],
     });
     (header, data)
@@ -224,6 +228,41 @@ fn archive_and_import() {
     assert_eq!(all_journeys_before, all_journeys(&mut main_db));
 }
 
+#[test]
+fn archive_and_import_custom_journey_kind() {
+    let temp_dir = TempDir::new("archive-custom_journey_kind").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+
+    let (header, data) = sample_journey();
+    let journey_kind = JourneyKind::Custom("Cycling".to_string());
+    main_db
+        .with_txn(|txn| {
+            txn.create_and_insert_journey(
+                header.journey_date,
+                header.start,
+                header.end,
+                None,
+                journey_kind.clone(),
+                None,
+                data,
+            )
+        })
+        .unwrap();
+
+    let all_journeys_before = all_journeys(&mut main_db);
+    let mut buf = Cursor::new(Vec::new());
+    main_db
+        .with_txn(|txn| archive::export_all_journeys_as_mldx(txn, &mut buf, SectionVersion::V2))
+        .unwrap();
+    main_db.with_txn(|txn| txn.delete_all_journeys()).unwrap();
+
+    let mut reader = MldxReader::open(Cursor::new(buf.into_inner())).unwrap();
+    main_db.with_txn(|txn| reader.import(txn, None)).unwrap();
+    let all_journeys_after = all_journeys(&mut main_db);
+    assert_eq!(all_journeys_before, all_journeys_after);
+    assert_eq!(all_journeys_after[0].0.journey_kind, journey_kind);
+}
+
 #[test]
 fn delete_all_journeys() {
     let temp_dir = TempDir::new("archive-delete_all_journeys").unwrap();
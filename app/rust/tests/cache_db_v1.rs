@@ -12,9 +12,10 @@ use geo_data_format::{
 };
 use memolanes_core::{
     achievement::layer::AchievementLayer,
-    cache_db::{CacheDb, CacheDbV1},
+    cache_db::{CacheDb, CacheDbV1, CacheEntry, LayerKind},
     geo::GeoIndex,
     journey_bitmap::{Block, BlockKey, JourneyBitmap, TileKey},
+    journey_data::{serialize_journey_bitmap, JourneyData},
     journey_header::JourneyKind,
     main_db::MainDb,
     utils::db::{run_migrations, set_version_in_metadata, SchemaVersion},
@@ -169,3 +170,94 @@ fn achievement_reader_without_geo_has_no_regions() {
         })
         .unwrap();
 }
+
+#[test]
+fn overview_follows_full_cache_and_survives_invalidation() {
+    let (mut main_db, mut cache_db, _main_dir, _cache_dir) = setup("cache_db_v1-overview");
+    let default = LayerKind::JourneyKind(JourneyKind::DefaultKind);
+    assert!(cache_db.get_overview(&default).unwrap().is_none());
+
+    let mut full = main_db
+        .with_txn(|txn| cache_db.get_or_compute(txn, &default, None))
+        .unwrap();
+    assert_eq!(
+        cache_db.get_overview(&default).unwrap(),
+        Some(full.block_overview())
+    );
+
+    let entry = CacheEntry {
+        date: date("2025-02-01"),
+        kind: JourneyKind::DefaultKind,
+    };
+    let new_data = one_block(TileKey::new(1, 0), BlockKey::from_x_y(0, 0), 1);
+    cache_db
+        .merge_journey(&entry, &JourneyData::Bitmap(new_data.clone()), None)
+        .unwrap();
+    full.merge(new_data);
+    assert_eq!(
+        cache_db.get_overview(&default).unwrap(),
+        Some(full.block_overview())
+    );
+
+    // stale but still served
+    cache_db.invalidate(&[entry]).unwrap();
+    assert_eq!(
+        cache_db.get_overview(&default).unwrap(),
+        Some(full.block_overview())
+    );
+
+    cache_db.clear_all().unwrap();
+    assert!(cache_db.get_overview(&default).unwrap().is_none());
+}
+
+#[test]
+fn overview_is_backfilled_from_full_caches_of_older_versions() {
+    let cache_dir = TempDir::new("cache-db-overview-backfill").unwrap();
+    let mut full = one_block(TileKey::new(0, 0), BlockKey::from_x_y(3, 4), 25);
+    let mut data = Vec::new();
+    serialize_journey_bitmap(&mut full, &mut data).unwrap();
+
+    let mut conn = Connection::open(cache_dir.path().join("cache.db")).unwrap();
+    let tx = conn.transaction().unwrap();
+    run_migrations(&tx, "cache.db", &[]).unwrap();
+    set_version_in_metadata(&tx, SchemaVersion::new(1, 0)).unwrap();
+    tx.execute(
+        "CREATE TABLE journey_cache__full (
+            kind TEXT PRIMARY KEY NOT NULL UNIQUE,
+            data BLOB NOT NULL
+        )",
+        (),
+    )
+    .unwrap();
+    tx.execute(
+        "INSERT INTO journey_cache__full (kind, data) VALUES ('Default', ?1)",
+        (&data,),
+    )
+    .unwrap();
+    tx.commit().unwrap();
+    drop(conn);
+
+    let mut cache_db = CacheDbV1::open(cache_dir.path().to_str().unwrap());
+    assert_eq!(
+        cache_db
+            .get_overview(&LayerKind::JourneyKind(JourneyKind::DefaultKind))
+            .unwrap(),
+        Some(full.block_overview())
+    );
+}
+
+#[test]
+fn recomputing_all_drops_unused_custom_kinds() {
+    let (mut main_db, mut cache_db, _main_dir, _cache_dir) = setup("cache_db_v1-unused-kinds");
+    let gone = LayerKind::JourneyKind(JourneyKind::Custom("Gone".to_string()));
+
+    main_db
+        .with_txn(|txn| cache_db.get_or_compute(txn, &gone, None))
+        .unwrap();
+    assert!(cache_db.get_overview(&gone).unwrap().is_some());
+
+    main_db
+        .with_txn(|txn| cache_db.get_or_compute(txn, &LayerKind::All, None))
+        .unwrap();
+    assert!(cache_db.get_overview(&gone).unwrap().is_none());
+}
@@ -0,0 +1,167 @@
+use memolanes_core::api::edit_session::{AddLinesOutcome, EditSession};
+use memolanes_core::journey_bitmap::JourneyBitmap;
+use memolanes_core::journey_data::JourneyData;
+use memolanes_core::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+
+fn horizontal_track(start_lng: f64, end_lng: f64, lat: f64) -> TrackSegment {
+    TrackSegment {
+        track_points: (0..=10)
+            .map(|i| TrackPoint {
+                latitude: lat,
+                longitude: start_lng + (end_lng - start_lng) * i as f64 / 10.0,
+            })
+            .collect(),
+        inferred: false,
+    }
+}
+
+fn vector_session() -> EditSession {
+    EditSession::new_with_data(
+        "id".to_string(),
+        "revision".to_string(),
+        JourneyData::Vector(JourneyVector {
+            track_segments: vec![
+                horizontal_track(0.0, 1.0, 0.0),
+                horizontal_track(0.0, 1.0, 1.0),
+            ],
+        }),
+    )
+}
+
+fn bitmap_session() -> EditSession {
+    let mut bitmap = JourneyBitmap::new();
+    bitmap.add_line(0.0, 0.0, 1.0, 0.0);
+    EditSession::new_with_data(
+        "id".to_string(),
+        "revision".to_string(),
+        JourneyData::Bitmap(bitmap),
+    )
+}
+
+fn track_segments(session: &EditSession) -> Vec<TrackSegment> {
+    match session.journey_data() {
+        JourneyData::Vector(vector) => vector.track_segments,
+        JourneyData::Bitmap(_) => panic!("not a vector journey"),
+    }
+}
+
+#[test]
+fn vector_undo_and_redo() {
+    let mut session = vector_session();
+    let original = session.journey_data();
+    assert!(!session.can_undo());
+    assert!(!session.can_redo());
+
+    // cuts the first track only
+    session
+        .delete_in_polygon(&[(-0.1, 0.4), (-0.1, 0.6), (0.1, 0.6), (0.1, 0.4)])
+        .unwrap();
+    let edited = session.journey_data();
+    assert_eq!(track_segments(&session).len(), 3);
+    assert_eq!(track_segments(&session)[2], horizontal_track(0.0, 1.0, 1.0));
+    assert!(session.can_undo());
+
+    session.undo().unwrap();
+    assert_eq!(session.journey_data(), original);
+    assert!(!session.can_undo());
+    assert!(session.can_redo());
+
+    session.redo().unwrap();
+    assert_eq!(session.journey_data(), edited);
+    assert!(!session.can_redo());
+
+    // a new edit drops what could be redone
+    session.undo().unwrap();
+    session.delete_along_path(&[(1.0, 0.5)], 2000.0).unwrap();
+    assert_eq!(track_segments(&session).len(), 3);
+    assert_eq!(track_segments(&session)[0], horizontal_track(0.0, 1.0, 0.0));
+    assert!(!session.can_redo());
+
+    // nothing to delete, no history
+    session
+        .delete_points_in_box(10.0, 10.0, 11.0, 11.0)
+        .unwrap();
+    session.undo().unwrap();
+    assert_eq!(session.journey_data(), original);
+}
+
+#[test]
+fn vector_pending_changes() {
+    let mut session = vector_session();
+    let (removed, added) = session.pending_changes();
+    assert!(removed.is_empty() && added.is_empty());
+
+    session.delete_points_in_box(-0.1, 0.4, 0.1, 0.6).unwrap();
+    assert!(matches!(
+        session.add_lines(&[(2.0, 0.0), (2.0, 1.0)], false).unwrap(),
+        AddLinesOutcome::Added
+    ));
+    let (removed, added) = session.pending_changes();
+    let mut removed_expected = JourneyBitmap::new();
+    removed_expected.add_line(0.45, 0.0, 0.55, 0.0);
+    let mut removed_part = removed.clone();
+    removed_part.intersection(&removed_expected);
+    assert_eq!(removed_part, removed_expected);
+    let mut added_expected = JourneyBitmap::new();
+    added_expected.add_line(0.0, 2.0, 1.0, 2.0);
+    assert_eq!(added, added_expected);
+
+    session.undo().unwrap();
+    session.undo().unwrap();
+    let (removed, added) = session.pending_changes();
+    assert!(removed.is_empty() && added.is_empty());
+}
+
+#[test]
+fn bitmap_editing() {
+    let mut session = bitmap_session();
+    let original = session.journey_data();
+
+    session.delete_points_in_box(-0.1, 0.4, 0.1, 0.6).unwrap();
+    let (removed, added) = session.pending_changes();
+    assert!(!removed.is_empty());
+    assert!(added.is_empty());
+    let JourneyData::Bitmap(mut bitmap) = session.journey_data() else {
+        panic!("not a bitmap journey");
+    };
+    let mut erased = JourneyBitmap::new();
+    erased.add_line(0.45, 0.0, 0.55, 0.0);
+    erased.intersection(&bitmap);
+    assert!(erased.is_empty());
+    bitmap.merge(removed);
+    assert_eq!(JourneyData::Bitmap(bitmap), original);
+
+    assert!(matches!(
+        session.add_lines(&[(1.0, 0.0), (1.0, 1.0)], false).unwrap(),
+        AddLinesOutcome::Added
+    ));
+    // snapping needs tracks
+    assert!(matches!(
+        session.add_lines(&[(2.0, 0.0), (2.0, 1.0)], true).unwrap(),
+        AddLinesOutcome::LinkedDrawNeedsMultipleTracks
+    ));
+    let (_, added) = session.pending_changes();
+    let mut line = JourneyBitmap::new();
+    line.add_line(0.0, 1.0, 1.0, 1.0);
+    assert_eq!(added, line);
+
+    session.delete_along_path(&[(1.0, 0.5)], 2000.0).unwrap();
+    session.undo().unwrap();
+    session.undo().unwrap();
+    session.undo().unwrap();
+    assert_eq!(session.journey_data(), original);
+    assert!(!session.can_undo());
+
+    session.redo().unwrap();
+    session.redo().unwrap();
+    session.redo().unwrap();
+    assert!(!session.can_redo());
+    let (removed, added) = session.pending_changes();
+    assert!(!removed.is_empty());
+    let mut line = JourneyBitmap::new();
+    line.add_line(0.0, 1.0, 0.49, 1.0);
+    line.add_line(0.51, 1.0, 1.0, 1.0);
+    let mut added_part = added.clone();
+    added_part.intersection(&line);
+    assert_eq!(added_part, line);
+}
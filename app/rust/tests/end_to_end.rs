@@ -31,7 +31,7 @@ fn basic() {
 
     assert!(!api::has_ongoing_journey().unwrap());
     for (i, raw_data) in first_elements.iter().enumerate() {
-        api::on_location_update(raw_data.clone(), raw_data.timestamp_ms.unwrap());
+        api::on_location_update(raw_data.clone(), raw_data.timestamp_ms.unwrap(), None).unwrap();
         if i == 1000 {
             assert!(api::has_ongoing_journey().unwrap());
             assert!(api::finalize_ongoing_journey().unwrap());
@@ -59,7 +59,7 @@ fn basic() {
     assert!(!api::finalize_ongoing_journey().unwrap());
 
     for raw_data in remaining_elements {
-        api::on_location_update(raw_data.clone(), raw_data.timestamp_ms.unwrap());
+        api::on_location_update(raw_data.clone(), raw_data.timestamp_ms.unwrap(), None).unwrap();
     }
 
     {
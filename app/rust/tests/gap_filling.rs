@@ -0,0 +1,122 @@
+use memolanes_core::gap_filling;
+use memolanes_core::gps_processor::{self, PreprocessedData, ProcessResult};
+use memolanes_core::journey_data::{deserialize_journey_vector, serialize_journey_vector};
+use memolanes_core::journey_vector::{JourneyVector, TrackPoint};
+
+fn data(
+    timestamp_sec: Option<i64>,
+    longitude: f64,
+    process_result: ProcessResult,
+) -> PreprocessedData {
+    PreprocessedData {
+        timestamp_sec,
+        track_point: TrackPoint {
+            latitude: 30.0,
+            longitude,
+        },
+        process_result,
+    }
+}
+
+// Walk east, lose the signal for `gap_sec` while moving `gap_in_degree`, then
+// walk again.
+fn track_with_gap(gap_sec: Option<i64>, gap_in_degree: f64) -> Vec<PreprocessedData> {
+    let mut result = Vec::new();
+    for i in 0..10 {
+        result.push(data(
+            Some(i),
+            120.0 + i as f64 * 0.00001,
+            ProcessResult::Append,
+        ));
+    }
+    let start = 120.0 + 9.0 * 0.00001 + gap_in_degree;
+    for i in 0..10 {
+        let process_result = if i == 0 {
+            ProcessResult::NewSegment
+        } else {
+            ProcessResult::Append
+        };
+        result.push(data(
+            gap_sec.map(|gap_sec| 9 + gap_sec + i),
+            start + i as f64 * 0.00001,
+            process_result,
+        ));
+    }
+    result
+}
+
+fn build_and_fill(data: &[PreprocessedData]) -> JourneyVector {
+    let journey_vector = gps_processor::build_journey_vector(data.iter().cloned().map(Ok), None)
+        .unwrap()
+        .unwrap();
+    gap_filling::fill_gaps(data, journey_vector)
+}
+
+#[test]
+fn fill_subway_ride() {
+    // ~4.8km in 5 minutes
+    let data = track_with_gap(Some(300), 0.05);
+    let journey_vector = build_and_fill(&data);
+    assert_eq!(
+        journey_vector
+            .track_segments
+            .iter()
+            .map(|track_segment| track_segment.inferred)
+            .collect::<Vec<_>>(),
+        vec![false, true, false]
+    );
+    let inferred = &journey_vector.track_segments[1].track_points;
+    assert!(inferred.len() > 2);
+    assert_eq!(
+        inferred.first(),
+        journey_vector.track_segments[0].track_points.last()
+    );
+    assert_eq!(
+        inferred.last(),
+        journey_vector.track_segments[2].track_points.first()
+    );
+}
+
+#[test]
+fn keep_implausible_gaps() {
+    let segment_count = |data: &[PreprocessedData]| build_and_fill(data).track_segments.len();
+    // too slow: ~480m in 2 hours
+    assert_eq!(segment_count(&track_with_gap(Some(7200), 0.005)), 2);
+    // too fast: ~48km in 1 minute
+    assert_eq!(segment_count(&track_with_gap(Some(60), 0.5)), 2);
+    // too long
+    assert_eq!(segment_count(&track_with_gap(Some(6 * 3600), 2.0)), 2);
+    // no timestamps
+    assert_eq!(segment_count(&track_with_gap(None, 0.05)), 2);
+}
+
+#[test]
+fn ignored_data_does_not_count() {
+    let mut track = track_with_gap(Some(300), 0.05);
+    track.insert(5, data(Some(4), 121.0, ProcessResult::Ignore));
+    track.insert(0, data(Some(0), 121.0, ProcessResult::Ignore));
+    assert_eq!(build_and_fill(&track).track_segments.len(), 3);
+}
+
+#[test]
+fn serialization_keeps_inferred_flag() {
+    let roundtrip = |journey_vector: &JourneyVector| {
+        let mut buf = Vec::new();
+        serialize_journey_vector(journey_vector, &mut buf).unwrap();
+        (
+            buf[0..2].to_vec(),
+            deserialize_journey_vector(&buf[..]).unwrap(),
+        )
+    };
+
+    let journey_vector = build_and_fill(&track_with_gap(Some(300), 0.05));
+    let (magic_header, result) = roundtrip(&journey_vector);
+    assert_eq!(magic_header, b"V1");
+    assert_eq!(result, journey_vector);
+
+    // stays in the old format without inferred segments
+    let journey_vector = build_and_fill(&track_with_gap(None, 0.05));
+    let (magic_header, result) = roundtrip(&journey_vector);
+    assert_eq!(magic_header, b"V0");
+    assert_eq!(result, journey_vector);
+}
@@ -0,0 +1,42 @@
+pub mod test_utils;
+use memolanes_core::journey_bitmap::JourneyBitmap;
+use memolanes_core::{import_data, renderer::MapRenderer, utils};
+use test_utils::golden_utils::{render_tile_range_image, verify_golden_image};
+
+const ZOOMS: [i16; 4] = [3, 7, 11, 15];
+const BUFFER_SIZE_POWER: i16 = 8;
+
+// renders 3x3 tiles around (`lng`, `lat`) at every zoom in `ZOOMS`
+fn verify_fixture(name: &str, journey_bitmap: JourneyBitmap, lng: f64, lat: f64) {
+    let mut map_renderer = MapRenderer::new(journey_bitmap);
+    for zoom in ZOOMS {
+        let (x, y) = utils::lng_lat_to_tile_x_y(lng, lat, zoom as i32);
+        let image = render_tile_range_image(
+            &mut map_renderer,
+            x as i64 - 1,
+            y as i64 - 1,
+            zoom,
+            3,
+            3,
+            BUFFER_SIZE_POWER,
+        );
+        verify_golden_image(&format!("{name}_z{zoom:02}"), &image);
+    }
+}
+
+#[test]
+fn fow_sync_data() {
+    let (journey_bitmap, _) =
+        import_data::fow::load_fow_sync_data("./tests/data/fow_3.zip").unwrap();
+    // Shenzhen universiade
+    verify_fixture("fow_sync_data", journey_bitmap, 114.212470, 22.697006);
+}
+
+#[test]
+fn fow_snapshot() {
+    let (journey_bitmap, _) =
+        import_data::fow::load_fow_snapshot_data("./tests/data/Snapshot-20260601T232045+0800.fwss")
+            .unwrap();
+    // Chenzhou, along the Beijing-Guangzhou railway
+    verify_fixture("fow_snapshot", journey_bitmap, 113.224, 25.344);
+}
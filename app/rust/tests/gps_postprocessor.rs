@@ -0,0 +1,160 @@
+pub mod test_utils;
+
+use memolanes_core::gps_processor::{GpsPostprocessor, SegmentGapRule};
+use memolanes_core::import_data;
+use memolanes_core::journey_bitmap::{JourneyBitmap, BITMAP_WIDTH_OFFSET, TILE_WIDTH_OFFSET};
+use memolanes_core::journey_data::JourneyData;
+use memolanes_core::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+use std::collections::HashSet;
+
+fn load_journey_vector(name: &str) -> JourneyVector {
+    let (raw_data, _preprocessor) =
+        import_data::gpx::load_gpx(&format!("./tests/data/raw_gps_{name}.gpx")).unwrap();
+    import_data::conversion::journey_vector_from_raw_data_with_gps_preprocessor(
+        &raw_data,
+        Some(SegmentGapRule::Default),
+    )
+    .unwrap()
+}
+
+fn num_of_points(journey_vector: &JourneyVector) -> usize {
+    journey_vector
+        .track_segments
+        .iter()
+        .map(|segment| segment.track_points.len())
+        .sum()
+}
+
+fn serialized_size(journey_vector: &JourneyVector) -> usize {
+    let mut buf = Vec::new();
+    JourneyData::Vector(journey_vector.clone())
+        .serialize(&mut buf)
+        .unwrap();
+    buf.len()
+}
+
+// All visited pixels in the global pixel coordinate.
+fn visited_pixels(journey_vector: &JourneyVector) -> HashSet<(i64, i64)> {
+    let mut bitmap = JourneyBitmap::new();
+    bitmap.merge_vector(journey_vector);
+    let tile_keys: Vec<_> = bitmap.all_tile_keys().cloned().collect();
+    let mut pixels = HashSet::new();
+    for tile_key in tile_keys {
+        let tile = bitmap.get_tile(&tile_key).unwrap();
+        for (block_key, block) in tile.iter() {
+            for x in 0..(1 << BITMAP_WIDTH_OFFSET) {
+                for y in 0..(1 << BITMAP_WIDTH_OFFSET) {
+                    if block.is_visited(x, y) {
+                        let to_global = |tile: u16, block: u8, pixel: u8| {
+                            ((tile as i64) << (TILE_WIDTH_OFFSET + BITMAP_WIDTH_OFFSET))
+                                + ((block as i64) << BITMAP_WIDTH_OFFSET)
+                                + pixel as i64
+                        };
+                        pixels.insert((
+                            to_global(tile_key.x, block_key.x(), x),
+                            to_global(tile_key.y, block_key.y(), y),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+// Number of pixels in `a` that are more than one pixel away from `b`.
+fn num_of_pixels_not_nearby(a: &HashSet<(i64, i64)>, b: &HashSet<(i64, i64)>) -> usize {
+    a.iter()
+        .filter(|(x, y)| !(-1..=1).any(|dx| (-1..=1).any(|dy| b.contains(&(x + dx, y + dy)))))
+        .count()
+}
+
+fn run_though_test_data(name: &str) {
+    let original = load_journey_vector(name);
+    let processed = GpsPostprocessor::process(original.clone());
+
+    let (original_size, processed_size) = (serialized_size(&original), serialized_size(&processed));
+    println!(
+        "{name}: points {} -> {}, size {original_size} -> {processed_size}",
+        num_of_points(&original),
+        num_of_points(&processed)
+    );
+    assert_eq!(
+        original.track_segments.len(),
+        processed.track_segments.len()
+    );
+    assert!(processed_size * 2 < original_size);
+
+    // Pixels may move by one because of rasterization, but the coverage
+    // should stay the same.
+    let original_pixels = visited_pixels(&original);
+    let processed_pixels = visited_pixels(&processed);
+    assert_eq!(
+        num_of_pixels_not_nearby(&original_pixels, &processed_pixels),
+        0
+    );
+    assert_eq!(
+        num_of_pixels_not_nearby(&processed_pixels, &original_pixels),
+        0
+    );
+}
+
+#[test]
+fn run_though_test_data_shanghai() {
+    run_though_test_data("shanghai");
+}
+
+#[test]
+fn run_though_test_data_shenzhen_stationary() {
+    run_though_test_data("shenzhen_stationary");
+}
+
+#[test]
+fn run_though_test_data_laojunshan() {
+    run_though_test_data("laojunshan");
+}
+
+#[test]
+fn simplify_across_antimeridian() {
+    let track_points: Vec<TrackPoint> = (0..=20)
+        .map(|i| TrackPoint {
+            latitude: 10.0,
+            longitude: (179.99 + i as f64 * 0.001 + 180.0).rem_euclid(360.0) - 180.0,
+        })
+        .collect();
+    let journey_vector = JourneyVector {
+        track_segments: vec![TrackSegment {
+            track_points: track_points.clone(),
+            inferred: false,
+        }],
+    };
+    let processed = GpsPostprocessor::process(journey_vector.clone());
+    assert_eq!(
+        processed.track_segments[0].track_points,
+        vec![track_points[0].clone(), track_points[20].clone()]
+    );
+    assert_eq!(visited_pixels(&journey_vector), visited_pixels(&processed));
+}
+
+#[test]
+fn keep_short_segments() {
+    let journey_vector = JourneyVector {
+        track_segments: vec![TrackSegment {
+            track_points: vec![
+                TrackPoint {
+                    latitude: 31.2304,
+                    longitude: 121.4737,
+                },
+                TrackPoint {
+                    latitude: 31.2314,
+                    longitude: 121.4747,
+                },
+            ],
+            inferred: false,
+        }],
+    };
+    assert_eq!(
+        GpsPostprocessor::process(journey_vector.clone()),
+        journey_vector
+    );
+}
@@ -2,13 +2,15 @@ pub mod test_utils;
 use crate::test_utils::{
     draw_line1, draw_line2, draw_line3, draw_line4, END_LAT, END_LNG, START_LAT, START_LNG,
 };
+use journey_kernel::tile_range::TILE_RANGE_LAYER_DEFAULT;
 use memolanes_core::{
     gps_processor::SegmentGapRule,
     import_data, journey_area_utils,
-    journey_bitmap::{Block, BlockKey, JourneyBitmap, Tile, TileKey, MAP_WIDTH},
+    journey_bitmap::{Block, BlockKey, BlockOverview, JourneyBitmap, Tile, TileKey, MAP_WIDTH},
     journey_data::JourneyData,
     journey_header::JourneyType,
-    renderer::MapRenderer,
+    journey_vector::TrackPoint,
+    renderer::{map_renderer::MapLayer, MapRenderer},
 };
 
 #[test]
@@ -202,6 +204,79 @@ fn intersection_and_difference_produce_empty() {
     }
 }
 
+fn horizontal_line(start_lng: f64, end_lng: f64, lat: f64) -> JourneyBitmap {
+    let mut bitmap = JourneyBitmap::new();
+    bitmap.add_line(start_lng, lat, end_lng, lat);
+    bitmap
+}
+
+fn contains(bitmap: &JourneyBitmap, other: &JourneyBitmap) -> bool {
+    let mut other = other.clone();
+    other.difference(bitmap);
+    other.is_empty()
+}
+
+fn overlaps(bitmap: &JourneyBitmap, other: &JourneyBitmap) -> bool {
+    let mut other = other.clone();
+    other.intersection(bitmap);
+    !other.is_empty()
+}
+
+#[test]
+fn erase_rect() {
+    let mut bitmap = horizontal_line(0.0, 1.0, 10.0);
+    assert!(bitmap.erase_rect(0.6, 11.0, 0.4, 9.0));
+    assert!(!overlaps(&bitmap, &horizontal_line(0.41, 0.59, 10.0)));
+    assert!(contains(&bitmap, &horizontal_line(0.0, 0.39, 10.0)));
+    assert!(contains(&bitmap, &horizontal_line(0.61, 1.0, 10.0)));
+
+    // nothing left to erase
+    assert!(!bitmap.erase_rect(0.6, 11.0, 0.4, 9.0));
+    assert!(!bitmap.erase_rect(0.0, 20.0, 1.0, 30.0));
+}
+
+#[test]
+fn erase_polygon_cross_antimeridian() {
+    let mut bitmap = horizontal_line(179.5, -179.5, 0.0);
+    let polygon: Vec<TrackPoint> = [(179.8, -1.0), (-179.8, -1.0), (-179.8, 1.0), (179.8, 1.0)]
+        .iter()
+        .map(|(longitude, latitude)| TrackPoint {
+            latitude: *latitude,
+            longitude: *longitude,
+        })
+        .collect();
+    assert!(bitmap.erase_polygon(&polygon));
+    assert!(!overlaps(&bitmap, &horizontal_line(179.81, -179.81, 0.0)));
+    assert!(contains(&bitmap, &horizontal_line(179.5, 179.79, 0.0)));
+    assert!(contains(&bitmap, &horizontal_line(-179.79, -179.5, 0.0)));
+
+    // degenerated polygon
+    assert!(!bitmap.erase_polygon(&polygon[..2]));
+}
+
+#[test]
+fn erase_along_path() {
+    let mut bitmap = horizontal_line(0.0, 1.0, 0.0);
+    let path = [
+        TrackPoint {
+            latitude: -0.1,
+            longitude: 0.5,
+        },
+        TrackPoint {
+            latitude: 0.1,
+            longitude: 0.5,
+        },
+    ];
+    // about 0.009 degree on each side
+    assert!(bitmap.erase_along_path(&path, 2000.0));
+    assert!(!overlaps(&bitmap, &horizontal_line(0.495, 0.505, 0.0)));
+    assert!(contains(&bitmap, &horizontal_line(0.0, 0.48, 0.0)));
+    assert!(contains(&bitmap, &horizontal_line(0.52, 1.0, 0.0)));
+
+    assert!(!bitmap.erase_along_path(&path, 2000.0));
+    assert!(!bitmap.erase_along_path(&path, 0.0));
+}
+
 #[test]
 fn serialization() {
     let mut journey_bitmap = JourneyBitmap::new();
@@ -432,3 +507,40 @@ fn validate_prunes_empty_blocks_and_tiles() {
     assert!(keep_tile.get(&empty_block_key).is_none());
     assert!(keep_tile.get(&non_empty_block_key).is_some());
 }
+
+#[test]
+fn block_overview_renders_like_the_full_bitmap_at_low_zoom() {
+    let mut journey_bitmap = JourneyBitmap::new();
+    draw_line1(&mut journey_bitmap);
+    draw_line2(&mut journey_bitmap);
+    let overview = journey_bitmap.block_overview();
+    assert_eq!(overview.tile_count(), journey_bitmap.tile_count());
+    assert_eq!(
+        BlockOverview::deserialize(&overview.serialize().unwrap()).unwrap(),
+        overview
+    );
+
+    let mut full_renderer = MapRenderer::new(journey_bitmap);
+    let mut overview_renderer = MapRenderer::new_with_layers(vec![MapLayer {
+        id: TILE_RANGE_LAYER_DEFAULT,
+        name: "default".to_string(),
+        journey_bitmap: JourneyBitmap::new(),
+    }]);
+    overview_renderer.set_overviews(vec![(TILE_RANGE_LAYER_DEFAULT, overview)]);
+    let render = |map_renderer: &mut MapRenderer, z: i16| {
+        let (x, y) = memolanes_core::utils::lng_lat_to_tile_x_y(START_LNG, START_LAT, z as i32);
+        map_renderer
+            .get_tile_range_response(x as i64 - 1, y as i64 - 1, z, 3, 3, 8)
+            .unwrap()
+    };
+    for z in [2, 5, 8] {
+        assert_eq!(
+            render(&mut full_renderer, z),
+            render(&mut overview_renderer, z)
+        );
+    }
+    assert_ne!(
+        render(&mut full_renderer, 12),
+        render(&mut overview_renderer, 12)
+    );
+}
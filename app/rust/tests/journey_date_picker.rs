@@ -0,0 +1,65 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use memolanes_core::{journey_date_picker::JourneyDatePicker, journey_vector::TrackPoint};
+
+fn hours(h: i32) -> Option<FixedOffset> {
+    FixedOffset::east_opt(h * 3600)
+}
+
+fn point(longitude: f64) -> TrackPoint {
+    TrackPoint {
+        latitude: 30.0,
+        longitude,
+    }
+}
+
+#[test]
+fn date_is_in_the_utc_offset_of_each_point() {
+    // 2024-01-01 22:00 UTC
+    let time = DateTime::from_timestamp(1704146400, 0).unwrap();
+
+    let mut journey_date_picker = JourneyDatePicker::new();
+    journey_date_picker.add_point(time, hours(9), &point(120.0));
+    journey_date_picker.add_point(time, hours(9), &point(120.1));
+    assert_eq!(
+        journey_date_picker.pick_journey_date(),
+        NaiveDate::from_ymd_opt(2024, 1, 2)
+    );
+
+    let mut journey_date_picker = JourneyDatePicker::new();
+    journey_date_picker.add_point(time, hours(-5), &point(-75.0));
+    journey_date_picker.add_point(time, hours(-5), &point(-75.1));
+    assert_eq!(
+        journey_date_picker.pick_journey_date(),
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+    );
+}
+
+#[test]
+fn pick_the_bigger_day_across_timezones() {
+    // 2024-01-01 20:00 UTC, a short walk in UTC+8 (2024-01-02 04:00)
+    let start = DateTime::from_timestamp(1704139200, 0).unwrap();
+    let mut journey_date_picker = JourneyDatePicker::new();
+    journey_date_picker.add_point(start, hours(8), &point(120.0));
+    journey_date_picker.add_point(start, hours(8), &point(120.001));
+    // then a long flight landing in UTC-8, still 2024-01-01 locally
+    let end = DateTime::from_timestamp(1704171600, 0).unwrap();
+    journey_date_picker.add_point(end, hours(-8), &point(-122.0));
+    journey_date_picker.add_point(end, hours(-8), &point(-121.0));
+
+    assert_eq!(
+        journey_date_picker.pick_journey_date(),
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+    );
+    assert_eq!(journey_date_picker.min_time(), Some(start));
+    assert_eq!(journey_date_picker.max_time(), Some(end));
+    assert_eq!(journey_date_picker.min_time_utc_offset(), hours(8));
+    assert_eq!(journey_date_picker.max_time_utc_offset(), hours(-8));
+}
+
+#[test]
+fn no_point() {
+    let journey_date_picker = JourneyDatePicker::new();
+    assert_eq!(journey_date_picker.pick_journey_date(), None);
+    assert_eq!(journey_date_picker.min_time(), None);
+    assert_eq!(journey_date_picker.min_time_utc_offset(), None);
+}
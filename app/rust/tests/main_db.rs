@@ -1,16 +1,22 @@
 pub mod test_utils;
 
-use chrono::{DateTime, Datelike, NaiveDate};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate};
 use memolanes_core::{
     gps_processor::{self, Point, RawData},
     import_data,
+    journey_bitmap::JourneyBitmap,
     journey_data::JourneyData,
-    journey_header::JourneyKind,
-    journey_vector::JourneyVector,
-    main_db::{self, Action, CacheEntry, MainDb},
-    utils::db::{run_migrations, set_version_in_metadata, DbError, SchemaVersion},
+    journey_header::{JourneyHeader, JourneyKind, JourneyType},
+    journey_vector::{JourneyVector, TrackPoint, TrackSegment},
+    main_db::{self, Action, CacheEntry, JourneySearchQuery, MainDb, SearchArea, SplitPosition},
+    recording_journal::RecoveryReport,
+    utils::{
+        db::{run_migrations, set_version_in_metadata, DbError, SchemaVersion},
+        MapBounds,
+    },
 };
 use rusqlite::Connection;
+use std::io::Write;
 use tempdir::TempDir;
 
 #[test]
@@ -64,7 +70,7 @@ fn basic() {
             main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
         }
         main_db
-            .record(raw_data, gps_processor::ProcessResult::Append)
+            .record(raw_data, gps_processor::ProcessResult::Append, None)
             .unwrap();
     }
     main_db
@@ -86,7 +92,9 @@ fn basic() {
         JourneyData::Bitmap(_) => panic!("invalid"),
     };
     let num_of_gpx_data = journey_vector.track_segments[0].track_points.len();
-    assert_eq!(num_of_gpx_data_in_input, num_of_gpx_data);
+    // the postprocessor simplifies the track
+    assert!(num_of_gpx_data > 0);
+    assert!(num_of_gpx_data < num_of_gpx_data_in_input);
 
     // benefit from zstd
     let mut rough_raw_size: usize = 0;
@@ -166,6 +174,7 @@ fn get_ongoing_journey_timestamp_range() {
                 speed: None,
             },
             gps_processor::ProcessResult::Append,
+            None,
         )
         .unwrap();
     main_db
@@ -181,6 +190,7 @@ fn get_ongoing_journey_timestamp_range() {
                 speed: None,
             },
             gps_processor::ProcessResult::Append,
+            None,
         )
         .unwrap();
     main_db
@@ -196,6 +206,7 @@ fn get_ongoing_journey_timestamp_range() {
                 speed: None,
             },
             gps_processor::ProcessResult::Append,
+            None,
         )
         .unwrap();
     let result = main_db
@@ -1293,6 +1304,7 @@ fn finalize_ongoing_sets_merge_one() {
                 speed: None,
             },
             gps_processor::ProcessResult::Append,
+            None,
         )
         .unwrap();
     main_db
@@ -1308,6 +1320,7 @@ fn finalize_ongoing_sets_merge_one() {
                 speed: None,
             },
             gps_processor::ProcessResult::Append,
+            None,
         )
         .unwrap();
 
@@ -1326,6 +1339,200 @@ fn finalize_ongoing_sets_merge_one() {
     );
 }
 
+#[test]
+fn finalize_with_utc_offsets() {
+    let temp_dir = TempDir::new("main_db-finalize_with_utc_offsets").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+
+    // 2023-10-15 00:00 UTC, which is still 2023-10-14 in UTC-10 / UTC-9
+    for i in 0..4 {
+        main_db
+            .record(
+                &gps_processor::RawData {
+                    point: Point {
+                        latitude: 21.3 + (i % 2) as f64 * 0.01,
+                        longitude: -157.8 + i as f64 * 0.01,
+                    },
+                    timestamp_ms: Some(1697328000000 + i * 600_000),
+                    accuracy: None,
+                    altitude: None,
+                    speed: None,
+                },
+                gps_processor::ProcessResult::Append,
+                FixedOffset::east_opt(if i < 2 { -10 * 3600 } else { -9 * 3600 }),
+            )
+            .unwrap();
+    }
+
+    main_db
+        .with_txn(|txn| {
+            assert!(txn.finalize_ongoing_journey()?);
+            let journeys = txn.query_journeys(None, None)?;
+            assert_eq!(journeys.len(), 1);
+            let header = &journeys[0];
+            assert_eq!(header.journey_date, date("2023-10-14"));
+            assert_eq!(header.start_utc_offset_sec, Some(-10 * 3600));
+            assert_eq!(header.end_utc_offset_sec, Some(-9 * 3600));
+
+            let (first_id, second_id) =
+                txn.split_journey(&header.id, SplitPosition::PointIndex(1))?;
+            let first = txn.get_journey_header(&first_id)?.unwrap();
+            let second = txn.get_journey_header(&second_id)?.unwrap();
+            assert_eq!(first.journey_date, date("2023-10-14"));
+            assert_eq!(first.start_utc_offset_sec, Some(-10 * 3600));
+            assert_eq!(second.end_utc_offset_sec, Some(-9 * 3600));
+            assert_eq!(first.end_utc_offset_sec, second.start_utc_offset_sec);
+
+            let merged_id = txn.merge_journeys(&[first_id, second_id])?;
+            let merged = txn.get_journey_header(&merged_id)?.unwrap();
+            assert_eq!(merged.journey_date, date("2023-10-14"));
+            assert_eq!(merged.start_utc_offset_sec, Some(-10 * 3600));
+            assert_eq!(merged.end_utc_offset_sec, Some(-9 * 3600));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn recording_journal_recovery() {
+    let temp_dir = TempDir::new("main_db-recording_journal_recovery").unwrap();
+    let support_dir = temp_dir.path().to_str().unwrap();
+    let record = |main_db: &mut MainDb, i: i64| {
+        main_db
+            .record(
+                &gps_processor::RawData {
+                    point: Point {
+                        latitude: 30.0 + i as f64 * 0.00001,
+                        longitude: 120.0,
+                    },
+                    timestamp_ms: Some(1697349115000 + i * 1000),
+                    accuracy: None,
+                    altitude: Some(10.5),
+                    speed: None,
+                },
+                gps_processor::ProcessResult::Append,
+                FixedOffset::east_opt(8 * 3600),
+            )
+            .unwrap();
+    };
+    let num_of_ongoing_points = |main_db: &mut MainDb| {
+        main_db
+            .with_txn(|txn| txn.get_ongoing_journey(None))
+            .unwrap()
+            .map_or(0, |journey_vector| {
+                journey_vector
+                    .track_segments
+                    .iter()
+                    .map(|x| x.track_points.len())
+                    .sum()
+            })
+    };
+
+    // the app is killed before the points are committed, some of them are
+    // committed as a batch already
+    let mut main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(*main_db.recovery_report(), RecoveryReport::default());
+    for i in 0..40 {
+        record(&mut main_db, i);
+    }
+    drop(main_db);
+
+    let mut main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(
+        *main_db.recovery_report(),
+        RecoveryReport {
+            recovered_points: 8,
+            last_timestamp: DateTime::from_timestamp(1697349115 + 39, 0),
+            finalize_interrupted: false,
+        }
+    );
+    assert_eq!(num_of_ongoing_points(&mut main_db), 40);
+    drop(main_db);
+
+    // a partially written entry is ignored
+    let journal_path = temp_dir.path().join("ongoing_journey.journal");
+    let mut main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(*main_db.recovery_report(), RecoveryReport::default());
+    record(&mut main_db, 40);
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&journal_path)
+        .unwrap()
+        .write_all(b"P\t100\t16973")
+        .unwrap();
+    drop(main_db);
+    let mut main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(main_db.recovery_report().recovered_points, 1);
+    assert_eq!(num_of_ongoing_points(&mut main_db), 41);
+    drop(main_db);
+
+    // killed while finalizing
+    std::fs::write(&journal_path, "F\n").unwrap();
+    let mut main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(
+        *main_db.recovery_report(),
+        RecoveryReport {
+            recovered_points: 0,
+            last_timestamp: None,
+            finalize_interrupted: true,
+        }
+    );
+    assert_eq!(num_of_ongoing_points(&mut main_db), 41);
+    assert!(main_db
+        .with_txn(|txn| txn.finalize_ongoing_journey())
+        .unwrap());
+    assert_eq!(std::fs::read(&journal_path).unwrap(), b"");
+    drop(main_db);
+    let main_db = MainDb::open(support_dir).unwrap();
+    assert_eq!(*main_db.recovery_report(), RecoveryReport::default());
+}
+
+#[test]
+fn finalize_ongoing_detects_flight() {
+    let temp_dir = TempDir::new("main_db-finalize_detects_flight").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+
+    // ~230m/s at cruising altitude for an hour
+    for i in 0..120 {
+        main_db
+            .record(
+                &gps_processor::RawData {
+                    point: Point {
+                        latitude: 30.0,
+                        longitude: 100.0 + i as f64 * 0.07,
+                    },
+                    timestamp_ms: Some(1697349115000 + i * 30_000),
+                    accuracy: None,
+                    altitude: Some(10000.0),
+                    speed: Some(230.0),
+                },
+                gps_processor::ProcessResult::Append,
+                None,
+            )
+            .unwrap();
+    }
+
+    // already processed as a flight while recording, i.e. interpolated
+    let ongoing = main_db
+        .with_txn(|txn| txn.get_ongoing_journey(None))
+        .unwrap()
+        .unwrap();
+    let num_of_ongoing_points: usize = ongoing
+        .track_segments
+        .iter()
+        .map(|x| x.track_points.len())
+        .sum();
+    assert!(num_of_ongoing_points > 120);
+    let journey_headers = main_db
+        .with_txn(|txn| {
+            assert!(txn.finalize_ongoing_journey()?);
+            txn.query_journeys(None, None)
+        })
+        .unwrap();
+    assert_eq!(journey_headers.len(), 1);
+    assert_eq!(journey_headers[0].journey_kind, JourneyKind::Flight);
+}
+
 // === Theme D: Insert dedup precision ===
 
 /// MergeOne promises exactly one fresh insert, so a cache may apply it as a
@@ -1434,3 +1641,1059 @@ fn insert_same_date_different_kind_not_deduplicated() {
         other => panic!("Expected Invalidate with 2 entries, got {:?}", other),
     }
 }
+
+#[test]
+fn journey_kinds_follow_edits_trash_and_restore() {
+    let temp_dir = TempDir::new("main_db-journey_kinds_follow_edits").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let cycling = JourneyKind::Custom("Cycling".to_string());
+    let hiking = JourneyKind::Custom("Hiking".to_string());
+    let kinds = |main_db: &mut MainDb| main_db.with_txn(|txn| txn.journey_kinds()).unwrap();
+    let built_in_and = |custom: &[&JourneyKind]| {
+        let mut kinds = JourneyKind::BUILT_IN.to_vec();
+        kinds.extend(custom.iter().map(|kind| (*kind).clone()));
+        kinds
+    };
+
+    let bitmap = test_utils::make_bitmap_with_line(test_utils::draw_line1);
+    let id = main_db
+        .with_txn(|txn| {
+            Ok(test_utils::insert_bitmap_journey(
+                txn,
+                date("2024-03-15"),
+                hiking.clone(),
+                bitmap.clone(),
+            ))
+        })
+        .unwrap();
+    assert_eq!(kinds(&mut main_db), built_in_and(&[&hiking]));
+
+    main_db
+        .with_txn(|txn| {
+            txn.update_journey_metadata(&id, date("2024-03-15"), None, None, None, cycling.clone())
+        })
+        .unwrap();
+    assert_eq!(kinds(&mut main_db), built_in_and(&[&cycling]));
+
+    main_db.with_txn(|txn| txn.trash_journey(&id)).unwrap();
+    assert_eq!(kinds(&mut main_db), built_in_and(&[]));
+
+    main_db
+        .with_txn(|txn| txn.restore_journey_from_trash(&id))
+        .unwrap();
+    assert_eq!(kinds(&mut main_db), built_in_and(&[&cycling]));
+}
+
+#[test]
+fn custom_journey_kind() {
+    let temp_dir = TempDir::new("main_db-custom_journey_kind").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let cycling = JourneyKind::Custom("Cycling".to_string());
+
+    assert_eq!(
+        main_db.with_txn(|txn| txn.journey_kinds()).unwrap(),
+        vec![JourneyKind::DefaultKind, JourneyKind::Flight]
+    );
+
+    let bitmap = test_utils::make_bitmap_with_line(test_utils::draw_line1);
+    let id = main_db
+        .with_txn(|txn| {
+            Ok(test_utils::insert_bitmap_journey(
+                txn,
+                date("2024-03-15"),
+                cycling.clone(),
+                bitmap.clone(),
+            ))
+        })
+        .unwrap();
+    let header = main_db
+        .with_txn(|txn| txn.get_journey_header(&id))
+        .unwrap()
+        .unwrap();
+    assert_eq!(header.journey_kind, cycling);
+    assert_eq!(
+        main_db.with_txn(|txn| txn.journey_kinds()).unwrap(),
+        vec![
+            JourneyKind::DefaultKind,
+            JourneyKind::Flight,
+            cycling.clone()
+        ]
+    );
+
+    // invalid names
+    for name in ["", "  ", " Cycling"] {
+        let kind = JourneyKind::Custom(name.to_string());
+        assert!(main_db
+            .with_txn(|txn| txn.create_and_insert_journey(
+                date("2024-03-15"),
+                None,
+                None,
+                None,
+                kind.clone(),
+                None,
+                JourneyData::Bitmap(bitmap.clone()),
+            ))
+            .is_err());
+        assert!(main_db
+            .with_txn(|txn| txn.update_journey_metadata(
+                &id,
+                date("2024-03-15"),
+                None,
+                None,
+                None,
+                kind.clone(),
+            ))
+            .is_err());
+    }
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.get_journey_header(&id))
+            .unwrap()
+            .unwrap()
+            .journey_kind,
+        cycling
+    );
+}
+
+#[test]
+fn finalize_ongoing_fills_gaps_when_enabled() {
+    let temp_dir = TempDir::new("main_db-finalize_fills_gaps").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+
+    let record = |main_db: &mut MainDb| {
+        // walking, then ~4.8km in 5 minutes without signal, then walking again
+        for (i, (offset_sec, longitude)) in (0..10)
+            .map(|i| (i, 120.0 + i as f64 * 0.00001))
+            .chain((0..10).map(|i| (309 + i, 120.05 + i as f64 * 0.00001)))
+            .enumerate()
+        {
+            main_db
+                .record(
+                    &gps_processor::RawData {
+                        point: Point {
+                            latitude: 30.0,
+                            longitude,
+                        },
+                        timestamp_ms: Some(1697349115000 + offset_sec * 1000),
+                        accuracy: None,
+                        altitude: None,
+                        speed: None,
+                    },
+                    if i == 10 {
+                        gps_processor::ProcessResult::NewSegment
+                    } else {
+                        gps_processor::ProcessResult::Append
+                    },
+                    None,
+                )
+                .unwrap();
+        }
+    };
+    let finalize_and_get_inferred_flags = |main_db: &mut MainDb| {
+        main_db
+            .with_txn(|txn| {
+                assert!(txn.finalize_ongoing_journey()?);
+                let id = txn.query_journeys(None, None)?[0].id.clone();
+                let journey_data = txn.get_journey_data(&id)?;
+                txn.delete_journey(&id)?;
+                match journey_data {
+                    JourneyData::Vector(journey_vector) => Ok(journey_vector
+                        .track_segments
+                        .iter()
+                        .map(|track_segment| track_segment.inferred)
+                        .collect::<Vec<_>>()),
+                    JourneyData::Bitmap(_) => panic!("unexpected bitmap"),
+                }
+            })
+            .unwrap()
+    };
+
+    record(&mut main_db);
+    assert_eq!(
+        finalize_and_get_inferred_flags(&mut main_db),
+        vec![false, false]
+    );
+
+    main_db
+        .set_setting(main_db::Setting::GapFilling, true)
+        .unwrap();
+    record(&mut main_db);
+    assert_eq!(
+        finalize_and_get_inferred_flags(&mut main_db),
+        vec![false, true, false]
+    );
+}
+
+// Two segments of 5 points each, from 12:00 to 13:00 (UTC).
+fn insert_vector_journey_for_split(txn: &mut main_db::Txn, id: &str) -> JourneyVector {
+    let segment = |start: f64| TrackSegment {
+        track_points: (0..5)
+            .map(|i| TrackPoint {
+                latitude: 30.0,
+                longitude: start + i as f64 * 0.001,
+            })
+            .collect(),
+        inferred: false,
+    };
+    let journey_vector = JourneyVector {
+        track_segments: vec![segment(120.0), segment(120.005)],
+    };
+    txn.insert_journey(
+        JourneyHeader {
+            id: id.to_string(),
+            revision: "rev".to_string(),
+            journey_date: date("2024-03-15"),
+            created_at: DateTime::from_timestamp(1710504000, 0).unwrap(),
+            updated_at: None,
+            start: DateTime::from_timestamp(1710504000, 0),
+            end: DateTime::from_timestamp(1710507600, 0),
+            start_utc_offset_sec: None,
+            end_utc_offset_sec: None,
+            journey_type: JourneyType::Vector,
+            journey_kind: JourneyKind::DefaultKind,
+            note: Some("note".to_string()),
+            postprocessor_algo: Some("1".to_string()),
+            tags: Vec::new(),
+        },
+        JourneyData::Vector(journey_vector.clone()),
+    )
+    .unwrap();
+    journey_vector
+}
+
+fn get_journey(txn: &main_db::Txn, id: &str) -> (JourneyHeader, JourneyData) {
+    (
+        txn.get_journey_header(id).unwrap().unwrap(),
+        txn.get_journey_data(id).unwrap(),
+    )
+}
+
+#[test]
+fn split_and_merge_journey() {
+    let temp_dir = TempDir::new("main_db-split_and_merge_journey").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let journey_vector = main_db
+        .with_txn(|txn| Ok(insert_vector_journey_for_split(txn, "a")))
+        .unwrap();
+
+    let (first_id, second_id) = main_db
+        .with_txn(|txn| {
+            let ids = txn.split_journey("a", SplitPosition::SegmentBoundary(1))?;
+            match &txn.action {
+                Some(Action::Invalidate { entries }) => assert!(entries.contains(&CacheEntry {
+                    date: date("2024-03-15"),
+                    kind: JourneyKind::DefaultKind,
+                })),
+                other => panic!("Expected Invalidate, got {:?}", other),
+            }
+            Ok(ids)
+        })
+        .unwrap();
+    assert_eq!(first_id, "a");
+
+    let ((first_header, first_data), (second_header, second_data)) = main_db
+        .with_txn(|txn| Ok((get_journey(txn, &first_id), get_journey(txn, &second_id))))
+        .unwrap();
+    assert_eq!(
+        first_data,
+        JourneyData::Vector(JourneyVector {
+            track_segments: vec![journey_vector.track_segments[0].clone()],
+        })
+    );
+    assert_eq!(
+        second_data,
+        JourneyData::Vector(JourneyVector {
+            track_segments: vec![journey_vector.track_segments[1].clone()],
+        })
+    );
+    assert_eq!(first_header.start, DateTime::from_timestamp(1710504000, 0));
+    assert_eq!(second_header.end, DateTime::from_timestamp(1710507600, 0));
+    assert_eq!(first_header.end, second_header.start);
+    let split_time = first_header.end.unwrap().timestamp();
+    assert!(1710504000 < split_time && split_time < 1710507600);
+    for header in [&first_header, &second_header] {
+        assert_eq!(header.journey_date, date("2024-03-15"));
+        assert_eq!(header.note, Some("note".to_string()));
+        assert_eq!(header.postprocessor_algo, Some("1".to_string()));
+    }
+
+    // merging them back gives the original journey
+    let merged_id = main_db
+        .with_txn(|txn| txn.merge_journeys(&[second_id.clone(), first_id.clone()]))
+        .unwrap();
+    assert_eq!(merged_id, "a");
+    let (merged_header, merged_data) = main_db
+        .with_txn(|txn| {
+            assert_eq!(txn.get_journey_header(&second_id)?, None);
+            Ok(get_journey(txn, &merged_id))
+        })
+        .unwrap();
+    assert_eq!(merged_data, JourneyData::Vector(journey_vector));
+    assert_eq!(merged_header.start, DateTime::from_timestamp(1710504000, 0));
+    assert_eq!(merged_header.end, DateTime::from_timestamp(1710507600, 0));
+    assert_eq!(merged_header.journey_date, date("2024-03-15"));
+    assert_eq!(merged_header.note, Some("note\nnote".to_string()));
+}
+
+#[test]
+fn split_journey_positions() {
+    let temp_dir = TempDir::new("main_db-split_journey_positions").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let num_of_points = |journey_data: &JourneyData| match journey_data {
+        JourneyData::Vector(journey_vector) => journey_vector
+            .track_segments
+            .iter()
+            .map(|track_segment| track_segment.track_points.len())
+            .collect::<Vec<_>>(),
+        JourneyData::Bitmap(_) => panic!("unexpected bitmap"),
+    };
+    let split = |main_db: &mut MainDb, at| {
+        main_db.with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "a");
+            let (first_id, second_id) = txn.split_journey("a", at)?;
+            let result = (
+                num_of_points(&txn.get_journey_data(&first_id)?),
+                num_of_points(&txn.get_journey_data(&second_id)?),
+            );
+            txn.delete_all_journeys()?;
+            Ok(result)
+        })
+    };
+
+    // the split point is shared within a segment
+    assert_eq!(
+        split(&mut main_db, SplitPosition::PointIndex(2)).unwrap(),
+        (vec![3], vec![3, 5])
+    );
+    assert_eq!(
+        split(&mut main_db, SplitPosition::PointIndex(4)).unwrap(),
+        (vec![5], vec![5])
+    );
+    assert_eq!(
+        split(&mut main_db, SplitPosition::PointIndex(5)).unwrap(),
+        (vec![5], vec![5])
+    );
+    // the middle of the journey in time is the middle in distance
+    assert_eq!(
+        split(
+            &mut main_db,
+            SplitPosition::Timestamp(DateTime::from_timestamp(1710505800, 0).unwrap())
+        )
+        .unwrap(),
+        (vec![5], vec![5])
+    );
+
+    for at in [
+        SplitPosition::PointIndex(0),
+        SplitPosition::PointIndex(10),
+        SplitPosition::SegmentBoundary(0),
+        SplitPosition::SegmentBoundary(2),
+        SplitPosition::Timestamp(DateTime::from_timestamp(1710507600, 0).unwrap()),
+    ] {
+        assert!(split(&mut main_db, at).is_err());
+    }
+
+    // nothing changes on errors, and bitmap journeys cannot be split
+    let id = main_db
+        .with_txn(|txn| {
+            assert!(!txn.has_journeys()?);
+            Ok(test_utils::insert_bitmap_journey(
+                txn,
+                date("2024-03-15"),
+                JourneyKind::DefaultKind,
+                test_utils::make_bitmap_with_line(test_utils::draw_line1),
+            ))
+        })
+        .unwrap();
+    assert!(main_db
+        .with_txn(|txn| txn.split_journey(&id, SplitPosition::PointIndex(1)))
+        .is_err());
+}
+
+#[test]
+fn merge_journeys_into_bitmap() {
+    let temp_dir = TempDir::new("main_db-merge_journeys_into_bitmap").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let bitmap = test_utils::make_bitmap_with_line(test_utils::draw_line1);
+    let (vector_id, bitmap_id, flight_id, journey_vector) = main_db
+        .with_txn(|txn| {
+            let journey_vector = insert_vector_journey_for_split(txn, "a");
+            let bitmap_id = test_utils::insert_bitmap_journey(
+                txn,
+                date("2024-03-16"),
+                JourneyKind::DefaultKind,
+                bitmap.clone(),
+            );
+            let flight_id = test_utils::insert_bitmap_journey(
+                txn,
+                date("2024-03-16"),
+                JourneyKind::Flight,
+                bitmap.clone(),
+            );
+            Ok(("a".to_string(), bitmap_id, flight_id, journey_vector))
+        })
+        .unwrap();
+
+    assert!(main_db
+        .with_txn(|txn| txn.merge_journeys(&[vector_id.clone(), flight_id.clone()]))
+        .is_err());
+    assert!(main_db
+        .with_txn(|txn| txn.merge_journeys(&[vector_id.clone(), vector_id.clone()]))
+        .is_err());
+    assert!(main_db
+        .with_txn(|txn| txn.merge_journeys(std::slice::from_ref(&vector_id)))
+        .is_err());
+
+    let merged_id = main_db
+        .with_txn(|txn| {
+            let merged_id = txn.merge_journeys(&[bitmap_id.clone(), vector_id.clone()])?;
+            match &txn.action {
+                Some(Action::Invalidate { entries }) => {
+                    for journey_date in ["2024-03-15", "2024-03-16"] {
+                        assert!(entries.contains(&CacheEntry {
+                            date: date(journey_date),
+                            kind: JourneyKind::DefaultKind,
+                        }));
+                    }
+                }
+                other => panic!("Expected Invalidate, got {:?}", other),
+            }
+            Ok(merged_id)
+        })
+        .unwrap();
+    let mut expected = bitmap;
+    expected.merge_vector(&journey_vector);
+    let (header, journey_data) = main_db
+        .with_txn(|txn| {
+            assert_eq!(txn.query_journeys(None, None)?.len(), 2);
+            Ok(get_journey(txn, &merged_id))
+        })
+        .unwrap();
+    assert_eq!(header.journey_type, JourneyType::Bitmap);
+    assert_eq!(header.postprocessor_algo, None);
+    assert_eq!(journey_data, JourneyData::Bitmap(expected));
+    let _: JourneyBitmap = match journey_data {
+        JourneyData::Bitmap(bitmap) => bitmap,
+        JourneyData::Vector(_) => panic!("unexpected vector"),
+    };
+}
+
+#[test]
+fn journey_history_and_restore() {
+    let temp_dir = TempDir::new("main_db-journey_history_and_restore").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let journey_vector = main_db
+        .with_txn(|txn| Ok(insert_vector_journey_for_split(txn, "a")))
+        .unwrap();
+    let (original_header, _) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+
+    let shorter = JourneyData::Vector(JourneyVector {
+        track_segments: vec![journey_vector.track_segments[0].clone()],
+    });
+    main_db
+        .with_txn(|txn| {
+            txn.update_journey_metadata(
+                "a",
+                date("2024-03-16"),
+                original_header.start,
+                original_header.end,
+                Some("edited".to_string()),
+                JourneyKind::Flight,
+            )?;
+            txn.update_journey_data_with_latest_postprocessor("a", shorter)
+        })
+        .unwrap();
+    let (_, edited_data) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+
+    let revisions = main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap();
+    assert_eq!(revisions.len(), 2);
+    // the latest first
+    assert_eq!(revisions[0].header.note, Some("edited".to_string()));
+    assert_eq!(revisions[1].header, original_header);
+    assert!(revisions[0].archived_at >= revisions[1].archived_at);
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.get_journey_revision_data(revisions[1].history_id))
+            .unwrap(),
+        JourneyData::Vector(journey_vector.clone())
+    );
+
+    main_db
+        .with_txn(|txn| txn.restore_journey_revision(revisions[1].history_id))
+        .unwrap();
+    let (header, data) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+    assert_eq!(data, JourneyData::Vector(journey_vector.clone()));
+    assert_eq!(header.journey_date, date("2024-03-15"));
+    assert_eq!(header.journey_kind, JourneyKind::DefaultKind);
+    assert_eq!(header.note, Some("note".to_string()));
+    assert_ne!(header.revision, original_header.revision);
+    assert!(header.updated_at.is_some());
+    // the edited journey is kept as well
+    let revisions = main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap();
+    assert_eq!(revisions.len(), 3);
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.get_journey_revision_data(revisions[0].history_id))
+            .unwrap(),
+        edited_data
+    );
+
+    // a deleted journey can be brought back
+    main_db.with_txn(|txn| txn.delete_journey("a")).unwrap();
+    let revisions = main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap();
+    assert_eq!(revisions.len(), 4);
+    main_db
+        .with_txn(|txn| txn.restore_journey_revision(revisions[0].history_id))
+        .unwrap();
+    let (_, data) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+    assert_eq!(data, JourneyData::Vector(journey_vector));
+
+    main_db.with_txn(|txn| txn.purge_journey_history()).unwrap();
+    assert!(main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn journey_history_retention() {
+    let temp_dir = TempDir::new("main_db-journey_history_retention").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    main_db
+        .with_txn(|txn| Ok(insert_vector_journey_for_split(txn, "a")))
+        .unwrap();
+    let update_note = |main_db: &mut MainDb, note: &str| {
+        main_db
+            .with_txn(|txn| {
+                let header = txn.get_journey_header("a")?.unwrap();
+                txn.update_journey_metadata(
+                    "a",
+                    header.journey_date,
+                    header.start,
+                    header.end,
+                    Some(note.to_string()),
+                    header.journey_kind,
+                )
+            })
+            .unwrap();
+    };
+
+    main_db
+        .set_setting(main_db::Setting::JourneyHistoryMaxRevisions, 2)
+        .unwrap();
+    for note in ["1", "2", "3"] {
+        update_note(&mut main_db, note);
+    }
+    let notes: Vec<_> = main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.header.note.unwrap())
+        .collect();
+    assert_eq!(notes, vec!["2", "1"]);
+
+    // lowering the limit only applies once pruned
+    main_db
+        .set_setting(main_db::Setting::JourneyHistoryMaxRevisions, 1)
+        .unwrap();
+    main_db
+        .with_txn(|txn| txn.prune_journey_history(None))
+        .unwrap();
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.list_journey_revisions("a"))
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // disabled
+    main_db
+        .set_setting(main_db::Setting::JourneyHistoryMaxRevisions, 0)
+        .unwrap();
+    main_db.with_txn(|txn| txn.purge_journey_history()).unwrap();
+    update_note(&mut main_db, "4");
+    assert!(main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn journey_history_of_metadata_changes() {
+    let temp_dir = TempDir::new("main_db-journey_history_of_metadata_changes").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    main_db
+        .with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "a");
+            let (mut header, data) = get_journey(txn, "a");
+            txn.delete_journey("a")?;
+            txn.purge_journey_history()?;
+            // processed by an older postprocessor
+            header.postprocessor_algo = None;
+            txn.insert_journey(header, data)
+        })
+        .unwrap();
+    main_db
+        .with_txn(|txn| txn.set_journey_tags("a", vec!["trip".to_string()]))
+        .unwrap();
+
+    // optimizing is not a revision
+    main_db.with_txn(|txn| txn.optimize()).unwrap();
+    let (header, data) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+    assert!(header.postprocessor_algo.is_some());
+    let revisions = main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert!(revisions[0].header.tags.is_empty());
+    // only the header is kept, the data is the current one
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.get_journey_revision_data(revisions[0].history_id))
+            .unwrap(),
+        data
+    );
+
+    // still there while the journey is in the trash
+    main_db.with_txn(|txn| txn.trash_journey("a")).unwrap();
+    main_db
+        .with_txn(|txn| txn.restore_journey_revision(revisions[0].history_id))
+        .unwrap();
+    let (header, restored_data) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+    assert!(header.tags.is_empty());
+    assert_eq!(restored_data, data);
+
+    // but gone with it
+    main_db
+        .with_txn(|txn| {
+            txn.purge_journey_history()?;
+            txn.set_journey_tags("a", vec!["trip".to_string()])?;
+            txn.trash_journey("a")?;
+            txn.empty_trash()
+        })
+        .unwrap();
+    assert!(main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn trash_and_restore() {
+    let temp_dir = TempDir::new("main_db-trash_and_restore").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    main_db
+        .with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "a");
+            insert_vector_journey_for_split(txn, "b");
+            Ok(())
+        })
+        .unwrap();
+    let (header_a, data_a) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+
+    main_db
+        .with_txn(|txn| {
+            txn.trash_journey("a")?;
+            assert!(matches!(txn.action, Some(Action::Invalidate { .. })));
+            Ok(())
+        })
+        .unwrap();
+    let (journeys, trash) = main_db
+        .with_txn(|txn| Ok((txn.query_journeys(None, None)?, txn.list_trash()?)))
+        .unwrap();
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(journeys[0].id, "b");
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].header, header_a);
+    assert_eq!(
+        main_db
+            .with_txn(|txn| txn.get_trashed_journey_data("a"))
+            .unwrap(),
+        data_a
+    );
+    // trashing is not a revision
+    assert!(main_db
+        .with_txn(|txn| txn.list_journey_revisions("a"))
+        .unwrap()
+        .is_empty());
+
+    main_db
+        .with_txn(|txn| txn.restore_journey_from_trash("a"))
+        .unwrap();
+    assert_eq!(
+        main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap(),
+        (header_a, data_a)
+    );
+    assert!(main_db.with_txn(|txn| txn.list_trash()).unwrap().is_empty());
+    assert!(main_db
+        .with_txn(|txn| txn.restore_journey_from_trash("a"))
+        .is_err());
+
+    // deleting everything can be undone, except for journeys that exist again
+    main_db
+        .with_txn(|txn| {
+            txn.trash_all_journeys()?;
+            assert!(matches!(txn.action, Some(Action::CompleteRebuilt)));
+            Ok(())
+        })
+        .unwrap();
+    main_db
+        .with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "b");
+            txn.update_journey_metadata(
+                "b",
+                date("2024-03-15"),
+                None,
+                None,
+                None,
+                JourneyKind::DefaultKind,
+            )
+        })
+        .unwrap();
+    main_db
+        .with_txn(|txn| {
+            assert_eq!(txn.list_trash()?.len(), 2);
+            assert!(txn.restore_journey_from_trash("b").is_err());
+            assert_eq!(txn.restore_all_journeys_from_trash()?, 1);
+            assert!(matches!(txn.action, Some(Action::CompleteRebuilt)));
+            assert_eq!(txn.query_journeys(None, None)?.len(), 2);
+            assert_eq!(txn.list_trash()?.len(), 1);
+            txn.empty_trash()?;
+            assert!(txn.list_trash()?.is_empty());
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn trash_retention() {
+    let temp_dir = TempDir::new("main_db-trash_retention").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    main_db
+        .with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "a");
+            insert_vector_journey_for_split(txn, "b");
+            txn.trash_journey("a")
+        })
+        .unwrap();
+    // pretend it was deleted a long time ago
+    let conn = Connection::open(temp_dir.path().join("main.db")).unwrap();
+    conn.execute("UPDATE journey_trash SET deleted_at = 0;", ())
+        .unwrap();
+
+    main_db
+        .set_setting(main_db::Setting::TrashRetentionInDays, 0)
+        .unwrap();
+    main_db.with_txn(|txn| txn.purge_trash()).unwrap();
+    assert_eq!(main_db.with_txn(|txn| txn.list_trash()).unwrap().len(), 1);
+
+    main_db
+        .set_setting(main_db::Setting::TrashRetentionInDays, 30)
+        .unwrap();
+    main_db.with_txn(|txn| txn.trash_journey("b")).unwrap();
+    let trash = main_db.with_txn(|txn| txn.list_trash()).unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].header.id, "b");
+}
+
+#[test]
+fn tags_and_search() {
+    let temp_dir = TempDir::new("main_db-tags_and_search").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let flight_id = main_db
+        .with_txn(|txn| {
+            insert_vector_journey_for_split(txn, "a");
+            txn.set_journey_tags("a", vec![" trip ".to_string(), "food".to_string()])?;
+            let mut journey_bitmap = JourneyBitmap::new();
+            journey_bitmap.add_line(139.0, 35.0, 140.0, 35.5);
+            txn.create_and_insert_journey(
+                date("2024-05-01"),
+                None,
+                None,
+                None,
+                JourneyKind::Flight,
+                Some("飞往东京的航班 50%".to_string()),
+                JourneyData::Bitmap(journey_bitmap),
+            )
+        })
+        .unwrap();
+    main_db
+        .with_txn(|txn| txn.set_journey_tags(&flight_id, vec!["trip".to_string()]))
+        .unwrap();
+
+    let (header, _) = main_db.with_txn(|txn| Ok(get_journey(txn, "a"))).unwrap();
+    assert_eq!(header.tags, vec!["food", "trip"]);
+    assert_ne!(header.revision, "rev");
+    assert_eq!(
+        main_db.with_txn(|txn| txn.list_all_tags()).unwrap(),
+        vec!["food", "trip"]
+    );
+
+    let search = |main_db: &mut MainDb, query: JourneySearchQuery| -> Vec<String> {
+        main_db
+            .with_txn(|txn| txn.search_journeys(&query))
+            .unwrap()
+            .into_iter()
+            .map(|header| header.id)
+            .collect()
+    };
+    let both = vec![flight_id.clone(), "a".to_string()];
+    assert_eq!(search(&mut main_db, JourneySearchQuery::default()), both);
+    let by_tags = |tags: &[&str]| JourneySearchQuery {
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        ..Default::default()
+    };
+    assert_eq!(search(&mut main_db, by_tags(&["trip"])), both);
+    assert_eq!(search(&mut main_db, by_tags(&["trip", "food"])), vec!["a"]);
+    assert!(search(&mut main_db, by_tags(&["work"])).is_empty());
+
+    let by_text = |text: &str| JourneySearchQuery {
+        text: Some(text.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(search(&mut main_db, by_text("NOTE")), vec!["a"]);
+    assert_eq!(
+        search(&mut main_db, by_text("东京的")),
+        vec![flight_id.clone()]
+    );
+    // shorter than a trigram
+    assert_eq!(
+        search(&mut main_db, by_text("东京")),
+        vec![flight_id.clone()]
+    );
+    assert_eq!(search(&mut main_db, by_text("0%")), vec![flight_id.clone()]);
+    assert_eq!(search(&mut main_db, by_text("%")), vec![flight_id.clone()]);
+    assert!(search(&mut main_db, by_text("_")).is_empty());
+    assert!(search(&mut main_db, by_text("\"")).is_empty());
+
+    assert_eq!(
+        search(
+            &mut main_db,
+            JourneySearchQuery {
+                from_date_inclusive: Some(date("2024-04-01")),
+                ..Default::default()
+            }
+        ),
+        vec![flight_id.clone()]
+    );
+    assert_eq!(
+        search(
+            &mut main_db,
+            JourneySearchQuery {
+                kinds: vec![JourneyKind::DefaultKind],
+                ..Default::default()
+            }
+        ),
+        vec!["a"]
+    );
+
+    let by_area = |area: SearchArea| JourneySearchQuery {
+        area: Some(area),
+        ..Default::default()
+    };
+    let bounds = |west, south, east, north| {
+        SearchArea::Bounds(MapBounds {
+            west,
+            south,
+            east,
+            north,
+        })
+    };
+    assert_eq!(
+        search(&mut main_db, by_area(bounds(119.0, 29.0, 121.0, 31.0))),
+        vec!["a"]
+    );
+    assert_eq!(
+        search(&mut main_db, by_area(bounds(100.0, 0.0, 150.0, 40.0))),
+        both
+    );
+    // within the bounds of the flight, but away from the line
+    assert!(search(&mut main_db, by_area(bounds(139.0, 35.4, 139.1, 35.5))).is_empty());
+    // crossing the antimeridian the other way round
+    assert_eq!(
+        search(&mut main_db, by_area(bounds(139.5, 0.0, 121.0, 40.0))),
+        both
+    );
+    assert_eq!(
+        search(
+            &mut main_db,
+            by_area(SearchArea::Point {
+                lng: 120.003,
+                lat: 30.0005,
+                tolerance_in_m: 100.0,
+            })
+        ),
+        vec!["a"]
+    );
+    assert!(search(
+        &mut main_db,
+        by_area(SearchArea::Point {
+            lng: 120.003,
+            lat: 30.01,
+            tolerance_in_m: 100.0,
+        })
+    )
+    .is_empty());
+
+    // pagination
+    let page = |offset, limit| JourneySearchQuery {
+        offset,
+        limit: Some(limit),
+        ..Default::default()
+    };
+    assert_eq!(search(&mut main_db, page(0, 1)), vec![flight_id.clone()]);
+    assert_eq!(search(&mut main_db, page(1, 1)), vec!["a"]);
+    assert!(search(&mut main_db, page(2, 1)).is_empty());
+    let page_in_area = |offset, limit| JourneySearchQuery {
+        area: Some(bounds(100.0, 0.0, 150.0, 40.0)),
+        ..page(offset, limit)
+    };
+    assert_eq!(
+        search(&mut main_db, page_in_area(0, 1)),
+        vec![flight_id.clone()]
+    );
+    assert_eq!(search(&mut main_db, page_in_area(1, 5)), vec!["a"]);
+    assert_eq!(
+        search(
+            &mut main_db,
+            JourneySearchQuery {
+                kinds: vec![JourneyKind::Flight],
+                ..page(0, 5)
+            }
+        ),
+        vec![flight_id.clone()]
+    );
+
+    // the index follows updates, deletes and restores
+    main_db
+        .with_txn(|txn| {
+            txn.update_journey_metadata(
+                "a",
+                date("2024-03-15"),
+                None,
+                None,
+                Some("lunch".to_string()),
+                JourneyKind::DefaultKind,
+            )
+        })
+        .unwrap();
+    assert!(search(&mut main_db, by_text("note")).is_empty());
+    assert_eq!(search(&mut main_db, by_text("lunch")), vec!["a"]);
+    main_db.with_txn(|txn| txn.trash_journey("a")).unwrap();
+    assert!(search(&mut main_db, by_tags(&["food"])).is_empty());
+    assert_eq!(
+        main_db.with_txn(|txn| txn.list_all_tags()).unwrap(),
+        vec!["trip"]
+    );
+    main_db
+        .with_txn(|txn| txn.restore_journey_from_trash("a"))
+        .unwrap();
+    assert_eq!(
+        search(&mut main_db, by_area(bounds(119.0, 29.0, 121.0, 31.0))),
+        vec!["a"]
+    );
+    main_db.with_txn(|txn| txn.delete_all_journeys()).unwrap();
+    assert!(main_db
+        .with_txn(|txn| txn.list_all_tags())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn search_area_across_antimeridian() {
+    let temp_dir = TempDir::new("main_db-search_area_across_antimeridian").unwrap();
+    let mut main_db = MainDb::open(temp_dir.path().to_str().unwrap()).unwrap();
+    let segment = |start: f64, end: f64| TrackSegment {
+        track_points: vec![
+            TrackPoint {
+                latitude: 10.0,
+                longitude: start,
+            },
+            TrackPoint {
+                latitude: 10.0,
+                longitude: end,
+            },
+        ],
+        inferred: false,
+    };
+    let id = main_db
+        .with_txn(|txn| {
+            txn.create_and_insert_journey(
+                date("2024-03-15"),
+                None,
+                None,
+                None,
+                JourneyKind::DefaultKind,
+                None,
+                JourneyData::Vector(JourneyVector {
+                    track_segments: vec![segment(179.95, 179.999), segment(-179.999, -179.95)],
+                }),
+            )
+        })
+        .unwrap();
+
+    let search = |main_db: &mut MainDb, area: SearchArea| -> Vec<String> {
+        main_db
+            .with_txn(|txn| {
+                txn.search_journeys(&JourneySearchQuery {
+                    area: Some(area),
+                    ..Default::default()
+                })
+            })
+            .unwrap()
+            .into_iter()
+            .map(|header| header.id)
+            .collect()
+    };
+    let point = |lng, lat| SearchArea::Point {
+        lng,
+        lat,
+        tolerance_in_m: 100.0,
+    };
+    assert_eq!(search(&mut main_db, point(-179.97, 10.0)), vec![id.clone()]);
+    assert_eq!(search(&mut main_db, point(179.97, 10.0)), vec![id.clone()]);
+    assert!(search(&mut main_db, point(179.97, 10.01)).is_empty());
+    assert!(search(&mut main_db, point(179.5, 10.0)).is_empty());
+    let bounds = |west, east| {
+        SearchArea::Bounds(MapBounds {
+            west,
+            south: 9.0,
+            east,
+            north: 11.0,
+        })
+    };
+    assert_eq!(
+        search(&mut main_db, bounds(-179.98, -179.9)),
+        vec![id.clone()]
+    );
+    assert_eq!(
+        search(&mut main_db, bounds(179.9, -179.9)),
+        vec![id.clone()]
+    );
+    assert!(search(&mut main_db, bounds(170.0, 179.0)).is_empty());
+    assert_eq!(
+        search(&mut main_db, bounds(-180.0, 180.0)),
+        vec![id.clone()]
+    );
+
+    // the footprint follows data updates
+    main_db
+        .with_txn(|txn| {
+            txn.update_journey_data_with_latest_postprocessor(
+                &id,
+                JourneyData::Vector(JourneyVector {
+                    track_segments: vec![segment(179.95, 179.999)],
+                }),
+            )
+        })
+        .unwrap();
+    assert!(search(&mut main_db, point(-179.97, 10.0)).is_empty());
+    assert_eq!(search(&mut main_db, point(179.97, 10.0)), vec![id]);
+}
@@ -0,0 +1,182 @@
+use memolanes_core::journey_vector::{JourneyVector, TrackPoint, TrackSegment};
+use memolanes_core::map_matching::{self, RoadGraph};
+
+// roughly 111m in latitude
+const STEP_IN_DEGREE: f64 = 0.001;
+
+fn point(latitude: f64, longitude: f64) -> TrackPoint {
+    TrackPoint {
+        latitude,
+        longitude,
+    }
+}
+
+// A 5x5 grid of streets, ~111m apart.
+fn grid_road_graph() -> RoadGraph {
+    const SIZE: u32 = 5;
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            nodes.push(point(
+                30.0 + y as f64 * STEP_IN_DEGREE,
+                120.0 + x as f64 * STEP_IN_DEGREE,
+            ));
+            let index = y * SIZE + x;
+            if x > 0 {
+                edges.push((index - 1, index));
+            }
+            if y > 0 {
+                edges.push((index - SIZE, index));
+            }
+        }
+    }
+    RoadGraph::new(nodes, edges).unwrap()
+}
+
+fn match_points(road_graph: &RoadGraph, track_points: Vec<TrackPoint>) -> Vec<TrackPoint> {
+    let journey_vector = JourneyVector {
+        track_segments: vec![TrackSegment {
+            track_points,
+            inferred: false,
+        }],
+    };
+    let mut matched = map_matching::match_journey_vector(road_graph, journey_vector);
+    assert_eq!(matched.track_segments.len(), 1);
+    matched.track_segments.remove(0).track_points
+}
+
+#[test]
+fn snap_noisy_track_to_street() {
+    let road_graph = grid_road_graph();
+    // walking east along the street at latitude 30.001 with some noise, away
+    // from the intersections
+    let track_points = (0..20)
+        .map(|i| {
+            let noise = if i % 2 == 0 { 0.00008 } else { -0.00006 };
+            point(30.001 + noise, 120.0012 + i as f64 * 0.00003)
+        })
+        .collect();
+    let matched = match_points(&road_graph, track_points);
+    assert!(matched.len() >= 20);
+    for track_point in &matched {
+        assert!((track_point.latitude - 30.001).abs() < 1e-9);
+    }
+    for pair in matched.windows(2) {
+        assert!(pair[0].longitude <= pair[1].longitude);
+    }
+}
+
+#[test]
+fn insert_road_node_at_turn() {
+    let road_graph = grid_road_graph();
+    // going east on latitude 30.001 then turning north on longitude 120.002
+    let track_points = vec![
+        point(30.00105, 120.0008),
+        point(30.00095, 120.0014),
+        point(30.0011, 120.0019),
+        point(30.0016, 120.00205),
+        point(30.0022, 120.00195),
+    ];
+    let matched = match_points(&road_graph, track_points);
+    assert!(matched
+        .iter()
+        .any(|p| (p.latitude - 30.001).abs() < 1e-9 && (p.longitude - 120.002).abs() < 1e-9));
+    let last = matched.last().unwrap();
+    assert!((last.longitude - 120.002).abs() < 1e-9);
+}
+
+#[test]
+fn keep_off_network_points() {
+    let road_graph = grid_road_graph();
+    let far_away = vec![point(31.0, 121.0), point(31.0001, 121.0001)];
+    let matched = match_points(&road_graph, far_away.clone());
+    assert_eq!(matched, far_away);
+
+    // mixed: on-network points are snapped, off-network ones are untouched
+    let track_points = vec![
+        point(30.00102, 120.0005),
+        point(30.00098, 120.0008),
+        point(31.0, 121.0),
+    ];
+    let matched = match_points(&road_graph, track_points);
+    assert_eq!(matched.last().unwrap(), &point(31.0, 121.0));
+    assert!((matched[0].latitude - 30.001).abs() < 1e-9);
+}
+
+#[test]
+fn empty_journey_vector() {
+    let road_graph = grid_road_graph();
+    assert_eq!(match_points(&road_graph, vec![]), vec![]);
+}
+
+#[test]
+fn invalid_edge() {
+    assert!(RoadGraph::new(vec![point(30.0, 120.0)], vec![(0, 1)]).is_err());
+}
+
+#[test]
+fn serialization_roundtrip() {
+    let road_graph = grid_road_graph();
+    let mut buf = Vec::new();
+    road_graph.serialize(&mut buf).unwrap();
+    let road_graph_2 = RoadGraph::deserialize(&buf[..]).unwrap();
+    assert_eq!(road_graph_2.node_count(), road_graph.node_count());
+    assert_eq!(road_graph_2.edge_count(), road_graph.edge_count());
+
+    // coordinates are stored with a fixed precision, so compare the matching
+    // result instead.
+    let track_points = vec![point(30.00102, 120.0005), point(30.00098, 120.0008)];
+    assert_eq!(
+        match_points(&road_graph, track_points.clone()),
+        match_points(&road_graph_2, track_points)
+    );
+
+    assert!(RoadGraph::deserialize(&b"V0"[..]).is_err());
+}
+
+#[test]
+fn road_graph_from_osm_xml() {
+    let osm = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="30.001" lon="120.000"/>
+  <node id="2" lat="30.001" lon="120.001"/>
+  <node id="3" lat="30.001" lon="120.002">
+    <tag k="highway" v="traffic_signals"/>
+  </node>
+  <node id="4" lat="30.002" lon="120.002"/>
+  <node id="5" lat="30.003" lon="120.003"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <nd ref="3"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="11">
+    <nd ref="3"/>
+    <nd ref="4"/>
+    <nd ref="404"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="12">
+    <nd ref="4"/>
+    <nd ref="5"/>
+    <tag k="building" v="yes"/>
+  </way>
+  <way id="13">
+    <nd ref="4"/>
+    <nd ref="5"/>
+    <tag k="highway" v="proposed"/>
+  </way>
+</osm>"#;
+    let road_graph = RoadGraph::from_osm_xml(osm.as_bytes()).unwrap();
+    // node 5 is only used by ways that are not roads, node 404 is missing
+    assert_eq!(road_graph.node_count(), 4);
+    assert_eq!(road_graph.edge_count(), 3);
+
+    // walking east along way 10
+    let track_points = vec![point(30.00105, 120.0004), point(30.00095, 120.0006)];
+    for track_point in match_points(&road_graph, track_points) {
+        assert!((track_point.latitude - 30.001).abs() < 1e-9);
+    }
+}
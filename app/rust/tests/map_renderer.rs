@@ -30,3 +30,105 @@ fn basic() {
 
     test_utils::verify_image("map_renderer_basic", &render_result.data);
 }
+
+#[test]
+fn load_tiles_replaces_the_overview_closest_to_the_view_first() {
+    use journey_kernel::tile_range::{parse_tile_range_header, TILE_RANGE_LAYER_DEFAULT};
+    use memolanes_core::journey_bitmap::TileKey;
+    use memolanes_core::renderer::map_renderer::{MapLayer, PendingLayers};
+    use memolanes_core::utils::lng_lat_to_tile_x_y;
+
+    let mut full = JourneyBitmap::new();
+    // Sydney and Paris
+    full.add_line(151.20, -33.86, 151.21, -33.87);
+    full.add_line(2.35, 48.85, 2.36, 48.86);
+    let layer = |journey_bitmap| MapLayer {
+        id: TILE_RANGE_LAYER_DEFAULT,
+        name: "default".to_string(),
+        journey_bitmap,
+    };
+    let mut map_renderer = MapRenderer::new_with_layers(vec![layer(JourneyBitmap::new())]);
+    map_renderer.set_overviews(vec![(TILE_RANGE_LAYER_DEFAULT, full.block_overview())]);
+    map_renderer.set_loading(true);
+
+    let (sydney_x, sydney_y) = lng_lat_to_tile_x_y(151.20, -33.86, 9);
+    let (paris_x, paris_y) = lng_lat_to_tile_x_y(2.35, 48.85, 9);
+    let sydney = TileKey::new(sydney_x as u16, sydney_y as u16);
+    let paris = TileKey::new(paris_x as u16, paris_y as u16);
+    let (x, y) = (sydney_x as i64 - 1, sydney_y as i64 - 1);
+    let loading_version = map_renderer.get_tile_range_version(x, y, 9, 3, 3, 8, false);
+    let response = map_renderer
+        .get_tile_range_response(x, y, 9, 3, 3, 8)
+        .unwrap();
+    assert!(parse_tile_range_header(&response).unwrap().is_loading());
+
+    let tile = |map_renderer: &MapRenderer, key: &TileKey| {
+        map_renderer
+            .peek_latest_bitmap()
+            .peek_tile_without_updating_cache(key, |tile| tile.cloned())
+    };
+    let full_sydney = full.peek_tile_without_updating_cache(&sydney, |tile| tile.cloned());
+    let full_paris = full.peek_tile_without_updating_cache(&paris, |tile| tile.cloned());
+    assert_ne!(tile(&map_renderer, &sydney), full_sydney);
+
+    let mut pending = PendingLayers::new(vec![layer(full.clone())]);
+    assert!(!map_renderer.load_tiles(&mut pending, 1));
+    assert!(map_renderer.is_loading());
+    assert_eq!(tile(&map_renderer, &sydney), full_sydney);
+    assert_ne!(tile(&map_renderer, &paris), full_paris);
+
+    while !map_renderer.load_tiles(&mut pending, 1) {}
+    assert!(!map_renderer.is_loading());
+    assert_eq!(map_renderer.peek_latest_bitmap(), &full);
+    assert_ne!(
+        map_renderer.get_tile_range_version(x, y, 9, 3, 3, 8, false),
+        loading_version
+    );
+    let response = map_renderer
+        .get_tile_range_response(x, y, 9, 3, 3, 8)
+        .unwrap();
+    assert!(!parse_tile_range_header(&response).unwrap().is_loading());
+}
+
+#[test]
+fn load_tiles_merged_only_keeps_the_union() {
+    use journey_kernel::tile_range::{TILE_RANGE_LAYER_CURRENT_JOURNEY, TILE_RANGE_LAYER_DEFAULT};
+    use memolanes_core::renderer::map_renderer::{MapLayer, PendingLayers};
+
+    let layer = |id, journey_bitmap| MapLayer {
+        id,
+        name: String::new(),
+        journey_bitmap,
+    };
+    let mut full = JourneyBitmap::new();
+    full.add_line(151.20, -33.86, 151.21, -33.87);
+    let mut ongoing = JourneyBitmap::new();
+    ongoing.add_line(151.20, -33.87, 151.21, -33.86);
+    let mut map_renderer = MapRenderer::new_with_layers(vec![
+        layer(TILE_RANGE_LAYER_CURRENT_JOURNEY, ongoing.clone()),
+        layer(TILE_RANGE_LAYER_DEFAULT, JourneyBitmap::new()),
+    ]);
+    map_renderer.set_overviews(vec![(TILE_RANGE_LAYER_DEFAULT, full.block_overview())]);
+    map_renderer.set_loading(true);
+    // recorded while loading
+    map_renderer.update_with_layer(
+        TILE_RANGE_LAYER_CURRENT_JOURNEY,
+        |journey_bitmap, tile_changed| {
+            journey_bitmap.add_line_with_change_callback(
+                151.20,
+                -33.865,
+                151.21,
+                -33.865,
+                tile_changed,
+            );
+        },
+    );
+    ongoing.add_line(151.20, -33.865, 151.21, -33.865);
+
+    let mut pending =
+        PendingLayers::new_merged(vec![layer(TILE_RANGE_LAYER_DEFAULT, full.clone())]);
+    while !map_renderer.load_tiles(&mut pending, 1) {}
+    assert!(!map_renderer.has_layers());
+    full.merge(ongoing);
+    assert_eq!(map_renderer.peek_latest_bitmap(), &full);
+}
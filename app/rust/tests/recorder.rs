@@ -0,0 +1,51 @@
+use memolanes_core::recorder::Recorder;
+use memolanes_core::storage::Storage;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tempdir::TempDir;
+
+static STORAGE: OnceLock<(TempDir, Storage)> = OnceLock::new();
+
+fn storage() -> &'static Storage {
+    &STORAGE
+        .get_or_init(|| {
+            let temp_dir = TempDir::new("recorder").unwrap();
+            let sub_folder = |sub| {
+                let path = temp_dir.path().join(sub);
+                fs::create_dir(&path).unwrap();
+                path.into_os_string().into_string().unwrap()
+            };
+            let storage = Storage::init(
+                sub_folder("temp/"),
+                sub_folder("doc/"),
+                sub_folder("support/"),
+                sub_folder("cache/"),
+            )
+            .unwrap();
+            (temp_dir, storage)
+        })
+        .1
+}
+
+static PANIC_ONCE: AtomicBool = AtomicBool::new(true);
+
+// panics the recorder thread the first time it is used
+fn storage_panicking_once() -> &'static Storage {
+    if PANIC_ONCE.swap(false, Ordering::SeqCst) {
+        panic!("panic on the recorder thread");
+    }
+    storage()
+}
+
+#[test]
+fn respawn_after_panic() {
+    let recorder = Recorder::spawn(storage_panicking_once);
+    assert!(recorder
+        .finalize(|txn| txn.finalize_ongoing_journey())
+        .is_err());
+    // the next message goes to a new thread
+    assert!(!recorder
+        .finalize(|txn| txn.finalize_ongoing_journey())
+        .unwrap());
+}
@@ -125,11 +125,12 @@ pub fn read_on_demand(storage: &Storage, geo: &dyn GeoLookup) -> Answers {
             let mut out = Answers::new();
             for layer in AchievementLayer::ALL_LAYERS {
                 for (name, ids) in probes() {
-                    let mut areas: Vec<_> = region_areas_from_snapshot(snap, geo, layer, &ids)?
-                        .into_iter()
-                        .collect();
+                    let mut areas: Vec<_> =
+                        region_areas_from_snapshot(snap, geo, layer.clone(), &ids)?
+                            .into_iter()
+                            .collect();
                     areas.sort();
-                    out.push((layer, name, areas));
+                    out.push((layer.clone(), name, areas));
                 }
             }
             Ok(out)
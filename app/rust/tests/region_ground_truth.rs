@@ -236,7 +236,7 @@ fn region_api_reports_correct_countries_and_areas() {
         insert(
             &storage,
             (*i as u32) + 1,
-            CITIES[*i].kind,
+            CITIES[*i].kind.clone(),
             patch_journey(*tile, *block),
         );
     }
@@ -34,6 +34,7 @@ fn storage_for_main_map_renderer() {
             raw_data,
             ProcessResult::Append,
             raw_data.timestamp_ms.unwrap(),
+            None,
         );
         if i == 1000 {
             let _: JourneyBitmap = storage
@@ -942,3 +943,87 @@ fn explored_area_per_layer_is_consistent() {
         assert!(all_area >= flight_area);
     });
 }
+
+#[test]
+fn custom_journey_kind_layers() {
+    setup_storage_for_test(|storage| {
+        let cycling = JourneyKind::Custom("Cycling".to_string());
+        let default_bitmap = test_utils::make_bitmap_with_line(draw_line1);
+        let cycling_bitmap = test_utils::make_bitmap_with_line(draw_line2);
+        storage
+            .with_db_txn(|txn| {
+                test_utils::insert_bitmap_journey(
+                    txn,
+                    date("2024-03-15"),
+                    JourneyKind::DefaultKind,
+                    default_bitmap.clone(),
+                );
+                test_utils::insert_bitmap_journey(
+                    txn,
+                    date("2024-04-15"),
+                    cycling.clone(),
+                    cycling_bitmap.clone(),
+                );
+                Ok(())
+            })
+            .unwrap();
+
+        let layer = |kind: &JourneyKind| {
+            storage
+                .get_latest_bitmap_for_main_map_renderer(
+                    &Some(LayerKind::JourneyKind(kind.clone())),
+                    false,
+                )
+                .unwrap()
+        };
+        assert_eq!(layer(&cycling), cycling_bitmap);
+        assert_eq!(layer(&JourneyKind::DefaultKind), default_bitmap);
+
+        let mut all = default_bitmap.clone();
+        all.merge(cycling_bitmap.clone());
+        assert_eq!(
+            storage
+                .get_latest_bitmap_for_main_map_renderer(&Some(LayerKind::All), false)
+                .unwrap(),
+            all
+        );
+        assert_eq!(
+            storage
+                .get_latest_bitmap_for_main_map_renderer_by_kind(
+                    |kind| *kind != JourneyKind::DefaultKind,
+                    false
+                )
+                .unwrap(),
+            cycling_bitmap
+        );
+        assert_eq!(
+            storage
+                .get_range_bitmap(date("2024-01-01"), date("2024-12-31"), Some(&cycling))
+                .unwrap(),
+            cycling_bitmap
+        );
+
+        // changing the kind invalidates the cache of both kinds
+        let id = storage
+            .with_db_txn(|txn| txn.query_journeys(None, None))
+            .unwrap()
+            .into_iter()
+            .find(|header| header.journey_kind == cycling)
+            .unwrap()
+            .id;
+        storage
+            .with_db_txn(|txn| {
+                txn.update_journey_metadata(
+                    &id,
+                    date("2024-04-15"),
+                    None,
+                    None,
+                    None,
+                    JourneyKind::DefaultKind,
+                )
+            })
+            .unwrap();
+        assert_eq!(layer(&cycling).all_tile_keys().count(), 0);
+        assert_eq!(layer(&JourneyKind::DefaultKind), all);
+    });
+}
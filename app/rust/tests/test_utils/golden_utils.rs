@@ -0,0 +1,120 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+
+use memolanes_core::renderer::MapRenderer;
+
+use super::render_utils::{image_to_png_data, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR};
+
+/* Golden-image checks for the renderer. A view is rendered the way clients
+see it: `MapRenderer::get_tile_range_response`, decoded by `journey_kernel`,
+then rasterized into a PNG. The result is compared with the golden in
+`tests/golden`. A missing golden fails the check, running the tests with
+`UPDATE_GOLDENS=1` creates or rewrites all of them. On mismatch the rendered image and a
+diff image (differing pixels in red) are written to `tests/for_inspection`. */
+
+pub const GOLDEN_DIR: &str = "tests/golden";
+pub const UPDATE_GOLDENS_ENV: &str = "UPDATE_GOLDENS";
+/// Per-channel difference that still counts as the same pixel.
+pub const MAX_CHANNEL_DIFF: u8 = 8;
+/// Fraction of pixels that may differ before a golden check fails.
+pub const MAX_DIFF_PIXEL_RATIO: f64 = 0.001;
+
+const DIFF_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// Renders `width` x `height` tiles starting at (`x`, `y`), each tile
+/// `1 << buffer_size_power` pixels wide.
+pub fn render_tile_range_image(
+    map_renderer: &mut MapRenderer,
+    x: i64,
+    y: i64,
+    z: i16,
+    width: i64,
+    height: i64,
+    buffer_size_power: i16,
+) -> RgbaImage {
+    let response = map_renderer
+        .get_tile_range_response(x, y, z, width, height, buffer_size_power)
+        .unwrap();
+    let tiles = journey_kernel::tile_range::decode_tile_range_response(&response).unwrap();
+
+    let tile_size = 1u32 << buffer_size_power;
+    let mut image = RgbaImage::from_pixel(
+        tile_size * width as u32,
+        tile_size * height as u32,
+        DEFAULT_BG_COLOR,
+    );
+    for (tile_x, tile_y, bitmap) in tiles {
+        let start_x = (tile_x as i64 - x) as u32 * tile_size;
+        let start_y = (tile_y as i64 - y) as u32 * tile_size;
+        let side = bitmap.side() as u32;
+        for py in 0..tile_size {
+            for px in 0..tile_size {
+                let (bx, by) = (px * side / tile_size, py * side / tile_size);
+                if bitmap.get(bx as usize, by as usize) {
+                    image.put_pixel(start_x + px, start_y + py, DEFAULT_FG_COLOR);
+                }
+            }
+        }
+    }
+    image
+}
+
+fn is_same_pixel(a: &Rgba<u8>, b: &Rgba<u8>) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .all(|(a, b)| a.abs_diff(*b) <= MAX_CHANNEL_DIFF)
+}
+
+/// Compares `image` with the golden called `name`.
+pub fn verify_golden_image(name: &str, image: &RgbaImage) {
+    let golden_path = Path::new(GOLDEN_DIR).join(format!("{name}.png"));
+    let output_path = format!("tests/for_inspection/{name}.png");
+    std::fs::write(&output_path, image_to_png_data(image)).unwrap();
+
+    if std::env::var_os(UPDATE_GOLDENS_ENV).is_some() {
+        std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+        std::fs::write(&golden_path, image_to_png_data(image)).unwrap();
+        println!("Wrote golden image: {}", golden_path.display());
+        return;
+    }
+    assert!(
+        golden_path.exists(),
+        "Missing golden image {}, see {output_path}. \
+         If it is a new golden, re-run the tests with {UPDATE_GOLDENS_ENV}=1.",
+        golden_path.display()
+    );
+
+    let golden = image::open(&golden_path).unwrap().to_rgba8();
+    assert_eq!(
+        golden.dimensions(),
+        image.dimensions(),
+        "Image size mismatch for {name}, see {output_path}"
+    );
+
+    let mut diff = RgbaImage::new(image.width(), image.height());
+    let mut diff_count = 0;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let golden_pixel = golden.get_pixel(x, y);
+        if is_same_pixel(pixel, golden_pixel) {
+            let mut faded = *golden_pixel;
+            faded.0[3] /= 4;
+            diff.put_pixel(x, y, faded);
+        } else {
+            diff.put_pixel(x, y, DIFF_COLOR);
+            diff_count += 1;
+        }
+    }
+
+    let diff_ratio = diff_count as f64 / (image.width() * image.height()) as f64;
+    if diff_ratio > MAX_DIFF_PIXEL_RATIO {
+        let diff_path = format!("tests/for_inspection/{name}_diff.png");
+        std::fs::write(&diff_path, image_to_png_data(&diff)).unwrap();
+        panic!(
+            "Image mismatch for {name}: {diff_count} pixels ({:.3}%) differ from {}, see {output_path} and {diff_path}. \
+             If the change is intended, re-run the tests with {UPDATE_GOLDENS_ENV}=1.",
+            diff_ratio * 100.0,
+            golden_path.display()
+        );
+    }
+    println!("Verified golden image: {name}");
+}
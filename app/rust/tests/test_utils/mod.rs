@@ -6,6 +6,7 @@ use memolanes_core::journey_header::JourneyKind;
 use memolanes_core::main_db::MainDb;
 use memolanes_core::renderer::map_renderer::*;
 use memolanes_core::utils;
+pub mod golden_utils;
 mod render_utils;
 use render_utils::*;
 use tempdir::TempDir;
@@ -0,0 +1,121 @@
+use memolanes_core::journey_vector::{TrackPoint, TrackSegment};
+use memolanes_core::track_selection::{delete_along_path, delete_in_polygon};
+
+fn points(lng_lat: &[(f64, f64)]) -> Vec<TrackPoint> {
+    lng_lat
+        .iter()
+        .map(|(longitude, latitude)| TrackPoint {
+            latitude: *latitude,
+            longitude: *longitude,
+        })
+        .collect()
+}
+
+fn segment(lng_lat: &[(f64, f64)]) -> TrackSegment {
+    TrackSegment {
+        track_points: points(lng_lat),
+        inferred: false,
+    }
+}
+
+fn box_polygon(west: f64, south: f64, east: f64, north: f64) -> Vec<TrackPoint> {
+    points(&[(west, south), (east, south), (east, north), (west, north)])
+}
+
+fn assert_lng_lats(segment: &TrackSegment, expected: &[(f64, f64)]) {
+    assert_eq!(segment.track_points.len(), expected.len(), "{segment:?}");
+    for (point, (lng, lat)) in segment.track_points.iter().zip(expected) {
+        assert!(
+            (point.longitude - lng).abs() < 1e-6 && (point.latitude - lat).abs() < 1e-6,
+            "{point:?} != ({lng}, {lat})"
+        );
+    }
+}
+
+#[test]
+fn polygon_splits_segments_at_the_boundary() {
+    let segments = vec![segment(&[(0.0, 0.0), (0.5, 0.0), (1.0, 0.0)])];
+
+    let result = delete_in_polygon(&segments, &box_polygon(0.2, -0.1, 0.4, 0.1));
+    assert_eq!(result.len(), 2);
+    assert_lng_lats(&result[0], &[(0.0, 0.0), (0.2, 0.0)]);
+    assert_lng_lats(&result[1], &[(0.4, 0.0), (0.5, 0.0), (1.0, 0.0)]);
+
+    // fully inside or outside
+    assert!(delete_in_polygon(&segments, &box_polygon(-1.0, -1.0, 2.0, 1.0)).is_empty());
+    assert_eq!(
+        delete_in_polygon(&segments, &box_polygon(0.2, 0.1, 0.4, 0.2)),
+        segments
+    );
+
+    // not a polygon
+    assert_eq!(
+        delete_in_polygon(&segments, &points(&[(0.2, -0.1), (0.4, 0.1)])),
+        segments
+    );
+}
+
+#[test]
+fn lasso_crossing_a_single_edge_several_times() {
+    let segments = vec![TrackSegment {
+        track_points: points(&[(0.0, 0.0), (1.0, 0.0)]),
+        inferred: true,
+    }];
+    // a U shape with its two arms over the track
+    let lasso = points(&[
+        (0.2, 0.1),
+        (0.2, -0.2),
+        (0.7, -0.2),
+        (0.7, 0.1),
+        (0.6, 0.1),
+        (0.6, -0.1),
+        (0.3, -0.1),
+        (0.3, 0.1),
+    ]);
+
+    let result = delete_in_polygon(&segments, &lasso);
+    assert_eq!(result.len(), 3);
+    assert_lng_lats(&result[0], &[(0.0, 0.0), (0.2, 0.0)]);
+    assert_lng_lats(&result[1], &[(0.3, 0.0), (0.6, 0.0)]);
+    assert_lng_lats(&result[2], &[(0.7, 0.0), (1.0, 0.0)]);
+    assert!(result.iter().all(|segment| segment.inferred));
+}
+
+#[test]
+fn polygon_cross_antimeridian() {
+    let segments = vec![
+        segment(&[(179.5, 0.0), (-179.5, 0.0)]),
+        segment(&[(-179.9, 0.5), (-179.6, 0.5)]),
+    ];
+
+    let result = delete_in_polygon(&segments, &box_polygon(179.8, -1.0, -179.8, 1.0));
+    assert_eq!(result.len(), 3);
+    assert_lng_lats(&result[0], &[(179.5, 0.0), (179.8, 0.0)]);
+    assert_lng_lats(&result[1], &[(-179.8, 0.0), (-179.5, 0.0)]);
+    assert_lng_lats(&result[2], &[(-179.8, 0.5), (-179.6, 0.5)]);
+}
+
+#[test]
+fn brush_along_path() {
+    let segments = vec![segment(&[(0.0, 0.0), (1.0, 0.0)])];
+    let path = points(&[(0.5, -0.1), (0.5, 0.1)]);
+
+    let result = delete_along_path(&segments, &path, 2000.0);
+    assert_eq!(result.len(), 2);
+    // 1000m is about 0.009 degree
+    let radius_in_degree = 1000.0 / 111_320.0;
+    assert_lng_lats(&result[0], &[(0.0, 0.0), (0.5 - radius_in_degree, 0.0)]);
+    assert_lng_lats(&result[1], &[(0.5 + radius_in_degree, 0.0), (1.0, 0.0)]);
+
+    // the round end of the brush
+    let result = delete_along_path(&segments, &points(&[(0.5, 0.0)]), 2000.0);
+    assert_eq!(result.len(), 2);
+    assert_lng_lats(&result[1], &[(0.5 + radius_in_degree, 0.0), (1.0, 0.0)]);
+
+    // too far away
+    assert_eq!(
+        delete_along_path(&segments, &points(&[(0.5, 0.1)]), 2000.0),
+        segments
+    );
+    assert_eq!(delete_along_path(&segments, &path, 0.0), segments);
+}
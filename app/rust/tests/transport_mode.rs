@@ -0,0 +1,207 @@
+use memolanes_core::gps_processor::{Point, RawData};
+use memolanes_core::import_data::conversion;
+use memolanes_core::journey_header::JourneyKind;
+use memolanes_core::transport_mode::{self, TransportMode};
+
+const START_TIMESTAMP_MS: i64 = 1697349115000;
+
+struct TrackBuilder {
+    raw_data: Vec<RawData>,
+    latitude: f64,
+    longitude: f64,
+    timestamp_ms: i64,
+}
+
+impl TrackBuilder {
+    fn new() -> Self {
+        Self {
+            raw_data: Vec::new(),
+            latitude: 30.0,
+            longitude: 120.0,
+            timestamp_ms: START_TIMESTAMP_MS,
+        }
+    }
+
+    // heading east
+    fn go(mut self, speed: f64, duration_sec: i64, interval_sec: i64) -> Self {
+        let meters_per_degree = 111_320.0 * self.latitude.to_radians().cos();
+        for _ in 0..(duration_sec / interval_sec) {
+            self.longitude += speed * interval_sec as f64 / meters_per_degree;
+            self.timestamp_ms += interval_sec * 1000;
+            self.raw_data.push(RawData {
+                point: Point {
+                    latitude: self.latitude,
+                    longitude: self.longitude,
+                },
+                timestamp_ms: Some(self.timestamp_ms),
+                accuracy: None,
+                altitude: None,
+                speed: None,
+            });
+        }
+        self
+    }
+
+    fn build(self) -> Vec<RawData> {
+        self.raw_data
+    }
+}
+
+fn modes(raw_data: &[RawData]) -> Vec<TransportMode> {
+    transport_mode::classify(raw_data)
+        .into_iter()
+        .map(|span| span.mode)
+        .collect()
+}
+
+#[test]
+fn empty() {
+    assert_eq!(transport_mode::classify(&[]), vec![]);
+    assert_eq!(
+        transport_mode::suggest_journey_kind(&[]),
+        JourneyKind::DefaultKind
+    );
+}
+
+#[test]
+fn single_mode() {
+    let walking = TrackBuilder::new().go(1.4, 600, 5).build();
+    assert_eq!(modes(&walking), vec![TransportMode::Walking]);
+
+    let cycling = TrackBuilder::new().go(5.0, 600, 5).build();
+    assert_eq!(modes(&cycling), vec![TransportMode::Cycling]);
+
+    let driving = TrackBuilder::new().go(20.0, 600, 5).build();
+    assert_eq!(modes(&driving), vec![TransportMode::Driving]);
+
+    let train = TrackBuilder::new().go(80.0, 600, 5).build();
+    assert_eq!(modes(&train), vec![TransportMode::Train]);
+
+    let flight = TrackBuilder::new().go(230.0, 3600, 30).build();
+    assert_eq!(modes(&flight), vec![TransportMode::Flight]);
+}
+
+#[test]
+fn mixed_modes() {
+    let raw_data = TrackBuilder::new()
+        .go(1.4, 600, 5)
+        .go(15.0, 1200, 5)
+        .go(230.0, 7200, 30)
+        .go(1.4, 600, 5)
+        .build();
+    let spans = transport_mode::classify(&raw_data);
+    assert_eq!(
+        spans.iter().map(|span| span.mode).collect::<Vec<_>>(),
+        vec![
+            TransportMode::Walking,
+            TransportMode::Driving,
+            TransportMode::Flight,
+            TransportMode::Walking
+        ]
+    );
+    // spans cover everything
+    assert_eq!(spans.first().unwrap().start, 0);
+    assert_eq!(spans.last().unwrap().end, raw_data.len());
+    for pair in spans.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+
+    assert_eq!(
+        transport_mode::suggest_journey_kind(std::slice::from_ref(&raw_data)),
+        JourneyKind::Flight
+    );
+    assert_eq!(
+        transport_mode::split_by_journey_kind(&[raw_data])
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect::<Vec<_>>(),
+        vec![
+            JourneyKind::DefaultKind,
+            JourneyKind::Flight,
+            JourneyKind::DefaultKind
+        ]
+    );
+}
+
+#[test]
+fn short_flight_is_not_the_main_kind() {
+    let raw_data = TrackBuilder::new()
+        .go(20.0, 3600, 5)
+        .go(200.0, 1200, 30)
+        .go(20.0, 3600, 5)
+        .build();
+    assert!(modes(&raw_data).contains(&TransportMode::Flight));
+    assert_eq!(
+        transport_mode::suggest_journey_kind(&[raw_data]),
+        JourneyKind::DefaultKind
+    );
+}
+
+#[test]
+fn ignore_glitches() {
+    let mut raw_data = TrackBuilder::new().go(1.4, 600, 5).build();
+    // a single point jumping far away and back
+    raw_data[60].point.longitude += 0.05;
+    assert_eq!(modes(&raw_data), vec![TransportMode::Walking]);
+
+    // a short burst of high speed
+    let raw_data = TrackBuilder::new()
+        .go(1.4, 600, 5)
+        .go(120.0, 30, 5)
+        .go(1.4, 600, 5)
+        .build();
+    assert_eq!(modes(&raw_data), vec![TransportMode::Walking]);
+}
+
+#[test]
+fn use_reported_speed_and_altitude() {
+    let mut raw_data = TrackBuilder::new().go(1.4, 600, 5).build();
+    for data in raw_data.iter_mut() {
+        data.timestamp_ms = None;
+        data.speed = Some(60.0);
+    }
+    assert_eq!(modes(&raw_data), vec![TransportMode::Train]);
+
+    for data in raw_data.iter_mut() {
+        data.altitude = Some(8000.0);
+    }
+    assert_eq!(modes(&raw_data), vec![TransportMode::Flight]);
+}
+
+#[test]
+fn no_speed_info() {
+    let mut raw_data = TrackBuilder::new().go(1.4, 600, 5).build();
+    for data in raw_data.iter_mut() {
+        data.timestamp_ms = None;
+    }
+    assert_eq!(modes(&raw_data), vec![TransportMode::Walking]);
+}
+
+#[test]
+fn flight_spans_use_flight_track_processor() {
+    let raw_data = vec![TrackBuilder::new()
+        .go(1.4, 600, 5)
+        .go(230.0, 7200, 300)
+        .go(1.4, 600, 5)
+        .build()];
+    let num_of_points = |raw_data: &[Vec<RawData>]| raw_data.iter().map(|x| x.len()).sum::<usize>();
+
+    let journey_vector =
+        conversion::journey_vector_from_raw_data_with_transport_mode_detection(&raw_data, None)
+            .unwrap();
+    assert_eq!(journey_vector.track_segments.len(), 3);
+    // the flight part is interpolated
+    let num_of_output_points: usize = journey_vector
+        .track_segments
+        .iter()
+        .map(|segment| segment.track_points.len())
+        .sum();
+    assert!(num_of_output_points > num_of_points(&raw_data));
+
+    // nothing changes without flights
+    let raw_data = vec![TrackBuilder::new().go(1.4, 600, 5).build()];
+    assert_eq!(
+        conversion::journey_vector_from_raw_data_with_transport_mode_detection(&raw_data, None),
+        conversion::journey_vector_from_raw_data_with_gps_preprocessor(&raw_data, None)
+    );
+}